@@ -0,0 +1,418 @@
+// bit level operations on string values. Bit 0 is the most significant bit of the first byte,
+// like in redis.
+
+use super::{BackendError, Db, NotifyFlags};
+use crate::BulkString;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperator {
+    And,
+    Or,
+    Xor,
+    Not,
+    // the bits set in the first key but in none of the others
+    Diff,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFieldType {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOp {
+    Get(BitFieldType, usize),
+    Set(BitFieldType, usize, i64),
+    IncrBy(BitFieldType, usize, i64),
+    Overflow(Overflow),
+}
+
+impl Db {
+    pub fn setbit(&self, key: String, offset: usize, on: bool) -> Result<u8, BackendError> {
+        let old = self.update_bytes(key.clone(), |bytes| setbit(bytes, offset, on))?;
+        self.notify(NotifyFlags::STRING, "setbit", &key);
+        Ok(old)
+    }
+
+    pub fn getbit(&self, key: &str, offset: usize) -> Result<u8, BackendError> {
+        Ok(self
+            .read_bytes(key, |bytes| getbit(bytes, offset))?
+            .unwrap_or(0))
+    }
+
+    // `range` is an inclusive byte range, or bit range when `bit` is set
+    pub fn bitcount(
+        &self,
+        key: &str,
+        range: Option<(i64, i64)>,
+        bit: bool,
+    ) -> Result<i64, BackendError> {
+        let count = self.read_bytes(key, |bytes| {
+            let (start, end) = range.unwrap_or((0, -1));
+            match bit_range(start, end, bytes.len(), bit) {
+                Some((start, end)) => bitcount(bytes, start, end),
+                None => 0,
+            }
+        })?;
+        Ok(count.unwrap_or(0))
+    }
+
+    pub fn bitpos(
+        &self,
+        key: &str,
+        bit: u8,
+        start: Option<i64>,
+        end: Option<i64>,
+        bit_unit: bool,
+    ) -> Result<i64, BackendError> {
+        let pos = self.read_bytes(key, |bytes| {
+            let range = bit_range(start.unwrap_or(0), end.unwrap_or(-1), bytes.len(), bit_unit);
+            match range {
+                Some((from, to)) => match bitpos(bytes, bit, from, to) {
+                    Some(pos) => pos as i64,
+                    // looking for a clear bit without an explicit end, the string is
+                    // considered to be padded with zeros on the right
+                    None if bit == 0 && end.is_none() => to as i64 + 1,
+                    None => -1,
+                },
+                None => -1,
+            }
+        })?;
+        Ok(pos.unwrap_or(if bit == 1 { -1 } else { 0 }))
+    }
+
+    // store the result of `op` over `keys` at `dest`, returns the length of the result
+    pub fn bitop(
+        &self,
+        op: BitOperator,
+        dest: String,
+        keys: &[String],
+    ) -> Result<i64, BackendError> {
+        let sources = keys
+            .iter()
+            .map(|key| Ok(self.read_bytes(key, |b| b.to_vec())?.unwrap_or_default()))
+            .collect::<Result<Vec<_>, BackendError>>()?;
+        let result = bitop(op, &sources);
+        let len = result.len() as i64;
+        if result.is_empty() {
+            self.del(&[dest]);
+        } else {
+            self.set(dest, BulkString::new(result).into());
+        }
+        Ok(len)
+    }
+
+    // run the operations in order, None replies are overflows with the FAIL policy
+    pub fn bitfield(
+        &self,
+        key: String,
+        ops: &[BitFieldOp],
+    ) -> Result<Vec<Option<i64>>, BackendError> {
+        let read_only = ops
+            .iter()
+            .all(|op| matches!(op, BitFieldOp::Get(..) | BitFieldOp::Overflow(_)));
+        if read_only {
+            let replies = self.read_bytes(&key, |bytes| bitfield_read(bytes, ops))?;
+            return Ok(replies.unwrap_or_else(|| bitfield_read(&[], ops)));
+        }
+        let replies = self.update_bytes(key.clone(), |bytes| {
+            let mut overflow = Overflow::Wrap;
+            let mut replies = Vec::with_capacity(ops.len());
+            for op in ops {
+                match *op {
+                    BitFieldOp::Get(ty, offset) => {
+                        replies.push(Some(bitfield_get(bytes, offset, ty)));
+                    }
+                    BitFieldOp::Set(ty, offset, value) => {
+                        // like redis, values for unsigned fields are taken as u64
+                        let value = if ty.signed {
+                            value as i128
+                        } else {
+                            value as u64 as i128
+                        };
+                        let old = bitfield_get(bytes, offset, ty);
+                        match bitfield_overflow(value, ty, overflow) {
+                            Some(value) => {
+                                bitfield_set(bytes, offset, ty, value);
+                                replies.push(Some(old));
+                            }
+                            None => replies.push(None),
+                        }
+                    }
+                    BitFieldOp::IncrBy(ty, offset, incr) => {
+                        let old = bitfield_get(bytes, offset, ty);
+                        let old = if ty.signed {
+                            old as i128
+                        } else {
+                            old as u64 as i128
+                        };
+                        match bitfield_overflow(old + incr as i128, ty, overflow) {
+                            Some(value) => {
+                                bitfield_set(bytes, offset, ty, value);
+                                replies.push(Some(value));
+                            }
+                            None => replies.push(None),
+                        }
+                    }
+                    BitFieldOp::Overflow(o) => overflow = o,
+                }
+            }
+            replies
+        })?;
+        self.notify(NotifyFlags::STRING, "setbit", &key);
+        Ok(replies)
+    }
+}
+
+fn bitfield_read(bytes: &[u8], ops: &[BitFieldOp]) -> Vec<Option<i64>> {
+    ops.iter()
+        .filter_map(|op| match *op {
+            BitFieldOp::Get(ty, offset) => Some(Some(bitfield_get(bytes, offset, ty))),
+            _ => None,
+        })
+        .collect()
+}
+
+// resolve a byte or bit range into an inclusive range of bit positions
+fn bit_range(start: i64, end: i64, len: usize, bit: bool) -> Option<(usize, usize)> {
+    if bit {
+        resolve_range(start, end, len * 8)
+    } else {
+        resolve_range(start, end, len).map(|(start, end)| (start * 8, end * 8 + 7))
+    }
+}
+
+pub fn getbit(bytes: &[u8], offset: usize) -> u8 {
+    match bytes.get(offset / 8) {
+        Some(byte) => (byte >> (7 - offset % 8)) & 1,
+        None => 0,
+    }
+}
+
+// set the bit at `offset`, growing the string as needed. Returns the previous bit.
+pub fn setbit(bytes: &mut Vec<u8>, offset: usize, on: bool) -> u8 {
+    let idx = offset / 8;
+    if bytes.len() <= idx {
+        bytes.resize(idx + 1, 0);
+    }
+    let mask = 1 << (7 - offset % 8);
+    let old = (bytes[idx] & mask != 0) as u8;
+    if on {
+        bytes[idx] |= mask;
+    } else {
+        bytes[idx] &= !mask;
+    }
+    old
+}
+
+// resolve redis style inclusive `start..=end` indexes, negative ones counting from the end
+pub fn resolve_range(start: i64, end: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let end = if end < 0 { (end + len).max(0) } else { end };
+    let end = end.min(len - 1);
+    if len == 0 || start > end {
+        return None;
+    }
+    Some((start as usize, end as usize))
+}
+
+// count the set bits between the bit positions `start..=end`
+pub fn bitcount(bytes: &[u8], start: usize, end: usize) -> i64 {
+    if start / 8 == end / 8 {
+        return (start..=end).map(|i| getbit(bytes, i) as i64).sum();
+    }
+    let head = (start..(start / 8 + 1) * 8)
+        .map(|i| getbit(bytes, i) as i64)
+        .sum::<i64>();
+    let tail = ((end / 8) * 8..=end)
+        .map(|i| getbit(bytes, i) as i64)
+        .sum::<i64>();
+    let body = bytes[start / 8 + 1..end / 8]
+        .iter()
+        .map(|b| b.count_ones() as i64)
+        .sum::<i64>();
+    head + body + tail
+}
+
+// the position of the first bit equal to `bit` between the bit positions `start..=end`
+pub fn bitpos(bytes: &[u8], bit: u8, start: usize, end: usize) -> Option<usize> {
+    let skip = if bit == 1 { 0x00 } else { 0xff };
+    let mut pos = start;
+    while pos <= end {
+        // skip whole bytes that cannot contain the bit
+        if pos.is_multiple_of(8) && pos + 7 <= end && bytes[pos / 8] == skip {
+            pos += 8;
+            continue;
+        }
+        if getbit(bytes, pos) == bit {
+            return Some(pos);
+        }
+        pos += 1;
+    }
+    None
+}
+
+pub fn bitop(op: BitOperator, sources: &[Vec<u8>]) -> Vec<u8> {
+    let len = sources.iter().map(|s| s.len()).max().unwrap_or(0);
+    let byte = |src: &Vec<u8>, i: usize| src.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let mut iter = sources.iter().map(|s| byte(s, i));
+            let first = iter.next().unwrap_or(0);
+            match op {
+                BitOperator::And => iter.fold(first, |acc, b| acc & b),
+                BitOperator::Or => iter.fold(first, |acc, b| acc | b),
+                BitOperator::Xor => iter.fold(first, |acc, b| acc ^ b),
+                BitOperator::Not => !first,
+                BitOperator::Diff => first & !iter.fold(0, |acc, b| acc | b),
+            }
+        })
+        .collect()
+}
+
+impl BitFieldType {
+    pub fn min(&self) -> i128 {
+        if self.signed {
+            -(1i128 << (self.bits - 1))
+        } else {
+            0
+        }
+    }
+
+    pub fn max(&self) -> i128 {
+        if self.signed {
+            (1i128 << (self.bits - 1)) - 1
+        } else {
+            (1i128 << self.bits) - 1
+        }
+    }
+}
+
+pub fn bitfield_get(bytes: &[u8], offset: usize, ty: BitFieldType) -> i64 {
+    let mut value: u64 = 0;
+    for i in 0..ty.bits as usize {
+        value = (value << 1) | getbit(bytes, offset + i) as u64;
+    }
+    if ty.signed && ty.bits < 64 && value & (1 << (ty.bits - 1)) != 0 {
+        value |= u64::MAX << ty.bits;
+    }
+    value as i64
+}
+
+pub fn bitfield_set(bytes: &mut Vec<u8>, offset: usize, ty: BitFieldType, value: i64) {
+    let value = value as u64;
+    for i in 0..ty.bits as usize {
+        let on = (value >> (ty.bits as usize - 1 - i)) & 1 == 1;
+        setbit(bytes, offset + i, on);
+    }
+}
+
+// fit `value` into the range of `ty` according to the overflow policy, None means FAIL
+pub fn bitfield_overflow(value: i128, ty: BitFieldType, overflow: Overflow) -> Option<i64> {
+    if value >= ty.min() && value <= ty.max() {
+        return Some(value as i64);
+    }
+    match overflow {
+        Overflow::Fail => None,
+        Overflow::Sat => Some(value.clamp(ty.min(), ty.max()) as i64),
+        Overflow::Wrap => {
+            let wrapped = value.rem_euclid(1i128 << ty.bits);
+            if ty.signed && wrapped > ty.max() {
+                Some((wrapped - (1i128 << ty.bits)) as i64)
+            } else {
+                Some(wrapped as i64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setbit_getbit() {
+        let mut bytes = Vec::new();
+        assert_eq!(setbit(&mut bytes, 7, true), 0);
+        assert_eq!(bytes, vec![0x01]);
+        assert_eq!(setbit(&mut bytes, 7, true), 1);
+        assert_eq!(setbit(&mut bytes, 16, true), 0);
+        assert_eq!(bytes, vec![0x01, 0x00, 0x80]);
+        assert_eq!(getbit(&bytes, 16), 1);
+        assert_eq!(getbit(&bytes, 100), 0);
+    }
+
+    #[test]
+    fn test_bitcount_bitpos() {
+        let bytes = b"foobar";
+        assert_eq!(bitcount(bytes, 0, 47), 26);
+        let (start, end) = resolve_range(1, 1, bytes.len()).unwrap();
+        assert_eq!(bitcount(bytes, start * 8, end * 8 + 7), 6);
+        assert_eq!(bitcount(bytes, 5, 30), 17);
+        assert_eq!(resolve_range(-2, -1, 6), Some((4, 5)));
+        assert_eq!(resolve_range(4, 2, 6), None);
+
+        let bytes = [0xff, 0xf0, 0x00];
+        assert_eq!(bitpos(&bytes, 0, 0, 23), Some(12));
+        assert_eq!(bitpos(&bytes, 1, 8, 23), Some(8));
+        assert_eq!(bitpos(&bytes, 1, 16, 23), None);
+    }
+
+    #[test]
+    fn test_bitop() {
+        let sources = vec![vec![0b1100_1100], vec![0b1010_1010, 0xff]];
+        assert_eq!(bitop(BitOperator::And, &sources), vec![0b1000_1000, 0x00]);
+        assert_eq!(bitop(BitOperator::Or, &sources), vec![0b1110_1110, 0xff]);
+        assert_eq!(bitop(BitOperator::Xor, &sources), vec![0b0110_0110, 0xff]);
+        assert_eq!(bitop(BitOperator::Diff, &sources), vec![0b0100_0100, 0x00]);
+        assert_eq!(bitop(BitOperator::Not, &sources[..1]), vec![0b0011_0011]);
+    }
+
+    #[test]
+    fn test_bitfield() {
+        let i8 = BitFieldType {
+            signed: true,
+            bits: 8,
+        };
+        let u4 = BitFieldType {
+            signed: false,
+            bits: 4,
+        };
+        let mut bytes = Vec::new();
+        bitfield_set(&mut bytes, 0, i8, -100);
+        assert_eq!(bitfield_get(&bytes, 0, i8), -100);
+        bitfield_set(&mut bytes, 3, u4, 15);
+        assert_eq!(bitfield_get(&bytes, 3, u4), 15);
+
+        assert_eq!(bitfield_overflow(128, i8, Overflow::Wrap), Some(-128));
+        assert_eq!(bitfield_overflow(128, i8, Overflow::Sat), Some(127));
+        assert_eq!(bitfield_overflow(-129, i8, Overflow::Sat), Some(-128));
+        assert_eq!(bitfield_overflow(16, u4, Overflow::Wrap), Some(0));
+        assert_eq!(bitfield_overflow(-1, u4, Overflow::Sat), Some(0));
+        assert_eq!(bitfield_overflow(16, u4, Overflow::Fail), None);
+
+        let i64 = BitFieldType {
+            signed: true,
+            bits: 64,
+        };
+        bitfield_set(&mut bytes, 5, i64, i64::MIN + 1);
+        assert_eq!(bitfield_get(&bytes, 5, i64), i64::MIN + 1);
+        assert_eq!(
+            bitfield_overflow(i64::MAX as i128 + 1, i64, Overflow::Wrap),
+            Some(i64::MIN)
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::oneshot;
+
+use super::{Backend, BackendError, Db};
+use crate::RespFrame;
+
+// the work of a blocked client: Some(reply) once it could be served, None to keep waiting
+pub type BlockedOp = Box<dyn Fn(&Db) -> Result<Option<RespFrame>, BackendError> + Send + Sync>;
+
+// clients blocked on a key of a database, in the order they blocked
+type Queues = HashMap<(usize, String), VecDeque<Arc<Waiter>>>;
+
+#[derive(Default)]
+pub struct Blocking {
+    queues: Mutex<Queues>,
+}
+
+struct Waiter {
+    db: usize,
+    keys: Vec<String>,
+    // a key the operation pushes to, whose own waiters may be served afterwards
+    pushes: Option<String>,
+    op: BlockedOp,
+    // taken by whoever finishes the wait: a push serving it, the timeout or the client leaving
+    reply: Mutex<Option<oneshot::Sender<RespFrame>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Attempt {
+    // served, or the client is gone
+    Finished,
+    // nothing to serve yet
+    Waiting,
+}
+
+// deregisters the waiter when the client stops waiting, also if the connection is dropped
+struct WaitGuard<'a> {
+    backend: &'a Backend,
+    waiter: &'a Arc<Waiter>,
+}
+
+impl Backend {
+    // run `op` against the selected database, parking the client until it produces a reply or
+    // `timeout` elapses (None waits forever). Clients blocked on the same key are served in the
+    // order they blocked, whenever `signal_ready` is called for it.
+    pub async fn block_on(
+        &self,
+        keys: Vec<String>,
+        timeout: Option<Duration>,
+        pushes: Option<String>,
+        op: BlockedOp,
+    ) -> Option<RespFrame> {
+        let (tx, mut rx) = oneshot::channel();
+        let waiter = Arc::new(Waiter {
+            db: self.session.db(),
+            keys,
+            pushes,
+            op,
+            reply: Mutex::new(Some(tx)),
+        });
+        // register before the first attempt so that a push in between cannot be missed
+        let queued = self.blocking.register(&waiter);
+        let guard = WaitGuard {
+            backend: self,
+            waiter: &waiter,
+        };
+        // the first attempt runs like any other command, not in the middle of an EXEC
+        let attempt = {
+            let _shared = self.shared_lock().await;
+            // clients that blocked earlier on the same keys get served before this one
+            for key in &queued {
+                self.signal_ready_at(waiter.db, key);
+            }
+            let attempt = self.try_serve(&waiter, false);
+            if attempt == Attempt::Finished {
+                if let Some(key) = &waiter.pushes {
+                    self.signal_ready_at(waiter.db, key);
+                }
+            }
+            attempt
+        };
+        if attempt == Attempt::Finished {
+            drop(guard);
+            return rx.await.ok();
+        }
+        let served = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, &mut rx).await.ok(),
+            None => Some((&mut rx).await),
+        };
+        let reply = match served {
+            Some(reply) => reply.ok(),
+            // timed out, unless a push served the client in the meantime
+            None => {
+                let pending = waiter.reply.lock().unwrap().take();
+                match pending {
+                    Some(_) => None,
+                    None => rx.await.ok(),
+                }
+            }
+        };
+        drop(guard);
+        reply
+    }
+
+    // serve the clients blocked on `key` of the selected database
+    pub fn signal_ready(&self, key: &str) {
+        self.signal_ready_at(self.session.db(), key);
+    }
+
+    pub fn signal_ready_at(&self, db: usize, key: &str) {
+        let mut ready = vec![(db, key.to_string())];
+        while let Some(key) = ready.pop() {
+            let mut queues = self.blocking.queues.lock().unwrap();
+            let waiters = match queues.get(&key) {
+                Some(queue) => queue.iter().cloned().collect::<Vec<_>>(),
+                None => continue,
+            };
+            for waiter in waiters {
+                match self.try_serve(&waiter, true) {
+                    Attempt::Finished => {
+                        Blocking::remove(&mut queues, &waiter);
+                        if let Some(pushes) = &waiter.pushes {
+                            ready.push((waiter.db, pushes.clone()));
+                        }
+                    }
+                    // later clients may still be served, like XREAD readers waiting for
+                    // other ids or pops of the type the key now holds
+                    Attempt::Waiting => (),
+                }
+            }
+        }
+    }
+
+    // serve the clients blocked on any key of database `db`, after its data was replaced
+    pub fn signal_db(&self, db: usize) {
+        let keys: Vec<String> = {
+            let queues = self.blocking.queues.lock().unwrap();
+            queues
+                .keys()
+                .filter(|(index, _)| *index == db)
+                .map(|(_, key)| key.clone())
+                .collect()
+        };
+        keys.iter().for_each(|key| self.signal_ready_at(db, key));
+    }
+
+    // the number of clients currently blocked, across all databases
+    pub fn blocked_clients(&self) -> usize {
+        self.blocking.blocked_clients()
+    }
+
+    // run the operation of a waiter unless it is gone already. A client that is `woken` keeps
+    // waiting when the key now holds another type, like Redis only serving matching types.
+    fn try_serve(&self, waiter: &Waiter, woken: bool) -> Attempt {
+        let mut reply = waiter.reply.lock().unwrap();
+        match reply.as_ref() {
+            Some(sender) if !sender.is_closed() => (),
+            _ => return Attempt::Finished,
+        }
+        let Ok(db) = self.db_at(waiter.db) else {
+            return Attempt::Finished;
+        };
+        let frame = match (waiter.op)(&db) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Attempt::Waiting,
+            Err(BackendError::WrongType) if woken => return Attempt::Waiting,
+            Err(e) => e.into(),
+        };
+        if let Some(sender) = reply.take() {
+            let _ = sender.send(frame);
+        }
+        Attempt::Finished
+    }
+}
+
+impl Blocking {
+    fn blocked_clients(&self) -> usize {
+        let queues = self.queues.lock().unwrap();
+        let mut waiters: Vec<_> = queues.values().flatten().map(Arc::as_ptr).collect();
+        waiters.sort();
+        waiters.dedup();
+        waiters.len()
+    }
+
+    // queue the client on each of its keys, returning the keys other clients wait on already
+    fn register(&self, waiter: &Arc<Waiter>) -> Vec<String> {
+        let mut queues = self.queues.lock().unwrap();
+        let mut queued = Vec::new();
+        for (i, key) in waiter.keys.iter().enumerate() {
+            // a key given twice only queues the client once
+            if waiter.keys[..i].contains(key) {
+                continue;
+            }
+            let queue = queues.entry((waiter.db, key.clone())).or_default();
+            if !queue.is_empty() {
+                queued.push(key.clone());
+            }
+            queue.push_back(waiter.clone());
+        }
+        queued
+    }
+
+    fn remove(queues: &mut Queues, waiter: &Arc<Waiter>) {
+        for key in &waiter.keys {
+            let key = (waiter.db, key.clone());
+            if let Some(queue) = queues.get_mut(&key) {
+                queue.retain(|w| !Arc::ptr_eq(w, waiter));
+                if queue.is_empty() {
+                    queues.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Blocking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blocking")
+            .field("blocked_clients", &self.blocked_clients())
+            .finish()
+    }
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        self.waiter.reply.lock().unwrap().take();
+        let mut queues = self.backend.blocking.queues.lock().unwrap();
+        Blocking::remove(&mut queues, self.waiter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, ListEnd};
+
+    fn pop_op(key: &str) -> BlockedOp {
+        let key = key.to_string();
+        Box::new(move |db| {
+            Ok(db
+                .pop(&key, ListEnd::Left, 1)?
+                .and_then(|values| values.into_iter().next()))
+        })
+    }
+
+    fn push(backend: &Backend, key: &str, value: &str) {
+        backend
+            .db()
+            .push(
+                key.to_string(),
+                ListEnd::Right,
+                vec![BulkString::new(value).into()],
+                true,
+            )
+            .unwrap();
+        backend.signal_ready(key);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_served_immediately() {
+        let backend = Backend::new();
+        push(&backend, "list", "a");
+        let reply = backend
+            .block_on(vec!["list".to_string()], None, None, pop_op("list"))
+            .await;
+        assert_eq!(reply, Some(BulkString::new("a").into()));
+        assert_eq!(backend.blocked_clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_timeout() {
+        let backend = Backend::new();
+        let reply = backend
+            .block_on(
+                vec!["list".to_string()],
+                Some(Duration::from_millis(10)),
+                None,
+                pop_op("list"),
+            )
+            .await;
+        assert_eq!(reply, None);
+        assert_eq!(backend.blocked_clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_served_in_order() {
+        let backend = Backend::new();
+        let first = backend.connect();
+        let second = backend.connect();
+        let first = tokio::spawn(async move {
+            first
+                .block_on(vec!["list".to_string()], None, None, pop_op("list"))
+                .await
+        });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        let second = tokio::spawn(async move {
+            second
+                .block_on(vec!["list".to_string()], None, None, pop_op("list"))
+                .await
+        });
+        while backend.blocked_clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+        push(&backend, "list", "a");
+        push(&backend, "list", "b");
+        assert_eq!(first.await.unwrap(), Some(BulkString::new("a").into()));
+        assert_eq!(second.await.unwrap(), Some(BulkString::new("b").into()));
+        assert_eq!(backend.blocked_clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_queues_behind_waiters() {
+        let backend = Backend::new();
+        let first = backend.connect();
+        let first = tokio::spawn(async move {
+            first
+                .block_on(vec!["list".to_string()], None, None, pop_op("list"))
+                .await
+        });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        // an element pushed but not signalled yet goes to the client that blocked first
+        backend
+            .db()
+            .push(
+                "list".to_string(),
+                ListEnd::Right,
+                vec![BulkString::new("a").into()],
+                true,
+            )
+            .unwrap();
+        let reply = backend
+            .block_on(
+                vec!["list".to_string()],
+                Some(Duration::from_millis(10)),
+                None,
+                pop_op("list"),
+            )
+            .await;
+        assert_eq!(reply, None);
+        assert_eq!(first.await.unwrap(), Some(BulkString::new("a").into()));
+        assert_eq!(backend.blocked_clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_abandoned() {
+        let backend = Backend::new();
+        let waiting = backend.connect();
+        let task = tokio::spawn(async move {
+            waiting
+                .block_on(vec!["list".to_string()], None, None, pop_op("list"))
+                .await
+        });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        task.abort();
+        let _ = task.await;
+        assert_eq!(backend.blocked_clients(), 0);
+        // nothing was consumed on behalf of the client that left
+        push(&backend, "list", "a");
+        assert_eq!(backend.db().llen("list"), Ok(1));
+    }
+}
@@ -0,0 +1,559 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use dashmap::{
+    mapref::one::{Ref, RefMut},
+    DashMap, DashSet,
+};
+use rand::Rng;
+
+use super::{
+    glob_match, lazy_free, string_bytes, BackendError, Entry, Invalidations, NotifyFlags,
+    ScanIndex, Value,
+};
+use crate::{BulkString, RespFrame};
+
+#[derive(Debug, Default)]
+pub struct Db {
+    map: DashMap<String, Entry>,
+    // the keys in the order SCAN walks them, see `insert` and `remove_if`
+    index: ScanIndex,
+    // the notify-keyspace-events setting, shared by every database of the server
+    notify_flags: Arc<AtomicU32>,
+    // keyspace events waiting to be published, the database does not know its index
+    events: Mutex<Vec<(&'static str, String)>>,
+    // the dirty flags of the transactions watching a key
+    watchers: DashMap<String, Vec<Arc<AtomicBool>>>,
+    // the reads and modifications of keys, for the clients tracking them
+    invalidations: Arc<Invalidations>,
+    // the keys that may have a ttl or hash fields with one, see `active_expire_cycle`
+    volatile: DashSet<String>,
+    // the shard of `volatile` the next active expire cycle looks at
+    expire_cursor: AtomicUsize,
+}
+
+impl Db {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_notify(notify_flags: Arc<AtomicU32>, invalidations: Arc<Invalidations>) -> Self {
+        Db {
+            notify_flags,
+            invalidations,
+            ..Default::default()
+        }
+    }
+
+    // record a keyspace event, if its class is enabled. Every modification of a key reports
+    // an event, so this is also where the transactions watching it and the clients tracking
+    // it learn about it.
+    pub fn notify(&self, class: NotifyFlags, event: &'static str, key: &str) {
+        if class != NotifyFlags::KEY_MISS {
+            self.touch_watched(key);
+            self.invalidations.record_write(key);
+        }
+        let flags = NotifyFlags::from_bits(self.notify_flags.load(Ordering::Relaxed));
+        if flags.wants(class) {
+            self.events.lock().unwrap().push((event, key.to_string()));
+        }
+    }
+
+    // record the event of a removal from a collection, followed by a `del` when that left
+    // the key empty
+    pub(super) fn notify_removal(&self, class: NotifyFlags, event: &'static str, key: &str) {
+        self.notify(class, event, key);
+        if !self.contains(key) {
+            self.notify(NotifyFlags::GENERIC, "del", key);
+        }
+    }
+
+    // set `dirty` once `key` is modified
+    pub fn watch(&self, key: &str, dirty: &Arc<AtomicBool>) {
+        self.watchers
+            .entry(key.to_string())
+            .or_default()
+            .push(dirty.clone());
+    }
+
+    pub fn unwatch(&self, key: &str, dirty: &Arc<AtomicBool>) {
+        self.watchers.remove_if_mut(key, |_, watchers| {
+            watchers.retain(|w| !Arc::ptr_eq(w, dirty));
+            watchers.is_empty()
+        });
+    }
+
+    // flag the transactions watching `key`, they are released at the same time
+    pub(super) fn touch_watched(&self, key: &str) {
+        if let Some((_, watchers)) = self.watchers.remove(key) {
+            watchers
+                .iter()
+                .for_each(|dirty| dirty.store(true, Ordering::Relaxed));
+        }
+    }
+
+    pub fn take_events(&self) -> Vec<(&'static str, String)> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<RespFrame>, BackendError> {
+        match self.lookup_read(key) {
+            Some(entry) => match &entry.value {
+                Value::String(v) => Ok(Some(v.clone())),
+                _ => Err(BackendError::WrongType),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn set(&self, key: String, value: RespFrame) {
+        self.purge_expired(&key);
+        if !self.map.contains_key(&key) {
+            self.notify(NotifyFlags::NEW, "new", &key);
+        }
+        self.notify(NotifyFlags::STRING, "set", &key);
+        self.insert(key, Entry::new(Value::String(value)));
+    }
+
+    // run `f` on the raw bytes of the string stored at `key`
+    pub fn read_bytes<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&[u8]) -> R,
+    ) -> Result<Option<R>, BackendError> {
+        match self.lookup_read(key) {
+            Some(entry) => match &entry.value {
+                Value::String(v) => Ok(Some(f(&string_bytes(v)))),
+                _ => Err(BackendError::WrongType),
+            },
+            None => Ok(None),
+        }
+    }
+
+    // run `f` on the raw bytes of the string stored at `key`. A missing key is created as an
+    // empty string first, and removed again if the string is still empty afterwards.
+    pub fn update_bytes<R>(
+        &self,
+        key: String,
+        f: impl FnOnce(&mut Vec<u8>) -> R,
+    ) -> Result<R, BackendError> {
+        let mut entry = self.lookup_or_insert(key.clone(), || {
+            Value::String(BulkString::new(Vec::new()).into())
+        });
+        let (result, empty) = match &mut entry.value {
+            Value::String(frame) => {
+                let mut bytes = match &mut *frame {
+                    RespFrame::BulkString(s) => std::mem::take(&mut s.0),
+                    other => string_bytes(other).into_owned(),
+                };
+                let result = f(&mut bytes);
+                let empty = bytes.is_empty();
+                *frame = BulkString::new(bytes).into();
+                (result, empty)
+            }
+            _ => return Err(BackendError::WrongType),
+        };
+        drop(entry);
+        if empty {
+            self.remove_if(&key, |entry| {
+                matches!(&entry.value, Value::String(RespFrame::BulkString(s)) if s.is_empty())
+            });
+        }
+        Ok(result)
+    }
+
+    pub fn del(&self, keys: &[String]) -> i64 {
+        let mut count = 0;
+        for key in keys {
+            if self.remove(key).is_some() {
+                count += 1;
+                self.notify(NotifyFlags::GENERIC, "del", key);
+            }
+        }
+        count
+    }
+
+    // remove the keys from the keyspace right away, but leave the actual deallocation of
+    // large values to a background task so that the request path is not blocked
+    pub fn unlink(&self, keys: &[String]) -> i64 {
+        let mut count = 0;
+        for key in keys {
+            if let Some(entry) = self.remove(key) {
+                count += 1;
+                self.notify(NotifyFlags::GENERIC, "del", key);
+                lazy_free(entry);
+            }
+        }
+        count
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    pub fn exists(&self, keys: &[String]) -> i64 {
+        keys.iter().filter(|key| self.lookup(key).is_some()).count() as i64
+    }
+
+    pub fn key_type(&self, key: &str) -> &'static str {
+        self.lookup(key)
+            .map(|entry| entry.value.type_name())
+            .unwrap_or("none")
+    }
+
+    // move the value (and its ttl) of `src` to `dst`. When `nx` is set the rename is
+    // skipped if `dst` already exists, and Ok(false) is returned.
+    pub fn rename(&self, src: &str, dst: &str, nx: bool) -> Result<bool, BackendError> {
+        if self.lookup(src).is_none() {
+            return Err(BackendError::NoSuchKey);
+        }
+        if src == dst {
+            return Ok(!nx);
+        }
+        if nx && self.lookup(dst).is_some() {
+            return Ok(false);
+        }
+        match self.remove_if(src, |_| true) {
+            Some(entry) => {
+                if let Some(old) = self.insert(dst.to_string(), entry) {
+                    lazy_free(old);
+                }
+                self.notify(NotifyFlags::GENERIC, "rename_from", src);
+                self.notify(NotifyFlags::GENERIC, "rename_to", dst);
+                Ok(true)
+            }
+            None => Err(BackendError::NoSuchKey),
+        }
+    }
+
+    // a detached copy of the value and ttl stored at `key`
+    pub fn get_entry(&self, key: &str) -> Option<Entry> {
+        self.lookup(key).map(|entry| (*entry).clone())
+    }
+
+    pub fn take_entry(&self, key: &str) -> Option<Entry> {
+        self.remove(key)
+    }
+
+    // store `entry` at `key`, unless the key exists and `replace` is not set
+    pub fn put_entry(&self, key: String, entry: Entry, replace: bool) -> bool {
+        self.purge_expired(&key);
+        match self.map.entry(key) {
+            dashmap::mapref::entry::Entry::Occupied(mut occupied) if replace => {
+                self.mark_volatile(occupied.key(), &entry);
+                lazy_free(occupied.insert(entry));
+                true
+            }
+            dashmap::mapref::entry::Entry::Occupied(_) => false,
+            dashmap::mapref::entry::Entry::Vacant(vacant) => {
+                self.notify(NotifyFlags::NEW, "new", vacant.key());
+                self.index.insert(&self.map, vacant.key());
+                self.mark_volatile(vacant.key(), &entry);
+                vacant.insert(entry);
+                true
+            }
+        }
+    }
+
+    pub fn touch(&self, keys: &[String]) -> i64 {
+        self.exists(keys)
+    }
+
+    // set the absolute expire time of a key in unix milliseconds, None to persist it
+    pub fn expire_at(&self, key: &str, at: Option<u64>) -> bool {
+        self.purge_expired(key);
+        match self.map.get_mut(key) {
+            Some(mut entry) => {
+                entry.expire_at = at;
+                self.mark_volatile(key, &entry);
+                drop(entry);
+                let event = if at.is_some() { "expire" } else { "persist" };
+                self.notify(NotifyFlags::GENERIC, event, key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn keys(&self, pattern: &str) -> Vec<String> {
+        let mut keys = Vec::new();
+        self.visit_keys(&mut |key| {
+            if glob_match(pattern.as_bytes(), key.as_bytes(), false) {
+                keys.push(key.to_string());
+            }
+        });
+        keys
+    }
+
+    // MATCH and TYPE are applied after the page has been selected, so like redis a call may
+    // return fewer keys than `count`, or none at all, while the iteration is not finished yet
+    pub fn scan(
+        &self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+        key_type: Option<&str>,
+    ) -> (u64, Vec<String>) {
+        let (next, keys) = self.index.page(&self.map, cursor, count);
+        let keys = keys
+            .into_iter()
+            .filter(|key| self.map.get(key).is_some_and(|entry| !entry.is_expired()))
+            .filter(|key| pattern.is_none_or(|p| glob_match(p.as_bytes(), key.as_bytes(), false)))
+            .filter(|key| key_type.is_none_or(|t| self.key_type(key).eq_ignore_ascii_case(t)))
+            .collect();
+        (next, keys)
+    }
+
+    // expired keys not purged yet are not counted, only the volatile keys can be among them
+    pub fn dbsize(&self) -> i64 {
+        let volatile = self
+            .volatile
+            .iter()
+            .map(|key| key.key().clone())
+            .collect::<Vec<_>>();
+        volatile.iter().for_each(|key| self.purge_expired(key));
+        self.map.len() as i64
+    }
+
+    // a random entry of a random shard, the first shard holding keys from a random one on
+    pub fn random_key(&self) -> Option<String> {
+        let shards = self.map.shards();
+        let mut rng = rand::thread_rng();
+        // expired keys are purged lazily, retry a few times before giving up on them
+        for _ in 0..16 {
+            let start = rng.gen_range(0..shards.len());
+            let (key, expired) = (0..shards.len()).find_map(|i| {
+                let shard = shards[(start + i) % shards.len()].read();
+                let idx = rng.gen_range(0..shard.len().max(1));
+                let (key, entry) = shard.iter().nth(idx)?;
+                Some((key.clone(), entry.get().is_expired()))
+            })?;
+            if !expired {
+                return Some(key);
+            }
+            self.purge_expired(&key);
+        }
+        None
+    }
+
+    pub fn expire_time(&self, key: &str) -> Option<u64> {
+        self.lookup(key).and_then(|entry| entry.expire_at())
+    }
+
+    fn remove(&self, key: &str) -> Option<Entry> {
+        match self.remove_if(key, |_| true) {
+            Some(entry) if !entry.is_expired() => Some(entry),
+            Some(_) => {
+                self.notify(NotifyFlags::EXPIRED, "expired", key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn visit_keys(&self, f: &mut dyn FnMut(&str)) {
+        for entry in self.map.iter() {
+            if !entry.is_expired() {
+                f(entry.key());
+            }
+        }
+    }
+
+    fn purge_expired(&self, key: &str) {
+        if self.remove_if(key, |entry| entry.is_expired()).is_some() {
+            self.notify(NotifyFlags::EXPIRED, "expired", key);
+        }
+    }
+
+    // remember that `entry`, about to be stored at `key` or stored there already, has a ttl.
+    // Called while the key is locked, so that `active_expire_cycle` cannot miss it.
+    fn mark_volatile(&self, key: &str, entry: &Entry) {
+        if entry.is_volatile() && !self.volatile.contains(key) {
+            self.volatile.insert(key.to_string());
+        }
+    }
+
+    // remove the expired keys and hash fields of one shard of the volatile keys, without
+    // waiting for a client to access them. Each call moves on to the next shard, returns the
+    // number of keys and hashes that had expired data.
+    pub fn active_expire_cycle(&self) -> usize {
+        let shards = self.volatile.shards();
+        let shard = self.expire_cursor.fetch_add(1, Ordering::Relaxed) % shards.len();
+        let keys = shards[shard].read().keys().cloned().collect::<Vec<_>>();
+        let mut expired = 0;
+        for key in keys {
+            if self.remove_if(&key, |entry| entry.is_expired()).is_some() {
+                self.notify(NotifyFlags::EXPIRED, "expired", &key);
+                expired += 1;
+            }
+            let volatile = self.map.get(&key).is_some_and(|entry| {
+                if let Value::Hash(hash) = &entry.value {
+                    if hash.purge_expired() {
+                        self.notify(NotifyFlags::HASH, "hexpired", &key);
+                        expired += 1;
+                    }
+                }
+                entry.is_volatile()
+            });
+            // the last fields may have expired since the key was checked
+            if self
+                .remove_if(&key, |entry| entry.value.is_empty())
+                .is_some()
+            {
+                self.notify(NotifyFlags::GENERIC, "del", &key);
+            }
+            // a ttl set in between marks the key again while it is locked, which the second
+            // look sees
+            if !volatile {
+                self.volatile.remove(&key);
+                if let Some(entry) = self.map.get(&key) {
+                    self.mark_volatile(&key, &entry);
+                }
+            }
+        }
+        expired
+    }
+
+    fn lookup(&self, key: &str) -> Option<Ref<'_, String, Entry>> {
+        self.invalidations.record_read(key);
+        self.purge_expired(key);
+        self.map.get(key)
+    }
+
+    // a lookup by a read command, which reports a key miss
+    fn lookup_read(&self, key: &str) -> Option<Ref<'_, String, Entry>> {
+        let entry = self.lookup(key);
+        if entry.is_none() {
+            self.notify(NotifyFlags::KEY_MISS, "keymiss", key);
+        }
+        entry
+    }
+
+    // run `f` on the value stored at `key`, if any
+    pub(super) fn read<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&Value) -> Result<R, BackendError>,
+    ) -> Result<Option<R>, BackendError> {
+        match self.lookup_read(key) {
+            Some(entry) => f(&entry.value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // run `f` on the values stored at `keys` together, None for a missing key. The keys stay
+    // read locked while `f` runs, so it sees all of them in one state.
+    pub(super) fn read_many<R>(
+        &self,
+        keys: &[String],
+        f: impl FnOnce(&[Option<&Value>]) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        // removing an expired key takes a write lock, which must not wait for our own reads
+        for key in keys {
+            self.invalidations.record_read(key);
+            self.purge_expired(key);
+        }
+        let entries = keys
+            .iter()
+            .map(|key| self.map.get(key).filter(|entry| !entry.is_expired()))
+            .collect::<Vec<_>>();
+        for (key, entry) in keys.iter().zip(&entries) {
+            if entry.is_none() {
+                self.notify(NotifyFlags::KEY_MISS, "keymiss", key);
+            }
+        }
+        let values = entries
+            .iter()
+            .map(|entry| entry.as_ref().map(|entry| &entry.value))
+            .collect::<Vec<_>>();
+        f(&values)
+    }
+
+    // run `f` on the value stored at `key`, creating it with `default` if the key does not
+    // exist. Collections left empty by `f` are removed from the keyspace.
+    pub(super) fn write<R>(
+        &self,
+        key: String,
+        default: impl FnOnce() -> Value,
+        f: impl FnOnce(&mut Value) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        let mut entry = self.lookup_or_insert(key.clone(), default);
+        let result = f(&mut entry.value);
+        self.mark_volatile(&key, &entry);
+        let empty = entry.value.is_empty();
+        drop(entry);
+        if empty {
+            self.remove_if(&key, |entry| entry.value.is_empty());
+        }
+        result
+    }
+
+    // like `write`, but leaves missing keys alone
+    pub(super) fn modify<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut Value) -> Result<R, BackendError>,
+    ) -> Result<Option<R>, BackendError> {
+        self.purge_expired(key);
+        let mut entry = match self.map.get_mut(key) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let result = f(&mut entry.value);
+        self.mark_volatile(key, &entry);
+        let empty = entry.value.is_empty();
+        drop(entry);
+        if empty {
+            self.remove_if(key, |entry| entry.value.is_empty());
+        }
+        result.map(Some)
+    }
+
+    // every key enters and leaves the map through `insert`, `remove_if` and the vacant entries
+    // of `put_entry` and `lookup_or_insert`, which update the scan index while the shard of the
+    // key is still locked
+    fn insert(&self, key: String, entry: Entry) -> Option<Entry> {
+        match self.map.entry(key) {
+            dashmap::mapref::entry::Entry::Occupied(mut occupied) => {
+                self.mark_volatile(occupied.key(), &entry);
+                Some(occupied.insert(entry))
+            }
+            dashmap::mapref::entry::Entry::Vacant(vacant) => {
+                self.index.insert(&self.map, vacant.key());
+                self.mark_volatile(vacant.key(), &entry);
+                vacant.insert(entry);
+                None
+            }
+        }
+    }
+
+    fn remove_if(&self, key: &str, f: impl FnOnce(&Entry) -> bool) -> Option<Entry> {
+        self.map
+            .remove_if(key, |key, entry| {
+                let remove = f(entry);
+                if remove {
+                    self.index.remove(&self.map, key);
+                }
+                remove
+            })
+            .map(|(_, entry)| entry)
+    }
+
+    fn lookup_or_insert(
+        &self,
+        key: String,
+        default: impl FnOnce() -> Value,
+    ) -> RefMut<'_, String, Entry> {
+        self.purge_expired(&key);
+        match self.map.entry(key) {
+            dashmap::mapref::entry::Entry::Occupied(occupied) => occupied.into_ref(),
+            dashmap::mapref::entry::Entry::Vacant(vacant) => {
+                self.notify(NotifyFlags::NEW, "new", vacant.key());
+                self.index.insert(&self.map, vacant.key());
+                vacant.insert(Entry::new(default()))
+            }
+        }
+    }
+}
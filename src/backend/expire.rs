@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use super::Backend;
+
+// how often the databases are swept for expired keys, the 10 hz of redis
+pub const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Backend {
+    // Remove expired keys and hash fields in the background, so that their `expired` and
+    // `hexpired` events fire even when no client touches them again. Every tick looks at one
+    // shard of the volatile keys of each database, the next tick at the next shard. The
+    // sweep waits for EXEC and scripts like any command, keys do not expire under them.
+    pub async fn active_expire(self) {
+        let mut interval = tokio::time::interval(ACTIVE_EXPIRE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let shared = self.shared_lock().await;
+            for db in &self.dbs {
+                let db = db.read().unwrap().clone();
+                db.active_expire_cycle();
+            }
+            drop(shared);
+            self.publish_keyspace_events();
+            self.send_invalidations();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{now_ms, BulkString, NotifyFlags, RespFrame, RespPush, SubscriptionKind};
+
+    #[test]
+    fn test_active_expire_cycle() {
+        let server = Backend::new();
+        let db = server.db();
+        server.set_notify_keyspace_events(NotifyFlags::parse("Eh$x").unwrap());
+        db.set("gone".to_string(), BulkString::new("v").into());
+        db.set("kept".to_string(), BulkString::new("v").into());
+        db.set("persisted".to_string(), BulkString::new("v").into());
+        db.expire_at("gone", Some(now_ms() + 10));
+        db.expire_at("kept", Some(now_ms() + 60_000));
+        db.expire_at("persisted", Some(now_ms() + 10));
+        db.expire_at("persisted", None);
+        db.hset(
+            "hash".to_string(),
+            vec![
+                ("a".to_string(), BulkString::new("1").into()),
+                ("b".to_string(), BulkString::new("2").into()),
+            ],
+        )
+        .unwrap();
+        db.hexpire("hash", &["a".to_string()], now_ms() + 10, None)
+            .unwrap();
+        db.take_events();
+        std::thread::sleep(Duration::from_millis(20));
+
+        // one cycle per shard sweeps every volatile key once
+        let shards = dashmap::DashMap::<(), ()>::new().shards().len();
+        let expired = (0..shards).map(|_| db.active_expire_cycle()).sum::<usize>();
+        assert_eq!(expired, 2);
+        let mut events = db.take_events();
+        events.sort();
+        assert_eq!(
+            events,
+            vec![
+                ("expired", "gone".to_string()),
+                ("hexpired", "hash".to_string())
+            ]
+        );
+        assert_eq!(db.dbsize(), 3);
+        assert_eq!(db.hlen("hash"), Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_active_expire_publishes_events() {
+        let server = Backend::new();
+        let subscriber = server.connect();
+        let mut pushes = subscriber.open_pushes();
+        subscriber.subscribe(
+            SubscriptionKind::Channel,
+            &["__keyevent@0__:expired".to_string()],
+        );
+        pushes.recv().await;
+        server.set_notify_keyspace_events(NotifyFlags::parse("Ex").unwrap());
+        server
+            .db()
+            .set("k".to_string(), BulkString::new("v").into());
+        server.db().expire_at("k", Some(now_ms() + 10));
+
+        let sweeper = tokio::spawn(server.clone().active_expire());
+        let message = tokio::time::timeout(Duration::from_secs(30), pushes.recv()).await;
+        sweeper.abort();
+        let expected: RespFrame = RespPush::new(vec![
+            BulkString::new("message").into(),
+            BulkString::new("__keyevent@0__:expired").into(),
+            BulkString::new("k").into(),
+        ])
+        .into();
+        assert_eq!(message.ok().flatten(), Some(expected));
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    sync::{Mutex, MutexGuard},
+};
+
+use mlua::{Lua, RegistryKey};
+
+use super::{script::new_lua, Backend, BackendError};
+
+// the function libraries and the interpreter holding their code, apart from the one of EVAL
+pub struct Functions {
+    lua: Mutex<Lua>,
+    libraries: Mutex<BTreeMap<String, Library>>,
+}
+
+// what happens to the libraries already loaded when others are added
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibraryPolicy {
+    // fail when a library of the same name exists
+    Append,
+    Replace,
+    // drop all of them first
+    Flush,
+}
+
+#[derive(Debug)]
+pub struct Library {
+    pub name: String,
+    pub code: String,
+    pub functions: Vec<LibraryFunction>,
+}
+
+#[derive(Debug)]
+pub struct LibraryFunction {
+    pub name: String,
+    pub description: Option<String>,
+    pub flags: Vec<String>,
+    // the Lua function, kept in the registry of the functions interpreter
+    pub callback: RegistryKey,
+}
+
+impl Backend {
+    // the interpreter of the functions, held while one is loaded or called
+    pub fn function_lua(&self) -> MutexGuard<'_, Lua> {
+        self.functions.lua.lock().unwrap()
+    }
+
+    pub fn libraries(&self) -> MutexGuard<'_, BTreeMap<String, Library>> {
+        self.functions.libraries.lock().unwrap()
+    }
+
+    // add libraries, checking all of them before any is added. Their functions may not share
+    // a name with those of another library.
+    pub fn add_libraries(
+        &self,
+        batch: Vec<Library>,
+        policy: LibraryPolicy,
+    ) -> Result<(), BackendError> {
+        let mut libraries = self.libraries();
+        // with FLUSH the batch is checked against no libraries at all, the existing ones are
+        // only dropped once it passed
+        let flush = policy == LibraryPolicy::Flush;
+        {
+            let mut names = HashSet::new();
+            for library in &batch {
+                let exists =
+                    policy == LibraryPolicy::Append && libraries.contains_key(&library.name);
+                if exists || !names.insert(library.name.as_str()) {
+                    return Err(BackendError::LibraryExists(library.name.clone()));
+                }
+            }
+            // the functions of the libraries that stay
+            let mut taken = libraries
+                .values()
+                .filter(|library| !flush && !names.contains(library.name.as_str()))
+                .flat_map(|library| &library.functions)
+                .map(|function| function.name.as_str())
+                .collect::<HashSet<_>>();
+            let added = batch.iter().flat_map(|library| &library.functions);
+            for function in added {
+                if !taken.insert(function.name.as_str()) {
+                    return Err(BackendError::FunctionExists(function.name.clone()));
+                }
+            }
+        }
+        if flush {
+            libraries.clear();
+        }
+        for library in batch {
+            libraries.insert(library.name.clone(), library);
+        }
+        Ok(())
+    }
+
+    pub fn delete_library(&self, name: &str) -> Result<(), BackendError> {
+        match self.libraries().remove(name) {
+            Some(_) => Ok(()),
+            None => Err(BackendError::LibraryNotFound),
+        }
+    }
+
+    // drop every library, together with the interpreter
+    pub fn flush_functions(&self) {
+        let mut lua = self.function_lua();
+        self.libraries().clear();
+        *lua = new_lua();
+    }
+}
+
+impl Library {
+    pub fn function(&self, name: &str) -> Option<&LibraryFunction> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+impl LibraryFunction {
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+impl Default for Functions {
+    fn default() -> Self {
+        Functions {
+            lua: Mutex::new(new_lua()),
+            libraries: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl fmt::Debug for Functions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Functions")
+            .field("libraries", &self.libraries.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(backend: &Backend, name: &str, functions: &[&str]) -> Library {
+        let lua = backend.function_lua();
+        Library {
+            name: name.to_string(),
+            code: String::new(),
+            functions: functions
+                .iter()
+                .map(|name| LibraryFunction {
+                    name: name.to_string(),
+                    description: None,
+                    flags: vec![],
+                    callback: lua.create_registry_value(true).unwrap(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_libraries() {
+        let server = Backend::new();
+        let lib = library(&server, "lib", &["a", "b"]);
+        assert_eq!(
+            server.add_libraries(vec![lib], LibraryPolicy::Append),
+            Ok(())
+        );
+        let lib = library(&server, "lib", &["a"]);
+        assert_eq!(
+            server.add_libraries(vec![lib], LibraryPolicy::Append),
+            Err(BackendError::LibraryExists("lib".to_string()))
+        );
+        let other = library(&server, "other", &["b"]);
+        assert_eq!(
+            server.add_libraries(vec![other], LibraryPolicy::Append),
+            Err(BackendError::FunctionExists("b".to_string()))
+        );
+        // a replaced library gives up its function names
+        let lib = library(&server, "lib", &["a"]);
+        assert_eq!(
+            server.add_libraries(vec![lib], LibraryPolicy::Replace),
+            Ok(())
+        );
+        let other = library(&server, "other", &["b"]);
+        assert_eq!(
+            server.add_libraries(vec![other], LibraryPolicy::Append),
+            Ok(())
+        );
+
+        // nothing is added when one of the libraries conflicts
+        let batch = vec![
+            library(&server, "third", &["c"]),
+            library(&server, "fourth", &["c"]),
+        ];
+        assert_eq!(
+            server.add_libraries(batch, LibraryPolicy::Append),
+            Err(BackendError::FunctionExists("c".to_string()))
+        );
+        assert_eq!(server.libraries().len(), 2);
+        // a FLUSH batch that fails leaves the loaded libraries in place
+        let batch = vec![
+            library(&server, "third", &["c"]),
+            library(&server, "third", &["d"]),
+        ];
+        assert_eq!(
+            server.add_libraries(batch, LibraryPolicy::Flush),
+            Err(BackendError::LibraryExists("third".to_string()))
+        );
+        assert_eq!(
+            server.libraries().keys().collect::<Vec<_>>(),
+            vec!["lib", "other"]
+        );
+        let batch = vec![library(&server, "third", &["a"])];
+        assert_eq!(server.add_libraries(batch, LibraryPolicy::Flush), Ok(()));
+        assert_eq!(server.libraries().keys().collect::<Vec<_>>(), vec!["third"]);
+
+        assert_eq!(server.delete_library("third"), Ok(()));
+        assert_eq!(
+            server.delete_library("third"),
+            Err(BackendError::LibraryNotFound)
+        );
+        server
+            .add_libraries(vec![library(&server, "lib", &["a"])], LibraryPolicy::Append)
+            .unwrap();
+        server.flush_functions();
+        assert!(server.libraries().is_empty());
+    }
+}
@@ -0,0 +1,640 @@
+use super::{BackendError, Db, Entry, NotifyFlags, ScoreBound, ScoreRange, Value, ZSet};
+
+// the geohash and distance math of Redis, so that scores and search results agree with it
+const GEO_STEP_MAX: u8 = 26;
+const GEO_LAT_MIN: f64 = -85.05112878;
+const GEO_LAT_MAX: f64 = 85.05112878;
+const GEO_LONG_MIN: f64 = -180.0;
+const GEO_LONG_MAX: f64 = 180.0;
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+const GEO_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+const WGS84_LONG: (f64, f64) = (GEO_LONG_MIN, GEO_LONG_MAX);
+const WGS84_LAT: (f64, f64) = (GEO_LAT_MIN, GEO_LAT_MAX);
+
+// the area searched by GEOSEARCH, in the unit of the search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    Member(String),
+    LonLat(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoOrder {
+    Asc,
+    Desc,
+}
+
+// what GEOSEARCH looks for. `unit` is the number of meters in the unit of the shape and of
+// the distances returned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoQuery {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    pub unit: f64,
+    pub order: Option<GeoOrder>,
+    pub count: Option<usize>,
+    pub any: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: String,
+    pub dist: f64,
+    pub hash: u64,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+// a geohash of `step` bits per coordinate, latitude bits at even and longitude bits at odd
+// positions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct HashBits {
+    bits: u64,
+    step: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    lon: (f64, f64),
+    lat: (f64, f64),
+}
+
+// the 52 bit geohash stored as the score of a member, None outside of the coordinates
+// that can be indexed
+pub fn geohash_score(lon: f64, lat: f64) -> Option<u64> {
+    encode(WGS84_LONG, WGS84_LAT, lon, lat, GEO_STEP_MAX).map(align52)
+}
+
+// the center of the area of a score
+pub fn geohash_decode(score: u64) -> (f64, f64) {
+    let area = decode(
+        WGS84_LONG,
+        WGS84_LAT,
+        HashBits {
+            bits: score,
+            step: GEO_STEP_MAX,
+        },
+    );
+    let lon = ((area.lon.0 + area.lon.1) / 2.0).clamp(GEO_LONG_MIN, GEO_LONG_MAX);
+    let lat = ((area.lat.0 + area.lat.1) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX);
+    (lon, lat)
+}
+
+// the distance in meters between two points on the earth
+pub fn geo_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let v = ((lon2.to_radians() - lon1.to_radians()) / 2.0).sin();
+    // the same longitude needs no trigonometry
+    if v == 0.0 {
+        return lat_distance(lat1, lat2);
+    }
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let a = u * u + lat1.cos() * lat2.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+impl Db {
+    pub fn geopos(
+        &self,
+        key: &str,
+        members: &[String],
+    ) -> Result<Vec<Option<(f64, f64)>>, BackendError> {
+        let positions = self.read_zset(key, |zset| {
+            members
+                .iter()
+                .map(|member| zset.score(member).map(|score| geohash_decode(score as u64)))
+                .collect()
+        })?;
+        Ok(positions.unwrap_or_else(|| vec![None; members.len()]))
+    }
+
+    // the distance in meters, None if either member is missing
+    pub fn geodist(&self, key: &str, from: &str, to: &str) -> Result<Option<f64>, BackendError> {
+        let dist = self.read_zset(key, |zset| {
+            let (lon1, lat1) = geohash_decode(zset.score(from)? as u64);
+            let (lon2, lat2) = geohash_decode(zset.score(to)? as u64);
+            Some(geo_distance(lon1, lat1, lon2, lat2))
+        })?;
+        Ok(dist.flatten())
+    }
+
+    // the standard 11 character geohash of each member
+    pub fn geohash(
+        &self,
+        key: &str,
+        members: &[String],
+    ) -> Result<Vec<Option<String>>, BackendError> {
+        let hashes = self.read_zset(key, |zset| {
+            members
+                .iter()
+                .map(|member| {
+                    zset.score(member)
+                        .map(|score| standard_geohash(score as u64))
+                })
+                .collect()
+        })?;
+        Ok(hashes.unwrap_or_else(|| vec![None; members.len()]))
+    }
+
+    pub fn geosearch(&self, key: &str, query: &GeoQuery) -> Result<Vec<GeoMatch>, BackendError> {
+        let matches = self.read_zset(key, |zset| query.search(zset))?;
+        Ok(matches.transpose()?.unwrap_or_default())
+    }
+
+    // store the members found at `dst` scored by geohash, or with `storedist` by distance.
+    // An empty result deletes it.
+    pub fn geosearchstore(
+        &self,
+        dst: String,
+        key: &str,
+        query: &GeoQuery,
+        storedist: bool,
+    ) -> Result<i64, BackendError> {
+        let matches = self.geosearch(key, query)?;
+        let len = matches.len() as i64;
+        if matches.is_empty() {
+            self.del(&[dst]);
+        } else {
+            let zset = matches
+                .into_iter()
+                .map(|m| {
+                    let score = if storedist { m.dist } else { m.hash as f64 };
+                    (m.member, score)
+                })
+                .collect::<ZSet>();
+            self.put_entry(dst.clone(), Entry::new(Value::ZSet(zset)), true);
+            self.notify(NotifyFlags::ZSET, "geosearchstore", &dst);
+        }
+        Ok(len)
+    }
+}
+
+impl GeoQuery {
+    // scan the boxes covering the shape like Redis, so that ANY stops at the same members
+    fn search(&self, zset: &ZSet) -> Result<Vec<GeoMatch>, BackendError> {
+        let (lon, lat) = match &self.origin {
+            GeoOrigin::Member(member) => {
+                geohash_decode(zset.score(member).ok_or(BackendError::GeoMemberNotFound)? as u64)
+            }
+            GeoOrigin::LonLat(lon, lat) => (*lon, *lat),
+        };
+        let limit = self.count.filter(|_| self.any);
+        let boxes = self.boxes(lon, lat);
+        let mut matches = Vec::new();
+        let mut last = 0;
+        for (i, hash) in boxes.iter().enumerate() {
+            if *hash == HashBits::default() {
+                continue;
+            }
+            // neighbors of a huge box can be the same, the first box is never compared
+            if last != 0 && *hash == boxes[last] {
+                continue;
+            }
+            if limit.is_some_and(|limit| matches.len() >= limit) {
+                break;
+            }
+            let range = ScoreRange {
+                min: ScoreBound {
+                    value: align52(*hash) as f64,
+                    exclusive: false,
+                },
+                max: ScoreBound {
+                    value: align52(HashBits {
+                        bits: hash.bits + 1,
+                        step: hash.step,
+                    }) as f64,
+                    exclusive: true,
+                },
+            };
+            for (member, score) in zset.range_by_score(&range, false, 0, None) {
+                let (plon, plat) = geohash_decode(score as u64);
+                if let Some(dist) = self.distance_within(lon, lat, plon, plat) {
+                    matches.push(GeoMatch {
+                        member,
+                        dist,
+                        hash: score as u64,
+                        lon: plon,
+                        lat: plat,
+                    });
+                    if limit.is_some_and(|limit| matches.len() >= limit) {
+                        break;
+                    }
+                }
+            }
+            last = i;
+        }
+
+        // COUNT without ANY returns the nearest members
+        let order = match (self.order, self.count) {
+            (None, Some(_)) if !self.any => Some(GeoOrder::Asc),
+            (order, _) => order,
+        };
+        match order {
+            Some(GeoOrder::Asc) => matches.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            Some(GeoOrder::Desc) => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            None => (),
+        }
+        if let Some(count) = self.count {
+            matches.truncate(count);
+        }
+        for m in &mut matches {
+            m.dist /= self.unit;
+        }
+        Ok(matches)
+    }
+
+    // the distance in meters from the origin to a point within the shape
+    fn distance_within(&self, lon: f64, lat: f64, plon: f64, plat: f64) -> Option<f64> {
+        match self.shape {
+            GeoShape::Radius(radius) => {
+                let dist = geo_distance(lon, lat, plon, plat);
+                (dist <= radius * self.unit).then_some(dist)
+            }
+            GeoShape::Box { width, height } => {
+                // the latitude is cheaper to check first
+                if lat_distance(plat, lat) > height * self.unit / 2.0 {
+                    return None;
+                }
+                if geo_distance(plon, plat, lon, plat) > width * self.unit / 2.0 {
+                    return None;
+                }
+                Some(geo_distance(lon, lat, plon, plat))
+            }
+        }
+    }
+
+    // the box of the origin and its eight neighbors, with the boxes the shape does not reach
+    // zeroed
+    fn boxes(&self, lon: f64, lat: f64) -> [HashBits; 9] {
+        let (half_width, half_height) = match self.shape {
+            GeoShape::Radius(radius) => (radius * self.unit, radius * self.unit),
+            GeoShape::Box { width, height } => (width / 2.0 * self.unit, height / 2.0 * self.unit),
+        };
+        let lat_delta = (half_height / EARTH_RADIUS_IN_METERS).to_degrees();
+        let long_delta_top =
+            (half_width / EARTH_RADIUS_IN_METERS / (lat + lat_delta).to_radians().cos())
+                .to_degrees();
+        let long_delta_bottom =
+            (half_width / EARTH_RADIUS_IN_METERS / (lat - lat_delta).to_radians().cos())
+                .to_degrees();
+        // the widest side is north on the southern hemisphere and south on the northern one
+        let long_delta = if lat < 0.0 {
+            long_delta_bottom
+        } else {
+            long_delta_top
+        };
+        let (min_lon, max_lon) = (lon - long_delta, lon + long_delta);
+        let (min_lat, max_lat) = (lat - lat_delta, lat + lat_delta);
+
+        let radius = match self.shape {
+            GeoShape::Radius(radius) => radius,
+            GeoShape::Box { width, height } => {
+                ((width / 2.0).powi(2) + (height / 2.0).powi(2)).sqrt()
+            }
+        };
+        let mut step = estimate_steps(radius * self.unit, lat);
+        let mut boxes =
+            neighbors(encode(WGS84_LONG, WGS84_LAT, lon, lat, step).unwrap_or_default());
+
+        // a box next to the edge of the shape may be too small to cover it
+        let decode = |hash| decode(WGS84_LONG, WGS84_LAT, hash);
+        let [_, north, south, east, west, ..] = boxes.map(decode);
+        let too_small = north.lat.1 < max_lat
+            || south.lat.0 > min_lat
+            || east.lon.1 < max_lon
+            || west.lon.0 > min_lon;
+        if step > 1 && too_small {
+            step -= 1;
+            boxes = neighbors(encode(WGS84_LONG, WGS84_LAT, lon, lat, step).unwrap_or_default());
+        }
+
+        if step >= 2 {
+            let area = decode(boxes[0]);
+            let mut skip = |indexes: [usize; 3]| {
+                for i in indexes {
+                    boxes[i] = HashBits::default();
+                }
+            };
+            if area.lat.0 < min_lat {
+                skip([SOUTH, SOUTH_WEST, SOUTH_EAST]);
+            }
+            if area.lat.1 > max_lat {
+                skip([NORTH, NORTH_EAST, NORTH_WEST]);
+            }
+            if area.lon.0 < min_lon {
+                skip([WEST, SOUTH_WEST, NORTH_WEST]);
+            }
+            if area.lon.1 > max_lon {
+                skip([EAST, SOUTH_EAST, NORTH_EAST]);
+            }
+        }
+        boxes
+    }
+}
+
+// the order Redis scans the boxes in
+const NORTH: usize = 1;
+const SOUTH: usize = 2;
+const EAST: usize = 3;
+const WEST: usize = 4;
+const NORTH_EAST: usize = 5;
+const NORTH_WEST: usize = 6;
+const SOUTH_EAST: usize = 7;
+const SOUTH_WEST: usize = 8;
+
+fn neighbors(hash: HashBits) -> [HashBits; 9] {
+    let moved = |dx: i8, dy: i8| move_y(move_x(hash, dx), dy);
+    [
+        hash,
+        moved(0, 1),
+        moved(0, -1),
+        moved(1, 0),
+        moved(-1, 0),
+        moved(1, 1),
+        moved(-1, 1),
+        moved(1, -1),
+        moved(-1, -1),
+    ]
+}
+
+fn move_x(hash: HashBits, d: i8) -> HashBits {
+    if d == 0 {
+        return hash;
+    }
+    let shift = 64 - hash.step as u32 * 2;
+    let mut x = hash.bits & 0xaaaaaaaaaaaaaaaa;
+    let y = hash.bits & 0x5555555555555555;
+    let zz = 0x5555555555555555u64 >> shift;
+    if d > 0 {
+        x = x.wrapping_add(zz + 1);
+    } else {
+        x = (x | zz).wrapping_sub(zz + 1);
+    }
+    x &= 0xaaaaaaaaaaaaaaaa >> shift;
+    HashBits {
+        bits: x | y,
+        step: hash.step,
+    }
+}
+
+fn move_y(hash: HashBits, d: i8) -> HashBits {
+    if d == 0 {
+        return hash;
+    }
+    let shift = 64 - hash.step as u32 * 2;
+    let x = hash.bits & 0xaaaaaaaaaaaaaaaa;
+    let mut y = hash.bits & 0x5555555555555555;
+    let zz = 0xaaaaaaaaaaaaaaaau64 >> shift;
+    if d > 0 {
+        y = y.wrapping_add(zz + 1);
+    } else {
+        y = (y | zz).wrapping_sub(zz + 1);
+    }
+    y &= 0x5555555555555555 >> shift;
+    HashBits {
+        bits: x | y,
+        step: hash.step,
+    }
+}
+
+// the precision of the boxes to search for a radius, coarser towards the poles
+fn estimate_steps(mut range: f64, lat: f64) -> u8 {
+    if range == 0.0 {
+        return GEO_STEP_MAX;
+    }
+    let mut step: i32 = 1;
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+    step -= 2;
+    if !(-66.0..=66.0).contains(&lat) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&lat) {
+            step -= 1;
+        }
+    }
+    step.clamp(1, GEO_STEP_MAX as i32) as u8
+}
+
+fn encode(
+    lon_range: (f64, f64),
+    lat_range: (f64, f64),
+    lon: f64,
+    lat: f64,
+    step: u8,
+) -> Option<HashBits> {
+    if !(GEO_LONG_MIN..=GEO_LONG_MAX).contains(&lon) || !(GEO_LAT_MIN..=GEO_LAT_MAX).contains(&lat)
+    {
+        return None;
+    }
+    if !(lon_range.0..=lon_range.1).contains(&lon) || !(lat_range.0..=lat_range.1).contains(&lat) {
+        return None;
+    }
+    let scale = (1u64 << step) as f64;
+    let lat_offset = (lat - lat_range.0) / (lat_range.1 - lat_range.0) * scale;
+    let lon_offset = (lon - lon_range.0) / (lon_range.1 - lon_range.0) * scale;
+    Some(HashBits {
+        bits: spread(lat_offset as u32) | spread(lon_offset as u32) << 1,
+        step,
+    })
+}
+
+fn decode(lon_range: (f64, f64), lat_range: (f64, f64), hash: HashBits) -> Area {
+    let scale = (1u64 << hash.step) as f64;
+    let lat = squash(hash.bits) as f64;
+    let lon = squash(hash.bits >> 1) as f64;
+    let lat_scale = lat_range.1 - lat_range.0;
+    let lon_scale = lon_range.1 - lon_range.0;
+    Area {
+        lon: (
+            lon_range.0 + (lon / scale) * lon_scale,
+            lon_range.0 + ((lon + 1.0) / scale) * lon_scale,
+        ),
+        lat: (
+            lat_range.0 + (lat / scale) * lat_scale,
+            lat_range.0 + ((lat + 1.0) / scale) * lat_scale,
+        ),
+    }
+}
+
+fn align52(hash: HashBits) -> u64 {
+    hash.bits << (52 - hash.step as u32 * 2)
+}
+
+// the bits of `v` moved to the even positions
+fn spread(v: u32) -> u64 {
+    let mut v = v as u64;
+    v = (v | v << 16) & 0x0000ffff0000ffff;
+    v = (v | v << 8) & 0x00ff00ff00ff00ff;
+    v = (v | v << 4) & 0x0f0f0f0f0f0f0f0f;
+    v = (v | v << 2) & 0x3333333333333333;
+    (v | v << 1) & 0x5555555555555555
+}
+
+// the bits at the even positions of `v`
+fn squash(v: u64) -> u32 {
+    let mut v = v & 0x5555555555555555;
+    v = (v | v >> 1) & 0x3333333333333333;
+    v = (v | v >> 2) & 0x0f0f0f0f0f0f0f0f;
+    v = (v | v >> 4) & 0x00ff00ff00ff00ff;
+    v = (v | v >> 8) & 0x0000ffff0000ffff;
+    (v | v >> 16) as u32
+}
+
+fn lat_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (lat2.to_radians() - lat1.to_radians()).abs()
+}
+
+// the geohash is recomputed over the standard latitude range of -90 to 90, and the eleventh
+// character, for which there are no bits left, is always 0
+fn standard_geohash(score: u64) -> String {
+    let (lon, lat) = geohash_decode(score);
+    let bits = encode(WGS84_LONG, (-90.0, 90.0), lon, lat, GEO_STEP_MAX)
+        .unwrap_or_default()
+        .bits;
+    (0..11)
+        .map(|i| {
+            let index = if i == 10 {
+                0
+            } else {
+                (bits >> (52 - (i + 1) * 5)) & 0x1f
+            };
+            GEO_ALPHABET[index as usize] as char
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ZAddOptions;
+
+    fn sicily() -> Db {
+        let db = Db::new();
+        let pairs = [
+            (13.361389, 38.115556, "Palermo"),
+            (15.087269, 37.502669, "Catania"),
+            (12.758489, 38.788135, "edge1"),
+            (17.241510, 38.788135, "edge2"),
+        ]
+        .into_iter()
+        .map(|(lon, lat, m)| {
+            (
+                geohash_score(lon, lat).unwrap_or_default() as f64,
+                m.to_string(),
+            )
+        })
+        .collect();
+        let _ = db.zadd("Sicily".to_string(), ZAddOptions::default(), pairs);
+        db
+    }
+
+    fn query(origin: GeoOrigin, shape: GeoShape) -> GeoQuery {
+        GeoQuery {
+            origin,
+            shape,
+            unit: 1000.0,
+            order: None,
+            count: None,
+            any: false,
+        }
+    }
+
+    fn members(matches: &[GeoMatch]) -> Vec<&str> {
+        matches.iter().map(|m| m.member.as_str()).collect()
+    }
+
+    #[test]
+    fn test_geohash_score() {
+        // the scores Redis stores for these coordinates
+        assert_eq!(geohash_score(13.361389, 38.115556), Some(3479099956230698));
+        assert_eq!(geohash_score(15.087269, 37.502669), Some(3479447370796909));
+        assert_eq!(geohash_score(0.0, 86.0), None);
+        assert_eq!(geohash_score(181.0, 0.0), None);
+
+        let (lon, lat) = geohash_decode(3479099956230698);
+        assert!((lon - 13.361389).abs() < 1e-5 && (lat - 38.115556).abs() < 1e-5);
+        assert_eq!(standard_geohash(3479099956230698), "sqc8b49rny0");
+        assert_eq!(standard_geohash(3479447370796909), "sqdtr74hyu0");
+    }
+
+    #[test]
+    fn test_geodist() -> Result<(), BackendError> {
+        let db = sicily();
+        let dist = db
+            .geodist("Sicily", "Palermo", "Catania")?
+            .unwrap_or_default();
+        assert_eq!(format!("{:.4}", dist), "166274.1516");
+        assert_eq!(db.geodist("Sicily", "Palermo", "Rome")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch() -> Result<(), BackendError> {
+        let db = sicily();
+        let mut q = query(GeoOrigin::LonLat(15.0, 37.0), GeoShape::Radius(200.0));
+        q.order = Some(GeoOrder::Asc);
+        let matches = db.geosearch("Sicily", &q)?;
+        assert_eq!(members(&matches), ["Catania", "Palermo"]);
+        assert_eq!(format!("{:.4}", matches[0].dist), "56.4413");
+        assert_eq!(format!("{:.4}", matches[1].dist), "190.4424");
+
+        let mut q = query(
+            GeoOrigin::LonLat(15.0, 37.0),
+            GeoShape::Box {
+                width: 400.0,
+                height: 400.0,
+            },
+        );
+        q.order = Some(GeoOrder::Desc);
+        let matches = db.geosearch("Sicily", &q)?;
+        assert_eq!(members(&matches), ["edge1", "edge2", "Palermo", "Catania"]);
+
+        // COUNT sorts by distance unless ANY is given
+        q.order = None;
+        q.count = Some(1);
+        assert_eq!(members(&db.geosearch("Sicily", &q)?), ["Catania"]);
+
+        let q = query(
+            GeoOrigin::Member("Palermo".to_string()),
+            GeoShape::Radius(1.0),
+        );
+        assert_eq!(members(&db.geosearch("Sicily", &q)?), ["Palermo"]);
+        let q = query(GeoOrigin::Member("Rome".to_string()), GeoShape::Radius(1.0));
+        assert_eq!(
+            db.geosearch("Sicily", &q),
+            Err(BackendError::GeoMemberNotFound)
+        );
+        assert_eq!(db.geosearch("none", &q), Ok(vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearchstore() -> Result<(), BackendError> {
+        let db = sicily();
+        let q = query(GeoOrigin::LonLat(15.0, 37.0), GeoShape::Radius(200.0));
+        assert_eq!(
+            db.geosearchstore("near".to_string(), "Sicily", &q, false)?,
+            2
+        );
+        assert_eq!(db.zscore("near", "Palermo")?, Some(3479099956230698.0));
+        assert_eq!(
+            db.geosearchstore("dist".to_string(), "Sicily", &q, true)?,
+            2
+        );
+        let dist = db.zscore("dist", "Catania")?.unwrap_or_default();
+        assert_eq!(format!("{:.4}", dist), "56.4413");
+        assert_eq!(db.geosearchstore("near".to_string(), "none", &q, false)?, 0);
+        assert_eq!(db.zcard("near")?, 0);
+        Ok(())
+    }
+}
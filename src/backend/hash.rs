@@ -0,0 +1,542 @@
+use std::ops::Deref;
+
+use dashmap::{mapref::one::RefMut, DashMap};
+
+use super::{
+    now_ms, random_elements, string_bytes, BackendError, Db, NotifyFlags, ScanIndex, Value,
+};
+use crate::{BulkString, RespFrame};
+
+// field ttls beyond this are rejected, like the 48 bit limit of redis
+pub const MAX_FIELD_EXPIRE_MS: u64 = (1 << 48) - 1;
+
+#[derive(Debug, Clone, Default)]
+pub struct Hash {
+    fields: DashMap<String, RespFrame>,
+    // absolute unix time in milliseconds of the fields that have a ttl
+    expires: DashMap<String, u64>,
+    // the fields in the order HSCAN walks them
+    index: ScanIndex,
+}
+
+// the NX, XX, GT and LT options of the HEXPIRE family, a field without ttl counts as never
+// expiring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    Nx,
+    Xx,
+    Gt,
+    Lt,
+}
+
+// what HGETEX and HSETEX do with the ttl of the fields they touch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldTtl {
+    Keep,
+    Persist,
+    At(u64),
+}
+
+// the FNX and FXX options of HSETEX
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    Nx,
+    Xx,
+}
+
+impl Deref for Hash {
+    type Target = DashMap<String, RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fields
+    }
+}
+
+impl Hash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // overwriting a field drops its ttl, like HSET does
+    pub fn insert(&self, field: String, value: RespFrame) -> Option<RespFrame> {
+        self.expires.remove(&field);
+        match self.fields.entry(field) {
+            dashmap::mapref::entry::Entry::Occupied(mut occupied) => Some(occupied.insert(value)),
+            dashmap::mapref::entry::Entry::Vacant(vacant) => {
+                self.index.insert(&self.fields, vacant.key());
+                vacant.insert(value);
+                None
+            }
+        }
+    }
+
+    // the value of `field`, created with `default` if the field does not exist
+    pub fn get_or_insert_with(
+        &self,
+        field: String,
+        default: impl FnOnce() -> RespFrame,
+    ) -> RefMut<'_, String, RespFrame> {
+        match self.fields.entry(field) {
+            dashmap::mapref::entry::Entry::Occupied(occupied) => occupied.into_ref(),
+            dashmap::mapref::entry::Entry::Vacant(vacant) => {
+                self.index.insert(&self.fields, vacant.key());
+                vacant.insert(default())
+            }
+        }
+    }
+
+    pub fn remove(&self, field: &str) -> Option<(String, RespFrame)> {
+        self.expires.remove(field);
+        self.remove_field(field)
+    }
+
+    // fields only leave the map here, which updates the scan index while the field is locked
+    fn remove_field(&self, field: &str) -> Option<(String, RespFrame)> {
+        self.fields.remove_if(field, |field, _| {
+            self.index.remove(&self.fields, field);
+            true
+        })
+    }
+
+    pub fn expire_at(&self, field: &str) -> Option<u64> {
+        self.expires.get(field).map(|at| *at)
+    }
+
+    fn set_expire_at(&self, field: &str, at: Option<u64>) {
+        match at {
+            Some(at) => self.expires.insert(field.to_string(), at),
+            None => self.expires.remove(field).map(|(_, at)| at),
+        };
+    }
+
+    // apply a ttl option to an existing field, returns false if that deleted the field
+    fn apply_ttl(&self, field: &str, ttl: FieldTtl) -> bool {
+        match ttl {
+            FieldTtl::Keep => true,
+            FieldTtl::Persist => {
+                self.set_expire_at(field, None);
+                true
+            }
+            FieldTtl::At(at) if at <= now_ms() => {
+                self.remove(field);
+                false
+            }
+            FieldTtl::At(at) => {
+                self.set_expire_at(field, Some(at));
+                true
+            }
+        }
+    }
+
+    pub fn has_field_ttls(&self) -> bool {
+        !self.expires.is_empty()
+    }
+
+    // true when every field of the hash has expired, the key is gone then as well
+    pub fn is_expired(&self) -> bool {
+        if self.fields.is_empty() || self.expires.len() < self.fields.len() {
+            return false;
+        }
+        let now = now_ms();
+        self.expires.iter().all(|at| *at <= now)
+    }
+
+    // drop the fields whose ttl has passed, returns whether there were any
+    pub(super) fn purge_expired(&self) -> bool {
+        if self.expires.is_empty() {
+            return false;
+        }
+        let now = now_ms();
+        let mut purged = false;
+        self.expires.retain(|field, at| {
+            if *at > now {
+                return true;
+            }
+            self.remove_field(field);
+            purged = true;
+            false
+        });
+        purged
+    }
+}
+
+impl ExpireCondition {
+    fn allows(&self, current: Option<u64>, at: u64) -> bool {
+        match self {
+            ExpireCondition::Nx => current.is_none(),
+            ExpireCondition::Xx => current.is_some(),
+            ExpireCondition::Gt => current.is_some_and(|current| at > current),
+            ExpireCondition::Lt => current.is_none_or(|current| at < current),
+        }
+    }
+}
+
+impl Db {
+    pub fn hget(&self, key: &str, field: &str) -> Result<Option<RespFrame>, BackendError> {
+        let value = self.read_hash(key, |hash| hash.get(field).map(|v| v.value().clone()))?;
+        Ok(value.flatten())
+    }
+
+    // set the given fields, returns the number of fields that did not exist before
+    pub fn hset(&self, key: String, pairs: Vec<(String, RespFrame)>) -> Result<i64, BackendError> {
+        let added = self.write_hash(key.clone(), |hash| {
+            let added = pairs
+                .into_iter()
+                .filter(|(field, value)| hash.insert(field.clone(), value.clone()).is_none())
+                .count();
+            Ok(added as i64)
+        })?;
+        self.notify(NotifyFlags::HASH, "hset", &key);
+        Ok(added)
+    }
+
+    pub fn hsetnx(
+        &self,
+        key: String,
+        field: String,
+        value: RespFrame,
+    ) -> Result<bool, BackendError> {
+        let set = self.write_hash(key.clone(), |hash| {
+            let mut inserted = false;
+            hash.get_or_insert_with(field, || {
+                inserted = true;
+                value
+            });
+            Ok(inserted)
+        })?;
+        if set {
+            self.notify(NotifyFlags::HASH, "hset", &key);
+        }
+        Ok(set)
+    }
+
+    pub fn hgetall(&self, key: &str) -> Result<Option<Hash>, BackendError> {
+        self.read_hash(key, |hash| hash.clone())
+    }
+
+    pub fn hdel(&self, key: &str, fields: &[String]) -> Result<i64, BackendError> {
+        let removed = self.modify_hash(key, |hash| {
+            Ok(fields.iter().filter(|f| hash.remove(f).is_some()).count() as i64)
+        })?;
+        let removed = removed.unwrap_or(0);
+        if removed > 0 {
+            self.notify_removal(NotifyFlags::HASH, "hdel", key);
+        }
+        Ok(removed)
+    }
+
+    pub fn hexists(&self, key: &str, field: &str) -> Result<bool, BackendError> {
+        Ok(self
+            .read_hash(key, |hash| hash.contains_key(field))?
+            .unwrap_or(false))
+    }
+
+    pub fn hlen(&self, key: &str) -> Result<i64, BackendError> {
+        Ok(self.read_hash(key, |hash| hash.len() as i64)?.unwrap_or(0))
+    }
+
+    pub fn hkeys(&self, key: &str) -> Result<Vec<String>, BackendError> {
+        let keys = self.read_hash(key, |hash| hash.iter().map(|v| v.key().clone()).collect())?;
+        Ok(keys.unwrap_or_default())
+    }
+
+    pub fn hvals(&self, key: &str) -> Result<Vec<RespFrame>, BackendError> {
+        let vals = self.read_hash(key, |hash| hash.iter().map(|v| v.value().clone()).collect())?;
+        Ok(vals.unwrap_or_default())
+    }
+
+    pub fn hstrlen(&self, key: &str, field: &str) -> Result<i64, BackendError> {
+        let len = self.read_hash(key, |hash| {
+            hash.get(field)
+                .map(|v| string_bytes(v.value()).len() as i64)
+                .unwrap_or(0)
+        })?;
+        Ok(len.unwrap_or(0))
+    }
+
+    pub fn hincrby(&self, key: String, field: String, incr: i64) -> Result<i64, BackendError> {
+        let result = self.write_hash(key.clone(), |hash| {
+            let mut value = hash.get_or_insert_with(field, || BulkString::new("0").into());
+            let current: i64 = std::str::from_utf8(&string_bytes(&value))
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .ok_or(BackendError::HashValueNotInteger)?;
+            let result = current
+                .checked_add(incr)
+                .ok_or(BackendError::IncrOverflow)?;
+            *value = BulkString::new(result.to_string()).into();
+            Ok(result)
+        })?;
+        self.notify(NotifyFlags::HASH, "hincrby", &key);
+        Ok(result)
+    }
+
+    pub fn hincrbyfloat(&self, key: String, field: String, incr: f64) -> Result<f64, BackendError> {
+        let result = self.write_hash(key.clone(), |hash| {
+            let mut value = hash.get_or_insert_with(field, || BulkString::new("0").into());
+            let current: f64 = std::str::from_utf8(&string_bytes(&value))
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .ok_or(BackendError::HashValueNotFloat)?;
+            let result = current + incr;
+            if !result.is_finite() {
+                return Err(BackendError::NanOrInfinity);
+            }
+            *value = BulkString::new(result.to_string()).into();
+            Ok(result)
+        })?;
+        self.notify(NotifyFlags::HASH, "hincrbyfloat", &key);
+        Ok(result)
+    }
+
+    // random fields like HRANDFIELD, see `random_elements`
+    pub fn hrandfield(
+        &self,
+        key: &str,
+        count: Option<i64>,
+    ) -> Result<Vec<(String, RespFrame)>, BackendError> {
+        let fields = self.read_hash(key, |hash| {
+            let fields = random_elements(hash.iter().map(|e| e.key().clone()), hash.len(), count)?;
+            // only the values of the chosen fields are copied
+            Ok(fields
+                .into_iter()
+                .filter_map(|field| {
+                    let value = hash.get(&field)?.value().clone();
+                    Some((field, value))
+                })
+                .collect())
+        })?;
+        Ok(fields.transpose()?.unwrap_or_default())
+    }
+
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, RespFrame)>), BackendError> {
+        let page = self.read_hash(key, |hash| {
+            let (next, fields) = hash.index.page(&hash.fields, cursor, count);
+            let fields = fields
+                .into_iter()
+                .filter(|field| {
+                    pattern.is_none_or(|p| super::glob_match(p.as_bytes(), field.as_bytes(), false))
+                })
+                .filter_map(|field| {
+                    let value = hash.get(&field)?.value().clone();
+                    Some((field, value))
+                })
+                .collect();
+            (next, fields)
+        })?;
+        Ok(page.unwrap_or((0, Vec::new())))
+    }
+
+    // set the expire time of each field like HPEXPIREAT. The reply per field is -2 when the
+    // field does not exist, 0 when `cond` is not met, 1 when the ttl was set and 2 when `at`
+    // is not in the future and the field got deleted right away.
+    pub fn hexpire(
+        &self,
+        key: &str,
+        fields: &[String],
+        at: u64,
+        cond: Option<ExpireCondition>,
+    ) -> Result<Vec<i64>, BackendError> {
+        let replies = self.modify_hash(key, |hash| {
+            let replies = fields
+                .iter()
+                .map(|field| {
+                    if !hash.contains_key(field) {
+                        return -2;
+                    }
+                    if cond.is_some_and(|cond| !cond.allows(hash.expire_at(field), at)) {
+                        return 0;
+                    }
+                    if hash.apply_ttl(field, FieldTtl::At(at)) {
+                        1
+                    } else {
+                        2
+                    }
+                })
+                .collect();
+            Ok(replies)
+        })?;
+        let replies = replies.unwrap_or_else(|| vec![-2; fields.len()]);
+        if replies.contains(&1) {
+            self.notify(NotifyFlags::HASH, "hexpire", key);
+        }
+        if replies.contains(&2) {
+            self.notify_removal(NotifyFlags::HASH, "hdel", key);
+        }
+        Ok(replies)
+    }
+
+    // the absolute expire time of each field in milliseconds, -1 for fields without ttl and
+    // -2 for missing fields
+    pub fn hexpire_time(&self, key: &str, fields: &[String]) -> Result<Vec<i64>, BackendError> {
+        let replies = self.read_hash(key, |hash| {
+            fields
+                .iter()
+                .map(|field| match hash.contains_key(field) {
+                    true => hash.expire_at(field).map(|at| at as i64).unwrap_or(-1),
+                    false => -2,
+                })
+                .collect()
+        })?;
+        Ok(replies.unwrap_or_else(|| vec![-2; fields.len()]))
+    }
+
+    // drop the ttl of each field, replying 1 when it had one, -1 when not and -2 for missing
+    // fields
+    pub fn hpersist(&self, key: &str, fields: &[String]) -> Result<Vec<i64>, BackendError> {
+        let replies = self.modify_hash(key, |hash| {
+            let replies = fields
+                .iter()
+                .map(|field| match hash.contains_key(field) {
+                    true if hash.expire_at(field).is_some() => {
+                        hash.set_expire_at(field, None);
+                        1
+                    }
+                    true => -1,
+                    false => -2,
+                })
+                .collect();
+            Ok(replies)
+        })?;
+        let replies = replies.unwrap_or_else(|| vec![-2; fields.len()]);
+        if replies.contains(&1) {
+            self.notify(NotifyFlags::HASH, "hpersist", key);
+        }
+        Ok(replies)
+    }
+
+    // HMGET that also changes the ttl of the fields it returns
+    pub fn hgetex(
+        &self,
+        key: &str,
+        fields: &[String],
+        ttl: FieldTtl,
+    ) -> Result<Vec<Option<RespFrame>>, BackendError> {
+        let (mut changed, mut deleted) = (false, false);
+        let values = self.modify_hash(key, |hash| {
+            let values = fields
+                .iter()
+                .map(|field| {
+                    let value = hash.get(field).map(|v| v.value().clone());
+                    if value.is_some() {
+                        match hash.apply_ttl(field, ttl) {
+                            true => changed = true,
+                            false => deleted = true,
+                        }
+                    }
+                    value
+                })
+                .collect();
+            Ok(values)
+        })?;
+        match ttl {
+            FieldTtl::At(_) if changed => self.notify(NotifyFlags::HASH, "hexpire", key),
+            FieldTtl::Persist if changed => self.notify(NotifyFlags::HASH, "hpersist", key),
+            _ => {}
+        }
+        if deleted {
+            self.notify_removal(NotifyFlags::HASH, "hdel", key);
+        }
+        Ok(values.unwrap_or_else(|| vec![None; fields.len()]))
+    }
+
+    // HSET with a ttl for the fields it sets. With `cond` the fields are only set if none
+    // (Nx) or all (Xx) of them exist, Ok(false) is returned when they were not set.
+    pub fn hsetex(
+        &self,
+        key: String,
+        pairs: Vec<(String, RespFrame)>,
+        cond: Option<SetCondition>,
+        ttl: FieldTtl,
+    ) -> Result<bool, BackendError> {
+        let mut deleted = false;
+        let set = self.write_hash(key.clone(), |hash| {
+            let allowed = match cond {
+                Some(SetCondition::Nx) => pairs.iter().all(|(f, _)| !hash.contains_key(f)),
+                Some(SetCondition::Xx) => pairs.iter().all(|(f, _)| hash.contains_key(f)),
+                None => true,
+            };
+            if !allowed {
+                return Ok(false);
+            }
+            for (field, value) in pairs {
+                let previous = hash.expire_at(&field);
+                hash.insert(field.clone(), value);
+                match ttl {
+                    FieldTtl::Keep => hash.set_expire_at(&field, previous),
+                    ttl => deleted |= !hash.apply_ttl(&field, ttl),
+                }
+            }
+            Ok(true)
+        })?;
+        // a ttl already in the past deletes the fields right away, and the key with them if
+        // nothing else is left in the hash
+        if set && deleted {
+            self.notify_removal(NotifyFlags::HASH, "hdel", &key);
+        } else if set {
+            self.notify(NotifyFlags::HASH, "hset", &key);
+            if matches!(ttl, FieldTtl::At(_)) {
+                self.notify(NotifyFlags::HASH, "hexpire", &key);
+            }
+        }
+        Ok(set)
+    }
+
+    fn read_hash<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&Hash) -> R,
+    ) -> Result<Option<R>, BackendError> {
+        self.read(key, |value| match value {
+            Value::Hash(hash) => {
+                if hash.purge_expired() {
+                    self.notify(NotifyFlags::HASH, "hexpired", key);
+                }
+                Ok(f(hash))
+            }
+            _ => Err(BackendError::WrongType),
+        })
+    }
+
+    fn write_hash<R>(
+        &self,
+        key: String,
+        f: impl FnOnce(&Hash) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        self.write(
+            key.clone(),
+            || Value::Hash(Hash::new()),
+            |value| match value {
+                Value::Hash(hash) => {
+                    if hash.purge_expired() {
+                        self.notify(NotifyFlags::HASH, "hexpired", &key);
+                    }
+                    f(hash)
+                }
+                _ => Err(BackendError::WrongType),
+            },
+        )
+    }
+
+    fn modify_hash<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&Hash) -> Result<R, BackendError>,
+    ) -> Result<Option<R>, BackendError> {
+        self.modify(key, |value| match value {
+            Value::Hash(hash) => {
+                if hash.purge_expired() {
+                    self.notify(NotifyFlags::HASH, "hexpired", key);
+                }
+                f(hash)
+            }
+            _ => Err(BackendError::WrongType),
+        })
+    }
+}
@@ -0,0 +1,518 @@
+use super::{string_bytes, BackendError, Db, NotifyFlags, Value};
+use crate::BulkString;
+
+// the layout of Redis, so that values can be moved between servers with GET and SET
+const HLL_P: u32 = 14;
+const HLL_Q: u32 = 64 - HLL_P;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+const HLL_HDR_SIZE: usize = 16;
+const HLL_DENSE_SIZE: usize = HLL_HDR_SIZE + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_MAGIC: &[u8] = b"HYLL";
+const HLL_SEED: u64 = 0xadc83b19;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+// a sparse value larger than this is turned dense, like hll-sparse-max-bytes of Redis
+const HLL_SPARSE_MAX_BYTES: usize = 3000;
+const HLL_SPARSE_VAL_MAX_VALUE: u8 = 32;
+const HLL_SPARSE_VAL_MAX_LEN: usize = 4;
+const HLL_SPARSE_ZERO_MAX_LEN: usize = 64;
+const HLL_SPARSE_XZERO_MAX_LEN: usize = 16384;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Dense = 0,
+    Sparse = 1,
+}
+
+// a HyperLogLog with its registers unpacked, encoded back to the Redis representation it was
+// read in unless a sparse one outgrew it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+    encoding: Encoding,
+    // the cardinality cached in the header, None once an update invalidated it
+    cached: Option<u64>,
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; HLL_REGISTERS],
+            encoding: Encoding::Sparse,
+            cached: Some(0),
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, BackendError> {
+        if bytes.len() < HLL_HDR_SIZE || &bytes[..4] != HLL_MAGIC {
+            return Err(BackendError::InvalidHll);
+        }
+        let card = &bytes[8..HLL_HDR_SIZE];
+        // the most significant bit of the last byte marks the cached cardinality stale
+        let cached =
+            (card[7] & 0x80 == 0).then(|| u64::from_le_bytes(card.try_into().unwrap_or_default()));
+        let (registers, encoding) = match bytes[4] {
+            0 if bytes.len() == HLL_DENSE_SIZE => {
+                let dense = &bytes[HLL_HDR_SIZE..];
+                let registers = (0..HLL_REGISTERS).map(|i| dense_get(dense, i)).collect();
+                (registers, Encoding::Dense)
+            }
+            1 => (sparse_decode(&bytes[HLL_HDR_SIZE..])?, Encoding::Sparse),
+            _ => return Err(BackendError::InvalidHll),
+        };
+        Ok(HyperLogLog {
+            registers,
+            encoding,
+            cached,
+        })
+    }
+
+    pub fn encode(&mut self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HLL_DENSE_SIZE);
+        bytes.extend_from_slice(HLL_MAGIC);
+        bytes.extend_from_slice(&[0; 4]);
+        match self.cached {
+            Some(card) => bytes.extend_from_slice(&card.to_le_bytes()),
+            None => bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x80]),
+        }
+        if self.encoding == Encoding::Sparse {
+            if let Some(sparse) = self.sparse_encode() {
+                bytes[4] = Encoding::Sparse as u8;
+                bytes.extend_from_slice(&sparse);
+                return bytes;
+            }
+            self.encoding = Encoding::Dense;
+        }
+        bytes[4] = Encoding::Dense as u8;
+        let mut dense = vec![0; HLL_DENSE_SIZE - HLL_HDR_SIZE];
+        for (i, value) in self.registers.iter().enumerate() {
+            dense_set(&mut dense, i, *value);
+        }
+        bytes.extend_from_slice(&dense);
+        bytes
+    }
+
+    // add an element, returning whether a register changed
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let (index, count) = pattern_len(element);
+        if self.registers[index] >= count {
+            return false;
+        }
+        self.registers[index] = count;
+        self.cached = None;
+        true
+    }
+
+    // the union with `other`, which turns dense if `other` is
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, value) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*value);
+        }
+        if other.encoding == Encoding::Dense {
+            self.encoding = Encoding::Dense;
+        }
+        self.cached = None;
+    }
+
+    // the estimated cardinality, cached until the next update
+    pub fn count(&mut self) -> u64 {
+        match self.cached {
+            Some(card) => card,
+            None => {
+                let card = self.estimate();
+                self.cached = Some(card);
+                card
+            }
+        }
+    }
+
+    // the estimator of Otmar Ertl used by Redis, from the histogram of the register values
+    fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let mut histogram = [0u32; 64];
+        for value in &self.registers {
+            histogram[*value as usize] += 1;
+        }
+        let q = HLL_Q as usize;
+        let mut z = m * tau((m - histogram[q + 1] as f64) / m);
+        for count in histogram[1..=q].iter().rev() {
+            z += *count as f64;
+            z *= 0.5;
+        }
+        z += m * sigma(histogram[0] as f64 / m);
+        (HLL_ALPHA_INF * m * m / z).round() as u64
+    }
+
+    // None if a register does not fit the sparse representation or it grew too large
+    fn sparse_encode(&self) -> Option<Vec<u8>> {
+        let mut sparse = Vec::new();
+        let mut i = 0;
+        while i < HLL_REGISTERS {
+            let value = self.registers[i];
+            let run = self.registers[i..]
+                .iter()
+                .take_while(|v| **v == value)
+                .count();
+            if value == 0 {
+                let mut left = run;
+                while left > 0 {
+                    let len = left.min(HLL_SPARSE_XZERO_MAX_LEN);
+                    if len <= HLL_SPARSE_ZERO_MAX_LEN {
+                        sparse.push((len - 1) as u8);
+                    } else {
+                        sparse.push(0x40 | ((len - 1) >> 8) as u8);
+                        sparse.push(((len - 1) & 0xff) as u8);
+                    }
+                    left -= len;
+                }
+            } else if value > HLL_SPARSE_VAL_MAX_VALUE {
+                return None;
+            } else {
+                let mut left = run;
+                while left > 0 {
+                    let len = left.min(HLL_SPARSE_VAL_MAX_LEN);
+                    sparse.push(0x80 | ((value - 1) << 2) | (len - 1) as u8);
+                    left -= len;
+                }
+            }
+            i += run;
+        }
+        (HLL_HDR_SIZE + sparse.len() <= HLL_SPARSE_MAX_BYTES).then_some(sparse)
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Db {
+    // whether the key was created or a register changed
+    pub fn pfadd(&self, key: String, elements: &[Vec<u8>]) -> Result<bool, BackendError> {
+        let mut created = false;
+        let changed = self.write(
+            key.clone(),
+            || {
+                created = true;
+                Value::String(BulkString::new(HyperLogLog::new().encode()).into())
+            },
+            |value| {
+                with_hll(value, |hll| {
+                    // every element is added, so no short circuit
+                    elements.iter().filter(|element| hll.add(element)).count() > 0
+                })
+            },
+        )?;
+        if created || changed {
+            self.notify(NotifyFlags::STRING, "pfadd", &key);
+        }
+        Ok(created || changed)
+    }
+
+    // the cardinality of a single key is cached in its header, that of several keys is
+    // estimated from their union
+    pub fn pfcount(&self, keys: &[String]) -> Result<i64, BackendError> {
+        if let [key] = keys {
+            let count = self.modify(key, |value| with_hll(value, |hll| hll.count()))?;
+            return Ok(count.unwrap_or(0) as i64);
+        }
+        Ok(self.pfunion(keys)?.count() as i64)
+    }
+
+    // merge the sources into `dst`, together with what it held already
+    pub fn pfmerge(&self, dst: String, sources: &[String]) -> Result<(), BackendError> {
+        let union = self.pfunion(sources)?;
+        self.write(
+            dst.clone(),
+            || Value::String(BulkString::new(HyperLogLog::new().encode()).into()),
+            |value| with_hll(value, |hll| hll.merge(&union)),
+        )?;
+        self.notify(NotifyFlags::STRING, "pfadd", &dst);
+        Ok(())
+    }
+
+    fn pfunion(&self, keys: &[String]) -> Result<HyperLogLog, BackendError> {
+        let mut union = HyperLogLog::new();
+        for key in keys {
+            let hll = self.read(key, |value| match value {
+                Value::String(frame) => HyperLogLog::decode(&string_bytes(frame)),
+                _ => Err(BackendError::WrongType),
+            })?;
+            if let Some(hll) = hll {
+                union.merge(&hll);
+            }
+        }
+        Ok(union)
+    }
+}
+
+// run `f` on the HyperLogLog stored in a string value and store it back
+fn with_hll<R>(
+    value: &mut Value,
+    f: impl FnOnce(&mut HyperLogLog) -> R,
+) -> Result<R, BackendError> {
+    match value {
+        Value::String(frame) => {
+            let mut hll = HyperLogLog::decode(&string_bytes(frame))?;
+            let result = f(&mut hll);
+            *frame = BulkString::new(hll.encode()).into();
+            Ok(result)
+        }
+        _ => Err(BackendError::WrongType),
+    }
+}
+
+// the register of an element and the position of the first set bit in the rest of its hash
+fn pattern_len(element: &[u8]) -> (usize, u8) {
+    let hash = murmur_hash64a(element, HLL_SEED);
+    let index = (hash & (HLL_REGISTERS as u64 - 1)) as usize;
+    // the extra bit bounds the count to Q + 1
+    let rest = (hash >> HLL_P) | (1 << HLL_Q);
+    (index, rest.trailing_zeros() as u8 + 1)
+}
+
+fn murmur_hash64a(data: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (data.len() as u64).wrapping_mul(M);
+    let mut chunks = data.chunks_exact(8);
+    for chunk in chunks.by_ref() {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap_or_default());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+// registers are packed six bits each, least significant bits first
+fn dense_get(dense: &[u8], index: usize) -> u8 {
+    let byte = index * HLL_BITS / 8;
+    let shift = index * HLL_BITS % 8;
+    let low = dense[byte] as u16;
+    let high = dense.get(byte + 1).copied().unwrap_or(0) as u16;
+    (((low | high << 8) >> shift) as u8) & HLL_REGISTER_MAX
+}
+
+fn dense_set(dense: &mut [u8], index: usize, value: u8) {
+    let byte = index * HLL_BITS / 8;
+    let shift = index * HLL_BITS % 8;
+    let mask = (HLL_REGISTER_MAX as u16) << shift;
+    let value = (value as u16) << shift;
+    dense[byte] = (dense[byte] & !(mask as u8)) | value as u8;
+    if let Some(next) = dense.get_mut(byte + 1) {
+        *next = (*next & !((mask >> 8) as u8)) | (value >> 8) as u8;
+    }
+}
+
+// the opcodes of the sparse representation: ZERO `00xxxxxx` and XZERO `01xxxxxx yyyyyyyy` are
+// runs of empty registers, VAL `1vvvvvxx` a run of up to four registers holding the same value
+fn sparse_decode(sparse: &[u8]) -> Result<Vec<u8>, BackendError> {
+    let mut registers = Vec::with_capacity(HLL_REGISTERS);
+    let mut bytes = sparse.iter();
+    while let Some(op) = bytes.next() {
+        let (value, run) = match op & 0xc0 {
+            0x00 => (0, (op & 0x3f) as usize + 1),
+            0x40 => {
+                let low = *bytes.next().ok_or(BackendError::CorruptedHll)?;
+                (0, (((op & 0x3f) as usize) << 8 | low as usize) + 1)
+            }
+            _ => (((op >> 2) & 0x1f) + 1, (op & 0x03) as usize + 1),
+        };
+        if registers.len() + run > HLL_REGISTERS {
+            return Err(BackendError::CorruptedHll);
+        }
+        registers.resize(registers.len() + run, value);
+    }
+    if registers.len() != HLL_REGISTERS {
+        return Err(BackendError::CorruptedHll);
+    }
+    Ok(registers)
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(n: usize, prefix: &str) -> HyperLogLog {
+        let mut hll = HyperLogLog::new();
+        for i in 0..n {
+            hll.add(format!("{}{}", prefix, i).as_bytes());
+        }
+        hll
+    }
+
+    fn assert_close(estimate: u64, actual: usize) {
+        let error = (estimate as f64 - actual as f64).abs() / actual as f64;
+        // the standard error with 16384 registers is 0.81%
+        assert!(error < 0.03, "estimate {} for {}", estimate, actual);
+    }
+
+    #[test]
+    fn test_murmur_hash() {
+        // a hash differing in any of the tail bytes lands in another register
+        assert_ne!(
+            murmur_hash64a(b"a", HLL_SEED),
+            murmur_hash64a(b"b", HLL_SEED)
+        );
+        assert_ne!(
+            murmur_hash64a(b"abcdefgh1", HLL_SEED),
+            murmur_hash64a(b"abcdefgh2", HLL_SEED)
+        );
+        assert_eq!(murmur_hash64a(b"", 0), 0);
+        let (index, count) = pattern_len(b"hello");
+        assert!(index < HLL_REGISTERS);
+        assert!((1..=HLL_Q as u8 + 1).contains(&count));
+    }
+
+    #[test]
+    fn test_empty_encoding() -> Result<(), BackendError> {
+        // what Redis stores for a new key: a single XZERO covering all registers
+        let bytes = HyperLogLog::new().encode();
+        assert_eq!(
+            bytes,
+            b"HYLL\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x7f\xff"
+        );
+        assert_eq!(HyperLogLog::decode(&bytes)?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_and_dense() -> Result<(), BackendError> {
+        let mut hll = filled(100, "a");
+        assert_eq!(hll.cached, None);
+        let bytes = hll.encode();
+        assert_eq!(bytes[4], Encoding::Sparse as u8);
+        assert_eq!(bytes[15] & 0x80, 0x80);
+        let mut decoded = HyperLogLog::decode(&bytes)?;
+        assert_eq!(decoded.registers, hll.registers);
+        assert_close(decoded.count(), 100);
+
+        // too many registers set for the sparse representation
+        let mut hll = filled(5000, "b");
+        let bytes = hll.encode();
+        assert_eq!(bytes[4], Encoding::Dense as u8);
+        assert_eq!(bytes.len(), HLL_DENSE_SIZE);
+        let mut decoded = HyperLogLog::decode(&bytes)?;
+        assert_eq!(decoded.registers, hll.registers);
+        let count = decoded.count();
+        assert_close(count, 5000);
+        // the count is cached in the header
+        let cached = HyperLogLog::decode(&decoded.encode())?;
+        assert_eq!(cached.cached, Some(count));
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_and_merge() {
+        for n in [10, 1000, 100_000] {
+            assert_close(filled(n, "x").count(), n);
+        }
+        let mut a = filled(3000, "x");
+        let b = filled(3000, "y");
+        a.merge(&b);
+        assert_close(a.count(), 6000);
+        // adding the same elements again changes nothing
+        let mut c = filled(10, "z");
+        assert!(!c.add(b"z3"));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert_eq!(
+            HyperLogLog::decode(b"not a hll value"),
+            Err(BackendError::InvalidHll)
+        );
+        // a dense value of the wrong size
+        assert_eq!(
+            HyperLogLog::decode(b"HYLL\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x7f\xff"),
+            Err(BackendError::InvalidHll)
+        );
+        // registers missing or past the end
+        assert_eq!(
+            HyperLogLog::decode(b"HYLL\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x7f\xfe"),
+            Err(BackendError::CorruptedHll)
+        );
+        assert_eq!(
+            HyperLogLog::decode(
+                b"HYLL\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x7f\xff\x80"
+            ),
+            Err(BackendError::CorruptedHll)
+        );
+    }
+
+    #[test]
+    fn test_db_commands() -> Result<(), BackendError> {
+        let db = Db::new();
+        assert_eq!(db.pfadd("a".to_string(), &[]), Ok(true));
+        assert_eq!(db.pfadd("a".to_string(), &[]), Ok(false));
+        assert_eq!(
+            db.pfadd("a".to_string(), &[b"x".to_vec(), b"y".to_vec()]),
+            Ok(true)
+        );
+        assert_eq!(db.pfadd("a".to_string(), &[b"x".to_vec()]), Ok(false));
+        db.pfadd("b".to_string(), &[b"y".to_vec(), b"z".to_vec()])?;
+        assert_eq!(db.pfcount(&["a".to_string()]), Ok(2));
+        assert_eq!(db.pfcount(&["a".to_string(), "b".to_string()]), Ok(3));
+        assert_eq!(db.pfcount(&["none".to_string()]), Ok(0));
+        db.pfmerge("c".to_string(), &["a".to_string(), "b".to_string()])?;
+        assert_eq!(db.pfcount(&["c".to_string()]), Ok(3));
+
+        db.set("s".to_string(), BulkString::new("plain").into());
+        assert_eq!(
+            db.pfcount(&["s".to_string()]),
+            Err(BackendError::InvalidHll)
+        );
+        assert_eq!(
+            db.pfadd("s".to_string(), &[b"x".to_vec()]),
+            Err(BackendError::InvalidHll)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,586 @@
+use std::collections::VecDeque;
+
+use super::{string_bytes, BackendError, Db, NotifyFlags, Value};
+use crate::RespFrame;
+
+// elements per chunk, a list is a deque of small deques so that pushes and pops at either end
+// never move more than one chunk and inserts in the middle only shift a single chunk
+const LIST_CHUNK_SIZE: usize = 128;
+
+#[derive(Debug, Clone, Default)]
+pub struct List {
+    chunks: VecDeque<VecDeque<RespFrame>>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl ListEnd {
+    // the keyspace events of pushing and popping at this end
+    fn push_event(self) -> &'static str {
+        match self {
+            ListEnd::Left => "lpush",
+            ListEnd::Right => "rpush",
+        }
+    }
+
+    fn pop_event(self) -> &'static str {
+        match self {
+            ListEnd::Left => "lpop",
+            ListEnd::Right => "rpop",
+        }
+    }
+}
+
+impl List {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, end: ListEnd, value: RespFrame) {
+        match end {
+            ListEnd::Left => {
+                if self
+                    .chunks
+                    .front()
+                    .is_none_or(|c| c.len() >= LIST_CHUNK_SIZE)
+                {
+                    self.chunks
+                        .push_front(VecDeque::with_capacity(LIST_CHUNK_SIZE));
+                }
+                if let Some(chunk) = self.chunks.front_mut() {
+                    chunk.push_front(value);
+                }
+            }
+            ListEnd::Right => {
+                if self
+                    .chunks
+                    .back()
+                    .is_none_or(|c| c.len() >= LIST_CHUNK_SIZE)
+                {
+                    self.chunks
+                        .push_back(VecDeque::with_capacity(LIST_CHUNK_SIZE));
+                }
+                if let Some(chunk) = self.chunks.back_mut() {
+                    chunk.push_back(value);
+                }
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self, end: ListEnd) -> Option<RespFrame> {
+        let value = match end {
+            ListEnd::Left => {
+                let value = self.chunks.front_mut()?.pop_front();
+                if self.chunks.front().is_some_and(|c| c.is_empty()) {
+                    self.chunks.pop_front();
+                }
+                value
+            }
+            ListEnd::Right => {
+                let value = self.chunks.back_mut()?.pop_back();
+                if self.chunks.back().is_some_and(|c| c.is_empty()) {
+                    self.chunks.pop_back();
+                }
+                value
+            }
+        };
+        if value.is_some() {
+            self.len -= 1;
+        }
+        value
+    }
+
+    pub fn get(&self, index: usize) -> Option<&RespFrame> {
+        let (chunk, offset) = self.locate(index)?;
+        self.chunks[chunk].get(offset)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut RespFrame> {
+        let (chunk, offset) = self.locate(index)?;
+        self.chunks[chunk].get_mut(offset)
+    }
+
+    // insert `value` so that it ends up at `index`, an index past the end appends
+    pub fn insert(&mut self, index: usize, value: RespFrame) {
+        let Some((chunk, offset)) = self.locate(index) else {
+            return self.push(ListEnd::Right, value);
+        };
+        self.chunks[chunk].insert(offset, value);
+        self.len += 1;
+        // keep chunks small by splitting the one that overflowed in two
+        if self.chunks[chunk].len() > LIST_CHUNK_SIZE {
+            let half = self.chunks[chunk].len() / 2;
+            let tail = self.chunks[chunk].split_off(half);
+            self.chunks.insert(chunk + 1, tail);
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<RespFrame> {
+        let (chunk, offset) = self.locate(index)?;
+        let value = self.chunks[chunk].remove(offset);
+        if self.chunks[chunk].is_empty() {
+            self.chunks.remove(chunk);
+        }
+        self.len -= 1;
+        value
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &RespFrame> {
+        self.chunks.iter().flatten()
+    }
+
+    // iterate starting at `index`, skipping whole chunks up to it
+    pub fn iter_from(&self, mut index: usize) -> impl Iterator<Item = &RespFrame> {
+        let mut skip = 0;
+        for chunk in self.chunks.iter() {
+            if index < chunk.len() {
+                break;
+            }
+            index -= chunk.len();
+            skip += 1;
+        }
+        self.chunks.iter().skip(skip).flatten().skip(index)
+    }
+
+    // drop `count` elements from the given end
+    pub fn truncate(&mut self, end: ListEnd, mut count: usize) {
+        count = count.min(self.len);
+        self.len -= count;
+        while count > 0 {
+            let chunk = match end {
+                ListEnd::Left => self.chunks.front_mut(),
+                ListEnd::Right => self.chunks.back_mut(),
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            if chunk.len() <= count {
+                count -= chunk.len();
+                match end {
+                    ListEnd::Left => self.chunks.pop_front(),
+                    ListEnd::Right => self.chunks.pop_back(),
+                };
+                continue;
+            }
+            match end {
+                ListEnd::Left => chunk.drain(..count),
+                ListEnd::Right => chunk.drain(chunk.len() - count..),
+            };
+            count = 0;
+        }
+    }
+
+    // chunk and offset within it of the element at `index`, searched from the nearest end
+    fn locate(&self, index: usize) -> Option<(usize, usize)> {
+        if index >= self.len {
+            return None;
+        }
+        if index < self.len / 2 {
+            let mut offset = index;
+            for (i, chunk) in self.chunks.iter().enumerate() {
+                if offset < chunk.len() {
+                    return Some((i, offset));
+                }
+                offset -= chunk.len();
+            }
+        } else {
+            let mut offset = self.len - 1 - index;
+            for (i, chunk) in self.chunks.iter().enumerate().rev() {
+                if offset < chunk.len() {
+                    return Some((i, chunk.len() - 1 - offset));
+                }
+                offset -= chunk.len();
+            }
+        }
+        None
+    }
+}
+
+impl FromIterator<RespFrame> for List {
+    fn from_iter<T: IntoIterator<Item = RespFrame>>(iter: T) -> Self {
+        let mut list = List::new();
+        for value in iter {
+            list.push(ListEnd::Right, value);
+        }
+        list
+    }
+}
+
+impl Db {
+    // LPUSH and RPUSH, or LPUSHX and RPUSHX when `create` is not set. Returns the length of
+    // the list afterwards.
+    pub fn push(
+        &self,
+        key: String,
+        end: ListEnd,
+        values: Vec<RespFrame>,
+        create: bool,
+    ) -> Result<i64, BackendError> {
+        let push = |list: &mut List| {
+            for value in values {
+                list.push(end, value);
+            }
+            Ok(list.len() as i64)
+        };
+        let len = if create {
+            self.write_list(key.clone(), push)?
+        } else {
+            self.modify_list(&key, push)?.unwrap_or(0)
+        };
+        if len > 0 {
+            self.notify(NotifyFlags::LIST, end.push_event(), &key);
+        }
+        Ok(len)
+    }
+
+    // pop up to `count` elements, None if the key does not exist
+    pub fn pop(
+        &self,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<RespFrame>>, BackendError> {
+        let popped = self.modify_list(key, |list| {
+            Ok((0..count.min(list.len()))
+                .filter_map(|_| list.pop(end))
+                .collect::<Vec<_>>())
+        })?;
+        if popped.as_ref().is_some_and(|values| !values.is_empty()) {
+            self.notify_removal(NotifyFlags::LIST, end.pop_event(), key);
+        }
+        Ok(popped)
+    }
+
+    pub fn llen(&self, key: &str) -> Result<i64, BackendError> {
+        Ok(self.read_list(key, |list| list.len() as i64)?.unwrap_or(0))
+    }
+
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<RespFrame>, BackendError> {
+        let values = self.read_list(key, |list| match resolve_range(start, stop, list.len()) {
+            Some((start, stop)) => list
+                .iter_from(start)
+                .take(stop - start + 1)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        })?;
+        Ok(values.unwrap_or_default())
+    }
+
+    pub fn lindex(&self, key: &str, index: i64) -> Result<Option<RespFrame>, BackendError> {
+        let value = self.read_list(key, |list| {
+            resolve_index(index, list.len()).and_then(|i| list.get(i).cloned())
+        })?;
+        Ok(value.flatten())
+    }
+
+    pub fn lset(&self, key: &str, index: i64, value: RespFrame) -> Result<(), BackendError> {
+        let set = self.modify_list(key, |list| {
+            let index = resolve_index(index, list.len()).ok_or(BackendError::IndexOutOfRange)?;
+            match list.get_mut(index) {
+                Some(v) => *v = value,
+                None => return Err(BackendError::IndexOutOfRange),
+            }
+            Ok(())
+        })?;
+        set.ok_or(BackendError::NoSuchKey)?;
+        self.notify(NotifyFlags::LIST, "lset", key);
+        Ok(())
+    }
+
+    // insert `value` before or after the first occurrence of `pivot`. Returns the new length,
+    // -1 when the pivot was not found and 0 when the key does not exist.
+    pub fn linsert(
+        &self,
+        key: &str,
+        before: bool,
+        pivot: &RespFrame,
+        value: RespFrame,
+    ) -> Result<i64, BackendError> {
+        let pivot = string_bytes(pivot);
+        let len = self.modify_list(key, |list| {
+            let Some(index) = list.iter().position(|v| string_bytes(v) == pivot) else {
+                return Ok(-1);
+            };
+            list.insert(if before { index } else { index + 1 }, value);
+            Ok(list.len() as i64)
+        })?;
+        let len = len.unwrap_or(0);
+        if len > 0 {
+            self.notify(NotifyFlags::LIST, "linsert", key);
+        }
+        Ok(len)
+    }
+
+    // remove occurrences of `value`: the first `count` from the head for a positive count,
+    // from the tail for a negative one, all of them for 0
+    pub fn lrem(&self, key: &str, count: i64, value: &RespFrame) -> Result<i64, BackendError> {
+        let value = string_bytes(value);
+        let removed = self.modify_list(key, |list| {
+            let limit = if count == 0 {
+                usize::MAX
+            } else {
+                count.unsigned_abs() as usize
+            };
+            let mut removed = 0;
+            let mut kept = Vec::with_capacity(list.len());
+            let mut matches = |v: &RespFrame| {
+                let matched = removed < limit && string_bytes(v) == value;
+                if matched {
+                    removed += 1;
+                }
+                matched
+            };
+            if count >= 0 {
+                kept.extend(list.iter().filter(|v| !matches(v)).cloned());
+            } else {
+                kept.extend(list.iter().rev().filter(|v| !matches(v)).cloned());
+                kept.reverse();
+            }
+            if removed > 0 {
+                *list = kept.into_iter().collect();
+            }
+            Ok(removed as i64)
+        })?;
+        let removed = removed.unwrap_or(0);
+        if removed > 0 {
+            self.notify_removal(NotifyFlags::LIST, "lrem", key);
+        }
+        Ok(removed)
+    }
+
+    pub fn ltrim(&self, key: &str, start: i64, stop: i64) -> Result<(), BackendError> {
+        let trimmed = self.modify_list(key, |list| {
+            let len = list.len();
+            match resolve_range(start, stop, len) {
+                Some((start, stop)) => {
+                    list.truncate(ListEnd::Right, len - 1 - stop);
+                    list.truncate(ListEnd::Left, start);
+                }
+                None => list.truncate(ListEnd::Left, len),
+            }
+            Ok(())
+        })?;
+        if trimmed.is_some() {
+            self.notify_removal(NotifyFlags::LIST, "ltrim", key);
+        }
+        Ok(())
+    }
+
+    // positions of `element` like LPOS: skip to the `rank`th match (counting from the tail
+    // when negative), return up to `count` matches (0 for all) and compare at most `maxlen`
+    // elements (0 for all)
+    pub fn lpos(
+        &self,
+        key: &str,
+        element: &RespFrame,
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> Result<Vec<i64>, BackendError> {
+        let element = string_bytes(element);
+        let positions = self.read_list(key, |list| {
+            let len = list.len();
+            let maxlen = if maxlen == 0 { len } else { maxlen.min(len) };
+            let count = if count == 0 { usize::MAX } else { count };
+            let skip = rank.unsigned_abs() as usize - 1;
+            let matches = |(_, v): &(usize, &RespFrame)| string_bytes(v) == element;
+            let positions: Vec<i64> = if rank > 0 {
+                list.iter()
+                    .enumerate()
+                    .take(maxlen)
+                    .filter(matches)
+                    .skip(skip)
+                    .take(count)
+                    .map(|(i, _)| i as i64)
+                    .collect()
+            } else {
+                list.iter()
+                    .rev()
+                    .enumerate()
+                    .take(maxlen)
+                    .filter(matches)
+                    .skip(skip)
+                    .take(count)
+                    .map(|(i, _)| (len - 1 - i) as i64)
+                    .collect()
+            };
+            positions
+        })?;
+        Ok(positions.unwrap_or_default())
+    }
+
+    // pop an element from one end of `src` and push it to an end of `dst`
+    pub fn lmove(
+        &self,
+        src: &str,
+        dst: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<RespFrame>, BackendError> {
+        // fail before popping anything if the destination holds another type
+        self.read_list(dst, |_| ())?;
+        let value = match self.modify_list(src, |list| Ok(list.pop(from)))? {
+            Some(Some(value)) => value,
+            _ => return Ok(None),
+        };
+        // another command may have stored something else at `dst` since it was checked, the
+        // element then goes back where it was popped from rather than being lost
+        let pushed = self.write_list(dst.to_string(), |list| {
+            list.push(to, value.clone());
+            Ok(())
+        });
+        if let Err(e) = pushed {
+            self.write_list(src.to_string(), |list| {
+                list.push(from, value);
+                Ok(())
+            })?;
+            return Err(e);
+        }
+        self.notify_removal(NotifyFlags::LIST, from.pop_event(), src);
+        self.notify(NotifyFlags::LIST, to.push_event(), dst);
+        Ok(Some(value))
+    }
+
+    // pop up to `count` elements from the first non empty list among `keys`
+    pub fn lmpop(
+        &self,
+        keys: &[String],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<(String, Vec<RespFrame>)>, BackendError> {
+        for key in keys {
+            if let Some(values) = self.pop(key, end, count)? {
+                return Ok(Some((key.clone(), values)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_list<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&List) -> R,
+    ) -> Result<Option<R>, BackendError> {
+        self.read(key, |value| match value {
+            Value::List(list) => Ok(f(list)),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+
+    fn write_list<R>(
+        &self,
+        key: String,
+        f: impl FnOnce(&mut List) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        self.write(
+            key,
+            || Value::List(List::new()),
+            |value| match value {
+                Value::List(list) => f(list),
+                _ => Err(BackendError::WrongType),
+            },
+        )
+    }
+
+    fn modify_list<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut List) -> Result<R, BackendError>,
+    ) -> Result<Option<R>, BackendError> {
+        self.modify(key, |value| match value {
+            Value::List(list) => f(list),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+}
+
+// resolve a possibly negative index against a list of `len` elements
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { index + len as i64 } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+// resolve an inclusive LRANGE style range, None when it selects nothing
+fn resolve_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        stop + len
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn frame(i: usize) -> RespFrame {
+        BulkString::new(i.to_string()).into()
+    }
+
+    fn values(list: &List) -> Vec<RespFrame> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_list_spans_chunks() {
+        let mut list = List::new();
+        for i in 0..1000 {
+            list.push(ListEnd::Right, frame(i));
+        }
+        assert_eq!(list.len(), 1000);
+        assert!(list.chunks.len() >= 1000 / LIST_CHUNK_SIZE);
+        assert_eq!(list.get(500), Some(&frame(500)));
+        assert_eq!(list.iter_from(999).next(), Some(&frame(999)));
+
+        list.insert(300, frame(5000));
+        assert_eq!(list.get(300), Some(&frame(5000)));
+        assert_eq!(list.get(301), Some(&frame(300)));
+        assert_eq!(list.remove(300), Some(frame(5000)));
+        assert_eq!(values(&list), (0..1000).map(frame).collect::<Vec<_>>());
+        assert!(list.chunks.iter().all(|c| c.len() <= LIST_CHUNK_SIZE));
+
+        list.truncate(ListEnd::Left, 130);
+        list.truncate(ListEnd::Right, 270);
+        assert_eq!(values(&list), (130..730).map(frame).collect::<Vec<_>>());
+
+        for i in 130..730 {
+            assert_eq!(list.pop(ListEnd::Left), Some(frame(i)));
+        }
+        assert!(list.is_empty());
+        assert!(list.chunks.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_range() {
+        assert_eq!(resolve_range(0, -1, 5), Some((0, 4)));
+        assert_eq!(resolve_range(-100, 100, 5), Some((0, 4)));
+        assert_eq!(resolve_range(3, 1, 5), None);
+        assert_eq!(resolve_range(5, 10, 5), None);
+        assert_eq!(resolve_range(0, -6, 5), None);
+        assert_eq!(resolve_index(-1, 5), Some(4));
+        assert_eq!(resolve_index(5, 5), None);
+    }
+}
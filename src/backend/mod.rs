@@ -1,23 +1,176 @@
-use std::{ops::Deref, sync::Arc};
+mod bitmap;
+mod blocking;
+mod db;
+mod expire;
+mod function;
+mod geo;
+mod glob;
+mod hash;
+mod hyperloglog;
+mod list;
+mod notify;
+mod pubsub;
+mod random;
+mod scan;
+mod script;
+mod session;
+mod set;
+mod slot;
+mod stream;
+mod stream_group;
+mod tracking;
+mod transaction;
+mod value;
+mod zset;
 
-use dashmap::{DashMap, DashSet};
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
 
-use crate::RespFrame;
+use thiserror::Error;
 
+use crate::{RespFrame, SimpleError};
+
+pub use self::{
+    bitmap::{BitFieldOp, BitFieldType, BitOperator, Overflow},
+    blocking::{BlockedOp, Blocking},
+    db::Db,
+    expire::ACTIVE_EXPIRE_INTERVAL,
+    function::{Functions, Library, LibraryFunction, LibraryPolicy},
+    geo::{geohash_score, GeoMatch, GeoOrder, GeoOrigin, GeoQuery, GeoShape},
+    glob::glob_match,
+    hash::{ExpireCondition, FieldTtl, Hash, SetCondition, MAX_FIELD_EXPIRE_MS},
+    hyperloglog::HyperLogLog,
+    list::{List, ListEnd},
+    notify::NotifyFlags,
+    pubsub::{PubSub, SubscriptionKind, PUSH_QUEUE_LIMIT},
+    random::{random_elements, RANDOM_COUNT_MAX},
+    scan::ScanIndex,
+    script::{sha1_hex, RunningScript, Scripts, BUSY_SCRIPT_THRESHOLD},
+    session::Session,
+    set::{Set, SetOperator},
+    slot::{key_slot, SLOT_COUNT},
+    stream::{NewStreamId, Stream, StreamEntry, StreamFields, StreamId, StreamTrim, TrimStrategy},
+    stream_group::{
+        AutoClaimed, ClaimOptions, ConsumerGroup, ConsumerInfo, GroupEntry, GroupInfo, GroupRead,
+        PendingDetail, PendingSummary, StreamInfo,
+    },
+    tracking::{Invalidations, Tracking, TrackingInfo, TrackingOptions, INVALIDATE_CHANNEL},
+    transaction::{Queued, Transaction},
+    value::{now_ms, string_bytes, Entry, Value, LAZYFREE_THRESHOLD},
+    zset::{
+        Aggregate, LexBound, LexRange, ScoreBound, ScoreRange, ZAddOptions, ZPopped, ZRangeSpec,
+        ZSet,
+    },
+};
+
+pub const DEFAULT_DATABASES: usize = 16;
+
+// A handle to the shared server state together with the state of the connection using it.
+// Cloning keeps both, use `connect` to get a handle for a new connection.
 #[derive(Debug, Clone)]
-pub struct Backend(Arc<BackendInner>);
+pub struct Backend {
+    inner: Arc<BackendInner>,
+    session: Arc<Session>,
+}
 
 #[derive(Debug)]
 pub struct BackendInner {
-    map: DashMap<String, RespFrame>,
-    hmap: DashMap<String, DashMap<String, RespFrame>>,
-    set: DashMap<String, DashSet<String>>,
+    dbs: Vec<RwLock<Arc<Db>>>,
+    blocking: Blocking,
+    pubsub: PubSub,
+    scripts: Scripts,
+    functions: Functions,
+    tracking: Tracking,
+    next_client_id: AtomicU64,
+    notify_flags: Arc<AtomicU32>,
+    // shared by running commands, taken exclusively by EXEC. Waiting for it must not hold up
+    // the runtime, a script may keep it for a long time.
+    exec_lock: tokio::sync::RwLock<()>,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BackendError {
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("ERR DB index is out of range")]
+    DbIndexOutOfRange,
+    #[error("ERR source and destination objects are the same")]
+    SameObject,
+    #[error("ERR hash value is not an integer")]
+    HashValueNotInteger,
+    #[error("ERR hash value is not a float")]
+    HashValueNotFloat,
+    #[error("ERR increment or decrement would overflow")]
+    IncrOverflow,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NanOrInfinity,
+    #[error("ERR index out of range")]
+    IndexOutOfRange,
+    #[error("ERR resulting score is not a number (NaN)")]
+    ScoreNan,
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+    #[error("ERR The stream has exhausted the last possible ID, unable to add more items")]
+    StreamExhausted,
+    #[error("NOGROUP No such key '{0}' or consumer group '{1}'")]
+    NoGroup(String, String),
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+    #[error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")]
+    NoStreamKey,
+    #[error("ERR could not decode requested zset member")]
+    GeoMemberNotFound,
+    #[error("WRONGTYPE Key is not a valid HyperLogLog string value.")]
+    InvalidHll,
+    #[error("INVALIDOBJ Corrupted HLL object detected")]
+    CorruptedHll,
+    #[error("ERR invalid expire time in '{0}' command")]
+    InvalidExpireTime(&'static str),
+    #[error("NOTBUSY No scripts in execution right now.")]
+    NotBusy,
+    #[error("UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.")]
+    Unkillable,
+    #[error("ERR Library '{0}' already exists")]
+    LibraryExists(String),
+    #[error("ERR Function {0} already exists")]
+    FunctionExists(String),
+    #[error("ERR Library not found")]
+    LibraryNotFound,
+    #[error("ERR The client ID you want redirect to does not exist")]
+    NoRedirectClient,
+    #[error("ERR You can't switch BCAST mode on/off before disabling tracking for this client, and then re-enabling it with a different mode.")]
+    TrackingBcastSwitch,
+    #[error("ERR You can't switch OPTIN/OPTOUT mode before disabling tracking for this client, and then re-enabling it with a different mode.")]
+    TrackingOptSwitch,
+    #[error("ERR CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled")]
+    CachingWithoutOpt,
+    #[error("ERR CLIENT CACHING YES is only valid when tracking is enabled in OPTIN mode.")]
+    CachingYesWithoutOptin,
+    #[error("ERR CLIENT CACHING NO is only valid when tracking is enabled in OPTOUT mode.")]
+    CachingNoWithoutOptout,
+    #[error("ERR value is out of range")]
+    RandomCountOutOfRange,
+}
+
+impl From<BackendError> for RespFrame {
+    fn from(e: BackendError) -> Self {
+        SimpleError::new(e.to_string()).into()
+    }
 }
 
 impl Deref for Backend {
     type Target = BackendInner;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
     }
 }
 
@@ -26,67 +179,192 @@ impl Backend {
         Self::default()
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    pub fn with_databases(databases: usize) -> Self {
+        Backend {
+            inner: Arc::new(BackendInner::new(databases)),
+            session: Arc::new(Session::default()),
+        }
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    // a handle sharing the server state with a fresh connection state
+    pub fn connect(&self) -> Self {
+        let id = self.next_client_id.fetch_add(1, Ordering::Relaxed);
+        Backend {
+            inner: self.inner.clone(),
+            session: Arc::new(Session::new(id)),
+        }
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    pub fn session(&self) -> &Session {
+        &self.session
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        if !self.hmap.contains_key(&key) {
-            self.hmap.insert(key.clone(), DashMap::new());
+    pub fn databases(&self) -> usize {
+        self.dbs.len()
+    }
+
+    // the currently selected database. The returned handle stays valid even if the
+    // database is flushed or swapped concurrently, in which case it refers to the old data.
+    pub fn db(&self) -> Arc<Db> {
+        self.dbs[self.session.db()].read().unwrap().clone()
+    }
+
+    pub fn db_at(&self, index: usize) -> Result<Arc<Db>, BackendError> {
+        match self.dbs.get(index) {
+            Some(db) => Ok(db.read().unwrap().clone()),
+            None => Err(BackendError::DbIndexOutOfRange),
+        }
+    }
+
+    pub fn select(&self, index: usize) -> Result<(), BackendError> {
+        if index >= self.dbs.len() {
+            return Err(BackendError::DbIndexOutOfRange);
         }
-        self.hmap.get(&key).unwrap().insert(field, value);
+        self.session.select(index);
+        Ok(())
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    // return the connection to the state it started in: no transaction, subscriptions or
+    // tracking, talking RESP2 to database 0
+    pub fn reset(&self) {
+        self.take_transaction();
+        self.unwatch();
+        self.clear_subscriptions();
+        self.disable_tracking();
+        self.session.select(0);
+        self.session.set_protocol(2);
     }
 
-    pub fn sadd(&self, key: String, members: Vec<String>) -> i64 {
-        if !self.set.contains_key(&key) {
-            self.set.insert(key.clone(), DashSet::new());
+    // replace the selected database with an empty one. With `lazy` set the old data is
+    // released on a background task, otherwise it is dropped before returning.
+    pub fn flushdb(&self, lazy: bool) {
+        self.flush_at(self.session.db(), lazy);
+        self.invalidate_everything();
+    }
+
+    pub fn flushall(&self, lazy: bool) {
+        (0..self.dbs.len()).for_each(|index| self.flush_at(index, lazy));
+        self.invalidate_everything();
+    }
+
+    // exchange the data of two databases, visible to every connection at once
+    pub fn swapdb(&self, a: usize, b: usize) -> Result<(), BackendError> {
+        if a >= self.dbs.len() || b >= self.dbs.len() {
+            return Err(BackendError::DbIndexOutOfRange);
         }
-        let mut count = 0;
-        for member in members {
-            if self.set.get(&key).unwrap().insert(member.clone()) {
-                count += 1;
-            }
+        if a == b {
+            return Ok(());
         }
-        count
+        // always lock in index order so that concurrent swaps cannot deadlock
+        let (first, second) = (a.min(b), a.max(b));
+        let mut first = self.dbs[first].write().unwrap();
+        let mut second = self.dbs[second].write().unwrap();
+        std::mem::swap(&mut *first, &mut *second);
+        drop((first, second));
+        self.signal_db(a);
+        self.signal_db(b);
+        Ok(())
     }
 
-    pub fn sismember(&self, key: &str, member: &str) -> Option<i64> {
-        self.set
-            .get(key)
-            .map(|v| if v.contains(member) { 1 } else { 0 })
+    // copy `src` of the selected database to `dst` of database `db` (the selected one if None)
+    pub fn copy(
+        &self,
+        src: &str,
+        dst: &str,
+        db: Option<usize>,
+        replace: bool,
+    ) -> Result<bool, BackendError> {
+        let source = self.db();
+        let index = db.unwrap_or_else(|| self.session.db());
+        let target = self.db_at(index)?;
+        if src == dst && Arc::ptr_eq(&source, &target) {
+            return Err(BackendError::SameObject);
+        }
+        let copied = match source.get_entry(src) {
+            Some(entry) => target.put_entry(dst.to_string(), entry, replace),
+            None => false,
+        };
+        if copied {
+            target.notify(NotifyFlags::GENERIC, "copy_to", dst);
+            self.signal_ready_at(index, dst);
+        }
+        Ok(copied)
+    }
+
+    // move `key` from the selected database to `db`, only if it does not exist there yet
+    pub fn move_key(&self, key: &str, db: usize) -> Result<bool, BackendError> {
+        let source = self.db();
+        let target = self.db_at(db)?;
+        if Arc::ptr_eq(&source, &target) {
+            return Err(BackendError::SameObject);
+        }
+        if target.exists(&[key.to_string()]) > 0 {
+            return Ok(false);
+        }
+        let moved = match source.take_entry(key) {
+            Some(entry) => target.put_entry(key.to_string(), entry, false),
+            None => false,
+        };
+        if moved {
+            source.notify(NotifyFlags::GENERIC, "move_from", key);
+            target.notify(NotifyFlags::GENERIC, "move_to", key);
+            self.signal_ready_at(db, key);
+        }
+        Ok(moved)
+    }
+
+    fn flush_at(&self, index: usize, lazy: bool) {
+        let db = Db::with_notify(self.notify_flags.clone(), self.tracking.invalidations());
+        let old = std::mem::replace(&mut *self.dbs[index].write().unwrap(), Arc::new(db));
+        if lazy {
+            drop_in_background(old);
+        }
+    }
+}
+
+fn lazy_free(entry: Entry) {
+    if entry.value.len() > LAZYFREE_THRESHOLD {
+        drop_in_background(entry);
+    }
+}
+
+fn drop_in_background<T: Send + 'static>(value: T) {
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn_blocking(move || drop(value));
+    }
+}
+
+impl BackendInner {
+    fn new(databases: usize) -> Self {
+        let notify_flags = Arc::new(AtomicU32::new(0));
+        let tracking = Tracking::default();
+        BackendInner {
+            dbs: (0..databases.max(1))
+                .map(|_| {
+                    let db = Db::with_notify(notify_flags.clone(), tracking.invalidations());
+                    RwLock::new(Arc::new(db))
+                })
+                .collect(),
+            blocking: Blocking::default(),
+            pubsub: PubSub::default(),
+            scripts: Scripts::default(),
+            functions: Functions::default(),
+            tracking,
+            next_client_id: AtomicU64::new(1),
+            notify_flags,
+            exec_lock: tokio::sync::RwLock::new(()),
+        }
     }
 }
 
 impl Default for Backend {
     fn default() -> Self {
-        Backend(Arc::new(BackendInner {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
-            set: DashMap::new(),
-        }))
+        Self::with_databases(DEFAULT_DATABASES)
     }
 }
 
 impl Default for BackendInner {
     fn default() -> Self {
-        BackendInner {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
-            set: DashMap::new(),
-        }
+        Self::new(DEFAULT_DATABASES)
     }
 }
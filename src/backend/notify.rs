@@ -0,0 +1,221 @@
+use std::{
+    fmt,
+    ops::BitOr,
+    sync::{atomic::Ordering, Arc},
+};
+
+use super::Backend;
+
+// the classes of notify-keyspace-events, with the flag letters of redis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NotifyFlags(u32);
+
+impl NotifyFlags {
+    pub const NONE: NotifyFlags = NotifyFlags(0);
+    pub const KEYSPACE: NotifyFlags = NotifyFlags(1 << 0);
+    pub const KEYEVENT: NotifyFlags = NotifyFlags(1 << 1);
+    pub const GENERIC: NotifyFlags = NotifyFlags(1 << 2);
+    pub const STRING: NotifyFlags = NotifyFlags(1 << 3);
+    pub const LIST: NotifyFlags = NotifyFlags(1 << 4);
+    pub const SET: NotifyFlags = NotifyFlags(1 << 5);
+    pub const HASH: NotifyFlags = NotifyFlags(1 << 6);
+    pub const ZSET: NotifyFlags = NotifyFlags(1 << 7);
+    pub const EXPIRED: NotifyFlags = NotifyFlags(1 << 8);
+    pub const EVICTED: NotifyFlags = NotifyFlags(1 << 9);
+    pub const STREAM: NotifyFlags = NotifyFlags(1 << 10);
+    pub const KEY_MISS: NotifyFlags = NotifyFlags(1 << 11);
+    pub const NEW: NotifyFlags = NotifyFlags(1 << 12);
+    // what `A` stands for, key misses and new keys have to be asked for on their own
+    pub const ALL: NotifyFlags = NotifyFlags(
+        Self::GENERIC.0
+            | Self::STRING.0
+            | Self::LIST.0
+            | Self::SET.0
+            | Self::HASH.0
+            | Self::ZSET.0
+            | Self::EXPIRED.0
+            | Self::EVICTED.0
+            | Self::STREAM.0,
+    );
+
+    // the class letters in the order redis prints them
+    const CLASSES: [(char, NotifyFlags); 9] = [
+        ('g', Self::GENERIC),
+        ('$', Self::STRING),
+        ('l', Self::LIST),
+        ('s', Self::SET),
+        ('h', Self::HASH),
+        ('z', Self::ZSET),
+        ('x', Self::EXPIRED),
+        ('e', Self::EVICTED),
+        ('t', Self::STREAM),
+    ];
+
+    // parse a notify-keyspace-events value, None if it holds an unknown letter
+    pub fn parse(value: &str) -> Option<Self> {
+        value.chars().try_fold(Self::NONE, |flags, c| {
+            let flag = match c {
+                'A' => Self::ALL,
+                'K' => Self::KEYSPACE,
+                'E' => Self::KEYEVENT,
+                'm' => Self::KEY_MISS,
+                'n' => Self::NEW,
+                c => Self::CLASSES.iter().find(|(l, _)| *l == c)?.1,
+            };
+            Some(flags | flag)
+        })
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        NotifyFlags(bits)
+    }
+
+    pub fn contains(self, other: NotifyFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // whether an event of `class` is published at all, which needs a channel type as well
+    pub fn wants(self, class: NotifyFlags) -> bool {
+        self.0 & class.0 != 0 && self.0 & (Self::KEYSPACE.0 | Self::KEYEVENT.0) != 0
+    }
+}
+
+impl BitOr for NotifyFlags {
+    type Output = NotifyFlags;
+
+    fn bitor(self, rhs: NotifyFlags) -> NotifyFlags {
+        NotifyFlags(self.0 | rhs.0)
+    }
+}
+
+impl fmt::Display for NotifyFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.contains(Self::ALL) {
+            f.write_str("A")?;
+        } else {
+            for (letter, flag) in Self::CLASSES {
+                if self.contains(flag) {
+                    write!(f, "{}", letter)?;
+                }
+            }
+        }
+        for (letter, flag) in [
+            ('K', Self::KEYSPACE),
+            ('E', Self::KEYEVENT),
+            ('m', Self::KEY_MISS),
+            ('n', Self::NEW),
+        ] {
+            if self.contains(flag) {
+                write!(f, "{}", letter)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Backend {
+    pub fn notify_keyspace_events(&self) -> NotifyFlags {
+        NotifyFlags(self.notify_flags.load(Ordering::Relaxed))
+    }
+
+    // events recorded under the previous setting are dropped, not published late
+    pub fn set_notify_keyspace_events(&self, flags: NotifyFlags) {
+        self.notify_flags.store(flags.0, Ordering::Relaxed);
+        for db in &self.dbs {
+            db.read().unwrap().take_events();
+        }
+    }
+
+    // publish the events recorded by the databases, called once a command is done so that
+    // the subscribers see them in the order they happened
+    pub fn publish_keyspace_events(&self) {
+        let flags = self.notify_keyspace_events();
+        if !flags.wants(NotifyFlags(!0)) {
+            return;
+        }
+        for (index, db) in self.dbs.iter().enumerate() {
+            let db = Arc::clone(&db.read().unwrap());
+            for (event, key) in db.take_events() {
+                if flags.contains(NotifyFlags::KEYSPACE) {
+                    self.publish(&format!("__keyspace@{}__:{}", index, key), event.as_bytes());
+                }
+                if flags.contains(NotifyFlags::KEYEVENT) {
+                    self.publish(&format!("__keyevent@{}__:{}", index, event), key.as_bytes());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespFrame, RespPush};
+
+    #[test]
+    fn test_notify_flags() {
+        assert_eq!(
+            NotifyFlags::parse("KEA").map(|f| f.to_string()).as_deref(),
+            Some("AKE")
+        );
+        assert_eq!(
+            NotifyFlags::parse("Ex$").map(|f| f.to_string()).as_deref(),
+            Some("$xE")
+        );
+        assert_eq!(
+            NotifyFlags::parse("").map(|f| f.to_string()).as_deref(),
+            Some("")
+        );
+        assert_eq!(
+            NotifyFlags::parse("g$lshzxetKEmn")
+                .map(|f| f.to_string())
+                .as_deref(),
+            Some("AKEmn")
+        );
+        assert_eq!(NotifyFlags::parse("Kq"), None);
+        // a class without a channel type publishes nothing
+        assert!(!NotifyFlags::parse("A").unwrap().wants(NotifyFlags::STRING));
+        assert!(NotifyFlags::parse("K$").unwrap().wants(NotifyFlags::STRING));
+        assert!(!NotifyFlags::parse("K$").unwrap().wants(NotifyFlags::LIST));
+    }
+
+    #[test]
+    fn test_publish_keyspace_events() {
+        let server = Backend::new();
+        let subscriber = server.connect();
+        let mut pushes = subscriber.open_pushes();
+        subscriber.subscribe(
+            crate::SubscriptionKind::Pattern,
+            &["__key*@0__:*".to_string()],
+        );
+        pushes.try_recv().ok();
+
+        // nothing is recorded while notifications are off
+        server.db().notify(NotifyFlags::STRING, "set", "k");
+        server.set_notify_keyspace_events(NotifyFlags::parse("KE$").unwrap());
+        server.publish_keyspace_events();
+        assert!(pushes.try_recv().is_err());
+
+        server.db().notify(NotifyFlags::GENERIC, "del", "k");
+        server.db().notify(NotifyFlags::STRING, "set", "k");
+        server.publish_keyspace_events();
+        let message = |channel: &str, payload: &str| -> RespFrame {
+            RespPush::new(vec![
+                BulkString::new("pmessage").into(),
+                BulkString::new("__key*@0__:*").into(),
+                BulkString::new(channel).into(),
+                BulkString::new(payload).into(),
+            ])
+            .into()
+        };
+        assert_eq!(
+            pushes.try_recv().ok(),
+            Some(message("__keyspace@0__:k", "set"))
+        );
+        assert_eq!(
+            pushes.try_recv().ok(),
+            Some(message("__keyevent@0__:set", "k"))
+        );
+        assert!(pushes.try_recv().is_err());
+    }
+}
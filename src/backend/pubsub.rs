@@ -0,0 +1,493 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use dashmap::DashMap;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use super::{glob_match, Backend, Session};
+use crate::{BulkString, RespFrame, RespNullBulkString, RespPush};
+
+// the pushes a client may leave unread before it is disconnected, so that a slow subscriber
+// never holds up its publishers
+pub const PUSH_QUEUE_LIMIT: usize = 8192;
+
+// the subscribers of every channel and pattern, and the push queue of every connection.
+// Locks are taken in that order: the channel or pattern first, then the client.
+#[derive(Debug, Default)]
+pub struct PubSub {
+    channels: DashMap<String, BTreeSet<u64>>,
+    patterns: DashMap<String, BTreeSet<u64>>,
+    // shard channels live apart from the others, a PUBLISH never reaches them
+    shards: DashMap<String, BTreeSet<u64>>,
+    clients: DashMap<u64, Client>,
+}
+
+#[derive(Debug)]
+struct Client {
+    pushes: mpsc::Sender<RespFrame>,
+    // the state of the connection, for the protocol it talks
+    session: Arc<Session>,
+    channels: Vec<String>,
+    patterns: Vec<String>,
+    shards: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    Channel,
+    Pattern,
+    Shard,
+}
+
+impl Backend {
+    // the queue of what is pushed to this connection: messages and the replies of the
+    // subscription commands, which go the same way to stay ordered with the messages
+    pub fn open_pushes(&self) -> mpsc::Receiver<RespFrame> {
+        let (sender, receiver) = mpsc::channel(PUSH_QUEUE_LIMIT);
+        let client = Client {
+            pushes: sender,
+            session: self.session.clone(),
+            channels: Vec::new(),
+            patterns: Vec::new(),
+            shards: Vec::new(),
+        };
+        self.pubsub.clients.insert(self.session.id(), client);
+        receiver
+    }
+
+    // drop the subscriptions of a closed connection
+    pub fn close_pushes(&self) {
+        self.pubsub.disconnect(self.session.id());
+    }
+
+    // drop every subscription of this connection without a reply, for RESET
+    pub fn clear_subscriptions(&self) {
+        let id = self.session.id();
+        let Some(mut client) = self.pubsub.clients.get_mut(&id) else {
+            return;
+        };
+        let channels = std::mem::take(&mut client.channels);
+        let patterns = std::mem::take(&mut client.patterns);
+        let shards = std::mem::take(&mut client.shards);
+        drop(client);
+        self.pubsub.forget(id, channels, patterns, shards);
+    }
+
+    // the channels and patterns this connection is subscribed to
+    pub fn subscriptions(&self) -> usize {
+        self.pubsub
+            .clients
+            .get(&self.session.id())
+            .map_or(0, |client| client.total())
+    }
+
+    // subscribe to each name, pushing a reply per name
+    pub fn subscribe(&self, kind: SubscriptionKind, names: &[String]) -> usize {
+        let id = self.session.id();
+        if !self.pubsub.clients.contains_key(&id) {
+            return 0;
+        }
+        let mut slow = false;
+        for name in names {
+            // the reply is pushed before a message can reach the new subscriber
+            let mut subscribers = self
+                .pubsub
+                .subscribers(kind)
+                .entry(name.clone())
+                .or_default();
+            let Some(mut client) = self.pubsub.clients.get_mut(&id) else {
+                break;
+            };
+            if subscribers.insert(id) {
+                client.names_mut(kind).push(name.clone());
+            }
+            let reply = kind.reply(true, BulkString::new(name.as_str()), client.count(kind));
+            slow |= client.pushes.try_send(reply).is_err();
+        }
+        if slow {
+            self.pubsub.disconnect(id);
+        }
+        self.subscriptions()
+    }
+
+    // unsubscribe from each name, or from every subscription of the kind without names,
+    // pushing a reply per name
+    pub fn unsubscribe(&self, kind: SubscriptionKind, names: &[String]) -> usize {
+        let id = self.session.id();
+        let names = match self.pubsub.clients.get(&id) {
+            Some(client) if names.is_empty() => client.names(kind).to_vec(),
+            Some(_) => names.to_vec(),
+            None => return 0,
+        };
+        let mut slow = false;
+        if names.is_empty() {
+            if let Some(client) = self.pubsub.clients.get(&id) {
+                let reply = kind.reply(false, RespNullBulkString, client.count(kind));
+                slow |= client.pushes.try_send(reply).is_err();
+            }
+        }
+        for name in names {
+            let subscribers = self.pubsub.subscribers(kind);
+            let mut entry = subscribers.entry(name.clone()).or_default();
+            let Some(mut client) = self.pubsub.clients.get_mut(&id) else {
+                break;
+            };
+            if entry.remove(&id) {
+                client.names_mut(kind).retain(|n| *n != name);
+            }
+            let reply = kind.reply(false, BulkString::new(name.as_str()), client.count(kind));
+            slow |= client.pushes.try_send(reply).is_err();
+            drop((client, entry));
+            subscribers.remove_if(&name, |_, ids| ids.is_empty());
+        }
+        if slow {
+            self.pubsub.disconnect(id);
+        }
+        self.subscriptions()
+    }
+
+    // send `message` to the subscribers of `channel` and of the patterns matching it,
+    // returning how many received it
+    pub fn publish(&self, channel: &str, message: &[u8]) -> i64 {
+        let (mut received, mut slow) = (0, Vec::new());
+        if let Some(subscribers) = self.pubsub.channels.get(channel) {
+            let frame = RespPush::new(vec![
+                BulkString::new("message").into(),
+                BulkString::new(channel).into(),
+                BulkString::new(message).into(),
+            ])
+            .into();
+            received += self.pubsub.deliver(&subscribers, &frame, &mut slow);
+        }
+        for subscribers in self.pubsub.patterns.iter() {
+            let pattern = subscribers.key();
+            if !glob_match(pattern.as_bytes(), channel.as_bytes(), false) {
+                continue;
+            }
+            let frame = RespPush::new(vec![
+                BulkString::new("pmessage").into(),
+                BulkString::new(pattern.as_str()).into(),
+                BulkString::new(channel).into(),
+                BulkString::new(message).into(),
+            ])
+            .into();
+            received += self.pubsub.deliver(&subscribers, &frame, &mut slow);
+        }
+        self.pubsub.drop_slow(slow);
+        received
+    }
+
+    // send `message` to the subscribers of the shard channel, patterns never match those
+    pub fn spublish(&self, channel: &str, message: &[u8]) -> i64 {
+        let (mut received, mut slow) = (0, Vec::new());
+        if let Some(subscribers) = self.pubsub.shards.get(channel) {
+            let frame = RespPush::new(vec![
+                BulkString::new("smessage").into(),
+                BulkString::new(channel).into(),
+                BulkString::new(message).into(),
+            ])
+            .into();
+            received += self.pubsub.deliver(&subscribers, &frame, &mut slow);
+        }
+        self.pubsub.drop_slow(slow);
+        received
+    }
+
+    // the channels with subscribers, those matching `pattern` if given
+    pub fn pubsub_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        PubSub::active(&self.pubsub.channels, pattern)
+    }
+
+    pub fn pubsub_numsub(&self, channels: &[String]) -> Vec<(String, i64)> {
+        PubSub::numsub(&self.pubsub.channels, channels)
+    }
+
+    // the shard channels with subscribers, those matching `pattern` if given
+    pub fn pubsub_shardchannels(&self, pattern: Option<&str>) -> Vec<String> {
+        PubSub::active(&self.pubsub.shards, pattern)
+    }
+
+    pub fn pubsub_shardnumsub(&self, channels: &[String]) -> Vec<(String, i64)> {
+        PubSub::numsub(&self.pubsub.shards, channels)
+    }
+
+    // the number of patterns with subscribers
+    pub fn pubsub_numpat(&self) -> i64 {
+        self.pubsub.patterns.len() as i64
+    }
+}
+
+impl PubSub {
+    fn subscribers(&self, kind: SubscriptionKind) -> &DashMap<String, BTreeSet<u64>> {
+        match kind {
+            SubscriptionKind::Channel => &self.channels,
+            SubscriptionKind::Pattern => &self.patterns,
+            SubscriptionKind::Shard => &self.shards,
+        }
+    }
+
+    fn active(subscribers: &DashMap<String, BTreeSet<u64>>, pattern: Option<&str>) -> Vec<String> {
+        subscribers
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|channel| {
+                pattern.is_none_or(|p| glob_match(p.as_bytes(), channel.as_bytes(), false))
+            })
+            .collect()
+    }
+
+    fn numsub(
+        subscribers: &DashMap<String, BTreeSet<u64>>,
+        channels: &[String],
+    ) -> Vec<(String, i64)> {
+        channels
+            .iter()
+            .map(|channel| {
+                let count = subscribers.get(channel).map_or(0, |ids| ids.len());
+                (channel.clone(), count as i64)
+            })
+            .collect()
+    }
+
+    // queue `frame` for each subscriber, returning how many got it. Clients whose queue is
+    // full are collected in `slow`, they are dropped once the subscribers lock is released.
+    fn deliver(&self, subscribers: &BTreeSet<u64>, frame: &RespFrame, slow: &mut Vec<u64>) -> i64 {
+        let mut received = 0;
+        for id in subscribers {
+            if let Some(client) = self.clients.get(id) {
+                match client.pushes.try_send(frame.clone()) {
+                    Ok(()) => received += 1,
+                    Err(_) => slow.push(*id),
+                }
+            }
+        }
+        received
+    }
+
+    pub(super) fn connected(&self, id: u64) -> bool {
+        self.clients.contains_key(&id)
+    }
+
+    // whether a connection talks RESP3, None when it is gone
+    pub(super) fn is_resp3(&self, id: u64) -> Option<bool> {
+        self.clients
+            .get(&id)
+            .map(|client| client.session.is_resp3())
+    }
+
+    pub(super) fn is_subscribed(&self, id: u64, channel: &str) -> bool {
+        self.channels
+            .get(channel)
+            .is_some_and(|subscribers| subscribers.contains(&id))
+    }
+
+    // queue `frame` for a single connection, dropping it when it cannot keep up
+    pub(super) fn push_to(&self, id: u64, frame: RespFrame) {
+        let sent = self
+            .clients
+            .get(&id)
+            .map(|client| client.pushes.try_send(frame).is_ok());
+        if sent == Some(false) {
+            self.drop_slow(vec![id]);
+        }
+    }
+
+    fn drop_slow(&self, slow: Vec<u64>) {
+        for id in slow {
+            warn!("disconnecting client {} too slow to read its messages", id);
+            self.disconnect(id);
+        }
+    }
+
+    // forget a client with its subscriptions. Dropping its queue closes the connection once
+    // the pushes left in it are written.
+    fn disconnect(&self, id: u64) {
+        if let Some((_, client)) = self.clients.remove(&id) {
+            self.forget(id, client.channels, client.patterns, client.shards);
+        }
+    }
+
+    // take a client off the subscribers of the names it was subscribed to
+    fn forget(&self, id: u64, channels: Vec<String>, patterns: Vec<String>, shards: Vec<String>) {
+        for (kind, names) in [
+            (SubscriptionKind::Channel, channels),
+            (SubscriptionKind::Pattern, patterns),
+            (SubscriptionKind::Shard, shards),
+        ] {
+            let subscribers = self.subscribers(kind);
+            for name in names {
+                if let Some(mut ids) = subscribers.get_mut(&name) {
+                    ids.remove(&id);
+                }
+                subscribers.remove_if(&name, |_, ids| ids.is_empty());
+            }
+        }
+    }
+}
+
+impl Client {
+    // the count in the replies: channels and patterns count together, like in Redis, shard
+    // channels on their own
+    fn count(&self, kind: SubscriptionKind) -> usize {
+        match kind {
+            SubscriptionKind::Shard => self.shards.len(),
+            _ => self.channels.len() + self.patterns.len(),
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.channels.len() + self.patterns.len() + self.shards.len()
+    }
+
+    fn names(&self, kind: SubscriptionKind) -> &[String] {
+        match kind {
+            SubscriptionKind::Channel => &self.channels,
+            SubscriptionKind::Pattern => &self.patterns,
+            SubscriptionKind::Shard => &self.shards,
+        }
+    }
+
+    fn names_mut(&mut self, kind: SubscriptionKind) -> &mut Vec<String> {
+        match kind {
+            SubscriptionKind::Channel => &mut self.channels,
+            SubscriptionKind::Pattern => &mut self.patterns,
+            SubscriptionKind::Shard => &mut self.shards,
+        }
+    }
+}
+
+impl SubscriptionKind {
+    // the reply to a subscription command for one name, with the subscriptions left
+    fn reply(self, subscribe: bool, name: impl Into<RespFrame>, count: usize) -> RespFrame {
+        let kind = match (self, subscribe) {
+            (SubscriptionKind::Channel, true) => "subscribe",
+            (SubscriptionKind::Channel, false) => "unsubscribe",
+            (SubscriptionKind::Pattern, true) => "psubscribe",
+            (SubscriptionKind::Pattern, false) => "punsubscribe",
+            (SubscriptionKind::Shard, true) => "ssubscribe",
+            (SubscriptionKind::Shard, false) => "sunsubscribe",
+        };
+        RespPush::new(vec![
+            BulkString::new(kind).into(),
+            name.into(),
+            RespFrame::Integer(count as i64),
+        ])
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(items: &[&str]) -> RespFrame {
+        RespPush::new(
+            items
+                .iter()
+                .map(|item| match item.parse::<i64>() {
+                    Ok(n) => RespFrame::Integer(n),
+                    Err(_) => BulkString::new(*item).into(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_subscribe_and_publish() {
+        let server = Backend::new();
+        let (a, b) = (server.connect(), server.connect());
+        let (mut pushes_a, mut pushes_b) = (a.open_pushes(), b.open_pushes());
+
+        assert_eq!(
+            a.subscribe(SubscriptionKind::Channel, &names(&["news", "sport"])),
+            2
+        );
+        assert_eq!(
+            pushes_a.try_recv().ok(),
+            Some(push(&["subscribe", "news", "1"]))
+        );
+        assert_eq!(
+            pushes_a.try_recv().ok(),
+            Some(push(&["subscribe", "sport", "2"]))
+        );
+        assert_eq!(b.subscribe(SubscriptionKind::Pattern, &names(&["n*"])), 1);
+        assert_eq!(
+            pushes_b.try_recv().ok(),
+            Some(push(&["psubscribe", "n*", "1"]))
+        );
+
+        assert_eq!(server.publish("news", b"hello"), 2);
+        assert_eq!(
+            pushes_a.try_recv().ok(),
+            Some(push(&["message", "news", "hello"]))
+        );
+        assert_eq!(
+            pushes_b.try_recv().ok(),
+            Some(push(&["pmessage", "n*", "news", "hello"]))
+        );
+        assert_eq!(server.publish("weather", b"rain"), 0);
+
+        assert_eq!(server.pubsub_channels(None).len(), 2);
+        assert_eq!(server.pubsub_channels(Some("s*")), names(&["sport"]));
+        assert_eq!(
+            server.pubsub_numsub(&names(&["news", "none"])),
+            vec![("news".to_string(), 1), ("none".to_string(), 0)]
+        );
+        assert_eq!(server.pubsub_numpat(), 1);
+
+        // without names every subscription of the kind is dropped
+        assert_eq!(a.unsubscribe(SubscriptionKind::Channel, &[]), 0);
+        assert_eq!(
+            pushes_a.try_recv().ok(),
+            Some(push(&["unsubscribe", "news", "1"]))
+        );
+        assert_eq!(
+            pushes_a.try_recv().ok(),
+            Some(push(&["unsubscribe", "sport", "0"]))
+        );
+        assert_eq!(a.unsubscribe(SubscriptionKind::Channel, &[]), 0);
+        assert_eq!(
+            pushes_a.try_recv().ok(),
+            Some(
+                RespPush::new(vec![
+                    BulkString::new("unsubscribe").into(),
+                    RespNullBulkString.into(),
+                    RespFrame::Integer(0),
+                ])
+                .into()
+            )
+        );
+        assert!(server.pubsub_channels(None).is_empty());
+
+        b.close_pushes();
+        assert_eq!(server.pubsub_numpat(), 0);
+        assert_eq!(server.publish("news", b"hello"), 0);
+    }
+
+    #[test]
+    fn test_slow_subscriber() {
+        let server = Backend::new();
+        let client = server.connect();
+        let mut pushes = client.open_pushes();
+        client.subscribe(SubscriptionKind::Channel, &names(&["ch"]));
+        // the subscribe reply takes one place in the queue
+        for _ in 1..PUSH_QUEUE_LIMIT {
+            assert_eq!(server.publish("ch", b"m"), 1);
+        }
+        assert_eq!(server.publish("ch", b"m"), 0);
+        assert_eq!(client.subscriptions(), 0);
+        assert!(server.pubsub_channels(None).is_empty());
+        // what was queued is still read before the queue closes
+        let mut read = 0;
+        while pushes.try_recv().is_ok() {
+            read += 1;
+        }
+        assert_eq!(read, PUSH_QUEUE_LIMIT);
+        assert!(pushes.try_recv().is_err());
+    }
+}
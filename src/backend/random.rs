@@ -0,0 +1,70 @@
+use rand::{seq::IteratorRandom, Rng};
+
+use super::BackendError;
+
+// the most elements a negative count may ask for, the reply holds that many of them
+pub const RANDOM_COUNT_MAX: u64 = 10_000_000;
+
+// random elements like SRANDMEMBER, HRANDFIELD and ZRANDMEMBER, out of the `len` ones
+// `elements` yields: without a count a single one, with a positive count up to count distinct
+// ones, with a negative count exactly -count possibly repeated ones
+pub fn random_elements<T: Clone>(
+    elements: impl Iterator<Item = T>,
+    len: usize,
+    count: Option<i64>,
+) -> Result<Vec<T>, BackendError> {
+    let mut rng = rand::thread_rng();
+    match count {
+        None => Ok(elements.choose(&mut rng).into_iter().collect()),
+        Some(count) if count >= 0 => {
+            // never more room than there are elements
+            let count = (count as u64).min(len as u64) as usize;
+            Ok(elements.choose_multiple(&mut rng, count))
+        }
+        Some(count) if count.unsigned_abs() > RANDOM_COUNT_MAX => {
+            Err(BackendError::RandomCountOutOfRange)
+        }
+        Some(_) if len == 0 => Ok(Vec::new()),
+        Some(count) => {
+            let all = elements.collect::<Vec<_>>();
+            Ok((0..count.unsigned_abs())
+                .map(|_| all[rng.gen_range(0..all.len())].clone())
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_elements() {
+        let elements = || 0..5;
+        assert_eq!(random_elements(elements(), 5, None).unwrap().len(), 1);
+        assert_eq!(random_elements(0..0, 0, None).unwrap(), Vec::<i32>::new());
+
+        let mut distinct = random_elements(elements(), 5, Some(i64::MAX)).unwrap();
+        distinct.sort();
+        assert_eq!(distinct, vec![0, 1, 2, 3, 4]);
+        assert_eq!(random_elements(elements(), 5, Some(3)).unwrap().len(), 3);
+
+        let repeated = random_elements(elements(), 5, Some(-20)).unwrap();
+        assert_eq!(repeated.len(), 20);
+        assert!(repeated.iter().all(|e| (0..5).contains(e)));
+        assert_eq!(
+            random_elements(0..0, 0, Some(-3)).unwrap(),
+            Vec::<i32>::new()
+        );
+
+        // a huge negative count is refused before anything is allocated
+        assert_eq!(
+            random_elements(elements(), 5, Some(-100_000_000_000)),
+            Err(BackendError::RandomCountOutOfRange)
+        );
+        assert_eq!(
+            random_elements(elements(), 5, Some(i64::MIN)),
+            Err(BackendError::RandomCountOutOfRange)
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    collections::{hash_map::RandomState, BTreeMap},
+    hash::BuildHasher,
+    sync::{Mutex, OnceLock},
+};
+
+use dashmap::{DashMap, DashSet};
+
+// The cursor of SCAN and friends is a position in the space of 64 bit key hashes, the hashes
+// the table itself stores its keys under. Every call returns the keys whose position falls in
+// [cursor, last] and hands out last + 1 as the next cursor, so a key present for the whole
+// iteration is returned exactly once, no matter how the table grows or shrinks between calls.
+//
+// dashmap picks the shard of a key from the bits of its hash right below the top 7. Rotating
+// those bits to the top gives the position, which keeps the positions of a shard contiguous
+// and the shards in order, so a page only ever looks at the shard the cursor points into.
+fn position(hash: u64) -> u64 {
+    hash.rotate_left(7)
+}
+
+fn hash_of(position: u64) -> u64 {
+    position.rotate_right(7)
+}
+
+// a table whose keys a ScanIndex orders: the keyspace, or the fields and members of a
+// collection
+pub trait ScanTable {
+    fn shard_count(&self) -> usize;
+
+    fn key_hash(&self, key: &str) -> u64;
+
+    fn shard_of(&self, hash: u64) -> usize;
+
+    // the keys of `shard` stored under the given hashes, with the number of keys under each
+    fn keys_at(&self, shard: usize, hashes: &[(u64, u32)]) -> Vec<String>;
+}
+
+impl<V> ScanTable for DashMap<String, V> {
+    fn shard_count(&self) -> usize {
+        self.shards().len()
+    }
+
+    fn key_hash(&self, key: &str) -> u64 {
+        self.hash_usize(&key) as u64
+    }
+
+    fn shard_of(&self, hash: u64) -> usize {
+        self.determine_shard(hash as usize)
+    }
+
+    fn keys_at(&self, shard: usize, hashes: &[(u64, u32)]) -> Vec<String> {
+        lookup(&self.shards()[shard].read(), hashes)
+    }
+}
+
+impl ScanTable for DashSet<String> {
+    fn shard_count(&self) -> usize {
+        self.shards().len()
+    }
+
+    fn key_hash(&self, key: &str) -> u64 {
+        self.hash_usize(&key) as u64
+    }
+
+    fn shard_of(&self, hash: u64) -> usize {
+        self.determine_shard(hash as usize)
+    }
+
+    fn keys_at(&self, shard: usize, hashes: &[(u64, u32)]) -> Vec<String> {
+        lookup(&self.shards()[shard].read(), hashes)
+    }
+}
+
+// a table that is not sharded, modified only through a mutable borrow
+impl<V> ScanTable for hashbrown::HashMap<String, V, RandomState> {
+    fn shard_count(&self) -> usize {
+        1
+    }
+
+    fn key_hash(&self, key: &str) -> u64 {
+        self.hasher().hash_one(key)
+    }
+
+    fn shard_of(&self, _hash: u64) -> usize {
+        0
+    }
+
+    fn keys_at(&self, _shard: usize, hashes: &[(u64, u32)]) -> Vec<String> {
+        lookup(self, hashes)
+    }
+}
+
+// find the keys by their hash in the table, walking it only for the hashes shared by several
+// keys
+fn lookup<V>(
+    table: &hashbrown::HashMap<String, V, RandomState>,
+    hashes: &[(u64, u32)],
+) -> Vec<String> {
+    let hash_one = |key: &String| table.hasher().hash_one(key);
+    let mut keys = Vec::with_capacity(hashes.len());
+    for &(hash, count) in hashes {
+        if count == 1 {
+            let found = table
+                .raw_entry()
+                .from_hash(hash, |key| hash_one(key) == hash);
+            keys.extend(found.map(|(key, _)| key.clone()));
+        } else {
+            keys.extend(table.keys().filter(|key| hash_one(key) == hash).cloned());
+        }
+    }
+    keys
+}
+
+// The positions of the keys of a table, ordered so that a page is a range lookup costing
+// O(count + log n) instead of a walk over the whole table. The keys stay in the table only,
+// the index keeps the number of keys at each position. It is sharded like its table and the
+// owner updates it while it holds the lock of the key in the table, so writers to different
+// shards never wait for each other.
+#[derive(Debug, Default)]
+pub struct ScanIndex(OnceLock<Box<[IndexShard]>>);
+
+// the number of keys at each position of a shard
+type IndexShard = Mutex<BTreeMap<u64, u32>>;
+
+impl ScanIndex {
+    pub fn insert(&self, table: &impl ScanTable, key: &str) {
+        let hash = table.key_hash(key);
+        let mut shard = self.shards(table)[table.shard_of(hash)].lock().unwrap();
+        *shard.entry(position(hash)).or_default() += 1;
+    }
+
+    pub fn remove(&self, table: &impl ScanTable, key: &str) {
+        let hash = table.key_hash(key);
+        let mut shard = self.shards(table)[table.shard_of(hash)].lock().unwrap();
+        if let Some(count) = shard.get_mut(&position(hash)) {
+            *count -= 1;
+            if *count == 0 {
+                shard.remove(&position(hash));
+            }
+        }
+    }
+
+    // the keys of the page starting at `cursor` and the cursor of the next page, 0 once the
+    // iteration is complete. Keys sharing a hash are returned together, so a page may hold a
+    // few more than `count` keys.
+    pub fn page(&self, table: &impl ScanTable, cursor: u64, count: usize) -> (u64, Vec<String>) {
+        let count = count.max(1);
+        let shards = self.shards(table);
+        let mut keys = Vec::with_capacity(count);
+        let (mut from, mut taken) = (cursor, 0);
+        let first = table.shard_of(hash_of(cursor));
+        for (shard, positions) in shards.iter().enumerate().skip(first) {
+            // the index lock is released before the table is read, the opposite of the order
+            // writers take them in
+            let positions = positions
+                .lock()
+                .unwrap()
+                .range(from..)
+                .take(count - taken)
+                .map(|(position, keys)| (*position, *keys))
+                .collect::<Vec<_>>();
+            from = 0;
+            let Some(&(last, _)) = positions.last() else {
+                continue;
+            };
+            let hashes = positions
+                .iter()
+                .map(|(position, keys)| (hash_of(*position), *keys))
+                .collect::<Vec<_>>();
+            keys.extend(table.keys_at(shard, &hashes));
+            taken += positions.len();
+            if taken == count {
+                return (last.checked_add(1).unwrap_or(0), keys);
+            }
+        }
+        (0, keys)
+    }
+
+    fn shards(&self, table: &impl ScanTable) -> &[IndexShard] {
+        self.0
+            .get_or_init(|| (0..table.shard_count()).map(|_| Mutex::default()).collect())
+    }
+}
+
+// a copy indexes the copy of the table, which dashmap and hashbrown clone with their hasher
+impl Clone for ScanIndex {
+    fn clone(&self) -> Self {
+        let index = OnceLock::new();
+        if let Some(shards) = self.0.get() {
+            let shards = shards
+                .iter()
+                .map(|shard| Mutex::new(shard.lock().unwrap().clone()))
+                .collect();
+            let _ = index.set(shards);
+        }
+        ScanIndex(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn insert(table: &DashMap<String, ()>, index: &ScanIndex, key: String) {
+        index.insert(table, &key);
+        table.insert(key, ());
+    }
+
+    fn remove(table: &DashMap<String, ()>, index: &ScanIndex, key: &str) {
+        table.remove_if(key, |key, _| {
+            index.remove(table, key);
+            true
+        });
+    }
+
+    #[test]
+    fn test_scan_index_returns_every_key() {
+        let (table, index) = (DashMap::new(), ScanIndex::default());
+        (0..100).for_each(|i| insert(&table, &index, format!("key:{}", i)));
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let (next, page) = index.page(&table, cursor, 7);
+            assert!(page.len() <= 7);
+            for key in page {
+                assert!(seen.insert(key));
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(seen.len(), 100);
+        assert_eq!(index.page(&table, 0, 1000).1.len(), 100);
+    }
+
+    #[test]
+    fn test_scan_index_with_concurrent_modification() {
+        let (table, index) = (DashMap::new(), ScanIndex::default());
+        (0..50).for_each(|i| insert(&table, &index, format!("stable:{}", i)));
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        let mut round = 0;
+        loop {
+            let (next, page) = index.page(&table, cursor, 5);
+            seen.extend(page);
+            // add and remove volatile keys between calls
+            insert(&table, &index, format!("volatile:{}", round));
+            if round % 2 == 0 && round > 0 {
+                remove(&table, &index, &format!("volatile:{}", round - 1));
+            }
+            round += 1;
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        for i in 0..50 {
+            assert!(seen.contains(&format!("stable:{}", i)));
+        }
+        assert_eq!(index.page(&table, u64::MAX, 10).0, 0);
+    }
+
+    #[test]
+    fn test_scan_index_of_unsharded_table() {
+        let mut table = hashbrown::HashMap::<String, f64, RandomState>::default();
+        let index = ScanIndex::default();
+        for i in 0..20 {
+            index.insert(&table, &format!("member:{}", i));
+            table.insert(format!("member:{}", i), i as f64);
+        }
+        let (next, page) = index.page(&table, 0, 15);
+        assert_eq!(page.len(), 15);
+        let (next, rest) = index.page(&table, next, 15);
+        assert_eq!((next, rest.len()), (0, 5));
+    }
+}
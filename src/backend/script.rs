@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use mlua::{Lua, LuaOptions, StdLib};
+
+use super::{Backend, BackendError, Session};
+
+// how long a script runs before other clients are told the server is busy
+pub const BUSY_SCRIPT_THRESHOLD: Duration = Duration::from_secs(5);
+
+// the script cache and the interpreter running them
+pub struct Scripts {
+    // the bodies of the loaded scripts by their sha1
+    bodies: Mutex<HashMap<String, String>>,
+    lua: Mutex<Lua>,
+    running: Mutex<Option<Arc<RunningScript>>>,
+}
+
+// the script being run, SCRIPT KILL looks at it from another connection
+#[derive(Debug)]
+pub struct RunningScript {
+    started: Instant,
+    killed: AtomicBool,
+    wrote: AtomicBool,
+}
+
+impl Backend {
+    // cache a script, returning its sha1
+    pub fn load_script(&self, body: &str) -> String {
+        let sha = sha1_hex(body.as_bytes());
+        self.scripts
+            .bodies
+            .lock()
+            .unwrap()
+            .entry(sha.clone())
+            .or_insert_with(|| body.to_string());
+        sha
+    }
+
+    pub fn script_body(&self, sha: &str) -> Option<String> {
+        let bodies = self.scripts.bodies.lock().unwrap();
+        bodies.get(&sha.to_ascii_lowercase()).cloned()
+    }
+
+    pub fn scripts_exist(&self, shas: &[String]) -> Vec<bool> {
+        let bodies = self.scripts.bodies.lock().unwrap();
+        shas.iter()
+            .map(|sha| bodies.contains_key(&sha.to_ascii_lowercase()))
+            .collect()
+    }
+
+    // forget the cached scripts and start over with a fresh interpreter
+    pub fn flush_scripts(&self) {
+        self.scripts.bodies.lock().unwrap().clear();
+        *self.scripts.lua.lock().unwrap() = new_lua();
+    }
+
+    // the interpreter, held while a script runs
+    pub fn lua(&self) -> MutexGuard<'_, Lua> {
+        self.scripts.lua.lock().unwrap()
+    }
+
+    // a handle for the commands a script calls: it starts on the database of the caller, but
+    // has its own selection and always talks RESP2
+    pub fn script_client(&self) -> Backend {
+        let session = Session::new(self.session.id());
+        session.select(self.session.db());
+        Backend {
+            inner: self.inner.clone(),
+            session: Arc::new(session),
+        }
+    }
+
+    pub fn start_script(&self) -> Arc<RunningScript> {
+        let script = Arc::new(RunningScript {
+            started: Instant::now(),
+            killed: AtomicBool::new(false),
+            wrote: AtomicBool::new(false),
+        });
+        *self.scripts.running.lock().unwrap() = Some(script.clone());
+        script
+    }
+
+    pub fn finish_script(&self) {
+        self.scripts.running.lock().unwrap().take();
+    }
+
+    // whether a script runs for so long already that other clients are turned away
+    pub fn script_busy(&self) -> bool {
+        self.scripts
+            .running
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|script| script.started.elapsed() >= BUSY_SCRIPT_THRESHOLD)
+    }
+
+    // stop the running script, unless it already modified the data
+    pub fn kill_script(&self) -> Result<(), BackendError> {
+        match self.scripts.running.lock().unwrap().as_ref() {
+            None => Err(BackendError::NotBusy),
+            Some(script) if script.wrote() => Err(BackendError::Unkillable),
+            Some(script) => {
+                script.killed.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl RunningScript {
+    pub fn killed(&self) -> bool {
+        self.killed.load(Ordering::Relaxed)
+    }
+
+    pub fn wrote(&self) -> bool {
+        self.wrote.load(Ordering::Relaxed)
+    }
+
+    pub fn set_wrote(&self) {
+        self.wrote.store(true, Ordering::Relaxed);
+    }
+}
+
+// lowercase hex sha1, the name of a script
+pub fn sha1_hex(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
+
+// an interpreter with the libraries redis offers to scripts, without access to files
+pub(super) fn new_lua() -> Lua {
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+        LuaOptions::default(),
+    )
+    .expect("the safe standard libraries always load");
+    for name in ["dofile", "loadfile"] {
+        lua.globals()
+            .raw_set(name, mlua::Value::Nil)
+            .expect("globals are writable while setting up");
+    }
+    lua
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Scripts {
+            bodies: Mutex::new(HashMap::new()),
+            lua: Mutex::new(new_lua()),
+            running: Mutex::new(None),
+        }
+    }
+}
+
+impl fmt::Debug for Scripts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scripts")
+            .field("cached", &self.bodies.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_cache() {
+        let server = Backend::new();
+        let sha = server.load_script("return 1");
+        assert_eq!(sha, "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+        assert_eq!(
+            server.script_body(&sha.to_uppercase()).as_deref(),
+            Some("return 1")
+        );
+        assert_eq!(
+            server.scripts_exist(&[sha.clone(), "ffff".to_string()]),
+            vec![true, false]
+        );
+        server.flush_scripts();
+        assert_eq!(server.script_body(&sha), None);
+    }
+
+    #[test]
+    fn test_kill_script() {
+        let server = Backend::new();
+        assert_eq!(server.kill_script(), Err(BackendError::NotBusy));
+        let script = server.start_script();
+        assert!(!server.script_busy());
+        assert_eq!(server.kill_script(), Ok(()));
+        assert!(script.killed());
+
+        let script = server.start_script();
+        script.set_wrote();
+        assert_eq!(server.kill_script(), Err(BackendError::Unkillable));
+        server.finish_script();
+        assert_eq!(server.kill_script(), Err(BackendError::NotBusy));
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use super::Transaction;
+
+// per connection state
+#[derive(Debug)]
+pub struct Session {
+    // the id of the connection, unique for the lifetime of the server
+    id: u64,
+    db: AtomicUsize,
+    // the RESP version negotiated with HELLO
+    protocol: AtomicU8,
+    transaction: Transaction,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Session {
+    pub fn new(id: u64) -> Self {
+        Session {
+            id,
+            db: AtomicUsize::new(0),
+            protocol: AtomicU8::new(2),
+            transaction: Transaction::default(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn db(&self) -> usize {
+        self.db.load(Ordering::Relaxed)
+    }
+
+    pub fn select(&self, index: usize) {
+        self.db.store(index, Ordering::Relaxed);
+    }
+
+    pub fn protocol(&self) -> u8 {
+        self.protocol.load(Ordering::Relaxed)
+    }
+
+    pub fn set_protocol(&self, protocol: u8) {
+        self.protocol.store(protocol, Ordering::Relaxed);
+    }
+
+    pub fn is_resp3(&self) -> bool {
+        self.protocol() == 3
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+}
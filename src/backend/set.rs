@@ -0,0 +1,314 @@
+use std::{collections::HashSet, ops::Deref};
+
+use dashmap::DashSet;
+use rand::seq::IteratorRandom;
+
+use super::{glob_match, random_elements, BackendError, Db, Entry, NotifyFlags, ScanIndex, Value};
+
+#[derive(Debug, Clone, Default)]
+pub struct Set {
+    members: DashSet<String>,
+    // the members in the order SSCAN walks them
+    index: ScanIndex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperator {
+    Inter,
+    Union,
+    Diff,
+}
+
+impl Deref for Set {
+    type Target = DashSet<String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.members
+    }
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // members only enter and leave the set here. Sets are modified while their key is write
+    // locked, so the scan index cannot fall out of step with the members.
+    pub fn insert(&self, member: String) -> bool {
+        if self.members.contains(&member) {
+            return false;
+        }
+        self.index.insert(&self.members, &member);
+        self.members.insert(member)
+    }
+
+    pub fn remove(&self, member: &str) -> Option<String> {
+        let member = self.members.remove(member)?;
+        self.index.remove(&self.members, &member);
+        Some(member)
+    }
+}
+
+impl FromIterator<String> for Set {
+    fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
+        let set = Set::new();
+        for member in iter {
+            set.insert(member);
+        }
+        set
+    }
+}
+
+impl Db {
+    pub fn sadd(&self, key: String, members: Vec<String>) -> Result<i64, BackendError> {
+        let added = self.write_set(key.clone(), |set| {
+            Ok(members
+                .into_iter()
+                .filter(|m| set.insert(m.clone()))
+                .count() as i64)
+        })?;
+        if added > 0 {
+            self.notify(NotifyFlags::SET, "sadd", &key);
+        }
+        Ok(added)
+    }
+
+    pub fn srem(&self, key: &str, members: &[String]) -> Result<i64, BackendError> {
+        let removed = self.modify_set(key, |set| {
+            Ok(members.iter().filter(|m| set.remove(m).is_some()).count() as i64)
+        })?;
+        let removed = removed.unwrap_or(0);
+        if removed > 0 {
+            self.notify_removal(NotifyFlags::SET, "srem", key);
+        }
+        Ok(removed)
+    }
+
+    pub fn sismember(&self, key: &str, member: &str) -> Result<bool, BackendError> {
+        Ok(self
+            .read_set(key, |set| set.contains(member))?
+            .unwrap_or(false))
+    }
+
+    pub fn smismember(&self, key: &str, members: &[String]) -> Result<Vec<bool>, BackendError> {
+        let found = self.read_set(key, |set| members.iter().map(|m| set.contains(m)).collect())?;
+        Ok(found.unwrap_or_else(|| vec![false; members.len()]))
+    }
+
+    pub fn smembers(&self, key: &str) -> Result<Vec<String>, BackendError> {
+        let members = self.read_set(key, |set| set.iter().map(|m| m.key().clone()).collect())?;
+        Ok(members.unwrap_or_default())
+    }
+
+    pub fn scard(&self, key: &str) -> Result<i64, BackendError> {
+        Ok(self.read_set(key, |set| set.len() as i64)?.unwrap_or(0))
+    }
+
+    // remove and return up to `count` random members
+    pub fn spop(&self, key: &str, count: usize) -> Result<Vec<String>, BackendError> {
+        let popped = self.modify_set(key, |set| {
+            let members = set
+                .iter()
+                .map(|m| m.key().clone())
+                .choose_multiple(&mut rand::thread_rng(), count.min(set.len()));
+            for member in members.iter() {
+                set.remove(member);
+            }
+            Ok(members)
+        })?;
+        let popped = popped.unwrap_or_default();
+        if !popped.is_empty() {
+            self.notify_removal(NotifyFlags::SET, "spop", key);
+        }
+        Ok(popped)
+    }
+
+    // random members like SRANDMEMBER, see `random_elements`
+    pub fn srandmember(&self, key: &str, count: Option<i64>) -> Result<Vec<String>, BackendError> {
+        let members = self.read_set(key, |set| {
+            random_elements(set.iter().map(|m| m.key().clone()), set.len(), count)
+        })?;
+        Ok(members.transpose()?.unwrap_or_default())
+    }
+
+    // move `member` from the set at `src` to the set at `dst`, Ok(false) if it is not in `src`
+    pub fn smove(&self, src: &str, dst: &str, member: &str) -> Result<bool, BackendError> {
+        // both keys have to hold sets (or not exist) before anything is changed
+        self.read_set(dst, |_| ())?;
+        if src == dst {
+            return self.sismember(src, member);
+        }
+        let moved = self.modify_set(src, |set| Ok(set.remove(member).is_some()))?;
+        if moved != Some(true) {
+            return Ok(false);
+        }
+        // another command may have stored something else at `dst` since it was checked, the
+        // member then goes back to `src` rather than being lost
+        let added = match self.write_set(dst.to_string(), |set| Ok(set.insert(member.to_string())))
+        {
+            Ok(added) => added,
+            Err(e) => {
+                self.write_set(src.to_string(), |set| Ok(set.insert(member.to_string())))?;
+                return Err(e);
+            }
+        };
+        self.notify_removal(NotifyFlags::SET, "srem", src);
+        if added {
+            self.notify(NotifyFlags::SET, "sadd", dst);
+        }
+        Ok(true)
+    }
+
+    pub fn sscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> Result<(u64, Vec<String>), BackendError> {
+        let page = self.read_set(key, |set| {
+            let (next, members) = set.index.page(&set.members, cursor, count);
+            let members = members
+                .into_iter()
+                .filter(|m| pattern.is_none_or(|p| glob_match(p.as_bytes(), m.as_bytes(), false)))
+                .collect();
+            (next, members)
+        })?;
+        Ok(page.unwrap_or((0, Vec::new())))
+    }
+
+    // SINTER, SUNION and SDIFF, computed on the sets themselves while all the keys are read
+    // locked, so they are seen in one state
+    pub fn set_op(
+        &self,
+        op: SetOperator,
+        keys: &[String],
+    ) -> Result<HashSet<String>, BackendError> {
+        self.read_sets(keys, |sets| match op {
+            SetOperator::Inter => {
+                let mut result = HashSet::new();
+                intersect(sets, usize::MAX, |m| {
+                    result.insert(m.clone());
+                });
+                result
+            }
+            SetOperator::Union => sets
+                .iter()
+                .flatten()
+                .flat_map(|set| set.iter().map(|m| m.key().clone()))
+                .collect(),
+            SetOperator::Diff => match sets.split_first() {
+                Some((Some(first), others)) => first
+                    .iter()
+                    .filter(|m| others.iter().flatten().all(|set| !set.contains(m.key())))
+                    .map(|m| m.key().clone())
+                    .collect(),
+                _ => HashSet::new(),
+            },
+        })
+    }
+
+    // store the result of `op` at `dst`, replacing whatever is there, and return its size.
+    // An empty result deletes `dst`.
+    pub fn set_op_store(
+        &self,
+        op: SetOperator,
+        dst: String,
+        keys: &[String],
+    ) -> Result<i64, BackendError> {
+        let result = self.set_op(op, keys)?;
+        let len = result.len() as i64;
+        if result.is_empty() {
+            self.del(&[dst]);
+        } else {
+            let set = result.into_iter().collect::<Set>();
+            self.put_entry(dst.clone(), Entry::new(Value::Set(set)), true);
+            let event = match op {
+                SetOperator::Inter => "sinterstore",
+                SetOperator::Union => "sunionstore",
+                SetOperator::Diff => "sdiffstore",
+            };
+            self.notify(NotifyFlags::SET, event, &dst);
+        }
+        Ok(len)
+    }
+
+    // the size of the intersection, counting stops at `limit` unless it is 0
+    pub fn sintercard(&self, keys: &[String], limit: usize) -> Result<i64, BackendError> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        self.read_sets(keys, |sets| {
+            let mut count = 0;
+            intersect(sets, limit, |_| count += 1);
+            count
+        })
+    }
+
+    // run `f` on the sets at `keys` together, None for missing keys, see `Db::read_many`
+    fn read_sets<R>(
+        &self,
+        keys: &[String],
+        f: impl FnOnce(&[Option<&Set>]) -> R,
+    ) -> Result<R, BackendError> {
+        self.read_many(keys, |values| {
+            let sets = values
+                .iter()
+                .map(|value| match value {
+                    Some(Value::Set(set)) => Ok(Some(set)),
+                    Some(_) => Err(BackendError::WrongType),
+                    None => Ok(None),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(f(&sets))
+        })
+    }
+
+    fn read_set<R>(&self, key: &str, f: impl FnOnce(&Set) -> R) -> Result<Option<R>, BackendError> {
+        self.read(key, |value| match value {
+            Value::Set(set) => Ok(f(set)),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+
+    fn write_set<R>(
+        &self,
+        key: String,
+        f: impl FnOnce(&Set) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        self.write(
+            key,
+            || Value::Set(Set::new()),
+            |value| match value {
+                Value::Set(set) => f(set),
+                _ => Err(BackendError::WrongType),
+            },
+        )
+    }
+
+    fn modify_set<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&Set) -> Result<R, BackendError>,
+    ) -> Result<Option<R>, BackendError> {
+        self.modify(key, |value| match value {
+            Value::Set(set) => f(set),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+}
+
+// call `f` with the members of the intersection, walking the smallest set and stopping once
+// `limit` members were found. A missing key is an empty set, which makes the intersection empty.
+fn intersect(sets: &[Option<&Set>], limit: usize, mut f: impl FnMut(&String)) {
+    let Some(mut sets) = sets.iter().copied().collect::<Option<Vec<_>>>() else {
+        return;
+    };
+    sets.sort_by_key(|set| set.len());
+    let Some((smallest, others)) = sets.split_first() else {
+        return;
+    };
+    smallest
+        .iter()
+        .filter(|m| others.iter().all(|set| set.contains(m.key())))
+        .take(limit)
+        .for_each(|m| f(m.key()));
+}
@@ -0,0 +1,563 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::{now_ms, BackendError, ConsumerGroup, Db, NotifyFlags, Value};
+use crate::RespFrame;
+
+// approximate trimming only removes whole nodes of this many entries, like the radix tree
+// nodes of Redis with the default stream-node-max-entries
+const STREAM_NODE_ENTRIES: usize = 100;
+// the default LIMIT of approximate trimming
+const STREAM_TRIM_LIMIT: usize = 100 * STREAM_NODE_ENTRIES;
+
+// the id of a stream entry: a millisecond timestamp and a sequence number within it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+// the id given to XADD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewStreamId {
+    // `*`, generated from the current time
+    Auto,
+    // `ms-*`, the sequence number is generated
+    Partial(u64),
+    Explicit(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+// the trimming of XADD and XTRIM. With `approx` (`~`) fewer entries may be removed, and
+// `limit` caps the number of removed entries, Some(0) meaning no cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    pub approx: bool,
+    pub limit: Option<usize>,
+}
+
+pub type StreamFields = Vec<(String, RespFrame)>;
+pub type StreamEntry = (StreamId, StreamFields);
+
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    pub(super) entries: BTreeMap<StreamId, StreamFields>,
+    // the id of the last entry ever added, later ids must be greater even after deletions
+    pub(super) last_id: StreamId,
+    // the entries added over the lifetime of the stream
+    pub(super) entries_added: u64,
+    pub(super) max_deleted_id: StreamId,
+    pub(super) groups: BTreeMap<String, ConsumerGroup>,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    // `ms-seq`, or just `ms` in which case the sequence number is `missing_seq`
+    pub fn parse(s: &str, missing_seq: u64) -> Option<Self> {
+        match s.split_once('-') {
+            Some((ms, seq)) => Some(StreamId::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(StreamId::new(s.parse().ok()?, missing_seq)),
+        }
+    }
+
+    // the smallest id greater than this one
+    pub fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    // the greatest id smaller than this one
+    pub fn prev(self) -> Option<Self> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn last_entry_id(&self) -> Option<StreamId> {
+        self.entries.last_key_value().map(|(id, _)| *id)
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    pub fn add(&mut self, id: NewStreamId, fields: StreamFields) -> Result<StreamId, BackendError> {
+        let id = self.next_id(id)?;
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+        Ok(id)
+    }
+
+    // the entries from `start` to `end`, both inclusive
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Vec<StreamEntry> {
+        if start > end {
+            return vec![];
+        }
+        let range = self.entries.range(start..=end);
+        let count = count.unwrap_or(usize::MAX);
+        let cloned = |(id, fields): (&StreamId, &StreamFields)| (*id, fields.clone());
+        if rev {
+            range.rev().take(count).map(cloned).collect()
+        } else {
+            range.take(count).map(cloned).collect()
+        }
+    }
+
+    // the entries with an id greater than `id`, as read by XREAD
+    pub fn after(&self, id: StreamId, count: Option<usize>) -> Vec<StreamEntry> {
+        match id.next() {
+            Some(start) => self.range(start, StreamId::MAX, false, count),
+            None => vec![],
+        }
+    }
+
+    pub fn delete(&mut self, ids: &[StreamId]) -> usize {
+        let mut deleted = 0;
+        for id in ids {
+            if self.entries.remove(id).is_some() {
+                self.max_deleted_id = self.max_deleted_id.max(*id);
+                deleted += 1;
+            }
+        }
+        deleted
+    }
+
+    // remove the oldest entries as `trim` asks, returning how many were removed
+    pub fn trim(&mut self, trim: &StreamTrim) -> usize {
+        let excess = match trim.strategy {
+            TrimStrategy::MaxLen(len) => self.entries.len().saturating_sub(len),
+            TrimStrategy::MinId(id) => self.entries.range(..id).count(),
+        };
+        let limit = match (trim.limit, trim.approx) {
+            (Some(0), _) | (None, false) => usize::MAX,
+            (Some(limit), _) => limit,
+            (None, true) => STREAM_TRIM_LIMIT,
+        };
+        let mut count = excess.min(limit);
+        if trim.approx {
+            count -= count % STREAM_NODE_ENTRIES;
+        }
+        for _ in 0..count {
+            self.entries.pop_first();
+        }
+        count
+    }
+
+    fn next_id(&self, id: NewStreamId) -> Result<StreamId, BackendError> {
+        let last = self.last_id;
+        let id = match id {
+            NewStreamId::Auto => {
+                let now = now_ms();
+                if now > last.ms {
+                    StreamId::new(now, 0)
+                } else {
+                    last.next().ok_or(BackendError::StreamExhausted)?
+                }
+            }
+            NewStreamId::Partial(ms) if ms > last.ms => StreamId::new(ms, 0),
+            NewStreamId::Partial(ms) if ms == last.ms => match last.seq.checked_add(1) {
+                Some(seq) => StreamId::new(ms, seq),
+                None => return Err(BackendError::StreamIdTooSmall),
+            },
+            NewStreamId::Partial(_) => return Err(BackendError::StreamIdTooSmall),
+            NewStreamId::Explicit(id) => id,
+        };
+        if id == StreamId::MIN {
+            return Err(BackendError::StreamIdZero);
+        }
+        if id <= last {
+            return Err(BackendError::StreamIdTooSmall);
+        }
+        Ok(id)
+    }
+}
+
+impl Db {
+    // append an entry and trim the stream afterwards. With `nomkstream` a missing key is not
+    // created and None is returned.
+    pub fn xadd(
+        &self,
+        key: String,
+        id: NewStreamId,
+        fields: StreamFields,
+        nomkstream: bool,
+        trim: Option<&StreamTrim>,
+    ) -> Result<Option<StreamId>, BackendError> {
+        // the one id that fails on a new stream, rejected before the stream is created
+        if id == NewStreamId::Explicit(StreamId::MIN) {
+            return Err(BackendError::StreamIdZero);
+        }
+        let mut trimmed = 0;
+        let add = |stream: &mut Stream| {
+            let id = stream.add(id, fields)?;
+            if let Some(trim) = trim {
+                trimmed = stream.trim(trim);
+            }
+            Ok(id)
+        };
+        let added = if nomkstream {
+            self.modify_stream(&key, add)?
+        } else {
+            Some(self.write_stream(key.clone(), add)?)
+        };
+        if added.is_some() {
+            self.notify(NotifyFlags::STREAM, "xadd", &key);
+            if trimmed > 0 {
+                self.notify(NotifyFlags::STREAM, "xtrim", &key);
+            }
+        }
+        Ok(added)
+    }
+
+    pub fn xlen(&self, key: &str) -> Result<i64, BackendError> {
+        Ok(self.read_stream(key, |s| s.len())?.unwrap_or(0) as i64)
+    }
+
+    pub fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Result<Vec<StreamEntry>, BackendError> {
+        Ok(self
+            .read_stream(key, |s| s.range(start, end, rev, count))?
+            .unwrap_or_default())
+    }
+
+    pub fn xdel(&self, key: &str, ids: &[StreamId]) -> Result<i64, BackendError> {
+        let deleted = self.modify_stream(key, |s| Ok(s.delete(ids)))?.unwrap_or(0);
+        if deleted > 0 {
+            self.notify(NotifyFlags::STREAM, "xdel", key);
+        }
+        Ok(deleted as i64)
+    }
+
+    pub fn xtrim(&self, key: &str, trim: &StreamTrim) -> Result<i64, BackendError> {
+        let trimmed = self.modify_stream(key, |s| Ok(s.trim(trim)))?.unwrap_or(0);
+        if trimmed > 0 {
+            self.notify(NotifyFlags::STREAM, "xtrim", key);
+        }
+        Ok(trimmed as i64)
+    }
+
+    // the last id and the id of the last entry of a stream, resolving the `$` and `+` of XREAD
+    pub fn xtail(&self, key: &str) -> Result<Option<(StreamId, Option<StreamId>)>, BackendError> {
+        self.read_stream(key, |s| (s.last_id(), s.last_entry_id()))
+    }
+
+    // the entries after the given id of each stream, leaving out the streams without any
+    pub fn xread(
+        &self,
+        streams: &[(String, StreamId)],
+        count: Option<usize>,
+    ) -> Result<Vec<(String, Vec<StreamEntry>)>, BackendError> {
+        let mut read = Vec::new();
+        for (key, id) in streams {
+            let entries = self
+                .read_stream(key, |s| s.after(*id, count))?
+                .unwrap_or_default();
+            if !entries.is_empty() {
+                read.push((key.clone(), entries));
+            }
+        }
+        Ok(read)
+    }
+
+    pub(super) fn read_stream<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&Stream) -> R,
+    ) -> Result<Option<R>, BackendError> {
+        self.read(key, |value| match value {
+            Value::Stream(stream) => Ok(f(stream)),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+
+    pub(super) fn write_stream<R>(
+        &self,
+        key: String,
+        f: impl FnOnce(&mut Stream) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        self.write(
+            key,
+            || Value::Stream(Stream::new()),
+            |value| match value {
+                Value::Stream(stream) => f(stream),
+                _ => Err(BackendError::WrongType),
+            },
+        )
+    }
+
+    pub(super) fn modify_stream<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut Stream) -> Result<R, BackendError>,
+    ) -> Result<Option<R>, BackendError> {
+        self.modify(key, |value| match value {
+            Value::Stream(stream) => f(stream),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn fields(field: &str) -> StreamFields {
+        vec![(field.to_string(), BulkString::new("v").into())]
+    }
+
+    fn ids(entries: Vec<StreamEntry>) -> Vec<String> {
+        entries.into_iter().map(|(id, _)| id.to_string()).collect()
+    }
+
+    fn trim(strategy: TrimStrategy, approx: bool, limit: Option<usize>) -> StreamTrim {
+        StreamTrim {
+            strategy,
+            approx,
+            limit,
+        }
+    }
+
+    #[test]
+    fn test_stream_id() {
+        assert_eq!(StreamId::parse("5-3", 0), Some(StreamId::new(5, 3)));
+        assert_eq!(
+            StreamId::parse("5", u64::MAX),
+            Some(StreamId::new(5, u64::MAX))
+        );
+        assert_eq!(StreamId::parse("5-x", 0), None);
+        assert_eq!(StreamId::parse("-1", 0), None);
+        assert_eq!(StreamId::new(1, u64::MAX).next(), Some(StreamId::new(2, 0)));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(StreamId::new(2, 0).prev(), Some(StreamId::new(1, u64::MAX)));
+        assert_eq!(StreamId::MIN.prev(), None);
+        assert_eq!(
+            StreamId::new(1526919030474, 55).to_string(),
+            "1526919030474-55"
+        );
+    }
+
+    #[test]
+    fn test_stream_add_ids() {
+        let mut stream = Stream::new();
+        assert_eq!(
+            stream.add(NewStreamId::Explicit(StreamId::MIN), fields("a")),
+            Err(BackendError::StreamIdZero)
+        );
+        assert_eq!(
+            stream.add(NewStreamId::Partial(0), fields("a")),
+            Ok(StreamId::new(0, 1))
+        );
+        assert_eq!(
+            stream.add(NewStreamId::Explicit(StreamId::new(5, 5)), fields("a")),
+            Ok(StreamId::new(5, 5))
+        );
+        assert_eq!(
+            stream.add(NewStreamId::Partial(5), fields("a")),
+            Ok(StreamId::new(5, 6))
+        );
+        assert_eq!(
+            stream.add(NewStreamId::Partial(4), fields("a")),
+            Err(BackendError::StreamIdTooSmall)
+        );
+        assert_eq!(
+            stream.add(NewStreamId::Explicit(StreamId::new(5, 6)), fields("a")),
+            Err(BackendError::StreamIdTooSmall)
+        );
+        let auto = stream.add(NewStreamId::Auto, fields("a")).unwrap();
+        assert!(auto > StreamId::new(5, 6));
+        assert_eq!(stream.len(), 4);
+        assert_eq!(stream.entries_added(), 4);
+
+        // ids keep increasing past the last entry even when it is deleted
+        let mut stream = Stream::new();
+        let future = StreamId::new(u64::MAX, 0);
+        stream
+            .add(NewStreamId::Explicit(future), fields("a"))
+            .unwrap();
+        assert_eq!(stream.delete(&[future]), 1);
+        assert_eq!(stream.max_deleted_id(), future);
+        assert_eq!(
+            stream.add(NewStreamId::Auto, fields("a")),
+            Ok(StreamId::new(u64::MAX, 1))
+        );
+        stream
+            .add(NewStreamId::Explicit(StreamId::MAX), fields("a"))
+            .unwrap();
+        assert_eq!(
+            stream.add(NewStreamId::Auto, fields("a")),
+            Err(BackendError::StreamExhausted)
+        );
+    }
+
+    #[test]
+    fn test_stream_range_and_trim() {
+        let mut stream = Stream::new();
+        for ms in 1..=250 {
+            stream.add(NewStreamId::Partial(ms), fields("a")).unwrap();
+        }
+        let range = stream.range(StreamId::new(2, 0), StreamId::new(4, 0), false, None);
+        assert_eq!(ids(range), ["2-0", "3-0", "4-0"]);
+        let range = stream.range(StreamId::MIN, StreamId::MAX, true, Some(2));
+        assert_eq!(ids(range), ["250-0", "249-0"]);
+        assert!(stream
+            .range(StreamId::new(4, 0), StreamId::new(2, 0), false, None)
+            .is_empty());
+        assert_eq!(
+            ids(stream.after(StreamId::new(248, 0), None)),
+            ["249-0", "250-0"]
+        );
+
+        // approximate trimming removes whole nodes only
+        assert_eq!(stream.trim(&trim(TrimStrategy::MaxLen(200), true, None)), 0);
+        assert_eq!(
+            stream.trim(&trim(TrimStrategy::MaxLen(120), true, None)),
+            100
+        );
+        assert_eq!(
+            stream.trim(&trim(TrimStrategy::MaxLen(10), false, None)),
+            140
+        );
+        assert_eq!(
+            ids(stream.range(StreamId::MIN, StreamId::MAX, false, Some(1))),
+            ["241-0"]
+        );
+        assert_eq!(
+            stream.trim(&trim(
+                TrimStrategy::MinId(StreamId::new(245, 0)),
+                false,
+                None
+            )),
+            4
+        );
+        assert_eq!(stream.len(), 6);
+        assert_eq!(
+            stream.trim(&trim(TrimStrategy::MaxLen(0), true, Some(0))),
+            0
+        );
+    }
+
+    #[test]
+    fn test_xadd_keeps_stream() -> Result<(), BackendError> {
+        let db = Db::new();
+        assert_eq!(
+            db.xadd("s".to_string(), NewStreamId::Auto, fields("a"), true, None),
+            Ok(None)
+        );
+        assert_eq!(db.key_type("s"), "none");
+        assert_eq!(
+            db.xadd(
+                "s".to_string(),
+                NewStreamId::Explicit(StreamId::MIN),
+                fields("a"),
+                false,
+                None
+            ),
+            Err(BackendError::StreamIdZero)
+        );
+        assert_eq!(db.key_type("s"), "none");
+
+        let maxlen = trim(TrimStrategy::MaxLen(0), false, None);
+        let id = db.xadd(
+            "s".to_string(),
+            NewStreamId::Partial(1),
+            fields("a"),
+            false,
+            Some(&maxlen),
+        )?;
+        assert_eq!(id, Some(StreamId::new(1, 0)));
+        // trimmed to nothing, but the stream stays around until its key is deleted
+        assert_eq!(db.xlen("s"), Ok(0));
+        assert_eq!(db.key_type("s"), "stream");
+        assert_eq!(db.xtail("s"), Ok(Some((StreamId::new(1, 0), None))));
+
+        db.xadd(
+            "s".to_string(),
+            NewStreamId::Partial(2),
+            fields("a"),
+            false,
+            None,
+        )?;
+        db.xadd(
+            "s".to_string(),
+            NewStreamId::Partial(3),
+            fields("a"),
+            false,
+            None,
+        )?;
+        assert_eq!(
+            db.xdel("s", &[StreamId::new(2, 0), StreamId::new(9, 0)]),
+            Ok(1)
+        );
+        let read = db.xread(
+            &[
+                ("s".to_string(), StreamId::new(1, 0)),
+                ("missing".to_string(), StreamId::MIN),
+            ],
+            None,
+        )?;
+        assert_eq!(read.len(), 1);
+        assert_eq!(ids(read[0].1.clone()), ["3-0"]);
+        db.set("str".to_string(), BulkString::new("x").into());
+        assert_eq!(db.xlen("str"), Err(BackendError::WrongType));
+        Ok(())
+    }
+}
@@ -0,0 +1,858 @@
+use std::collections::BTreeMap;
+
+use super::{now_ms, BackendError, Db, NotifyFlags, Stream, StreamEntry, StreamFields, StreamId};
+
+// a consumer group keeps its own position in the stream and the entries it delivered to its
+// consumers until they are acknowledged
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerGroup {
+    // the last entry delivered to any consumer of the group
+    last_id: StreamId,
+    // the logical position of `last_id` in the stream, None when it cannot be known anymore
+    entries_read: Option<u64>,
+    // the pending entries list: delivered but not acknowledged yet
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<String, Consumer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub consumer: String,
+    // unix time in milliseconds of the last delivery
+    pub delivered_at: u64,
+    pub deliveries: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Consumer {
+    // the last time the consumer interacted with the group
+    seen_at: u64,
+    // the last time it was delivered or claimed entries
+    active_at: Option<u64>,
+}
+
+// where XREADGROUP reads from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupRead {
+    // `>`, entries never delivered to the group
+    New,
+    // the pending entries of the consumer after an id
+    History(StreamId),
+}
+
+// the options of XCLAIM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimOptions {
+    // IDLE in milliseconds, or TIME as unix time in milliseconds
+    pub idle: Option<u64>,
+    pub time: Option<u64>,
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub justid: bool,
+    pub last_id: Option<StreamId>,
+}
+
+// an entry read by a consumer, without fields if it was deleted from the stream meanwhile
+pub type GroupEntry = (StreamId, Option<StreamFields>);
+
+// the number of pending entries, their smallest and greatest id and the count per consumer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingSummary {
+    pub count: usize,
+    pub bounds: Option<(StreamId, StreamId)>,
+    pub consumers: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingDetail {
+    pub id: StreamId,
+    pub consumer: String,
+    pub idle: u64,
+    pub deliveries: u64,
+}
+
+// the cursor to continue XAUTOCLAIM from, the claimed entries and the ids that were pending but
+// have been deleted from the stream
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutoClaimed {
+    pub next: StreamId,
+    pub claimed: Vec<StreamEntry>,
+    pub deleted: Vec<StreamId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_id: StreamId,
+    pub max_deleted_id: StreamId,
+    pub entries_added: u64,
+    pub first_id: StreamId,
+    pub groups: usize,
+    pub first_entry: Option<StreamEntry>,
+    pub last_entry: Option<StreamEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_id: StreamId,
+    pub entries_read: Option<u64>,
+    pub lag: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    pub idle: u64,
+    pub inactive: Option<u64>,
+}
+
+impl ConsumerGroup {
+    fn new(last_id: StreamId, entries_read: Option<u64>) -> Self {
+        ConsumerGroup {
+            last_id,
+            entries_read,
+            ..Default::default()
+        }
+    }
+
+    // look up a consumer, creating it if needed, and record the interaction
+    fn seen(&mut self, name: &str, now: u64) -> &mut Consumer {
+        let consumer = self.consumers.entry(name.to_string()).or_insert(Consumer {
+            seen_at: now,
+            active_at: None,
+        });
+        consumer.seen_at = now;
+        consumer
+    }
+
+    fn pending_of(&self, consumer: &str) -> impl Iterator<Item = (&StreamId, &PendingEntry)> {
+        let consumer = consumer.to_string();
+        self.pending
+            .iter()
+            .filter(move |(_, entry)| entry.consumer == consumer)
+    }
+}
+
+impl Stream {
+    // run `f` on a group while the rest of the stream can still be read
+    fn with_group<R>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&mut Stream, &mut ConsumerGroup) -> R,
+    ) -> Option<R> {
+        let mut group = self.groups.remove(name)?;
+        let result = f(self, &mut group);
+        self.groups.insert(name.to_string(), group);
+        Some(result)
+    }
+
+    // entries deleted at or after `start`, which makes counting entries from there unreliable
+    fn has_tombstones(&self, start: StreamId) -> bool {
+        !self.entries.is_empty()
+            && self.max_deleted_id != StreamId::MIN
+            && start <= self.max_deleted_id
+    }
+
+    // the logical position of `id` counted from the first entry ever added, if it can be known
+    fn estimate_entries_read(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 || (self.entries.is_empty() && id <= self.last_id) {
+            return Some(self.entries_added);
+        }
+        if id == self.last_id {
+            return Some(self.entries_added);
+        }
+        if id > self.last_id {
+            return None;
+        }
+        let first = *self.entries.first_key_value()?.0;
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first {
+            let before_first = self.entries_added - self.entries.len() as u64;
+            if id < first {
+                return Some(before_first);
+            }
+            if id == first {
+                return Some(before_first + 1);
+            }
+        }
+        None
+    }
+
+    // how many entries the group still has to read, if it can be known
+    fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        let entries_read = match group.entries_read {
+            Some(read) if !self.has_tombstones(group.last_id) => Some(read),
+            _ => self.estimate_entries_read(group.last_id),
+        };
+        entries_read.map(|read| self.entries_added.saturating_sub(read))
+    }
+
+    fn read_new(
+        &self,
+        group: &mut ConsumerGroup,
+        consumer: &str,
+        count: Option<usize>,
+        noack: bool,
+        now: u64,
+    ) -> Vec<GroupEntry> {
+        let entries = self.after(group.last_id, count);
+        for (id, _) in &entries {
+            group.entries_read = match group.entries_read {
+                Some(read) if !self.has_tombstones(*id) => Some(read + 1),
+                _ => self.estimate_entries_read(*id),
+            };
+            group.last_id = *id;
+            if !noack {
+                // an entry delivered before the group was moved back changes hands
+                group.pending.insert(
+                    *id,
+                    PendingEntry {
+                        consumer: consumer.to_string(),
+                        delivered_at: now,
+                        deliveries: 1,
+                    },
+                );
+            }
+        }
+        let seen = group.seen(consumer, now);
+        if !entries.is_empty() {
+            seen.active_at = Some(now);
+        }
+        entries
+            .into_iter()
+            .map(|(id, fields)| (id, Some(fields)))
+            .collect()
+    }
+
+    fn read_history(
+        &self,
+        group: &mut ConsumerGroup,
+        consumer: &str,
+        after: StreamId,
+        count: Option<usize>,
+        now: u64,
+    ) -> Vec<GroupEntry> {
+        let Some(start) = after.next() else {
+            return vec![];
+        };
+        let ids = group
+            .pending
+            .range(start..)
+            .filter(|(_, entry)| entry.consumer == consumer)
+            .map(|(id, _)| *id)
+            .take(count.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+        ids.into_iter()
+            .map(|id| {
+                if let Some(entry) = group.pending.get_mut(&id) {
+                    entry.delivered_at = now;
+                    entry.deliveries += 1;
+                }
+                (id, self.entries.get(&id).cloned())
+            })
+            .collect()
+    }
+}
+
+impl Db {
+    // create a group starting after `id`, None for the last id of the stream (`$`)
+    pub fn xgroup_create(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        let create = |stream: &mut Stream| {
+            if stream.groups.contains_key(group) {
+                return Err(BackendError::BusyGroup);
+            }
+            let last_id = id.unwrap_or(stream.last_id);
+            stream
+                .groups
+                .insert(group.to_string(), ConsumerGroup::new(last_id, entries_read));
+            Ok(())
+        };
+        if mkstream {
+            self.write_stream(key.to_string(), create)?;
+        } else {
+            self.modify_stream(key, create)?
+                .ok_or(BackendError::NoStreamKey)?;
+        }
+        self.notify(NotifyFlags::STREAM, "xgroup-create", key);
+        Ok(())
+    }
+
+    pub fn xgroup_destroy(&self, key: &str, group: &str) -> Result<bool, BackendError> {
+        let destroyed = self
+            .modify_stream(key, |stream| Ok(stream.groups.remove(group).is_some()))?
+            .ok_or(BackendError::NoStreamKey)?;
+        if destroyed {
+            self.notify(NotifyFlags::STREAM, "xgroup-destroy", key);
+        }
+        Ok(destroyed)
+    }
+
+    // move the group to `id`, None for the last id of the stream (`$`)
+    pub fn xgroup_setid(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        self.modify_stream(key, |stream| {
+            let last_id = id.unwrap_or(stream.last_id);
+            let cg = stream
+                .groups
+                .get_mut(group)
+                .ok_or_else(|| no_group(key, group))?;
+            cg.last_id = last_id;
+            cg.entries_read = entries_read;
+            Ok(())
+        })?
+        .ok_or(BackendError::NoStreamKey)?;
+        self.notify(NotifyFlags::STREAM, "xgroup-setid", key);
+        Ok(())
+    }
+
+    // whether the consumer was created
+    pub fn xgroup_createconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<bool, BackendError> {
+        let created = self.modify_group(key, group, |_, cg| {
+            if cg.consumers.contains_key(consumer) {
+                return false;
+            }
+            cg.seen(consumer, now_ms());
+            true
+        })?;
+        if created {
+            self.notify(NotifyFlags::STREAM, "xgroup-createconsumer", key);
+        }
+        Ok(created)
+    }
+
+    // delete a consumer together with its pending entries, returning how many it had
+    pub fn xgroup_delconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<i64, BackendError> {
+        let mut deleted = false;
+        let pending = self.modify_group(key, group, |_, cg| {
+            if cg.consumers.remove(consumer).is_none() {
+                return 0;
+            }
+            deleted = true;
+            let before = cg.pending.len();
+            cg.pending.retain(|_, entry| entry.consumer != consumer);
+            (before - cg.pending.len()) as i64
+        })?;
+        if deleted {
+            self.notify(NotifyFlags::STREAM, "xgroup-delconsumer", key);
+        }
+        Ok(pending)
+    }
+
+    // read for `consumer` of `group` from each stream. Streams read with `>` are left out when
+    // there was nothing new, history reads are always part of the result.
+    pub fn xreadgroup(
+        &self,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, GroupRead)],
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<(String, Vec<GroupEntry>)>, BackendError> {
+        // fail before anything is delivered if a group is missing
+        for (key, _) in streams {
+            let exists = self.read_stream(key, |stream| stream.groups.contains_key(group))?;
+            if exists != Some(true) {
+                return Err(no_group(key, group));
+            }
+        }
+        let now = now_ms();
+        let mut read = Vec::new();
+        for (key, from) in streams {
+            let entries = self.modify_group(key, group, |stream, cg| match from {
+                GroupRead::New => stream.read_new(cg, consumer, count, noack, now),
+                GroupRead::History(after) => {
+                    cg.seen(consumer, now);
+                    stream.read_history(cg, consumer, *after, count, now)
+                }
+            })?;
+            if !entries.is_empty() || matches!(from, GroupRead::History(_)) {
+                read.push((key.clone(), entries));
+            }
+        }
+        Ok(read)
+    }
+
+    pub fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> Result<i64, BackendError> {
+        let acked = self.modify_group(key, group, |_, cg| {
+            ids.iter()
+                .filter(|id| cg.pending.remove(id).is_some())
+                .count() as i64
+        });
+        match acked {
+            Err(BackendError::NoGroup(..)) => Ok(0),
+            acked => acked,
+        }
+    }
+
+    pub fn xpending_summary(&self, key: &str, group: &str) -> Result<PendingSummary, BackendError> {
+        self.read_group(key, group, |_, cg| {
+            let mut consumers = BTreeMap::<&str, usize>::new();
+            for entry in cg.pending.values() {
+                *consumers.entry(&entry.consumer).or_default() += 1;
+            }
+            let bounds = cg
+                .pending
+                .first_key_value()
+                .zip(cg.pending.last_key_value())
+                .map(|((first, _), (last, _))| (*first, *last));
+            PendingSummary {
+                count: cg.pending.len(),
+                bounds,
+                consumers: consumers
+                    .into_iter()
+                    .map(|(name, count)| (name.to_string(), count))
+                    .collect(),
+            }
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn xpending(
+        &self,
+        key: &str,
+        group: &str,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        consumer: Option<&str>,
+        min_idle: Option<u64>,
+    ) -> Result<Vec<PendingDetail>, BackendError> {
+        self.read_group(key, group, |_, cg| {
+            if start > end {
+                return vec![];
+            }
+            let now = now_ms();
+            cg.pending
+                .range(start..=end)
+                .filter(|(_, entry)| consumer.is_none_or(|c| entry.consumer == c))
+                .map(|(id, entry)| PendingDetail {
+                    id: *id,
+                    consumer: entry.consumer.clone(),
+                    idle: now.saturating_sub(entry.delivered_at),
+                    deliveries: entry.deliveries,
+                })
+                .filter(|detail| min_idle.is_none_or(|idle| detail.idle >= idle))
+                .take(count)
+                .collect()
+        })
+    }
+
+    // take over pending entries idle for at least `min_idle` milliseconds
+    pub fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Vec<StreamEntry>, BackendError> {
+        self.modify_group(key, group, |stream, cg| {
+            let now = now_ms();
+            let delivered_at = options
+                .time
+                .or(options.idle.map(|idle| now.saturating_sub(idle)))
+                .unwrap_or(now);
+            let mut claimed = Vec::new();
+            for id in ids {
+                let fields = stream.entries.get(id);
+                if !cg.pending.contains_key(id) {
+                    if !options.force || fields.is_none() {
+                        continue;
+                    }
+                    cg.pending.insert(
+                        *id,
+                        PendingEntry {
+                            consumer: consumer.to_string(),
+                            delivered_at: now,
+                            deliveries: 0,
+                        },
+                    );
+                }
+                let Some(fields) = fields else {
+                    // deleted from the stream, there is nothing left to deliver
+                    cg.pending.remove(id);
+                    continue;
+                };
+                let Some(entry) = cg.pending.get_mut(id) else {
+                    continue;
+                };
+                if min_idle > 0 && now.saturating_sub(entry.delivered_at) < min_idle {
+                    continue;
+                }
+                entry.consumer = consumer.to_string();
+                entry.delivered_at = delivered_at;
+                match options.retry_count {
+                    Some(count) => entry.deliveries = count,
+                    None if !options.justid => entry.deliveries += 1,
+                    None => (),
+                }
+                claimed.push((*id, fields.clone()));
+            }
+            if let Some(last_id) = options.last_id {
+                cg.last_id = cg.last_id.max(last_id);
+            }
+            let seen = cg.seen(consumer, now);
+            if !claimed.is_empty() {
+                seen.active_at = Some(now);
+            }
+            claimed
+        })
+    }
+
+    // XCLAIM for up to `count` pending entries from `start` on, looking at no more than ten
+    // times as many entries like Redis
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        justid: bool,
+    ) -> Result<AutoClaimed, BackendError> {
+        self.modify_group(key, group, |stream, cg| {
+            let now = now_ms();
+            let mut result = AutoClaimed::default();
+            let mut attempts = count.saturating_mul(10);
+            let mut cursor = Some(start);
+            while let Some(from) = cursor {
+                let Some(id) = cg.pending.range(from..).next().map(|(id, _)| *id) else {
+                    break;
+                };
+                if attempts == 0 || result.claimed.len() >= count {
+                    result.next = id;
+                    break;
+                }
+                attempts -= 1;
+                cursor = id.next();
+                let Some(fields) = stream.entries.get(&id) else {
+                    cg.pending.remove(&id);
+                    result.deleted.push(id);
+                    continue;
+                };
+                let Some(entry) = cg.pending.get_mut(&id) else {
+                    continue;
+                };
+                if now.saturating_sub(entry.delivered_at) < min_idle {
+                    continue;
+                }
+                entry.consumer = consumer.to_string();
+                entry.delivered_at = now;
+                if !justid {
+                    entry.deliveries += 1;
+                }
+                result.claimed.push((id, fields.clone()));
+            }
+            let seen = cg.seen(consumer, now);
+            if !result.claimed.is_empty() {
+                seen.active_at = Some(now);
+            }
+            result
+        })
+    }
+
+    pub fn xinfo_stream(&self, key: &str) -> Result<StreamInfo, BackendError> {
+        self.read_stream(key, |stream| {
+            let entry = |(id, fields): (&StreamId, &StreamFields)| (*id, fields.clone());
+            StreamInfo {
+                length: stream.len(),
+                last_id: stream.last_id,
+                max_deleted_id: stream.max_deleted_id,
+                entries_added: stream.entries_added,
+                first_id: stream.entries.keys().next().copied().unwrap_or_default(),
+                groups: stream.groups.len(),
+                first_entry: stream.entries.first_key_value().map(entry),
+                last_entry: stream.entries.last_key_value().map(entry),
+            }
+        })?
+        .ok_or(BackendError::NoSuchKey)
+    }
+
+    pub fn xinfo_groups(&self, key: &str) -> Result<Vec<GroupInfo>, BackendError> {
+        self.read_stream(key, |stream| {
+            stream
+                .groups
+                .iter()
+                .map(|(name, cg)| GroupInfo {
+                    name: name.clone(),
+                    consumers: cg.consumers.len(),
+                    pending: cg.pending.len(),
+                    last_id: cg.last_id,
+                    entries_read: cg.entries_read,
+                    lag: stream.lag(cg),
+                })
+                .collect()
+        })?
+        .ok_or(BackendError::NoSuchKey)
+    }
+
+    pub fn xinfo_consumers(
+        &self,
+        key: &str,
+        group: &str,
+    ) -> Result<Vec<ConsumerInfo>, BackendError> {
+        self.read_group(key, group, |_, cg| {
+            let now = now_ms();
+            cg.consumers
+                .iter()
+                .map(|(name, consumer)| ConsumerInfo {
+                    name: name.clone(),
+                    pending: cg.pending_of(name).count(),
+                    idle: now.saturating_sub(consumer.seen_at),
+                    inactive: consumer.active_at.map(|at| now.saturating_sub(at)),
+                })
+                .collect()
+        })
+    }
+
+    fn read_group<R>(
+        &self,
+        key: &str,
+        group: &str,
+        f: impl FnOnce(&Stream, &ConsumerGroup) -> R,
+    ) -> Result<R, BackendError> {
+        self.read_stream(key, |stream| {
+            stream.groups.get(group).map(|cg| f(stream, cg))
+        })?
+        .flatten()
+        .ok_or_else(|| no_group(key, group))
+    }
+
+    fn modify_group<R>(
+        &self,
+        key: &str,
+        group: &str,
+        f: impl FnOnce(&mut Stream, &mut ConsumerGroup) -> R,
+    ) -> Result<R, BackendError> {
+        self.modify_stream(key, |stream| Ok(stream.with_group(group, f)))?
+            .flatten()
+            .ok_or_else(|| no_group(key, group))
+    }
+}
+
+fn no_group(key: &str, group: &str) -> BackendError {
+    BackendError::NoGroup(key.to_string(), group.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, NewStreamId};
+
+    fn stream(ids: &[u64]) -> Db {
+        let db = Db::new();
+        for ms in ids {
+            let fields = vec![("f".to_string(), BulkString::new(ms.to_string()).into())];
+            db.xadd(
+                "s".to_string(),
+                NewStreamId::Partial(*ms),
+                fields,
+                false,
+                None,
+            )
+            .unwrap();
+        }
+        db
+    }
+
+    fn id(ms: u64) -> StreamId {
+        StreamId::new(ms, 0)
+    }
+
+    fn ids(read: &[GroupEntry]) -> Vec<u64> {
+        read.iter().map(|(id, _)| id.ms).collect()
+    }
+
+    #[test]
+    fn test_xgroup_create() -> Result<(), BackendError> {
+        let db = Db::new();
+        assert_eq!(
+            db.xgroup_create("s", "g", None, false, None),
+            Err(BackendError::NoStreamKey)
+        );
+        db.xgroup_create("s", "g", None, true, None)?;
+        assert_eq!(db.key_type("s"), "stream");
+        assert_eq!(
+            db.xgroup_create("s", "g", None, true, None),
+            Err(BackendError::BusyGroup)
+        );
+        assert_eq!(
+            db.xgroup_setid("s", "other", None, None),
+            Err(no_group("s", "other"))
+        );
+        assert_eq!(db.xgroup_createconsumer("s", "g", "c"), Ok(true));
+        assert_eq!(db.xgroup_createconsumer("s", "g", "c"), Ok(false));
+        assert_eq!(db.xinfo_consumers("s", "g")?.len(), 1);
+        assert_eq!(db.xgroup_destroy("s", "g"), Ok(true));
+        assert_eq!(db.xgroup_destroy("s", "g"), Ok(false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_xreadgroup_pending_and_ack() -> Result<(), BackendError> {
+        let db = stream(&[1, 2, 3]);
+        db.xgroup_create("s", "g", Some(StreamId::MIN), false, None)?;
+        let new = [("s".to_string(), GroupRead::New)];
+        let read = db.xreadgroup("g", "alice", &new, Some(2), false)?;
+        assert_eq!(ids(&read[0].1), [1, 2]);
+        let read = db.xreadgroup("g", "bob", &new, None, false)?;
+        assert_eq!(ids(&read[0].1), [3]);
+        // nothing new left, the stream is left out
+        assert!(db.xreadgroup("g", "bob", &new, None, false)?.is_empty());
+        assert_eq!(
+            db.xreadgroup(
+                "g",
+                "bob",
+                &[("x".to_string(), GroupRead::New)],
+                None,
+                false
+            ),
+            Err(no_group("x", "g"))
+        );
+
+        // the history of a consumer counts as another delivery
+        let history = [("s".to_string(), GroupRead::History(StreamId::MIN))];
+        let read = db.xreadgroup("g", "alice", &history, None, false)?;
+        assert_eq!(ids(&read[0].1), [1, 2]);
+        let summary = db.xpending_summary("s", "g")?;
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.bounds, Some((id(1), id(3))));
+        assert_eq!(
+            summary.consumers,
+            [("alice".to_string(), 2), ("bob".to_string(), 1)]
+        );
+        let detail = db.xpending(
+            "s",
+            "g",
+            StreamId::MIN,
+            StreamId::MAX,
+            10,
+            Some("alice"),
+            None,
+        )?;
+        assert_eq!(detail.len(), 2);
+        assert_eq!(detail[0].deliveries, 2);
+
+        // a deleted entry is still pending, without its fields
+        db.xdel("s", &[id(2)])?;
+        let read = db.xreadgroup("g", "alice", &history, None, false)?;
+        assert_eq!(read[0].1[1], (id(2), None));
+
+        assert_eq!(db.xack("s", "g", &[id(1), id(2), id(9)]), Ok(2));
+        assert_eq!(db.xack("s", "missing", &[id(3)]), Ok(0));
+        assert_eq!(db.xgroup_delconsumer("s", "g", "bob"), Ok(1));
+        assert_eq!(db.xpending_summary("s", "g")?, PendingSummary::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xclaim_and_xautoclaim() -> Result<(), BackendError> {
+        let db = stream(&[1, 2, 3, 4]);
+        db.xgroup_create("s", "g", Some(StreamId::MIN), false, None)?;
+        db.xreadgroup(
+            "g",
+            "alice",
+            &[("s".to_string(), GroupRead::New)],
+            None,
+            false,
+        )?;
+
+        // not idle long enough
+        let claimed = db.xclaim("s", "g", "bob", 60_000, &[id(1)], &ClaimOptions::default())?;
+        assert!(claimed.is_empty());
+        let options = ClaimOptions {
+            retry_count: Some(7),
+            ..Default::default()
+        };
+        let claimed = db.xclaim("s", "g", "bob", 0, &[id(1), id(9)], &options)?;
+        assert_eq!(claimed.len(), 1);
+        let detail = db.xpending("s", "g", id(1), id(1), 1, None, None)?;
+        assert_eq!(
+            (detail[0].consumer.as_str(), detail[0].deliveries),
+            ("bob", 7)
+        );
+
+        // FORCE creates the pending entry of an entry never delivered
+        db.xack("s", "g", &[id(2)])?;
+        let options = ClaimOptions {
+            force: true,
+            justid: true,
+            ..Default::default()
+        };
+        assert_eq!(db.xclaim("s", "g", "bob", 0, &[id(2)], &options)?.len(), 1);
+
+        db.xdel("s", &[id(3)])?;
+        let auto = db.xautoclaim("s", "g", "carol", 0, StreamId::MIN, 2, false)?;
+        assert_eq!(auto.claimed.len(), 2);
+        assert_eq!(auto.next, id(3));
+        let auto = db.xautoclaim("s", "g", "carol", 0, auto.next, 2, false)?;
+        assert_eq!(auto.deleted, [id(3)]);
+        assert_eq!(auto.claimed.len(), 1);
+        assert_eq!(auto.next, StreamId::MIN);
+        assert_eq!(
+            db.xpending_summary("s", "g")?.consumers,
+            [("carol".to_string(), 3)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xinfo_lag() -> Result<(), BackendError> {
+        let db = stream(&[1, 2, 3]);
+        db.xgroup_create("s", "g", Some(StreamId::MIN), false, None)?;
+        let lag = |db: &Db| db.xinfo_groups("s").map(|groups| groups[0].lag);
+        assert_eq!(lag(&db), Ok(Some(3)));
+        db.xreadgroup(
+            "g",
+            "c",
+            &[("s".to_string(), GroupRead::New)],
+            Some(1),
+            true,
+        )?;
+        assert_eq!(db.xinfo_groups("s")?[0].entries_read, Some(1));
+        assert_eq!(lag(&db), Ok(Some(2)));
+        // a deletion ahead of the group makes the lag unknown
+        db.xdel("s", &[id(2)])?;
+        assert_eq!(lag(&db), Ok(None));
+
+        let info = db.xinfo_stream("s")?;
+        assert_eq!(info.length, 2);
+        assert_eq!(info.entries_added, 3);
+        assert_eq!(info.max_deleted_id, id(2));
+        assert_eq!(info.first_id, id(1));
+        assert_eq!(info.last_entry.map(|(id, _)| id), Some(id(3)));
+        assert_eq!(db.xinfo_stream("none"), Err(BackendError::NoSuchKey));
+        Ok(())
+    }
+}
@@ -0,0 +1,584 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use dashmap::DashMap;
+
+use super::{Backend, BackendError};
+use crate::{BulkString, RespArray, RespFrame, RespNull, RespNullBulkString, RespPush};
+
+// the channel a RESP2 connection subscribes to, to receive the invalidations of the clients
+// redirecting to it
+pub const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+thread_local! {
+    // the client whose command runs on this thread, see `Backend::run_tracked`
+    static SCOPE: RefCell<Option<Scope>> = const { RefCell::new(None) };
+}
+
+struct Scope {
+    client: u64,
+    track_reads: bool,
+}
+
+// client side caching: which client read which key, so that it is told once the key changes
+#[derive(Debug, Default)]
+pub struct Tracking {
+    invalidations: Arc<Invalidations>,
+    clients: DashMap<u64, ClientTracking>,
+}
+
+// the keys read and modified while clients track keys, recorded by the databases
+#[derive(Debug, Default)]
+pub struct Invalidations {
+    active: AtomicBool,
+    // the clients that read a key since it last changed. Like in Redis, keys are tracked
+    // across databases.
+    keys: DashMap<String, BTreeSet<u64>>,
+    // each key with the client that modified it, when known
+    pending: Mutex<Vec<(String, Option<u64>)>>,
+}
+
+// the options of CLIENT TRACKING ON
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackingOptions {
+    pub redirect: Option<u64>,
+    pub prefixes: Vec<String>,
+    pub bcast: bool,
+    pub optin: bool,
+    pub optout: bool,
+    pub noloop: bool,
+}
+
+#[derive(Debug)]
+struct ClientTracking {
+    options: TrackingOptions,
+    // set by CLIENT CACHING for the next command only
+    caching: Option<bool>,
+    // the client invalidations are redirected to is gone
+    broken_redirect: bool,
+}
+
+// what CLIENT TRACKINGINFO shows
+#[derive(Debug, PartialEq)]
+pub struct TrackingInfo {
+    pub flags: Vec<&'static str>,
+    pub redirect: i64,
+    pub prefixes: Vec<String>,
+}
+
+impl Backend {
+    // turn tracking on for this connection. Turning it on again keeps the mode, but takes the
+    // new redirection and adds the prefixes.
+    pub fn enable_tracking(&self, options: TrackingOptions) -> Result<(), BackendError> {
+        if let Some(redirect) = options.redirect {
+            if !self.pubsub.connected(redirect) {
+                return Err(BackendError::NoRedirectClient);
+            }
+        }
+        let id = self.session.id();
+        match self.tracking.clients.get_mut(&id) {
+            Some(mut client) => {
+                if client.options.bcast != options.bcast {
+                    return Err(BackendError::TrackingBcastSwitch);
+                }
+                if (client.options.optin, client.options.optout) != (options.optin, options.optout)
+                {
+                    return Err(BackendError::TrackingOptSwitch);
+                }
+                for prefix in options.prefixes {
+                    if !client.options.prefixes.contains(&prefix) {
+                        client.options.prefixes.push(prefix);
+                    }
+                }
+                client.options.redirect = options.redirect;
+                client.options.noloop = options.noloop;
+                client.broken_redirect = false;
+            }
+            None => {
+                self.tracking.clients.insert(
+                    id,
+                    ClientTracking {
+                        options,
+                        caching: None,
+                        broken_redirect: false,
+                    },
+                );
+            }
+        }
+        self.tracking.update_active();
+        Ok(())
+    }
+
+    // turn tracking off for this connection, the keys it read are forgotten lazily
+    pub fn disable_tracking(&self) {
+        if self.tracking.clients.remove(&self.session.id()).is_some() {
+            self.tracking.update_active();
+        }
+    }
+
+    // CLIENT CACHING: whether the keys read by the next command are tracked, in OPTIN or
+    // OPTOUT mode
+    pub fn set_tracking_caching(&self, yes: bool) -> Result<(), BackendError> {
+        let mut client = match self.tracking.clients.get_mut(&self.session.id()) {
+            Some(client) if client.options.optin || client.options.optout => client,
+            _ => return Err(BackendError::CachingWithoutOpt),
+        };
+        match (yes, client.options.optin) {
+            (true, false) => Err(BackendError::CachingYesWithoutOptin),
+            (false, true) => Err(BackendError::CachingNoWithoutOptout),
+            _ => {
+                client.caching = Some(yes);
+                Ok(())
+            }
+        }
+    }
+
+    // the client invalidations go to: -1 without tracking, 0 for this connection itself
+    pub fn tracking_redirect(&self) -> i64 {
+        match self.tracking.clients.get(&self.session.id()) {
+            Some(client) => client.options.redirect.map_or(0, |id| id as i64),
+            None => -1,
+        }
+    }
+
+    pub fn tracking_info(&self) -> TrackingInfo {
+        let Some(client) = self.tracking.clients.get(&self.session.id()) else {
+            return TrackingInfo {
+                flags: vec!["off"],
+                redirect: -1,
+                prefixes: vec![],
+            };
+        };
+        let options = &client.options;
+        let flags = [
+            ("on", true),
+            ("bcast", options.bcast),
+            ("optin", options.optin),
+            ("optout", options.optout),
+            ("caching-yes", client.caching == Some(true)),
+            ("caching-no", client.caching == Some(false)),
+            ("noloop", options.noloop),
+            ("broken_redirect", client.broken_redirect),
+        ];
+        TrackingInfo {
+            flags: flags
+                .into_iter()
+                .filter(|(_, set)| *set)
+                .map(|(flag, _)| flag)
+                .collect(),
+            redirect: options.redirect.map_or(0, |id| id as i64),
+            prefixes: options.prefixes.clone(),
+        }
+    }
+
+    // run a command, remembering the keys it reads when `reads` is set and the connection
+    // tracks them. The keys it modifies are attributed to the connection, for NOLOOP.
+    pub fn run_tracked(&self, reads: bool, f: impl FnOnce() -> RespFrame) -> RespFrame {
+        if !self.tracking.invalidations.active() {
+            return f();
+        }
+        let id = self.session.id();
+        let track_reads = self.tracks_next_reads() && reads;
+        SCOPE.with(|scope| {
+            *scope.borrow_mut() = Some(Scope {
+                client: id,
+                track_reads,
+            })
+        });
+        let frame = f();
+        SCOPE.with(|scope| scope.borrow_mut().take());
+        frame
+    }
+
+    // push the invalidations for the keys modified since the last call, called once a
+    // command is done like `publish_keyspace_events`
+    pub fn send_invalidations(&self) {
+        let pending = self.tracking.invalidations.take();
+        if pending.is_empty() {
+            return;
+        }
+        // the keys to invalidate per client, in the order they were modified
+        let mut batches: HashMap<u64, Vec<String>> = HashMap::new();
+        let mut add = |id: u64, key: &str| {
+            let batch = batches.entry(id).or_default();
+            if !batch.iter().any(|k| k == key) {
+                batch.push(key.to_string());
+            }
+        };
+        for (key, writer) in &pending {
+            let readers = self
+                .tracking
+                .invalidations
+                .keys
+                .remove(key)
+                .map(|(_, ids)| ids)
+                .unwrap_or_default();
+            for client in self.tracking.clients.iter() {
+                let options = &client.options;
+                if options.noloop && *writer == Some(*client.key()) {
+                    continue;
+                }
+                let tracked = match options.bcast {
+                    true => {
+                        options.prefixes.is_empty()
+                            || options.prefixes.iter().any(|p| key.starts_with(p.as_str()))
+                    }
+                    false => readers.contains(client.key()),
+                };
+                if tracked {
+                    add(*client.key(), key);
+                }
+            }
+        }
+        for (id, keys) in batches {
+            self.invalidate(id, Some(keys));
+        }
+    }
+
+    // tell every tracking client to drop its whole cache, after a flush
+    pub(super) fn invalidate_everything(&self) {
+        if !self.tracking.invalidations.active() {
+            return;
+        }
+        self.tracking.invalidations.keys.clear();
+        let ids = self
+            .tracking
+            .clients
+            .iter()
+            .map(|client| *client.key())
+            .collect::<Vec<_>>();
+        ids.into_iter().for_each(|id| self.invalidate(id, None));
+    }
+
+    // whether the next command of this connection has its reads tracked, which consumes
+    // CLIENT CACHING
+    fn tracks_next_reads(&self) -> bool {
+        let Some(mut client) = self.tracking.clients.get_mut(&self.session.id()) else {
+            return false;
+        };
+        let caching = client.caching.take();
+        let options = &client.options;
+        match (options.bcast, options.optin, options.optout) {
+            (true, _, _) => false,
+            (_, true, _) => caching == Some(true),
+            (_, _, true) => caching != Some(false),
+            _ => true,
+        }
+    }
+
+    // send an invalidation to a tracking client, or where it redirects to. Without keys the
+    // client drops everything it cached.
+    fn invalidate(&self, id: u64, keys: Option<Vec<String>>) {
+        let Some(mut client) = self.tracking.clients.get_mut(&id) else {
+            return;
+        };
+        let target = client.options.redirect.unwrap_or(id);
+        let keys = |resp3: bool| -> RespFrame {
+            match &keys {
+                Some(keys) => RespArray::new(
+                    keys.iter()
+                        .map(|key| BulkString::new(key.as_str()).into())
+                        .collect::<Vec<RespFrame>>(),
+                )
+                .into(),
+                None if resp3 => RespFrame::Null(RespNull),
+                None => RespNullBulkString.into(),
+            }
+        };
+        let frame = match self.pubsub.is_resp3(target) {
+            // RESP3 connections get a push of their own
+            Some(true) => RespPush::new(vec![BulkString::new("invalidate").into(), keys(true)]),
+            // RESP2 ones only through the pub/sub channel, as a redirection target
+            Some(false)
+                if client.options.redirect.is_some()
+                    && self.pubsub.is_subscribed(target, INVALIDATE_CHANNEL) =>
+            {
+                RespPush::new(vec![
+                    BulkString::new("message").into(),
+                    BulkString::new(INVALIDATE_CHANNEL).into(),
+                    keys(false),
+                ])
+            }
+            Some(false) => return,
+            None if client.options.redirect.is_none() || client.broken_redirect => return,
+            None => {
+                client.broken_redirect = true;
+                if self.pubsub.is_resp3(id) != Some(true) {
+                    return;
+                }
+                drop(client);
+                let frame = RespPush::new(vec![
+                    BulkString::new("tracking-redir-broken").into(),
+                    RespFrame::Integer(target as i64),
+                ]);
+                self.pubsub.push_to(id, frame.into());
+                return;
+            }
+        };
+        drop(client);
+        self.pubsub.push_to(target, frame.into());
+    }
+}
+
+impl Tracking {
+    // the record of modifications, shared with the databases
+    pub fn invalidations(&self) -> Arc<Invalidations> {
+        self.invalidations.clone()
+    }
+
+    fn update_active(&self) {
+        self.invalidations
+            .active
+            .store(!self.clients.is_empty(), Ordering::Relaxed);
+    }
+}
+
+impl Invalidations {
+    fn active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    // a key was modified, by the client whose command runs on this thread if any
+    pub(super) fn record_write(&self, key: &str) {
+        if !self.active() {
+            return;
+        }
+        let client = SCOPE.with(|scope| scope.borrow().as_ref().map(|scope| scope.client));
+        self.pending.lock().unwrap().push((key.to_string(), client));
+    }
+
+    // a key is about to be read by the command running on this thread. The client is added
+    // to the readers of the key before the read, so a modification racing with it either
+    // comes first and is seen by the read, or finds the client when it is sent out.
+    pub(super) fn record_read(&self, key: &str) {
+        if !self.active() {
+            return;
+        }
+        let client = SCOPE.with(|scope| {
+            scope
+                .borrow()
+                .as_ref()
+                .filter(|scope| scope.track_reads)
+                .map(|scope| scope.client)
+        });
+        if let Some(client) = client {
+            self.keys.entry(key.to_string()).or_default().insert(client);
+        }
+    }
+
+    fn take(&self) -> Vec<(String, Option<u64>)> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubscriptionKind;
+
+    fn read(backend: &Backend, key: &str) {
+        backend.run_tracked(true, || {
+            backend.db().get(key).unwrap();
+            RespFrame::Integer(0)
+        });
+    }
+
+    fn write(backend: &Backend, key: &str) {
+        backend.run_tracked(false, || {
+            backend
+                .db()
+                .set(key.to_string(), BulkString::new("v").into());
+            RespFrame::Integer(0)
+        });
+        backend.send_invalidations();
+    }
+
+    fn invalidate(keys: &[&str]) -> RespFrame {
+        RespPush::new(vec![
+            BulkString::new("invalidate").into(),
+            RespArray::new(
+                keys.iter()
+                    .map(|key| BulkString::new(*key).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+        ])
+        .into()
+    }
+
+    #[test]
+    fn test_tracking_invalidates_read_keys() {
+        let server = Backend::new();
+        let client = server.connect();
+        client.session().set_protocol(3);
+        let mut pushes = client.open_pushes();
+        let other = server.connect();
+
+        client.enable_tracking(TrackingOptions::default()).unwrap();
+        read(&client, "a");
+        write(&other, "b");
+        assert!(pushes.try_recv().is_err());
+        write(&other, "a");
+        assert_eq!(pushes.try_recv().ok(), Some(invalidate(&["a"])));
+        // a key is invalidated once, until it is read again
+        write(&other, "a");
+        assert!(pushes.try_recv().is_err());
+
+        // the client's own writes are reported too, unless NOLOOP is set
+        read(&client, "a");
+        write(&client, "a");
+        assert_eq!(pushes.try_recv().ok(), Some(invalidate(&["a"])));
+        client
+            .enable_tracking(TrackingOptions {
+                noloop: true,
+                ..Default::default()
+            })
+            .unwrap();
+        read(&client, "a");
+        write(&client, "a");
+        assert!(pushes.try_recv().is_err());
+
+        server.flushall(false);
+        assert_eq!(
+            pushes.try_recv().ok(),
+            Some(
+                RespPush::new(vec![
+                    BulkString::new("invalidate").into(),
+                    RespFrame::Null(RespNull)
+                ])
+                .into()
+            )
+        );
+
+        client.disable_tracking();
+        read(&client, "a");
+        write(&other, "a");
+        assert!(pushes.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_tracking_write_during_read() {
+        let server = Backend::new();
+        let client = server.connect();
+        client.session().set_protocol(3);
+        let mut pushes = client.open_pushes();
+        let other = server.connect();
+
+        // a write sent out while the reading command has not finished yet still reaches it
+        client.enable_tracking(TrackingOptions::default()).unwrap();
+        client.run_tracked(true, || {
+            client.db().get("a").unwrap();
+            std::thread::scope(|s| {
+                s.spawn(|| write(&other, "a"));
+            });
+            RespFrame::Integer(0)
+        });
+        assert_eq!(pushes.try_recv().ok(), Some(invalidate(&["a"])));
+    }
+
+    #[test]
+    fn test_tracking_bcast_and_optin() {
+        let server = Backend::new();
+        let client = server.connect();
+        client.session().set_protocol(3);
+        let mut pushes = client.open_pushes();
+        let other = server.connect();
+
+        client
+            .enable_tracking(TrackingOptions {
+                bcast: true,
+                prefixes: vec!["user:".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        write(&other, "user:1");
+        write(&other, "order:1");
+        assert_eq!(pushes.try_recv().ok(), Some(invalidate(&["user:1"])));
+        assert!(pushes.try_recv().is_err());
+        assert_eq!(
+            client.enable_tracking(TrackingOptions::default()),
+            Err(BackendError::TrackingBcastSwitch)
+        );
+        client.disable_tracking();
+
+        // in OPTIN mode only the reads after CLIENT CACHING YES are tracked
+        let optin = TrackingOptions {
+            optin: true,
+            ..Default::default()
+        };
+        client.enable_tracking(optin).unwrap();
+        assert_eq!(
+            client.set_tracking_caching(false),
+            Err(BackendError::CachingNoWithoutOptout)
+        );
+        read(&client, "a");
+        client.set_tracking_caching(true).unwrap();
+        assert_eq!(
+            client.tracking_info().flags,
+            vec!["on", "optin", "caching-yes"]
+        );
+        read(&client, "b");
+        read(&client, "c");
+        write(&other, "a");
+        write(&other, "b");
+        write(&other, "c");
+        assert_eq!(pushes.try_recv().ok(), Some(invalidate(&["b"])));
+        assert!(pushes.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_tracking_redirect() {
+        let server = Backend::new();
+        let client = server.connect();
+        let receiver = server.connect();
+        let mut pushes = receiver.open_pushes();
+        let other = server.connect();
+
+        let missing = TrackingOptions {
+            redirect: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(
+            client.enable_tracking(missing),
+            Err(BackendError::NoRedirectClient)
+        );
+        assert_eq!(client.tracking_redirect(), -1);
+
+        let redirect = TrackingOptions {
+            redirect: Some(receiver.session().id()),
+            ..Default::default()
+        };
+        client.enable_tracking(redirect).unwrap();
+        assert_eq!(client.tracking_redirect(), receiver.session().id() as i64);
+        // a RESP2 connection only gets them once subscribed to the channel
+        read(&client, "a");
+        write(&other, "a");
+        assert!(pushes.try_recv().is_err());
+        receiver.subscribe(SubscriptionKind::Channel, &[INVALIDATE_CHANNEL.to_string()]);
+        pushes.try_recv().ok();
+        read(&client, "a");
+        write(&other, "a");
+        assert_eq!(
+            pushes.try_recv().ok(),
+            Some(
+                RespPush::new(vec![
+                    BulkString::new("message").into(),
+                    BulkString::new(INVALIDATE_CHANNEL).into(),
+                    RespArray::new(vec![BulkString::new("a").into()]).into(),
+                ])
+                .into()
+            )
+        );
+
+        receiver.close_pushes();
+        read(&client, "a");
+        write(&other, "a");
+        assert!(client.tracking_info().flags.contains(&"broken_redirect"));
+    }
+}
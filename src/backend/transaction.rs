@@ -0,0 +1,170 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use tokio::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use super::{Backend, Db};
+use crate::RespFrame;
+
+// the MULTI and WATCH state of a connection
+#[derive(Debug, Default)]
+pub struct Transaction {
+    // the requests queued since MULTI, None outside of a transaction
+    queued: Mutex<Option<Queued>>,
+    watched: Mutex<Vec<WatchedKey>>,
+    // set by the databases when a watched key is modified
+    dirty: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Default)]
+pub struct Queued {
+    pub requests: Vec<RespFrame>,
+    // a request was rejected while queueing, EXEC has to discard the transaction
+    pub aborted: bool,
+}
+
+#[derive(Debug)]
+struct WatchedKey {
+    index: usize,
+    // the database at the time of WATCH, to tell whether it was flushed or swapped since
+    db: Arc<Db>,
+    key: String,
+    existed: bool,
+}
+
+impl Backend {
+    pub fn in_multi(&self) -> bool {
+        self.transaction().queued.lock().unwrap().is_some()
+    }
+
+    // start queueing requests, false if a transaction is already open
+    pub fn multi(&self) -> bool {
+        let mut queued = self.transaction().queued.lock().unwrap();
+        if queued.is_some() {
+            return false;
+        }
+        *queued = Some(Queued::default());
+        true
+    }
+
+    pub fn queue_request(&self, request: RespFrame) {
+        if let Some(queued) = self.transaction().queued.lock().unwrap().as_mut() {
+            queued.requests.push(request);
+        }
+    }
+
+    // mark the open transaction as failed, EXEC will then refuse to run it
+    pub fn abort_transaction(&self) {
+        if let Some(queued) = self.transaction().queued.lock().unwrap().as_mut() {
+            queued.aborted = true;
+        }
+    }
+
+    // end the transaction, returning what was queued
+    pub fn take_transaction(&self) -> Option<Queued> {
+        self.transaction().queued.lock().unwrap().take()
+    }
+
+    pub fn watch(&self, keys: &[String]) {
+        let transaction = self.transaction();
+        let mut watched = transaction.watched.lock().unwrap();
+        let index = self.session().db();
+        let db = self.db();
+        for key in keys {
+            if watched.iter().any(|w| w.index == index && w.key == *key) {
+                continue;
+            }
+            // checked first, the lookup may expire the key and that is no change to report
+            let existed = db.contains(key);
+            db.watch(key, &transaction.dirty);
+            watched.push(WatchedKey {
+                index,
+                db: db.clone(),
+                key: key.clone(),
+                existed,
+            });
+        }
+    }
+
+    pub fn unwatch(&self) {
+        let transaction = self.transaction();
+        for watched in transaction.watched.lock().unwrap().drain(..) {
+            watched.db.unwatch(&watched.key, &transaction.dirty);
+        }
+        transaction.dirty.store(false, Ordering::Relaxed);
+    }
+
+    // whether a watched key was modified, expired, or its database flushed or swapped. Only
+    // meaningful while holding the exclusive lock, so that nothing changes before EXEC runs.
+    pub fn watched_keys_changed(&self) -> bool {
+        let transaction = self.transaction();
+        if transaction.dirty.load(Ordering::Relaxed) {
+            return true;
+        }
+        transaction.watched.lock().unwrap().iter().any(|watched| {
+            let Ok(current) = self.db_at(watched.index) else {
+                return true;
+            };
+            if !Arc::ptr_eq(&current, &watched.db) {
+                return watched.existed || current.contains(&watched.key);
+            }
+            // a key that expired without being touched since has not reported it yet
+            watched.existed && !current.contains(&watched.key)
+        })
+    }
+
+    // held by every command while it runs, so that EXEC can exclude them all
+    pub async fn shared_lock(&self) -> RwLockReadGuard<'_, ()> {
+        self.exec_lock.read().await
+    }
+
+    // held by EXEC and scripts, no other client runs a command until they are done
+    pub async fn exclusive_lock(&self) -> RwLockWriteGuard<'_, ()> {
+        self.exec_lock.write().await
+    }
+
+    fn transaction(&self) -> &Transaction {
+        self.session().transaction()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_watch() {
+        let server = Backend::new();
+        let client = server.connect();
+        let other = server.connect();
+        other.db().set("a".to_string(), BulkString::new("1").into());
+
+        client.watch(&["a".to_string(), "b".to_string()]);
+        assert!(!client.watched_keys_changed());
+        other.db().del(&["b".to_string()]);
+        assert!(!client.watched_keys_changed());
+        other.db().set("b".to_string(), BulkString::new("2").into());
+        assert!(client.watched_keys_changed());
+        client.unwatch();
+        assert!(!client.watched_keys_changed());
+
+        // a flush only matters for keys that existed
+        client.watch(&["a".to_string(), "missing".to_string()]);
+        other.flushdb(false);
+        assert!(client.watched_keys_changed());
+        client.unwatch();
+        client.watch(&["missing".to_string()]);
+        other.flushdb(false);
+        assert!(!client.watched_keys_changed());
+
+        // expiring counts as a modification even before the key is accessed
+        client.unwatch();
+        other.db().set("a".to_string(), BulkString::new("1").into());
+        client.watch(&["a".to_string()]);
+        other.db().expire_at("a", Some(1));
+        assert!(client.watched_keys_changed());
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    borrow::Cow,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{Hash, List, Set, Stream, ZSet};
+use crate::{RespEncode, RespFrame};
+
+/// values holding more elements than this are dropped on a background task by UNLINK
+pub const LAZYFREE_THRESHOLD: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(RespFrame),
+    Hash(Hash),
+    Set(Set),
+    List(List),
+    ZSet(ZSet),
+    Stream(Stream),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub(crate) value: Value,
+    // absolute unix time in milliseconds
+    pub(crate) expire_at: Option<u64>,
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::List(_) => "list",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    // number of elements the value owns, used to decide whether freeing it is expensive
+    pub fn len(&self) -> usize {
+        match self {
+            Value::String(_) => 1,
+            Value::Hash(v) => v.len(),
+            Value::Set(v) => v.len(),
+            Value::List(v) => v.len(),
+            Value::ZSet(v) => v.len(),
+            Value::Stream(v) => v.len(),
+        }
+    }
+
+    // empty collections are removed from the keyspace, except streams which outlive their
+    // entries until the key is deleted
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::Stream(_) => false,
+            _ => self.len() == 0,
+        }
+    }
+}
+
+impl Entry {
+    pub fn new(value: Value) -> Self {
+        Entry {
+            value,
+            expire_at: None,
+        }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn expire_at(&self) -> Option<u64> {
+        self.expire_at
+    }
+
+    // whether the key or some of its hash fields have a ttl
+    pub fn is_volatile(&self) -> bool {
+        match &self.value {
+            Value::Hash(hash) if hash.has_field_ttls() => true,
+            _ => self.expire_at.is_some(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match &self.value {
+            Value::Hash(hash) if hash.is_expired() => true,
+            _ => self.expire_at.is_some_and(|at| at <= now_ms()),
+        }
+    }
+}
+
+// the payload of a string value, values set by clients are always bulk strings
+pub fn string_bytes(frame: &RespFrame) -> Cow<'_, [u8]> {
+    match frame {
+        RespFrame::BulkString(s) => Cow::Borrowed(s.as_ref()),
+        RespFrame::SimpleString(s) => Cow::Borrowed(s.as_bytes()),
+        RespFrame::Integer(i) => Cow::Owned(i.to_string().into_bytes()),
+        RespFrame::Double(d) => Cow::Owned(d.to_string().into_bytes()),
+        frame => Cow::Owned(frame.clone().encode()),
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
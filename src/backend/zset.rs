@@ -0,0 +1,1247 @@
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::RandomState, HashMap, HashSet},
+};
+
+use rand::Rng;
+
+use super::{
+    glob_match, random_elements, BackendError, Db, Entry, NotifyFlags, ScanIndex, SetCondition,
+    SetOperator, Value,
+};
+
+const SKIPLIST_MAX_LEVEL: usize = 32;
+// the chance of a node to reach the next level
+const SKIPLIST_P: f64 = 0.25;
+// the arena slot of the head node, which holds no element
+const HEAD: usize = 0;
+
+// a sorted set: members are indexed by name for score lookups and kept in a skiplist ordered
+// by (score, member) for rank and range queries
+#[derive(Debug, Clone, Default)]
+pub struct ZSet {
+    scores: hashbrown::HashMap<String, f64, RandomState>,
+    list: SkipList,
+    // the members in the order ZSCAN walks them
+    index: ScanIndex,
+}
+
+// the nodes live in an arena and link to each other by index. Every link also records its
+// span, the number of elements it skips, so ranks are found in O(log n) like in Redis.
+#[derive(Debug, Clone)]
+struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    tail: Option<usize>,
+    level: usize,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: String,
+    score: f64,
+    backward: Option<usize>,
+    levels: Vec<Link>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Link {
+    forward: Option<usize>,
+    span: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreRange {
+    pub min: ScoreBound,
+    pub max: ScoreBound,
+}
+
+// `-` and `+` are Min and Max, `[member` is inclusive and `(member` exclusive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(String),
+    Exclusive(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexRange {
+    pub min: LexBound,
+    pub max: LexBound,
+}
+
+// what ZRANGE selects: a rank interval, a score range or a lexicographic range
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeSpec {
+    Rank(i64, i64),
+    Score(ScoreRange),
+    Lex(LexRange),
+}
+
+// the key popped from by ZMPOP together with the popped elements
+pub type ZPopped = (String, Vec<(String, f64)>);
+
+// how ZUNION and ZINTER combine the scores of a member found in several inputs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+// the options of ZADD besides INCR
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddOptions {
+    pub condition: Option<SetCondition>,
+    pub gt: bool,
+    pub lt: bool,
+    pub ch: bool,
+}
+
+impl ZSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    // add or update `member`, returning its previous score
+    pub fn insert(&mut self, member: String, score: f64) -> Option<f64> {
+        let old = self.scores.insert(member.clone(), score);
+        match old {
+            Some(old) if old == score => (),
+            Some(old) => {
+                self.list.remove(old, &member);
+                self.list.insert(score, member);
+            }
+            None => {
+                self.index.insert(&self.scores, &member);
+                self.list.insert(score, member);
+            }
+        }
+        old
+    }
+
+    pub fn remove(&mut self, member: &str) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.list.remove(score, member);
+        self.index.remove(&self.scores, member);
+        Some(score)
+    }
+
+    // the 0 based rank of `member`, counted from the highest score with `rev`
+    pub fn rank(&self, member: &str, rev: bool) -> Option<usize> {
+        let rank = self.list.rank(self.score(member)?, member)?;
+        Some(if rev { self.len() - 1 - rank } else { rank })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> + '_ {
+        self.list.walk(self.list.first(), false).map(|node| {
+            let node = &self.list.nodes[node];
+            (node.member.as_str(), node.score)
+        })
+    }
+
+    // the elements ranked `start..=stop`, ranks counted from the highest score with `rev`
+    pub fn range_by_rank(&self, start: usize, stop: usize, rev: bool) -> Vec<(String, f64)> {
+        if start > stop || start >= self.len() {
+            return Vec::new();
+        }
+        let stop = stop.min(self.len() - 1);
+        let first = if rev {
+            self.list.by_rank(self.len() - 1 - start)
+        } else {
+            self.list.by_rank(start)
+        };
+        self.collect(self.list.walk(first, rev).take(stop - start + 1))
+    }
+
+    // the elements within `range`, ordered by descending score with `rev`. `offset` elements
+    // are skipped and at most `limit` returned.
+    pub fn range_by_score(
+        &self,
+        range: &ScoreRange,
+        rev: bool,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Vec<(String, f64)> {
+        let first = if rev {
+            self.list.last_within(|node| range.below_max(node.score))
+        } else {
+            self.list.first_after(|node| !range.above_min(node.score))
+        };
+        let nodes = self
+            .list
+            .walk(first, rev)
+            .take_while(|&node| range.contains(self.list.nodes[node].score))
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX));
+        self.collect(nodes)
+    }
+
+    // like `range_by_score` for members between `range`, meaningful when all scores are equal
+    pub fn range_by_lex(
+        &self,
+        range: &LexRange,
+        rev: bool,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Vec<(String, f64)> {
+        let first = if rev {
+            self.list.last_within(|node| range.below_max(&node.member))
+        } else {
+            self.list.first_after(|node| !range.above_min(&node.member))
+        };
+        let nodes = self
+            .list
+            .walk(first, rev)
+            .take_while(|&node| range.contains(&self.list.nodes[node].member))
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX));
+        self.collect(nodes)
+    }
+
+    // the number of elements within `range`, from the ranks of its first and last element
+    pub fn count_by_score(&self, range: &ScoreRange) -> usize {
+        let first = self.list.first_after(|node| !range.above_min(node.score));
+        let last = self.list.last_within(|node| range.below_max(node.score));
+        self.count_between(first, last)
+    }
+
+    pub fn count_by_lex(&self, range: &LexRange) -> usize {
+        let first = self.list.first_after(|node| !range.above_min(&node.member));
+        let last = self.list.last_within(|node| range.below_max(&node.member));
+        self.count_between(first, last)
+    }
+
+    // remove and return up to `count` elements with the lowest, or with `max` highest, scores
+    pub fn pop(&mut self, max: bool, count: usize) -> Vec<(String, f64)> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let popped = self.range_by_rank(0, count - 1, max);
+        for (member, _) in popped.iter() {
+            self.remove(member);
+        }
+        popped
+    }
+
+    // remove the elements selected by `spec`, returning how many were removed
+    pub fn remove_range(&mut self, spec: &ZRangeSpec) -> usize {
+        let removed = zrange(self, spec, false, None);
+        for (member, _) in removed.iter() {
+            self.remove(member);
+        }
+        removed.len()
+    }
+
+    fn count_between(&self, first: Option<usize>, last: Option<usize>) -> usize {
+        let (Some(first), Some(last)) = (first, last) else {
+            return 0;
+        };
+        let rank = |node: usize| {
+            let node = &self.list.nodes[node];
+            self.list.rank(node.score, &node.member).unwrap_or(0)
+        };
+        (rank(last) + 1).saturating_sub(rank(first))
+    }
+
+    fn collect(&self, nodes: impl Iterator<Item = usize>) -> Vec<(String, f64)> {
+        nodes
+            .map(|node| {
+                let node = &self.list.nodes[node];
+                (node.member.clone(), node.score)
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<(String, f64)> for ZSet {
+    fn from_iter<T: IntoIterator<Item = (String, f64)>>(iter: T) -> Self {
+        let mut zset = ZSet::new();
+        for (member, score) in iter {
+            zset.insert(member, score);
+        }
+        zset
+    }
+}
+
+impl SkipList {
+    fn first(&self) -> Option<usize> {
+        self.nodes[HEAD].levels[0].forward
+    }
+
+    fn insert(&mut self, score: f64, member: String) {
+        let mut update = [HEAD; SKIPLIST_MAX_LEVEL];
+        let mut rank = [0; SKIPLIST_MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i + 1 == self.level { 0 } else { rank[i + 1] };
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.nodes[next].cmp(score, &member) != Ordering::Less {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        let level = random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = self.alloc(Node {
+            member,
+            score,
+            backward: (update[0] != HEAD).then_some(update[0]),
+            levels: vec![Link::default(); level],
+        });
+        for i in 0..level {
+            let prev = self.nodes[update[i]].levels[i];
+            self.nodes[node].levels[i] = Link {
+                forward: prev.forward,
+                span: prev.span - (rank[0] - rank[i]),
+            };
+            self.nodes[update[i]].levels[i] = Link {
+                forward: Some(node),
+                span: rank[0] - rank[i] + 1,
+            };
+        }
+        for (i, &prev) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[prev].levels[i].span += 1;
+        }
+        match self.nodes[node].levels[0].forward {
+            Some(next) => self.nodes[next].backward = Some(node),
+            None => self.tail = Some(node),
+        }
+        self.len += 1;
+    }
+
+    fn remove(&mut self, score: f64, member: &str) -> bool {
+        let mut update = [HEAD; SKIPLIST_MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.nodes[next].cmp(score, member) != Ordering::Less {
+                    break;
+                }
+                x = next;
+            }
+            update[i] = x;
+        }
+        let node = match self.nodes[x].levels[0].forward {
+            Some(node) if self.nodes[node].cmp(score, member) == Ordering::Equal => node,
+            _ => return false,
+        };
+
+        for (i, &prev) in update.iter().enumerate().take(self.level) {
+            let link = self.nodes[prev].levels[i];
+            if link.forward == Some(node) {
+                let removed = self.nodes[node].levels[i];
+                self.nodes[prev].levels[i] = Link {
+                    forward: removed.forward,
+                    span: link.span + removed.span - 1,
+                };
+            } else {
+                self.nodes[prev].levels[i].span -= 1;
+            }
+        }
+        let backward = self.nodes[node].backward;
+        match self.nodes[node].levels[0].forward {
+            Some(next) => self.nodes[next].backward = backward,
+            None => self.tail = backward,
+        }
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+        self.len -= 1;
+        self.release(node);
+        true
+    }
+
+    // the 0 based rank of the element
+    fn rank(&self, score: f64, member: &str) -> Option<usize> {
+        let mut rank = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.nodes[next].cmp(score, member) == Ordering::Greater {
+                    break;
+                }
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if x != HEAD && self.nodes[x].member == member {
+                return Some(rank - 1);
+            }
+        }
+        None
+    }
+
+    // the node at the 0 based `rank`
+    fn by_rank(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if traversed + self.nodes[x].levels[i].span > target {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    // the first node for which `before` is false, `before` must hold for a prefix of the list
+    fn first_after(&self, before: impl Fn(&Node) -> bool) -> Option<usize> {
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if !before(&self.nodes[next]) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        self.nodes[x].levels[0].forward
+    }
+
+    // the last node for which `within` holds, `within` must hold for a prefix of the list
+    fn last_within(&self, within: impl Fn(&Node) -> bool) -> Option<usize> {
+        let x = self.first_after(within);
+        match x {
+            Some(node) => self.nodes[node].backward,
+            None => self.tail,
+        }
+    }
+
+    // the nodes from `start` on, walking towards the tail or with `rev` towards the head
+    fn walk(&self, start: Option<usize>, rev: bool) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(start, move |&node| {
+            if rev {
+                self.nodes[node].backward
+            } else {
+                self.nodes[node].levels[0].forward
+            }
+        })
+    }
+
+    fn alloc(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn release(&mut self, slot: usize) {
+        let node = &mut self.nodes[slot];
+        node.member = String::new();
+        node.levels = Vec::new();
+        self.free.push(slot);
+    }
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            member: String::new(),
+            score: 0.0,
+            backward: None,
+            levels: vec![Link::default(); SKIPLIST_MAX_LEVEL],
+        };
+        SkipList {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: None,
+            level: 1,
+            len: 0,
+        }
+    }
+}
+
+impl Node {
+    // the order of this node relative to the element (score, member)
+    fn cmp(&self, score: f64, member: &str) -> Ordering {
+        self.score
+            .partial_cmp(&score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.member.as_str().cmp(member))
+    }
+}
+
+fn random_level() -> usize {
+    let mut rng = rand::thread_rng();
+    let mut level = 1;
+    while level < SKIPLIST_MAX_LEVEL && rng.gen_bool(SKIPLIST_P) {
+        level += 1;
+    }
+    level
+}
+
+impl ScoreRange {
+    pub fn above_min(&self, score: f64) -> bool {
+        if self.min.exclusive {
+            score > self.min.value
+        } else {
+            score >= self.min.value
+        }
+    }
+
+    pub fn below_max(&self, score: f64) -> bool {
+        if self.max.exclusive {
+            score < self.max.value
+        } else {
+            score <= self.max.value
+        }
+    }
+
+    pub fn contains(&self, score: f64) -> bool {
+        self.above_min(score) && self.below_max(score)
+    }
+}
+
+impl LexRange {
+    pub fn above_min(&self, member: &str) -> bool {
+        match &self.min {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(min) => member >= min.as_str(),
+            LexBound::Exclusive(min) => member > min.as_str(),
+        }
+    }
+
+    pub fn below_max(&self, member: &str) -> bool {
+        match &self.max {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(max) => member <= max.as_str(),
+            LexBound::Exclusive(max) => member < max.as_str(),
+        }
+    }
+
+    pub fn contains(&self, member: &str) -> bool {
+        self.above_min(member) && self.below_max(member)
+    }
+}
+
+impl Db {
+    // add or update the (score, member) pairs, returning the number of members added, or of
+    // members added or updated with CH
+    pub fn zadd(
+        &self,
+        key: String,
+        options: ZAddOptions,
+        pairs: Vec<(f64, String)>,
+    ) -> Result<i64, BackendError> {
+        let mut modified = false;
+        let changed = self.write_zset(key.clone(), |zset| {
+            let mut changed = 0;
+            for (score, member) in pairs {
+                let old = zset.score(&member);
+                if let Some(score) = zadd_score(old, score, false, &options)? {
+                    zset.insert(member, score);
+                    modified |= old != Some(score);
+                    if old.is_none() || (options.ch && old != Some(score)) {
+                        changed += 1;
+                    }
+                }
+            }
+            Ok(changed)
+        })?;
+        if modified {
+            self.notify(NotifyFlags::ZSET, "zadd", &key);
+        }
+        Ok(changed)
+    }
+
+    // ZADD with INCR and ZINCRBY: the new score, None if the options prevented the update
+    pub fn zadd_incr(
+        &self,
+        key: String,
+        options: ZAddOptions,
+        incr: f64,
+        member: String,
+    ) -> Result<Option<f64>, BackendError> {
+        let score = self.write_zset(key.clone(), |zset| {
+            let score = zadd_score(zset.score(&member), incr, true, &options)?;
+            if let Some(score) = score {
+                zset.insert(member, score);
+            }
+            Ok(score)
+        })?;
+        if score.is_some() {
+            self.notify(NotifyFlags::ZSET, "zincr", &key);
+        }
+        Ok(score)
+    }
+
+    pub fn zrem(&self, key: &str, members: &[String]) -> Result<i64, BackendError> {
+        let removed = self.modify_zset(key, |zset| {
+            Ok(members.iter().filter(|m| zset.remove(m).is_some()).count() as i64)
+        })?;
+        let removed = removed.unwrap_or(0);
+        if removed > 0 {
+            self.notify_removal(NotifyFlags::ZSET, "zrem", key);
+        }
+        Ok(removed)
+    }
+
+    pub fn zscore(&self, key: &str, member: &str) -> Result<Option<f64>, BackendError> {
+        Ok(self.read_zset(key, |zset| zset.score(member))?.flatten())
+    }
+
+    pub fn zmscore(&self, key: &str, members: &[String]) -> Result<Vec<Option<f64>>, BackendError> {
+        let scores = self.read_zset(key, |zset| members.iter().map(|m| zset.score(m)).collect())?;
+        Ok(scores.unwrap_or_else(|| vec![None; members.len()]))
+    }
+
+    pub fn zcard(&self, key: &str) -> Result<i64, BackendError> {
+        Ok(self.read_zset(key, |zset| zset.len() as i64)?.unwrap_or(0))
+    }
+
+    pub fn zcount(&self, key: &str, range: &ScoreRange) -> Result<i64, BackendError> {
+        let count = self.read_zset(key, |zset| zset.count_by_score(range) as i64)?;
+        Ok(count.unwrap_or(0))
+    }
+
+    // the rank of `member` together with its score
+    pub fn zrank(
+        &self,
+        key: &str,
+        member: &str,
+        rev: bool,
+    ) -> Result<Option<(i64, f64)>, BackendError> {
+        let rank = self.read_zset(key, |zset| {
+            let rank = zset.rank(member, rev)?;
+            Some((rank as i64, zset.score(member)?))
+        })?;
+        Ok(rank.flatten())
+    }
+
+    // the elements selected by `spec`. `limit` is an (offset, count) pair, a negative count
+    // returns all elements after the offset.
+    pub fn zrange(
+        &self,
+        key: &str,
+        spec: &ZRangeSpec,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+    ) -> Result<Vec<(String, f64)>, BackendError> {
+        let range = self.read_zset(key, |zset| zrange(zset, spec, rev, limit))?;
+        Ok(range.unwrap_or_default())
+    }
+
+    // store the result of ZRANGE at `dst`, an empty result deletes it
+    pub fn zrangestore(
+        &self,
+        dst: String,
+        src: &str,
+        spec: &ZRangeSpec,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+    ) -> Result<i64, BackendError> {
+        let range = self.zrange(src, spec, rev, limit)?;
+        let len = range.len() as i64;
+        if range.is_empty() {
+            self.del(&[dst]);
+        } else {
+            let zset = range.into_iter().collect::<ZSet>();
+            self.put_entry(dst.clone(), Entry::new(Value::ZSet(zset)), true);
+            self.notify(NotifyFlags::ZSET, "zrangestore", &dst);
+        }
+        Ok(len)
+    }
+
+    // remove and return up to `count` elements with the lowest or, with `max`, highest scores
+    pub fn zpop(
+        &self,
+        key: &str,
+        max: bool,
+        count: usize,
+    ) -> Result<Vec<(String, f64)>, BackendError> {
+        let popped = self.modify_zset(key, |zset| Ok(zset.pop(max, count)))?;
+        let popped = popped.unwrap_or_default();
+        if !popped.is_empty() {
+            let event = if max { "zpopmax" } else { "zpopmin" };
+            self.notify_removal(NotifyFlags::ZSET, event, key);
+        }
+        Ok(popped)
+    }
+
+    // random elements like ZRANDMEMBER, see `random_elements`
+    pub fn zrandmember(
+        &self,
+        key: &str,
+        count: Option<i64>,
+    ) -> Result<Vec<(String, f64)>, BackendError> {
+        let members = self.read_zset(key, |zset| {
+            let members = zset.iter().map(|(m, s)| (m.to_string(), s));
+            random_elements(members, zset.len(), count)
+        })?;
+        Ok(members.transpose()?.unwrap_or_default())
+    }
+
+    pub fn zscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, f64)>), BackendError> {
+        let page = self.read_zset(key, |zset| {
+            let (next, members) = zset.index.page(&zset.scores, cursor, count);
+            let members = members
+                .into_iter()
+                .filter(|member| {
+                    pattern.is_none_or(|p| glob_match(p.as_bytes(), member.as_bytes(), false))
+                })
+                .filter_map(|member| {
+                    let score = zset.score(&member)?;
+                    Some((member, score))
+                })
+                .collect();
+            (next, members)
+        })?;
+        Ok(page.unwrap_or((0, Vec::new())))
+    }
+
+    pub fn zlexcount(&self, key: &str, range: &LexRange) -> Result<i64, BackendError> {
+        let count = self.read_zset(key, |zset| zset.count_by_lex(range) as i64)?;
+        Ok(count.unwrap_or(0))
+    }
+
+    // ZREMRANGEBYRANK, ZREMRANGEBYSCORE and ZREMRANGEBYLEX
+    pub fn zremrange(&self, key: &str, spec: &ZRangeSpec) -> Result<i64, BackendError> {
+        let removed = self.modify_zset(key, |zset| Ok(zset.remove_range(spec) as i64))?;
+        let removed = removed.unwrap_or(0);
+        if removed > 0 {
+            let event = match spec {
+                ZRangeSpec::Rank(..) => "zremrangebyrank",
+                ZRangeSpec::Score(_) => "zremrangebyscore",
+                ZRangeSpec::Lex(_) => "zremrangebylex",
+            };
+            self.notify_removal(NotifyFlags::ZSET, event, key);
+        }
+        Ok(removed)
+    }
+
+    // pop up to `count` elements from the first non empty sorted set among `keys`
+    pub fn zmpop(
+        &self,
+        keys: &[String],
+        max: bool,
+        count: usize,
+    ) -> Result<Option<ZPopped>, BackendError> {
+        for key in keys {
+            let popped = self.zpop(key, max, count)?;
+            if !popped.is_empty() {
+                return Ok(Some((key.clone(), popped)));
+            }
+        }
+        Ok(None)
+    }
+
+    // combine the sorted sets at `keys`, plain sets count as members scored 1. Scores are
+    // multiplied by `weights` when given, one per key. The result is ordered by score.
+    pub fn zset_op(
+        &self,
+        op: SetOperator,
+        keys: &[String],
+        weights: Option<&[f64]>,
+        aggregate: Aggregate,
+    ) -> Result<Vec<(String, f64)>, BackendError> {
+        let inputs = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let weight = weights.map_or(1.0, |w| w[i]);
+                let members = self.snapshot_zset(key)?;
+                Ok(members.map(|members| {
+                    members
+                        .into_iter()
+                        .map(|(m, s)| (m, weighted(s, weight)))
+                        .collect::<HashMap<_, _>>()
+                }))
+            })
+            .collect::<Result<Vec<_>, BackendError>>()?;
+        let mut inputs = inputs.into_iter();
+        let mut result = inputs.next().flatten().unwrap_or_default();
+        for input in inputs {
+            let input = input.unwrap_or_default();
+            match op {
+                SetOperator::Union => {
+                    for (member, score) in input {
+                        result
+                            .entry(member)
+                            .and_modify(|s| *s = aggregate.apply(*s, score))
+                            .or_insert(score);
+                    }
+                }
+                SetOperator::Inter => {
+                    result.retain(|member, _| input.contains_key(member));
+                    for (member, score) in result.iter_mut() {
+                        *score = aggregate.apply(*score, input[member]);
+                    }
+                }
+                SetOperator::Diff => result.retain(|member, _| !input.contains_key(member)),
+            }
+        }
+        Ok(result
+            .into_iter()
+            .collect::<ZSet>()
+            .iter()
+            .map(|(m, s)| (m.to_string(), s))
+            .collect())
+    }
+
+    // store the result of `zset_op` at `dst`, an empty result deletes it
+    pub fn zset_op_store(
+        &self,
+        op: SetOperator,
+        dst: String,
+        keys: &[String],
+        weights: Option<&[f64]>,
+        aggregate: Aggregate,
+    ) -> Result<i64, BackendError> {
+        let result = self.zset_op(op, keys, weights, aggregate)?;
+        let len = result.len() as i64;
+        if result.is_empty() {
+            self.del(&[dst]);
+        } else {
+            let zset = result.into_iter().collect::<ZSet>();
+            self.put_entry(dst.clone(), Entry::new(Value::ZSet(zset)), true);
+            let event = match op {
+                SetOperator::Union => "zunionstore",
+                SetOperator::Inter => "zinterstore",
+                SetOperator::Diff => "zdiffstore",
+            };
+            self.notify(NotifyFlags::ZSET, event, &dst);
+        }
+        Ok(len)
+    }
+
+    // the size of the intersection, counting stops at `limit` unless it is 0
+    pub fn zintercard(&self, keys: &[String], limit: usize) -> Result<i64, BackendError> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let inputs = keys
+            .iter()
+            .map(|key| self.snapshot_zset(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some(mut inputs) = inputs.into_iter().collect::<Option<Vec<_>>>() else {
+            return Ok(0);
+        };
+        inputs.sort_by_key(|input| input.len());
+        let Some((smallest, others)) = inputs.split_first() else {
+            return Ok(0);
+        };
+        let others = others
+            .iter()
+            .map(|input| input.keys().collect::<HashSet<_>>())
+            .collect::<Vec<_>>();
+        let count = smallest
+            .keys()
+            .filter(|m| others.iter().all(|input| input.contains(m)))
+            .take(limit)
+            .count();
+        Ok(count as i64)
+    }
+
+    // the members and scores of a sorted set, or of a set with every score 1
+    fn snapshot_zset(&self, key: &str) -> Result<Option<HashMap<String, f64>>, BackendError> {
+        self.read(key, |value| match value {
+            Value::ZSet(zset) => Ok(zset.iter().map(|(m, s)| (m.to_string(), s)).collect()),
+            Value::Set(set) => Ok(set.iter().map(|m| (m.key().clone(), 1.0)).collect()),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+
+    pub(super) fn read_zset<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&ZSet) -> R,
+    ) -> Result<Option<R>, BackendError> {
+        self.read(key, |value| match value {
+            Value::ZSet(zset) => Ok(f(zset)),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+
+    fn write_zset<R>(
+        &self,
+        key: String,
+        f: impl FnOnce(&mut ZSet) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        self.write(
+            key,
+            || Value::ZSet(ZSet::new()),
+            |value| match value {
+                Value::ZSet(zset) => f(zset),
+                _ => Err(BackendError::WrongType),
+            },
+        )
+    }
+
+    fn modify_zset<R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut ZSet) -> Result<R, BackendError>,
+    ) -> Result<Option<R>, BackendError> {
+        self.modify(key, |value| match value {
+            Value::ZSet(zset) => f(zset),
+            _ => Err(BackendError::WrongType),
+        })
+    }
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            // inf + -inf is taken as 0 like in Redis
+            Aggregate::Sum => Some(a + b).filter(|s| !s.is_nan()).unwrap_or(0.0),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+// a score multiplied by its weight, inf * 0 is taken as 0
+fn weighted(score: f64, weight: f64) -> f64 {
+    Some(score * weight).filter(|s| !s.is_nan()).unwrap_or(0.0)
+}
+
+// the score a ZADD of `score` leaves the member with, None if the options skip it
+fn zadd_score(
+    old: Option<f64>,
+    score: f64,
+    incr: bool,
+    options: &ZAddOptions,
+) -> Result<Option<f64>, BackendError> {
+    match (old, options.condition) {
+        (Some(_), Some(SetCondition::Nx)) | (None, Some(SetCondition::Xx)) => return Ok(None),
+        _ => (),
+    }
+    let Some(old) = old else {
+        return Ok(Some(score));
+    };
+    let score = if incr { old + score } else { score };
+    if score.is_nan() {
+        return Err(BackendError::ScoreNan);
+    }
+    if (options.gt && score <= old) || (options.lt && score >= old) {
+        return Ok(None);
+    }
+    Ok(Some(score))
+}
+
+fn zrange(
+    zset: &ZSet,
+    spec: &ZRangeSpec,
+    rev: bool,
+    limit: Option<(i64, i64)>,
+) -> Vec<(String, f64)> {
+    let (offset, count) = match limit {
+        Some((offset, _)) if offset < 0 => return Vec::new(),
+        Some((offset, count)) => (offset as usize, (count >= 0).then_some(count as usize)),
+        None => (0, None),
+    };
+    match spec {
+        ZRangeSpec::Rank(start, stop) => {
+            let len = zset.len() as i64;
+            let start = if *start < 0 {
+                (start + len).max(0)
+            } else {
+                *start
+            };
+            let stop = if *stop < 0 { stop + len } else { *stop };
+            if stop < 0 || start > stop {
+                return Vec::new();
+            }
+            zset.range_by_rank(start as usize, stop as usize, rev)
+        }
+        ZRangeSpec::Score(range) => zset.range_by_score(range, rev, offset, count),
+        ZRangeSpec::Lex(range) => zset.range_by_lex(range, rev, offset, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(pairs: &[(&str, f64)]) -> ZSet {
+        pairs.iter().map(|(m, s)| (m.to_string(), *s)).collect()
+    }
+
+    fn members(range: Vec<(String, f64)>) -> Vec<String> {
+        range.into_iter().map(|(m, _)| m).collect()
+    }
+
+    fn score_range(min: f64, max: f64, exclusive: bool) -> ScoreRange {
+        ScoreRange {
+            min: ScoreBound {
+                value: min,
+                exclusive,
+            },
+            max: ScoreBound {
+                value: max,
+                exclusive,
+            },
+        }
+    }
+
+    #[test]
+    fn test_skiplist_ranks() {
+        let mut zset = ZSet::new();
+        for i in 0..1000 {
+            zset.insert(format!("m{:04}", i), (i % 100) as f64);
+        }
+        for i in (0..1000).step_by(3) {
+            zset.remove(&format!("m{:04}", i));
+        }
+        let ordered = zset
+            .iter()
+            .map(|(m, s)| (m.to_string(), s))
+            .collect::<Vec<_>>();
+        assert_eq!(ordered.len(), zset.len());
+        assert!(ordered
+            .windows(2)
+            .all(|w| (w[0].1, &w[0].0) < (w[1].1, &w[1].0)));
+        for (rank, (member, _)) in ordered.iter().enumerate() {
+            assert_eq!(zset.rank(member, false), Some(rank));
+            assert_eq!(zset.rank(member, true), Some(zset.len() - 1 - rank));
+            assert_eq!(zset.range_by_rank(rank, rank, false)[0].0, *member);
+        }
+        assert_eq!(zset.rank("m0000", false), None);
+    }
+
+    #[test]
+    fn test_zset_update_score() {
+        let mut zset = build(&[("a", 1.0), ("b", 2.0), ("c", 3.0)]);
+        assert_eq!(zset.insert("a".to_string(), 4.0), Some(1.0));
+        assert_eq!(members(zset.range_by_rank(0, 10, false)), ["b", "c", "a"]);
+        assert_eq!(members(zset.range_by_rank(0, 1, true)), ["a", "c"]);
+        assert_eq!(zset.remove("c"), Some(3.0));
+        assert_eq!(members(zset.pop(false, 1)), ["b"]);
+        assert_eq!(zset.len(), 1);
+    }
+
+    #[test]
+    fn test_zset_ranges() {
+        let zset = build(&[("a", 1.0), ("b", 2.0), ("c", 2.0), ("d", 3.0), ("e", 4.0)]);
+        let range = score_range(2.0, 3.0, false);
+        assert_eq!(
+            members(zset.range_by_score(&range, false, 0, None)),
+            ["b", "c", "d"]
+        );
+        assert_eq!(
+            members(zset.range_by_score(&range, true, 1, Some(1))),
+            ["c"]
+        );
+        assert_eq!(zset.count_by_score(&range), 3);
+        let range = score_range(1.0, 4.0, true);
+        assert_eq!(
+            members(zset.range_by_score(&range, true, 0, None)),
+            ["d", "c", "b"]
+        );
+        assert_eq!(zset.count_by_score(&score_range(5.0, 6.0, false)), 0);
+
+        let lex = build(&[("a", 0.0), ("b", 0.0), ("c", 0.0), ("d", 0.0)]);
+        let range = LexRange {
+            min: LexBound::Exclusive("a".to_string()),
+            max: LexBound::Max,
+        };
+        assert_eq!(
+            members(lex.range_by_lex(&range, false, 0, None)),
+            ["b", "c", "d"]
+        );
+        assert_eq!(
+            members(lex.range_by_lex(&range, true, 0, Some(2))),
+            ["d", "c"]
+        );
+        assert_eq!(lex.count_by_lex(&range), 3);
+    }
+
+    #[test]
+    fn test_zadd_options() -> Result<(), BackendError> {
+        let db = Db::new();
+        let pairs = |p: &[(f64, &str)]| p.iter().map(|(s, m)| (*s, m.to_string())).collect();
+        assert_eq!(
+            db.zadd(
+                "z".to_string(),
+                ZAddOptions::default(),
+                pairs(&[(1.0, "a"), (2.0, "b")])
+            )?,
+            2
+        );
+        let gt_ch = ZAddOptions {
+            gt: true,
+            ch: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            db.zadd(
+                "z".to_string(),
+                gt_ch,
+                pairs(&[(0.5, "a"), (3.0, "b"), (1.0, "c")])
+            )?,
+            2
+        );
+        assert_eq!(db.zscore("z", "a")?, Some(1.0));
+        assert_eq!(db.zscore("z", "b")?, Some(3.0));
+
+        let xx = ZAddOptions {
+            condition: Some(SetCondition::Xx),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.zadd_incr("z".to_string(), xx, 1.5, "missing".to_string())?,
+            None
+        );
+        assert_eq!(
+            db.zadd_incr("z".to_string(), xx, 1.5, "a".to_string())?,
+            Some(2.5)
+        );
+        db.zadd(
+            "inf".to_string(),
+            ZAddOptions::default(),
+            pairs(&[(f64::INFINITY, "a")]),
+        )?;
+        assert_eq!(
+            db.zadd_incr(
+                "inf".to_string(),
+                ZAddOptions::default(),
+                f64::NEG_INFINITY,
+                "a".to_string()
+            ),
+            Err(BackendError::ScoreNan)
+        );
+        assert_eq!(
+            db.zadd_incr("empty".to_string(), xx, 1.0, "a".to_string())?,
+            None
+        );
+        assert_eq!(db.exists(&["empty".to_string()]), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zset_op() -> Result<(), BackendError> {
+        let db = Db::new();
+        let pairs = |p: &[(f64, &str)]| p.iter().map(|(s, m)| (*s, m.to_string())).collect();
+        db.zadd(
+            "a".to_string(),
+            ZAddOptions::default(),
+            pairs(&[(1.0, "x"), (2.0, "y")]),
+        )?;
+        db.zadd(
+            "b".to_string(),
+            ZAddOptions::default(),
+            pairs(&[(10.0, "y"), (20.0, "z")]),
+        )?;
+        db.sadd("s".to_string(), vec!["y".to_string()])?;
+        let keys = ["a".to_string(), "b".to_string()];
+
+        let union = db.zset_op(SetOperator::Union, &keys, Some(&[2.0, 1.0]), Aggregate::Sum)?;
+        assert_eq!(
+            union,
+            vec![
+                ("x".to_string(), 2.0),
+                ("y".to_string(), 14.0),
+                ("z".to_string(), 20.0)
+            ]
+        );
+        let inter = db.zset_op(SetOperator::Inter, &keys, None, Aggregate::Max)?;
+        assert_eq!(inter, vec![("y".to_string(), 10.0)]);
+        let diff = db.zset_op(SetOperator::Diff, &keys, None, Aggregate::Sum)?;
+        assert_eq!(diff, vec![("x".to_string(), 1.0)]);
+        let with_set = ["s".to_string(), "a".to_string()];
+        let inter = db.zset_op(SetOperator::Inter, &with_set, None, Aggregate::Min)?;
+        assert_eq!(inter, vec![("y".to_string(), 1.0)]);
+        assert_eq!(db.zintercard(&keys, 0)?, 1);
+
+        assert_eq!(
+            db.zset_op_store(
+                SetOperator::Union,
+                "dst".to_string(),
+                &keys,
+                None,
+                Aggregate::Sum
+            )?,
+            3
+        );
+        assert_eq!(db.zremrange("dst", &ZRangeSpec::Rank(0, 0))?, 1);
+        let lex = LexRange {
+            min: LexBound::Min,
+            max: LexBound::Exclusive("z".to_string()),
+        };
+        assert_eq!(db.zlexcount("dst", &lex)?, 1);
+        assert_eq!(
+            db.zmpop(&["missing".to_string(), "dst".to_string()], true, 1)?,
+            Some(("dst".to_string(), vec![("z".to_string(), 20.0)]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_and_store() -> Result<(), BackendError> {
+        let db = Db::new();
+        let pairs = (0..10).map(|i| (i as f64, format!("m{}", i))).collect();
+        db.zadd("z".to_string(), ZAddOptions::default(), pairs)?;
+        let range = db.zrange("z", &ZRangeSpec::Rank(-3, -1), false, None)?;
+        assert_eq!(members(range), ["m7", "m8", "m9"]);
+        let range = db.zrange("z", &ZRangeSpec::Rank(0, 1), true, None)?;
+        assert_eq!(members(range), ["m9", "m8"]);
+        let spec = ZRangeSpec::Score(score_range(2.0, 8.0, false));
+        let range = db.zrange("z", &spec, false, Some((1, 2)))?;
+        assert_eq!(members(range), ["m3", "m4"]);
+        assert_eq!(
+            db.zrangestore("dst".to_string(), "z", &spec, true, Some((0, 3)))?,
+            3
+        );
+        assert_eq!(db.zcard("dst")?, 3);
+        assert_eq!(db.zrank("dst", "m6", false)?, Some((0, 6.0)));
+        assert_eq!(
+            db.zrangestore("dst".to_string(), "missing", &spec, false, None)?,
+            0
+        );
+        assert_eq!(db.exists(&["dst".to_string()]), 0);
+        assert_eq!(db.zcount("z", &score_range(2.0, 8.0, true))?, 5);
+        Ok(())
+    }
+}
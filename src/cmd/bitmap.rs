@@ -0,0 +1,651 @@
+use crate::{
+    extract_args, extract_int, extract_string, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BitFieldOp, BitFieldType, BitOperator, CommandError,
+    CommandExecutor, Overflow, RespArray, RespFrame,
+};
+
+use super::REST_NIL;
+
+// 512MB worth of bits, the maximum size of a string
+const MAX_BIT_OFFSET: i64 = 4 * 1024 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct SetBit {
+    key: String,
+    offset: usize,
+    on: bool,
+}
+
+#[derive(Debug)]
+pub struct GetBit {
+    key: String,
+    offset: usize,
+}
+
+#[derive(Debug)]
+pub struct BitCount {
+    key: String,
+    range: Option<(i64, i64)>,
+    bit: bool,
+}
+
+#[derive(Debug)]
+pub struct BitPos {
+    key: String,
+    bit: u8,
+    start: Option<i64>,
+    end: Option<i64>,
+    bit_unit: bool,
+}
+
+#[derive(Debug)]
+pub struct BitOp {
+    op: BitOperator,
+    dest: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct BitField {
+    key: String,
+    ops: Vec<BitFieldOp>,
+}
+
+impl BitField {
+    // BITFIELD with only GET subcommands is a read
+    pub fn writes(&self) -> bool {
+        self.ops
+            .iter()
+            .any(|op| matches!(op, BitFieldOp::Set(..) | BitFieldOp::IncrBy(..)))
+    }
+}
+
+impl CommandExecutor for SetBit {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().setbit(self.key.clone(), self.offset, self.on) {
+            Ok(old) => RespFrame::Integer(old as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GetBit {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().getbit(&self.key, self.offset) {
+            Ok(bit) => RespFrame::Integer(bit as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitCount {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().bitcount(&self.key, self.range, self.bit) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitPos {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .bitpos(&self.key, self.bit, self.start, self.end, self.bit_unit)
+        {
+            Ok(pos) => RespFrame::Integer(pos),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitOp {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().bitop(self.op, self.dest.clone(), &self.keys) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitField {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().bitfield(self.key.clone(), &self.ops) {
+            Ok(replies) => {
+                let replies = replies
+                    .into_iter()
+                    .map(|reply| match reply {
+                        Some(value) => RespFrame::Integer(value),
+                        None => REST_NIL.clone(),
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(replies).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn parse_bit_offset(value: RespFrame) -> Result<usize, CommandError> {
+    match extract_int(value) {
+        Ok(offset) if (0..MAX_BIT_OFFSET).contains(&offset) => Ok(offset as usize),
+        _ => Err(CommandError::InvalidArgument(
+            "bit offset is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+// `BYTE` or `BIT`, returns whether the range is given in bits
+fn parse_range_unit(value: RespFrame) -> Result<bool, CommandError> {
+    match extract_string(value)?.to_ascii_lowercase().as_str() {
+        "byte" => Ok(false),
+        "bit" => Ok(true),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+// `i<bits>` or `u<bits>`, i64 is the largest signed and u63 the largest unsigned type
+fn parse_bitfield_type(value: &str) -> Result<BitFieldType, CommandError> {
+    let err = || {
+        CommandError::InvalidArgument(
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                .to_string(),
+        )
+    };
+    let signed = match value.as_bytes().first() {
+        Some(b'i') | Some(b'I') => true,
+        Some(b'u') | Some(b'U') => false,
+        _ => return Err(err()),
+    };
+    let bits: u32 = value[1..].parse().map_err(|_| err())?;
+    let max = if signed { 64 } else { 63 };
+    if bits == 0 || bits > max {
+        return Err(err());
+    }
+    Ok(BitFieldType { signed, bits })
+}
+
+// a plain bit offset, or `#<n>` for the n-th field of the given type
+fn parse_bitfield_offset(value: &str, ty: BitFieldType) -> Result<usize, CommandError> {
+    let err = || {
+        CommandError::InvalidArgument("bit offset is not an integer or out of range".to_string())
+    };
+    let (multiply, value) = match value.strip_prefix('#') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let offset: i64 = value.parse().map_err(|_| err())?;
+    let offset = if multiply {
+        offset.checked_mul(ty.bits as i64).ok_or_else(err)?
+    } else {
+        offset
+    };
+    if !(0..MAX_BIT_OFFSET).contains(&offset) {
+        return Err(err());
+    }
+    Ok(offset as usize)
+}
+
+fn parse_bitfield_ops(
+    mut args: impl Iterator<Item = RespFrame>,
+    read_only: bool,
+) -> Result<Vec<BitFieldOp>, CommandError> {
+    let mut ops = Vec::new();
+    let syntax_err = || CommandError::InvalidArgument("syntax error".to_string());
+    while let Some(arg) = args.next() {
+        let subcommand = extract_string(arg)?.to_ascii_lowercase();
+        if read_only && subcommand != "get" {
+            return Err(CommandError::InvalidArgument(
+                "BITFIELD_RO only supports the GET subcommand".to_string(),
+            ));
+        }
+        let mut next = || args.next().ok_or_else(syntax_err).and_then(extract_string);
+        let op = match subcommand.as_str() {
+            "get" => {
+                let ty = parse_bitfield_type(&next()?)?;
+                BitFieldOp::Get(ty, parse_bitfield_offset(&next()?, ty)?)
+            }
+            "set" | "incrby" => {
+                let ty = parse_bitfield_type(&next()?)?;
+                let offset = parse_bitfield_offset(&next()?, ty)?;
+                let value = next()?.parse().map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "value is not an integer or out of range".to_string(),
+                    )
+                })?;
+                if subcommand == "set" {
+                    BitFieldOp::Set(ty, offset, value)
+                } else {
+                    BitFieldOp::IncrBy(ty, offset, value)
+                }
+            }
+            "overflow" => match next()?.to_ascii_lowercase().as_str() {
+                "wrap" => BitFieldOp::Overflow(Overflow::Wrap),
+                "sat" => BitFieldOp::Overflow(Overflow::Sat),
+                "fail" => BitFieldOp::Overflow(Overflow::Fail),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "Invalid OVERFLOW type specified".to_string(),
+                    ))
+                }
+            },
+            _ => return Err(syntax_err()),
+        };
+        ops.push(op);
+    }
+    Ok(ops)
+}
+
+//setbit :"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$1\r\n7\r\n$1\r\n1\r\n"
+impl TryFrom<RespArray> for SetBit {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["setbit"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(offset), Some(bit)) => Ok(SetBit {
+                key: extract_string(key)?,
+                offset: parse_bit_offset(offset)?,
+                on: match extract_int(bit) {
+                    Ok(0) => false,
+                    Ok(1) => true,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "bit is not an integer or out of range".to_string(),
+                        ))
+                    }
+                },
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GetBit {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["getbit"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(offset)) => Ok(GetBit {
+                key: extract_string(key)?,
+                offset: parse_bit_offset(offset)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+//bitcount :"*5\r\n$8\r\nbitcount\r\n$3\r\nkey\r\n$1\r\n0\r\n$2\r\n-1\r\n$3\r\nbit\r\n"
+impl TryFrom<RespArray> for BitCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["bitcount"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let (range, bit) = match (args.next(), args.next(), args.next(), args.next()) {
+            (None, None, None, None) => (None, false),
+            (Some(start), Some(end), unit, None) => (
+                Some((extract_int(start)?, extract_int(end)?)),
+                match unit {
+                    Some(unit) => parse_range_unit(unit)?,
+                    None => false,
+                },
+            ),
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(BitCount { key, range, bit })
+    }
+}
+
+impl TryFrom<RespArray> for BitPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["bitpos"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let bit = match args.next().map(extract_int) {
+            Some(Ok(bit)) if bit == 0 || bit == 1 => bit as u8,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "The bit argument must be 1 or 0.".to_string(),
+                ))
+            }
+        };
+        let start = args.next().map(extract_int).transpose()?;
+        let end = args.next().map(extract_int).transpose()?;
+        let bit_unit = match args.next() {
+            Some(unit) => parse_range_unit(unit)?,
+            None => false,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(BitPos {
+            key,
+            bit,
+            start,
+            end,
+            bit_unit,
+        })
+    }
+}
+
+//bitop :"*4\r\n$5\r\nbitop\r\n$3\r\nnot\r\n$4\r\ndest\r\n$3\r\nsrc\r\n"
+impl TryFrom<RespArray> for BitOp {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["bitop"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let op = match args.next().map(extract_string).transpose()? {
+            Some(op) => match op.to_ascii_lowercase().as_str() {
+                "and" => BitOperator::And,
+                "or" => BitOperator::Or,
+                "xor" => BitOperator::Xor,
+                "not" => BitOperator::Not,
+                "diff" => BitOperator::Diff,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            None => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        let dest = match args.next() {
+            Some(dest) => extract_string(dest)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let keys = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        match op {
+            BitOperator::Not if keys.len() != 1 => Err(CommandError::InvalidArgument(
+                "BITOP NOT must be called with a single source key.".to_string(),
+            )),
+            BitOperator::Diff if keys.len() < 2 => Err(CommandError::InvalidArgument(
+                "BITOP DIFF must be called with at least two source keys.".to_string(),
+            )),
+            _ => Ok(BitOp { op, dest, keys }),
+        }
+    }
+}
+
+//bitfield :"*5\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$3\r\nget\r\n$2\r\nu8\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for BitField {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let read_only = matches!(value.first(), Some(RespFrame::BulkString(cmd)) if cmd.eq_ignore_ascii_case(b"bitfield_ro"));
+        let name = if read_only { "bitfield_ro" } else { "bitfield" };
+        validate_command_minimum_length(&value, &[name], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        Ok(BitField {
+            key,
+            ops: parse_bitfield_ops(args, read_only)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+    use crate::BulkString;
+    use anyhow::Result;
+
+    #[test]
+    fn test_setbit_command_tryfrom() -> Result<()> {
+        let frames = decode(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$1\r\n7\r\n$1\r\n1\r\n")?;
+        let cmd = SetBit::try_from(frames)?;
+        assert_eq!(cmd.key, "key");
+        assert_eq!(cmd.offset, 7);
+        assert!(cmd.on);
+
+        let frames = decode(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$2\r\n-1\r\n$1\r\n1\r\n")?;
+        assert!(SetBit::try_from(frames).is_err());
+        let frames = decode(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$1\r\n7\r\n$1\r\n2\r\n")?;
+        assert!(SetBit::try_from(frames).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_command_tryfrom() -> Result<()> {
+        let frames = decode(
+            b"*10\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$8\r\noverflow\r\n$3\r\nsat\r\n$6\r\nincrby\r\n$2\r\ni5\r\n$2\r\n#2\r\n$2\r\n10\r\n$3\r\nget\r\n$2\r\nu4\r\n",
+        )?;
+        // GET is missing its offset
+        assert!(BitField::try_from(frames).is_err());
+
+        let frames = decode(
+            b"*11\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$8\r\noverflow\r\n$3\r\nsat\r\n$6\r\nincrby\r\n$2\r\ni5\r\n$2\r\n#2\r\n$2\r\n10\r\n$3\r\nget\r\n$2\r\nu4\r\n$1\r\n0\r\n",
+        )?;
+        let i5 = BitFieldType {
+            signed: true,
+            bits: 5,
+        };
+        let u4 = BitFieldType {
+            signed: false,
+            bits: 4,
+        };
+        let cmd = BitField::try_from(frames)?;
+        assert_eq!(
+            cmd.ops,
+            vec![
+                BitFieldOp::Overflow(Overflow::Sat),
+                BitFieldOp::IncrBy(i5, 10, 10),
+                BitFieldOp::Get(u4, 0),
+            ]
+        );
+
+        let frames = decode(
+            b"*6\r\n$11\r\nbitfield_ro\r\n$3\r\nkey\r\n$3\r\nset\r\n$2\r\nu8\r\n$1\r\n0\r\n$1\r\n1\r\n",
+        )?;
+        assert!(BitField::try_from(frames).is_err());
+        let frames =
+            decode(b"*5\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$3\r\nget\r\n$3\r\nu64\r\n$1\r\n0\r\n")?;
+        assert!(BitField::try_from(frames).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbit_getbit_bitcount_execute() -> Result<()> {
+        let backend = Backend::new();
+        let setbit = SetBit {
+            key: "visits".to_string(),
+            offset: 100,
+            on: true,
+        };
+        assert_eq!(setbit.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(setbit.execute(&backend), RespFrame::Integer(1));
+        let getbit = GetBit {
+            key: "visits".to_string(),
+            offset: 100,
+        };
+        assert_eq!(getbit.execute(&backend), RespFrame::Integer(1));
+        // the string grew to hold the bit
+        assert_eq!(backend.db().read_bytes("visits", |b| b.len())?, Some(13));
+
+        backend
+            .db()
+            .set("foobar".to_string(), BulkString::new("foobar").into());
+        let cases = [
+            (None, false, 26),
+            (Some((0, 0)), false, 4),
+            (Some((1, 1)), false, 6),
+            (Some((5, 30)), true, 17),
+            (Some((-2, -1)), false, 7),
+        ];
+        for (range, bit, expected) in cases {
+            let cmd = BitCount {
+                key: "foobar".to_string(),
+                range,
+                bit,
+            };
+            assert_eq!(cmd.execute(&backend), RespFrame::Integer(expected));
+        }
+        let cmd = BitCount {
+            key: "missing".to_string(),
+            range: None,
+            bit: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitpos_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend.db().set(
+            "key".to_string(),
+            BulkString::new(vec![0xff, 0xf0, 0x00]).into(),
+        );
+        let bitpos = |bit, start, end, bit_unit| BitPos {
+            key: "key".to_string(),
+            bit,
+            start,
+            end,
+            bit_unit,
+        };
+        assert_eq!(
+            bitpos(0, None, None, false).execute(&backend),
+            RespFrame::Integer(12)
+        );
+        assert_eq!(
+            bitpos(1, Some(2), None, false).execute(&backend),
+            RespFrame::Integer(-1)
+        );
+        assert_eq!(
+            bitpos(1, Some(7), Some(15), true).execute(&backend),
+            RespFrame::Integer(7)
+        );
+
+        backend
+            .db()
+            .set("ones".to_string(), BulkString::new(vec![0xff, 0xff]).into());
+        let cmd = BitPos {
+            key: "ones".to_string(),
+            bit: 0,
+            start: None,
+            end: None,
+            bit_unit: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(16));
+        let cmd = BitPos {
+            key: "ones".to_string(),
+            bit: 0,
+            start: Some(0),
+            end: Some(-1),
+            bit_unit: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-1));
+        let cmd = BitPos {
+            key: "missing".to_string(),
+            bit: 0,
+            start: None,
+            end: None,
+            bit_unit: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitop_execute() -> Result<()> {
+        let backend = Backend::new();
+        let db = backend.db();
+        db.set("a".to_string(), BulkString::new(vec![0b1100_1100]).into());
+        db.set(
+            "b".to_string(),
+            BulkString::new(vec![0b1010_1010, 0xff]).into(),
+        );
+
+        let cmd = BitOp {
+            op: BitOperator::And,
+            dest: "dest".to_string(),
+            keys: vec!["a".to_string(), "b".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            db.get("dest")?,
+            Some(BulkString::new(vec![0b1000_1000, 0x00]).into())
+        );
+
+        let cmd = BitOp {
+            op: BitOperator::Diff,
+            dest: "dest".to_string(),
+            keys: vec!["b".to_string(), "a".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            db.get("dest")?,
+            Some(BulkString::new(vec![0b0010_0010, 0xff]).into())
+        );
+
+        let cmd = BitOp {
+            op: BitOperator::Not,
+            dest: "dest".to_string(),
+            keys: vec!["missing".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(db.key_type("dest"), "none");
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_execute() -> Result<()> {
+        let backend = Backend::new();
+        let u8 = BitFieldType {
+            signed: false,
+            bits: 8,
+        };
+        let i8 = BitFieldType {
+            signed: true,
+            bits: 8,
+        };
+        let cmd = BitField {
+            key: "key".to_string(),
+            ops: vec![
+                BitFieldOp::Set(u8, 0, 255),
+                BitFieldOp::IncrBy(u8, 0, 10),
+                BitFieldOp::Overflow(Overflow::Sat),
+                BitFieldOp::IncrBy(i8, 8, 200),
+                BitFieldOp::Overflow(Overflow::Fail),
+                BitFieldOp::IncrBy(i8, 8, 1),
+                BitFieldOp::Get(u8, 0),
+            ],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![
+                RespFrame::Integer(0),
+                RespFrame::Integer(9),
+                RespFrame::Integer(127),
+                REST_NIL.clone(),
+                RespFrame::Integer(9),
+            ])
+            .into()
+        );
+
+        // read only operations do not create the key
+        let cmd = BitField {
+            key: "missing".to_string(),
+            ops: vec![BitFieldOp::Get(i8, 0)],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![RespFrame::Integer(0)]).into()
+        );
+        assert_eq!(backend.db().key_type("missing"), "none");
+        Ok(())
+    }
+}
@@ -0,0 +1,495 @@
+use std::time::Duration;
+
+use crate::{
+    command_name, extract_args, extract_string, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BlockedOp, BulkString, CommandError, CommandExecutor,
+    ListEnd, RespArray, RespFrame,
+};
+
+use super::{
+    list::{lmpop_reply, parse_list_end, parse_lmpop_args},
+    zset::{parse_zmpop_args, score_frame, zmpop_reply},
+    REST_NIL, REST_NIL_ARRAY,
+};
+
+// BLPOP and BRPOP
+#[derive(Debug)]
+pub struct BListPop {
+    keys: Vec<String>,
+    end: ListEnd,
+    timeout: Option<Duration>,
+}
+
+// BLMOVE and BRPOPLPUSH
+#[derive(Debug)]
+pub struct BLMove {
+    src: String,
+    dst: String,
+    from: ListEnd,
+    to: ListEnd,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMPop {
+    keys: Vec<String>,
+    end: ListEnd,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+// BZPOPMIN and BZPOPMAX
+#[derive(Debug)]
+pub struct BZPop {
+    keys: Vec<String>,
+    max: bool,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BZMPop {
+    keys: Vec<String>,
+    max: bool,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+// `execute` never blocks, it replies as if the timeout elapsed right away when there is nothing
+// to pop. Connections go through `execute_blocking` instead, see `Command::run`.
+impl CommandExecutor for BListPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        execute_now(backend, self.op(), REST_NIL_ARRAY.clone())
+    }
+}
+
+impl CommandExecutor for BLMove {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().lmove(&self.src, &self.dst, self.from, self.to) {
+            Ok(Some(value)) => {
+                backend.signal_ready(&self.dst);
+                value
+            }
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BLMPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        execute_now(backend, self.op(), lmpop_reply(None))
+    }
+}
+
+impl CommandExecutor for BZPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        execute_now(backend, self.op(backend), REST_NIL_ARRAY.clone())
+    }
+}
+
+impl CommandExecutor for BZMPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        execute_now(backend, self.op(backend), zmpop_reply(backend, None))
+    }
+}
+
+impl BListPop {
+    pub async fn execute_blocking(&self, backend: &Backend) -> RespFrame {
+        backend
+            .block_on(self.keys.clone(), self.timeout, None, self.op())
+            .await
+            .unwrap_or_else(|| REST_NIL_ARRAY.clone())
+    }
+
+    // the key popped from together with the element
+    fn op(&self) -> BlockedOp {
+        let (keys, end) = (self.keys.clone(), self.end);
+        Box::new(move |db| {
+            Ok(db.lmpop(&keys, end, 1)?.and_then(|(key, values)| {
+                let value = values.into_iter().next()?;
+                Some(RespArray::new(vec![BulkString::new(key).into(), value]).into())
+            }))
+        })
+    }
+}
+
+impl BLMove {
+    pub async fn execute_blocking(&self, backend: &Backend) -> RespFrame {
+        backend
+            .block_on(
+                vec![self.src.clone()],
+                self.timeout,
+                Some(self.dst.clone()),
+                self.op(),
+            )
+            .await
+            .unwrap_or_else(|| REST_NIL.clone())
+    }
+
+    fn op(&self) -> BlockedOp {
+        let (src, dst, from, to) = (self.src.clone(), self.dst.clone(), self.from, self.to);
+        Box::new(move |db| db.lmove(&src, &dst, from, to))
+    }
+}
+
+impl BLMPop {
+    pub async fn execute_blocking(&self, backend: &Backend) -> RespFrame {
+        backend
+            .block_on(self.keys.clone(), self.timeout, None, self.op())
+            .await
+            .unwrap_or_else(|| lmpop_reply(None))
+    }
+
+    fn op(&self) -> BlockedOp {
+        let (keys, end, count) = (self.keys.clone(), self.end, self.count);
+        Box::new(move |db| Ok(db.lmpop(&keys, end, count)?.map(|p| lmpop_reply(Some(p)))))
+    }
+}
+
+impl BZPop {
+    pub async fn execute_blocking(&self, backend: &Backend) -> RespFrame {
+        backend
+            .block_on(self.keys.clone(), self.timeout, None, self.op(backend))
+            .await
+            .unwrap_or_else(|| REST_NIL_ARRAY.clone())
+    }
+
+    // the key popped from, the member and its score
+    fn op(&self, backend: &Backend) -> BlockedOp {
+        let (keys, max, backend) = (self.keys.clone(), self.max, backend.clone());
+        Box::new(move |db| {
+            Ok(db.zmpop(&keys, max, 1)?.and_then(|(key, members)| {
+                let (member, score) = members.into_iter().next()?;
+                Some(
+                    RespArray::new(vec![
+                        BulkString::new(key).into(),
+                        BulkString::new(member).into(),
+                        score_frame(&backend, score),
+                    ])
+                    .into(),
+                )
+            }))
+        })
+    }
+}
+
+impl BZMPop {
+    pub async fn execute_blocking(&self, backend: &Backend) -> RespFrame {
+        backend
+            .block_on(self.keys.clone(), self.timeout, None, self.op(backend))
+            .await
+            .unwrap_or_else(|| zmpop_reply(backend, None))
+    }
+
+    fn op(&self, backend: &Backend) -> BlockedOp {
+        let (keys, max, count) = (self.keys.clone(), self.max, self.count);
+        let backend = backend.clone();
+        Box::new(move |db| {
+            let popped = db.zmpop(&keys, max, count)?;
+            Ok(popped.map(|popped| zmpop_reply(&backend, Some(popped))))
+        })
+    }
+}
+
+fn execute_now(backend: &Backend, op: BlockedOp, timed_out: RespFrame) -> RespFrame {
+    match op(&backend.db()) {
+        Ok(Some(reply)) => reply,
+        Ok(None) => timed_out,
+        Err(e) => e.into(),
+    }
+}
+
+//blpop :"*4\r\n$5\r\nblpop\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for BListPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["blpop", "brpop"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        let mut args = extract_args(value)?;
+        let timeout = parse_timeout(args.pop())?;
+        Ok(BListPop {
+            keys: args
+                .into_iter()
+                .map(extract_string)
+                .collect::<Result<Vec<_>, _>>()?,
+            end: if name == "blpop" {
+                ListEnd::Left
+            } else {
+                ListEnd::Right
+            },
+            timeout,
+        })
+    }
+}
+
+//blmove :"*6\r\n$6\r\nblmove\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$4\r\nleft\r\n$5\r\nright\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["blmove", "brpoplpush"]);
+        let rpoplpush = name == "brpoplpush";
+        validate_command_exact_length(&value, &[name], if rpoplpush { 3 } else { 5 })?;
+        let mut args = extract_args(value)?.into_iter();
+        let src = args.next().map(extract_string).transpose()?;
+        let dst = args.next().map(extract_string).transpose()?;
+        let (from, to) = if rpoplpush {
+            (ListEnd::Right, ListEnd::Left)
+        } else {
+            (parse_list_end(&mut args)?, parse_list_end(&mut args)?)
+        };
+        Ok(BLMove {
+            src: src.unwrap_or_default(),
+            dst: dst.unwrap_or_default(),
+            from,
+            to,
+            timeout: parse_timeout(args.next())?,
+        })
+    }
+}
+
+//blmpop :"*5\r\n$6\r\nblmpop\r\n$1\r\n0\r\n$1\r\n1\r\n$3\r\nkey\r\n$4\r\nleft\r\n"
+impl TryFrom<RespArray> for BLMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["blmpop"], 4)?;
+        let mut args = extract_args(value)?.into_iter();
+        let timeout = parse_timeout(args.next())?;
+        let (keys, end, count) = parse_lmpop_args(&mut args)?;
+        Ok(BLMPop {
+            keys,
+            end,
+            count,
+            timeout,
+        })
+    }
+}
+
+//bzpopmin :"*3\r\n$8\r\nbzpopmin\r\n$3\r\nkey\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for BZPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["bzpopmin", "bzpopmax"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        let mut args = extract_args(value)?;
+        let timeout = parse_timeout(args.pop())?;
+        Ok(BZPop {
+            keys: args
+                .into_iter()
+                .map(extract_string)
+                .collect::<Result<Vec<_>, _>>()?,
+            max: name == "bzpopmax",
+            timeout,
+        })
+    }
+}
+
+//bzmpop :"*5\r\n$6\r\nbzmpop\r\n$1\r\n0\r\n$1\r\n1\r\n$3\r\nkey\r\n$3\r\nmin\r\n"
+impl TryFrom<RespArray> for BZMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["bzmpop"], 4)?;
+        let mut args = extract_args(value)?.into_iter();
+        let timeout = parse_timeout(args.next())?;
+        let (keys, max, count) = parse_zmpop_args(&mut args)?;
+        Ok(BZMPop {
+            keys,
+            max,
+            count,
+            timeout,
+        })
+    }
+}
+
+// a timeout in seconds with decimals allowed, zero blocks forever
+pub fn parse_timeout(value: Option<RespFrame>) -> Result<Option<Duration>, CommandError> {
+    let timeout = value
+        .map(extract_string)
+        .transpose()?
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|t| t.is_finite())
+        .ok_or_else(|| {
+            CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+        })?;
+    if timeout < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    Ok((timeout > 0.0).then(|| Duration::from_secs_f64(timeout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+
+    use anyhow::Result;
+
+    fn push(backend: &Backend, key: &str, values: &[&str]) {
+        let values = values.iter().map(|v| BulkString::new(*v).into()).collect();
+        backend
+            .db()
+            .push(key.to_string(), ListEnd::Right, values, true)
+            .unwrap();
+        backend.signal_ready(key);
+    }
+
+    #[test]
+    fn test_blocking_command_tryfrom() -> Result<()> {
+        let cmd = BListPop::try_from(decode(
+            b"*4\r\n$5\r\nbrpop\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\n1.5\r\n",
+        )?)?;
+        assert_eq!(cmd.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(cmd.end, ListEnd::Right);
+        assert_eq!(cmd.timeout, Some(Duration::from_millis(1500)));
+
+        let cmd = BLMove::try_from(decode(
+            b"*4\r\n$10\r\nbrpoplpush\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$1\r\n0\r\n",
+        )?)?;
+        assert_eq!((cmd.from, cmd.to), (ListEnd::Right, ListEnd::Left));
+        assert_eq!(cmd.timeout, None);
+
+        let cmd = BLMPop::try_from(decode(
+            b"*7\r\n$6\r\nblmpop\r\n$1\r\n0\r\n$1\r\n1\r\n$3\r\nkey\r\n$5\r\nright\r\n$5\r\ncount\r\n$1\r\n2\r\n",
+        )?)?;
+        assert_eq!((cmd.keys.len(), cmd.end, cmd.count), (1, ListEnd::Right, 2));
+
+        assert_eq!(
+            BListPop::try_from(decode(b"*3\r\n$5\r\nblpop\r\n$1\r\na\r\n$2\r\n-1\r\n")?)
+                .unwrap_err(),
+            CommandError::InvalidArgument("timeout is negative".to_string())
+        );
+        assert_eq!(
+            BListPop::try_from(decode(b"*3\r\n$5\r\nblpop\r\n$1\r\na\r\n$3\r\ninf\r\n")?)
+                .unwrap_err(),
+            CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blpop_served_by_push() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BListPop::try_from(decode(
+            b"*4\r\n$5\r\nblpop\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n0\r\n",
+        )?)?;
+        assert_eq!(cmd.execute(&backend), REST_NIL_ARRAY.clone());
+
+        let blocked = backend.connect();
+        let task = tokio::spawn(async move { cmd.execute_blocking(&blocked).await });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        push(&backend, "b", &["x", "y"]);
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::new("b").into(),
+            BulkString::new("x").into(),
+        ])
+        .into();
+        assert_eq!(task.await?, expected);
+        assert_eq!(backend.db().llen("b"), Ok(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bzpop_served_by_zadd() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BZPop::try_from(decode(
+            b"*4\r\n$8\r\nbzpopmax\r\n$1\r\na\r\n$1\r\nz\r\n$1\r\n0\r\n",
+        )?)?;
+        let blocked = backend.connect();
+        let task = tokio::spawn(async move { cmd.execute_blocking(&blocked).await });
+        // a list pushed to one of the keys does not serve a sorted set pop
+        let list = BListPop::try_from(decode(b"*3\r\n$5\r\nblpop\r\n$1\r\na\r\n$1\r\n0\r\n")?)?;
+        let list_waiter = backend.connect();
+        let list_task = tokio::spawn(async move { list.execute_blocking(&list_waiter).await });
+        while backend.blocked_clients() < 2 {
+            tokio::task::yield_now().await;
+        }
+        let zadd = crate::Command::try_from(RespFrame::from(decode(
+            b"*6\r\n$4\r\nzadd\r\n$1\r\nz\r\n$1\r\n1\r\n$1\r\nx\r\n$1\r\n2\r\n$1\r\ny\r\n",
+        )?))?;
+        assert_eq!(zadd.execute(&backend), RespFrame::Integer(2));
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::new("z").into(),
+            BulkString::new("y").into(),
+            BulkString::new("2").into(),
+        ])
+        .into();
+        assert_eq!(task.await?, expected);
+
+        // the list waiter only wakes for its own type
+        backend.db().zadd(
+            "a".to_string(),
+            crate::ZAddOptions::default(),
+            vec![(1.0, "m".to_string())],
+        )?;
+        backend.signal_ready("a");
+        assert_eq!(backend.blocked_clients(), 1);
+        backend.db().del(&["a".to_string()]);
+        push(&backend, "a", &["v"]);
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::new("a").into(),
+            BulkString::new("v").into(),
+        ])
+        .into();
+        assert_eq!(list_task.await?, expected);
+
+        let cmd = BZMPop::try_from(decode(
+            b"*7\r\n$6\r\nbzmpop\r\n$1\r\n0\r\n$1\r\n1\r\n$1\r\nz\r\n$3\r\nmin\r\n$5\r\ncount\r\n$1\r\n3\r\n",
+        )?)?;
+        assert_eq!(
+            cmd.execute(&backend),
+            zmpop_reply(
+                &backend,
+                Some(("z".to_string(), vec![("x".to_string(), 1.0)]))
+            )
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocking_timeout() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BLMPop::try_from(decode(
+            b"*5\r\n$6\r\nblmpop\r\n$4\r\n0.01\r\n$1\r\n1\r\n$3\r\nkey\r\n$4\r\nleft\r\n",
+        )?)?;
+        assert_eq!(cmd.execute_blocking(&backend).await, lmpop_reply(None));
+        let cmd = BLMove::try_from(decode(
+            b"*6\r\n$6\r\nblmove\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$4\r\nleft\r\n$5\r\nright\r\n$4\r\n0.01\r\n",
+        )?)?;
+        assert_eq!(cmd.execute_blocking(&backend).await, REST_NIL.clone());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blmove_wakes_destination_waiters() -> Result<()> {
+        let backend = Backend::new();
+        let pop = BListPop::try_from(decode(b"*3\r\n$5\r\nblpop\r\n$3\r\ndst\r\n$1\r\n0\r\n")?)?;
+        let blocked = backend.connect();
+        let task = tokio::spawn(async move { pop.execute_blocking(&blocked).await });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        push(&backend, "src", &["a"]);
+        let cmd = BLMove::try_from(decode(
+            b"*6\r\n$6\r\nblmove\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$4\r\nleft\r\n$5\r\nright\r\n$1\r\n0\r\n",
+        )?)?;
+        assert_eq!(
+            cmd.execute_blocking(&backend).await,
+            BulkString::new("a").into()
+        );
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::new("dst").into(),
+            BulkString::new("a").into(),
+        ])
+        .into();
+        assert_eq!(task.await?, expected);
+        Ok(())
+    }
+}
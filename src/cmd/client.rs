@@ -0,0 +1,281 @@
+use crate::{
+    extract_args, extract_int, extract_string, validate_command_minimum_length, Backend,
+    BulkString, CommandError, CommandExecutor, RespArray, RespFrame, RespMap, RespSet,
+    TrackingOptions,
+};
+
+use super::REST_OK;
+
+#[derive(Debug)]
+pub struct Client {
+    op: ClientOp,
+}
+
+#[derive(Debug, PartialEq)]
+enum ClientOp {
+    Id,
+    // None turns tracking off
+    Tracking(Option<TrackingOptions>),
+    Caching(bool),
+    GetRedir,
+    TrackingInfo,
+}
+
+impl CommandExecutor for Client {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match &self.op {
+            ClientOp::Id => RespFrame::Integer(backend.session().id() as i64),
+            ClientOp::Tracking(Some(options)) => match backend.enable_tracking(options.clone()) {
+                Ok(()) => REST_OK.clone(),
+                Err(e) => e.into(),
+            },
+            ClientOp::Tracking(None) => {
+                backend.disable_tracking();
+                REST_OK.clone()
+            }
+            ClientOp::Caching(yes) => match backend.set_tracking_caching(*yes) {
+                Ok(()) => REST_OK.clone(),
+                Err(e) => e.into(),
+            },
+            ClientOp::GetRedir => RespFrame::Integer(backend.tracking_redirect()),
+            ClientOp::TrackingInfo => {
+                let info = backend.tracking_info();
+                let flags = info
+                    .flags
+                    .into_iter()
+                    .map(|flag| BulkString::new(flag).into())
+                    .collect::<Vec<RespFrame>>();
+                let prefixes = info
+                    .prefixes
+                    .into_iter()
+                    .map(|prefix| BulkString::new(prefix).into())
+                    .collect::<Vec<RespFrame>>();
+                let resp3 = backend.session().is_resp3();
+                let fields: [(&str, RespFrame); 3] = [
+                    (
+                        "flags",
+                        match resp3 {
+                            true => RespSet::new(flags).into(),
+                            false => RespArray::new(flags).into(),
+                        },
+                    ),
+                    ("redirect", RespFrame::Integer(info.redirect)),
+                    ("prefixes", RespArray::new(prefixes).into()),
+                ];
+                if resp3 {
+                    let mut map = RespMap::new();
+                    for (k, v) in fields {
+                        map.insert(k.to_string(), v);
+                    }
+                    map.into()
+                } else {
+                    let mut frames = Vec::with_capacity(fields.len() * 2);
+                    for (k, v) in fields {
+                        frames.push(BulkString::new(k).into());
+                        frames.push(v);
+                    }
+                    RespArray::new(frames).into()
+                }
+            }
+        }
+    }
+}
+
+// the options after CLIENT TRACKING ON
+fn tracking_options(args: Vec<RespFrame>) -> Result<TrackingOptions, CommandError> {
+    let mut options = TrackingOptions::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match extract_string(arg)?.to_ascii_lowercase().as_str() {
+            "redirect" => {
+                let id = extract_int(args.next().ok_or_else(syntax_error)?)?;
+                // redirecting to no client at all, as Redis takes it
+                options.redirect = (id != 0).then_some(id as u64);
+            }
+            "prefix" => options
+                .prefixes
+                .push(extract_string(args.next().ok_or_else(syntax_error)?)?),
+            "bcast" => options.bcast = true,
+            "optin" => options.optin = true,
+            "optout" => options.optout = true,
+            "noloop" => options.noloop = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    if !options.bcast && !options.prefixes.is_empty() {
+        return Err(CommandError::InvalidArgument(
+            "PREFIX option requires BCAST mode to be enabled".to_string(),
+        ));
+    }
+    if options.optin && options.optout {
+        return Err(CommandError::InvalidArgument(
+            "You can't use both OPTIN and OPTOUT".to_string(),
+        ));
+    }
+    if options.bcast && (options.optin || options.optout) {
+        return Err(CommandError::InvalidArgument(
+            "OPTIN and OPTOUT are not compatible with BCAST".to_string(),
+        ));
+    }
+    Ok(options)
+}
+
+//client :"*3\r\n$6\r\nclient\r\n$8\r\ntracking\r\n$2\r\non\r\n"
+impl TryFrom<RespArray> for Client {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["client"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let sub = extract_string(args.next().ok_or_else(syntax_error)?)?.to_ascii_lowercase();
+        let args = args.collect::<Vec<_>>();
+        let op = match (sub.as_str(), args.len()) {
+            ("id", 0) => ClientOp::Id,
+            ("tracking", n) if n > 0 => {
+                let mut args = args.into_iter();
+                let toggle = extract_string(args.next().ok_or_else(syntax_error)?)?;
+                let rest = args.collect::<Vec<_>>();
+                match toggle.to_ascii_lowercase().as_str() {
+                    "on" => ClientOp::Tracking(Some(tracking_options(rest)?)),
+                    "off" if rest.is_empty() => ClientOp::Tracking(None),
+                    _ => return Err(syntax_error()),
+                }
+            }
+            ("caching", 1) => {
+                let mode = extract_string(args.into_iter().next().ok_or_else(syntax_error)?)?;
+                match mode.to_ascii_lowercase().as_str() {
+                    "yes" => ClientOp::Caching(true),
+                    "no" => ClientOp::Caching(false),
+                    _ => return Err(syntax_error()),
+                }
+            }
+            ("getredir", 0) => ClientOp::GetRedir,
+            ("trackinginfo", 0) => ClientOp::TrackingInfo,
+            ("id" | "tracking" | "caching" | "getredir" | "trackinginfo", _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "wrong number of arguments for 'client|{}' command",
+                    sub
+                )))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try CLIENT HELP.",
+                    sub
+                )))
+            }
+        };
+        Ok(Client { op })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::request;
+    use crate::{RespPush, SimpleError};
+
+    fn client(backend: &Backend, args: &[&str]) -> RespFrame {
+        match Client::try_from(request(args)) {
+            Ok(cmd) => cmd.execute(backend),
+            Err(e) => e.into(),
+        }
+    }
+
+    #[test]
+    fn test_client_command_tryfrom() {
+        let cmd = Client::try_from(request(&[
+            "client", "tracking", "on", "bcast", "prefix", "a", "prefix", "b", "noloop",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cmd.op,
+            ClientOp::Tracking(Some(TrackingOptions {
+                prefixes: vec!["a".to_string(), "b".to_string()],
+                bcast: true,
+                noloop: true,
+                ..Default::default()
+            }))
+        );
+        let cmd =
+            Client::try_from(request(&["client", "tracking", "on", "redirect", "7"])).unwrap();
+        assert_eq!(
+            cmd.op,
+            ClientOp::Tracking(Some(TrackingOptions {
+                redirect: Some(7),
+                ..Default::default()
+            }))
+        );
+        for args in [
+            &["client", "tracking", "on", "prefix", "a"][..],
+            &["client", "tracking", "on", "optin", "optout"],
+            &["client", "tracking", "on", "bcast", "optin"],
+            &["client", "tracking", "maybe"],
+            &["client", "caching", "perhaps"],
+            &["client", "nope"],
+        ] {
+            assert!(Client::try_from(request(args)).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_tracking() {
+        let server = crate::Backend::new();
+        let backend = server.connect();
+        let mut pushes = backend.open_pushes();
+        let other = server.connect();
+        let run = |backend: &Backend, args: &[&str]| {
+            let cmd = crate::Command::try_from(RespFrame::from(request(args))).unwrap();
+            let backend = backend.clone();
+            async move { cmd.run(&backend).await }
+        };
+
+        assert_eq!(
+            client(&backend, &["client", "getredir"]),
+            RespFrame::Integer(-1)
+        );
+        backend.session().set_protocol(3);
+        assert_eq!(
+            client(&backend, &["client", "tracking", "on"]),
+            REST_OK.clone()
+        );
+        assert_eq!(
+            client(&backend, &["client", "getredir"]),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            client(&backend, &["client", "caching", "yes"]),
+            SimpleError::new("ERR CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled".to_string()).into()
+        );
+
+        run(&backend, &["get", "k"]).await;
+        run(&other, &["set", "k", "v"]).await;
+        assert_eq!(
+            pushes.try_recv().ok(),
+            Some(
+                RespPush::new(vec![
+                    BulkString::new("invalidate").into(),
+                    RespArray::new(vec![BulkString::new("k").into()]).into(),
+                ])
+                .into()
+            )
+        );
+
+        let mut info = RespMap::new();
+        info.insert(
+            "flags".to_string(),
+            RespSet::new(vec![BulkString::new("on").into()]).into(),
+        );
+        info.insert("redirect".to_string(), RespFrame::Integer(0));
+        info.insert("prefixes".to_string(), RespArray::new(vec![]).into());
+        assert_eq!(client(&backend, &["client", "trackinginfo"]), info.into());
+
+        client(&backend, &["client", "tracking", "off"]);
+        run(&backend, &["get", "k"]).await;
+        run(&other, &["set", "k", "w"]).await;
+        assert!(pushes.try_recv().is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    extract_args, extract_string, glob_match, validate_command_minimum_length, Backend, BulkString,
+    CommandError, CommandExecutor, NotifyFlags, RespArray, RespFrame, RespMap, SimpleError,
+};
+
+use super::REST_OK;
+
+// the parameters CONFIG knows about
+const PARAMETERS: [&str; 1] = ["notify-keyspace-events"];
+
+#[derive(Debug)]
+pub struct Config {
+    op: ConfigOp,
+}
+
+#[derive(Debug, PartialEq)]
+enum ConfigOp {
+    Get(Vec<String>),
+    Set(Vec<(String, String)>),
+}
+
+impl CommandExecutor for Config {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match &self.op {
+            ConfigOp::Get(patterns) => {
+                let values = PARAMETERS
+                    .iter()
+                    .filter(|name| {
+                        patterns
+                            .iter()
+                            .any(|pattern| glob_match(pattern.as_bytes(), name.as_bytes(), true))
+                    })
+                    .map(|name| (name.to_string(), parameter(backend, name)))
+                    .collect::<Vec<_>>();
+                config_reply(backend, values)
+            }
+            ConfigOp::Set(pairs) => {
+                // every value is checked before any is applied
+                let mut flags = None;
+                for (name, value) in pairs {
+                    match name.as_str() {
+                        "notify-keyspace-events" => match NotifyFlags::parse(value) {
+                            Some(parsed) => flags = Some(parsed),
+                            None => return SimpleError::new("ERR CONFIG SET failed (possibly related to argument 'notify-keyspace-events') - Invalid event class character. Use 'Ag$lshzxeKEtmn'.".to_string()).into(),
+                        },
+                        _ => {
+                            return SimpleError::new(format!(
+                                "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                                name
+                            ))
+                            .into()
+                        }
+                    }
+                }
+                if let Some(flags) = flags {
+                    backend.set_notify_keyspace_events(flags);
+                }
+                REST_OK.clone()
+            }
+        }
+    }
+}
+
+fn parameter(backend: &Backend, name: &str) -> String {
+    match name {
+        "notify-keyspace-events" => backend.notify_keyspace_events().to_string(),
+        _ => String::new(),
+    }
+}
+
+// the parameters with their values, as a map for RESP3 clients
+fn config_reply(backend: &Backend, values: Vec<(String, String)>) -> RespFrame {
+    if backend.session().is_resp3() {
+        let mut map = RespMap::new();
+        for (name, value) in values {
+            map.insert(name, BulkString::new(value).into());
+        }
+        map.into()
+    } else {
+        let mut frames = Vec::with_capacity(values.len() * 2);
+        for (name, value) in values {
+            frames.push(BulkString::new(name).into());
+            frames.push(BulkString::new(value).into());
+        }
+        RespArray::new(frames).into()
+    }
+}
+
+//config :"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$22\r\nnotify-keyspace-events\r\n$3\r\nKEA\r\n"
+impl TryFrom<RespArray> for Config {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["config"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let sub = extract_string(args.next().ok_or_else(syntax_error)?)?.to_ascii_lowercase();
+        let args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        let op = match sub.as_str() {
+            "get" if !args.is_empty() => ConfigOp::Get(args),
+            "set" if !args.is_empty() && args.len() % 2 == 0 => ConfigOp::Set(
+                args.chunks(2)
+                    .map(|pair| (pair[0].to_ascii_lowercase(), pair[1].clone()))
+                    .collect(),
+            ),
+            "get" | "set" => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "wrong number of arguments for 'config|{}' command",
+                    sub
+                )))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try CONFIG HELP.",
+                    sub
+                )))
+            }
+        };
+        Ok(Config { op })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+
+    use anyhow::Result;
+
+    #[test]
+    fn test_config_command_tryfrom() -> Result<()> {
+        let frames = decode(b"*3\r\n$6\r\nconfig\r\n$3\r\nget\r\n$7\r\nnotify*\r\n")?;
+        let cmd = Config::try_from(frames)?;
+        assert_eq!(cmd.op, ConfigOp::Get(vec!["notify*".to_string()]));
+
+        let frames = decode(b"*3\r\n$6\r\nconfig\r\n$3\r\nset\r\n$1\r\nx\r\n")?;
+        assert!(Config::try_from(frames).is_err());
+        let frames = decode(b"*2\r\n$6\r\nconfig\r\n$4\r\nnope\r\n")?;
+        assert!(Config::try_from(frames).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_command() -> Result<()> {
+        let backend = Backend::new();
+        let frames = decode(
+            b"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$22\r\nnotify-keyspace-events\r\n$3\r\nKEA\r\n",
+        )?;
+        let result = Config::try_from(frames)?.execute(&backend);
+        assert_eq!(result, REST_OK.clone());
+
+        let frames = decode(b"*3\r\n$6\r\nconfig\r\n$3\r\nget\r\n$7\r\nnotify*\r\n")?;
+        let result = Config::try_from(frames)?.execute(&backend);
+        assert_eq!(
+            result,
+            RespArray::new(vec![
+                BulkString::new("notify-keyspace-events").into(),
+                BulkString::new("AKE").into(),
+            ])
+            .into()
+        );
+
+        let frames = decode(
+            b"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$22\r\nnotify-keyspace-events\r\n$2\r\nKq\r\n",
+        )?;
+        let result = Config::try_from(frames)?.execute(&backend);
+        assert!(matches!(result, RespFrame::Error(_)));
+        assert_eq!(backend.notify_keyspace_events().to_string(), "AKE");
+
+        let frames = decode(b"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$7\r\nmaxdata\r\n$1\r\n1\r\n")?;
+        let result = Config::try_from(frames)?.execute(&backend);
+        assert!(matches!(result, RespFrame::Error(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keyspace_events_published() -> Result<()> {
+        let backend = Backend::new();
+        let subscriber = backend.connect();
+        let mut pushes = subscriber.open_pushes();
+        subscriber.subscribe(
+            crate::SubscriptionKind::Channel,
+            &["__keyevent@0__:set".to_string()],
+        );
+        pushes.try_recv().ok();
+
+        let frames = decode(
+            b"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$22\r\nnotify-keyspace-events\r\n$3\r\nKEA\r\n",
+        )?;
+        crate::Command::try_from(RespFrame::from(frames))?
+            .run(&backend)
+            .await;
+        let frames = decode(b"*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n")?;
+        crate::Command::try_from(RespFrame::from(frames))?
+            .run(&backend)
+            .await;
+        let message: RespFrame = crate::RespPush::new(vec![
+            BulkString::new("message").into(),
+            BulkString::new("__keyevent@0__:set").into(),
+            BulkString::new("hello").into(),
+        ])
+        .into();
+        assert_eq!(pushes.try_recv().ok(), Some(message));
+        assert!(pushes.try_recv().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    extract_args, extract_int, validate_command_exact_length, validate_command_minimum_length,
+    Backend, BulkString, CommandError, CommandExecutor, RespArray, RespFrame, RespMap, SimpleError,
+    SimpleString,
+};
+
+use super::REST_OK;
+
+#[derive(Debug)]
+pub struct Hello {
+    protocol: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<Vec<u8>>,
+}
+
+// the connection is closed once the reply is written, see `Command::closes_connection`
+#[derive(Debug)]
+pub struct Quit;
+
+#[derive(Debug)]
+pub struct Reset;
+
+#[derive(Debug)]
+pub struct Time;
+
+impl CommandExecutor for Hello {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match self.protocol {
+            Some(protocol @ (2 | 3)) => backend.session().set_protocol(protocol as u8),
+            Some(_) => {
+                return SimpleError::new("NOPROTO unsupported protocol version".to_string()).into()
+            }
+            None => {}
+        }
+        let protocol = backend.session().protocol();
+        let info: [(&str, RespFrame); 6] = [
+            ("server", BulkString::new("redis").into()),
+            ("version", BulkString::new(env!("CARGO_PKG_VERSION")).into()),
+            ("proto", RespFrame::Integer(protocol as i64)),
+            ("mode", BulkString::new("standalone").into()),
+            ("role", BulkString::new("master").into()),
+            ("modules", RespArray::new(vec![]).into()),
+        ];
+        if backend.session().is_resp3() {
+            let mut map = RespMap::new();
+            for (k, v) in info {
+                map.insert(k.to_string(), v);
+            }
+            map.into()
+        } else {
+            let mut frames = Vec::with_capacity(info.len() * 2);
+            for (k, v) in info {
+                frames.push(BulkString::new(k).into());
+                frames.push(v);
+            }
+            RespArray::new(frames).into()
+        }
+    }
+}
+
+// a RESP2 connection with subscriptions gets the reply shape of the messages it receives
+impl CommandExecutor for Ping {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if !backend.session().is_resp3() && backend.subscriptions() > 0 {
+            let message = self.message.clone().unwrap_or_default();
+            return RespArray::new(vec![
+                BulkString::new("pong").into(),
+                BulkString::new(message).into(),
+            ])
+            .into();
+        }
+        match &self.message {
+            Some(message) => BulkString::new(message.clone()).into(),
+            None => SimpleString::new("PONG").into(),
+        }
+    }
+}
+
+impl CommandExecutor for Quit {
+    fn execute(&self, _backend: &Backend) -> RespFrame {
+        REST_OK.clone()
+    }
+}
+
+impl CommandExecutor for Reset {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        backend.reset();
+        SimpleString::new("RESET").into()
+    }
+}
+
+// the unix time in seconds, and the microseconds elapsed in the current second
+impl CommandExecutor for Time {
+    fn execute(&self, _backend: &Backend) -> RespFrame {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        RespArray::new(vec![
+            BulkString::new(now.as_secs().to_string()).into(),
+            BulkString::new(now.subsec_micros().to_string()).into(),
+        ])
+        .into()
+    }
+}
+
+//hello :"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n"
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["hello"], 0)?;
+        if value.len() > 2 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let protocol = args
+            .next()
+            .map(|protocol| {
+                extract_int(protocol).map_err(|_| {
+                    CommandError::InvalidArgument("Protocol version is not an integer".to_string())
+                })
+            })
+            .transpose()?;
+        Ok(Hello { protocol })
+    }
+}
+
+//ping :"*2\r\n$4\r\nping\r\n$5\r\nhello\r\n"
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["ping"], 0)?;
+        if value.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'ping' command".to_string(),
+            ));
+        }
+        let message = match extract_args(value)?.into_iter().next() {
+            Some(RespFrame::BulkString(message)) => Some(message.0),
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => None,
+        };
+        Ok(Ping { message })
+    }
+}
+
+//quit :"*1\r\n$4\r\nquit\r\n"
+impl TryFrom<RespArray> for Quit {
+    type Error = CommandError;
+
+    // like Redis, arguments are ignored
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["quit"], 0)?;
+        Ok(Quit)
+    }
+}
+
+//reset :"*1\r\n$5\r\nreset\r\n"
+impl TryFrom<RespArray> for Reset {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["reset"], 0)?;
+        Ok(Reset)
+    }
+}
+
+//time :"*1\r\n$4\r\ntime\r\n"
+impl TryFrom<RespArray> for Time {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["time"], 0)?;
+        Ok(Time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::request;
+    use crate::{RespDecode, RespEncode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_hello_command_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::from(&b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n"[..]);
+        let cmd = Hello::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.protocol, Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_switches_protocol() -> Result<()> {
+        let backend = Backend::new();
+        assert!(matches!(
+            Hello { protocol: None }.execute(&backend),
+            RespFrame::Array(_)
+        ));
+        assert!(matches!(
+            Hello { protocol: Some(3) }.execute(&backend),
+            RespFrame::Map(_)
+        ));
+        assert!(backend.session().is_resp3());
+        assert!(matches!(
+            Hello { protocol: Some(4) }.execute(&backend),
+            RespFrame::Error(_)
+        ));
+        assert_eq!(backend.session().protocol(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ping() -> Result<()> {
+        let backend = Backend::new();
+        let ping = Ping::try_from(request(&["ping"]))?;
+        assert_eq!(ping.execute(&backend), SimpleString::new("PONG").into());
+        let echo = Ping::try_from(request(&["PING", "hello"]))?;
+        assert_eq!(echo.execute(&backend), BulkString::new("hello").into());
+        assert!(Ping::try_from(request(&["ping", "a", "b"])).is_err());
+        let empty = Ping::try_from(request(&["ping", ""]))?;
+        assert_eq!(empty.execute(&backend).encode(), b"$0\r\n\r\n");
+
+        // with subscriptions a RESP2 connection gets an array, like a message
+        let _pushes = backend.open_pushes();
+        backend.subscribe(crate::SubscriptionKind::Channel, &["news".to_string()]);
+        assert_eq!(
+            ping.execute(&backend),
+            RespArray::new(vec![
+                BulkString::new("pong").into(),
+                BulkString::new("").into()
+            ])
+            .into()
+        );
+        backend.session().set_protocol(3);
+        assert_eq!(echo.execute(&backend), BulkString::new("hello").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset() -> Result<()> {
+        let server = Backend::new();
+        let backend = server.connect();
+        let _pushes = backend.open_pushes();
+        backend.select(3)?;
+        backend.session().set_protocol(3);
+        backend.subscribe(crate::SubscriptionKind::Pattern, &["n*".to_string()]);
+        backend.watch(&["k".to_string()]);
+        backend.multi();
+        backend.enable_tracking(Default::default())?;
+
+        let reset = Reset::try_from(request(&["reset"]))?;
+        assert_eq!(reset.execute(&backend), SimpleString::new("RESET").into());
+        assert_eq!(backend.session().db(), 0);
+        assert_eq!(backend.session().protocol(), 2);
+        assert_eq!(backend.subscriptions(), 0);
+        assert_eq!(server.pubsub_numpat(), 0);
+        assert!(!backend.in_multi());
+        assert_eq!(backend.tracking_redirect(), -1);
+        // the connection keeps receiving pushes
+        backend.subscribe(crate::SubscriptionKind::Channel, &["news".to_string()]);
+        assert_eq!(backend.subscriptions(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time() -> Result<()> {
+        let backend = Backend::new();
+        let RespFrame::Array(time) = Time::try_from(request(&["time"]))?.execute(&backend) else {
+            panic!("time replies with an array");
+        };
+        let parts = time
+            .0
+            .into_iter()
+            .map(|part| match part {
+                RespFrame::BulkString(s) => String::from_utf8_lossy(&s.0).parse::<u64>().unwrap(),
+                _ => panic!("time replies with bulk strings"),
+            })
+            .collect::<Vec<_>>();
+        assert!(parts[0] > 1_600_000_000);
+        assert!(parts[1] < 1_000_000);
+        assert!(Time::try_from(request(&["time", "now"])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    extract_args, extract_int, extract_strings, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BackendError, CommandError, CommandExecutor,
+    RespArray, RespFrame,
+};
+
+use super::REST_OK;
+
+#[derive(Debug)]
+pub struct DbSize;
+
+#[derive(Debug)]
+pub struct FlushDb {
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct FlushAll {
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct Select {
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct SwapDb {
+    a: i64,
+    b: i64,
+}
+
+impl CommandExecutor for Select {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let result = usize::try_from(self.index)
+            .map_err(|_| BackendError::DbIndexOutOfRange)
+            .and_then(|index| backend.select(index));
+        match result {
+            Ok(()) => REST_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SwapDb {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let result = match (usize::try_from(self.a), usize::try_from(self.b)) {
+            (Ok(a), Ok(b)) => backend.swapdb(a, b),
+            _ => Err(BackendError::DbIndexOutOfRange),
+        };
+        match result {
+            Ok(()) => REST_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for DbSize {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.db().dbsize())
+    }
+}
+
+impl CommandExecutor for FlushDb {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        backend.flushdb(self.lazy);
+        REST_OK.clone()
+    }
+}
+
+impl CommandExecutor for FlushAll {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        backend.flushall(self.lazy);
+        REST_OK.clone()
+    }
+}
+
+//select :"*2\r\n$6\r\nselect\r\n$1\r\n1\r\n"
+impl TryFrom<RespArray> for Select {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["select"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        match args.next() {
+            Some(index) => Ok(Select {
+                index: extract_int(index)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid index".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SwapDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["swapdb"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(a), Some(b)) => Ok(SwapDb {
+                a: extract_int(a)?,
+                b: extract_int(b)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid index".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for DbSize {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["dbsize"], 0)?;
+        Ok(DbSize)
+    }
+}
+
+//flushdb :"*2\r\n$7\r\nflushdb\r\n$5\r\nasync\r\n"
+impl TryFrom<RespArray> for FlushDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["flushdb"], 0)?;
+        Ok(FlushDb {
+            lazy: parse_flush_mode(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FlushAll {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["flushall"], 0)?;
+        Ok(FlushAll {
+            lazy: parse_flush_mode(value)?,
+        })
+    }
+}
+
+// `[ASYNC|SYNC]`, returns whether the flush should free the old data lazily
+fn parse_flush_mode(value: RespArray) -> Result<bool, CommandError> {
+    let args = extract_strings(value)?;
+    match args.as_slice() {
+        [] => Ok(false),
+        [mode] if mode.eq_ignore_ascii_case("async") => Ok(true),
+        [mode] if mode.eq_ignore_ascii_case("sync") => Ok(false),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecode, BulkString};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_flushdb_command_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$7\r\nflushdb\r\n$5\r\nASYNC\r\n");
+        let frames = RespArray::decode(&mut buf)?;
+        assert!(FlushDb::try_from(frames)?.lazy);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$8\r\nflushall\r\n");
+        let frames = RespArray::decode(&mut buf)?;
+        assert!(!FlushAll::try_from(frames)?.lazy);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$7\r\nflushdb\r\n$4\r\nlazy\r\n");
+        let frames = RespArray::decode(&mut buf)?;
+        assert!(FlushDb::try_from(frames).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_swapdb_execute() -> Result<()> {
+        let backend = Backend::new();
+        let other = backend.connect();
+        backend
+            .db()
+            .set("key".to_string(), BulkString::new("db0").into());
+
+        assert_eq!(Select { index: 5 }.execute(&backend), REST_OK.clone());
+        assert_eq!(backend.db().get("key")?, None);
+        backend
+            .db()
+            .set("key".to_string(), BulkString::new("db5").into());
+        // the selected database is per connection
+        assert_eq!(other.db().get("key")?, Some(BulkString::new("db0").into()));
+
+        assert_eq!(SwapDb { a: 0, b: 5 }.execute(&backend), REST_OK.clone());
+        assert_eq!(other.db().get("key")?, Some(BulkString::new("db5").into()));
+        assert_eq!(
+            backend.db().get("key")?,
+            Some(BulkString::new("db0").into())
+        );
+
+        assert_eq!(
+            Select { index: 16 }.execute(&backend),
+            BackendError::DbIndexOutOfRange.into()
+        );
+        assert_eq!(
+            SwapDb { a: -1, b: 0 }.execute(&backend),
+            BackendError::DbIndexOutOfRange.into()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dbsize_flush_execute() -> Result<()> {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.db().sadd(
+                format!("set:{}", i),
+                (0..100).map(|m| m.to_string()).collect(),
+            )?;
+        }
+        assert_eq!(DbSize.execute(&backend), RespFrame::Integer(100));
+
+        let db = backend.db();
+        assert_eq!(FlushDb { lazy: true }.execute(&backend), REST_OK.clone());
+        assert_eq!(DbSize.execute(&backend), RespFrame::Integer(0));
+        // handles taken before the flush still see the old data
+        assert_eq!(db.dbsize(), 100);
+
+        backend
+            .db()
+            .set("key".to_string(), BulkString::new("value").into());
+        backend
+            .db_at(3)?
+            .set("key".to_string(), BulkString::new("value").into());
+        FlushDb { lazy: false }.execute(&backend);
+        assert_eq!(backend.db_at(3)?.dbsize(), 1);
+        assert_eq!(FlushAll { lazy: false }.execute(&backend), REST_OK.clone());
+        assert_eq!(backend.db_at(3)?.dbsize(), 0);
+        assert_eq!(DbSize.execute(&backend), RespFrame::Integer(0));
+
+        // a key whose ttl has passed is not counted, even before anything purged it
+        backend
+            .db()
+            .set("key".to_string(), BulkString::new("value").into());
+        backend.db().expire_at("key", Some(1));
+        assert_eq!(DbSize.execute(&backend), RespFrame::Integer(0));
+        Ok(())
+    }
+}
@@ -5,7 +5,8 @@ use crate::{
 
 #[derive(Debug)]
 pub struct Echo {
-    value: String,
+    // the message as given, which need not be valid UTF-8
+    value: Vec<u8>,
 }
 
 impl TryFrom<RespArray> for Echo {
@@ -15,8 +16,8 @@ impl TryFrom<RespArray> for Echo {
         validate_command_exact_length(&value, &["echo"], 1)?;
         let args = extract_args(value)?;
         match &args[0] {
-            RespFrame::BulkString(key) => Ok(Echo {
-                value: String::from_utf8_lossy(key).to_string(),
+            RespFrame::BulkString(message) => Ok(Echo {
+                value: message.0.clone(),
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
@@ -25,14 +26,14 @@ impl TryFrom<RespArray> for Echo {
 
 impl CommandExecutor for Echo {
     fn execute(&self, _backend: &Backend) -> RespFrame {
-        BulkString::new(self.value.to_owned()).into()
+        BulkString::new(self.value.clone()).into()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{backend, resp::RespDecode};
+    use crate::{backend, resp::RespDecode, RespEncode};
 
     #[test]
     fn test_echo() {
@@ -40,9 +41,30 @@ mod tests {
         let input = "$5\r\nhello\r\n".as_bytes();
         let frame = RespFrame::decode(&mut input.into()).unwrap();
         let cmd = Echo {
-            value: "hello".to_string(),
+            value: b"hello".to_vec(),
         };
         let resp = cmd.execute(&backend);
         assert_eq!(frame, resp);
     }
+
+    #[test]
+    fn test_echo_edge_cases() {
+        let backend = backend::Backend::new();
+        // binary and empty messages come back unchanged
+        let request = |message: &[u8]| {
+            RespArray::new(vec![
+                BulkString::new("echo").into(),
+                BulkString::new(message.to_vec()).into(),
+            ])
+        };
+        let cmd = Echo::try_from(request(b"\xff\x00")).unwrap();
+        assert_eq!(
+            cmd.execute(&backend),
+            BulkString::new(b"\xff\x00".to_vec()).into()
+        );
+        // an empty message is an empty bulk string, not a null one
+        let cmd = Echo::try_from(request(b"")).unwrap();
+        assert_eq!(cmd.execute(&backend).encode(), b"$0\r\n\r\n");
+        assert!(Echo::try_from(RespArray::new(vec![BulkString::new("echo").into()])).is_err());
+    }
 }
@@ -0,0 +1,856 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bytes::BytesMut;
+use mlua::{Function as LuaFunction, HookTriggers, Lua, MultiValue, Table, Value};
+
+use crate::{
+    command_name, extract_args, extract_int, extract_string, glob_match,
+    validate_command_minimum_length, Backend, BulkString, CommandError, CommandExecutor, Library,
+    LibraryFunction, LibraryPolicy, RespArray, RespDecode, RespEncode, RespFrame, RespMap, RespSet,
+    SimpleError,
+};
+
+use super::{
+    script::{
+        install_redis_lib, keys_and_args, run_script, script_error, string_sequence, CallError,
+        KILL_CHECK_INSTRUCTIONS,
+    },
+    REST_OK,
+};
+
+// the flags a function may be registered with
+const FUNCTION_FLAGS: [&str; 5] = [
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+// the first element of a FUNCTION DUMP payload, the library codes follow
+const DUMP_MARKER: &str = "FUNCTIONS 1";
+
+// how long the top level code of a library may run. It holds the functions interpreter and
+// cannot be killed, so like redis it is stopped once this has passed.
+const LOAD_TIMEOUT: Duration = Duration::from_millis(500);
+
+// FCALL and FCALL_RO
+#[derive(Debug)]
+pub struct FCall {
+    name: String,
+    keys: Vec<Vec<u8>>,
+    args: Vec<Vec<u8>>,
+    read_only: bool,
+}
+
+#[derive(Debug)]
+pub struct Function {
+    op: FunctionOp,
+}
+
+#[derive(Debug, PartialEq)]
+enum FunctionOp {
+    Load {
+        code: String,
+        replace: bool,
+    },
+    List {
+        pattern: Option<String>,
+        with_code: bool,
+    },
+    Delete(String),
+    Flush,
+    Dump,
+    Restore {
+        payload: Vec<u8>,
+        policy: LibraryPolicy,
+    },
+    Kill,
+}
+
+impl CommandExecutor for FCall {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let lua = backend.function_lua();
+        let (callback, read_only) = {
+            let libraries = backend.libraries();
+            let Some(function) = libraries
+                .values()
+                .find_map(|library| library.function(&self.name))
+            else {
+                return SimpleError::new("ERR Function not found".to_string()).into();
+            };
+            let no_writes = function.has_flag("no-writes");
+            if self.read_only && !no_writes {
+                return SimpleError::new(
+                    "ERR Can not execute a script with write flag using *_ro command.".to_string(),
+                )
+                .into();
+            }
+            match lua.registry_value::<LuaFunction>(&function.callback) {
+                Ok(callback) => (callback, self.read_only || no_writes),
+                Err(e) => return script_error(&e, &self.name),
+            }
+        };
+        let result = run_script(&lua, backend, read_only, || {
+            let keys = string_sequence(&lua, &self.keys)?;
+            let args = string_sequence(&lua, &self.args)?;
+            callback.call::<_, Value>((keys, args))
+        });
+        match result {
+            Ok(frame) => frame,
+            Err(e) => script_error(&e, &self.name),
+        }
+    }
+}
+
+impl CommandExecutor for Function {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match &self.op {
+            FunctionOp::Load { code, replace } => {
+                let policy = match replace {
+                    true => LibraryPolicy::Replace,
+                    false => LibraryPolicy::Append,
+                };
+                let lua = backend.function_lua();
+                let library = match load_library(&lua, code) {
+                    Ok(library) => library,
+                    Err(e) => return e,
+                };
+                let name = library.name.clone();
+                match backend.add_libraries(vec![library], policy) {
+                    Ok(()) => BulkString::new(name).into(),
+                    Err(e) => e.into(),
+                }
+            }
+            FunctionOp::List { pattern, with_code } => {
+                let libraries = backend.libraries();
+                let frames = libraries
+                    .values()
+                    .filter(|library| match pattern {
+                        Some(pattern) => {
+                            glob_match(pattern.as_bytes(), library.name.as_bytes(), true)
+                        }
+                        None => true,
+                    })
+                    .map(|library| library_info(backend, library, *with_code))
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            FunctionOp::Delete(name) => match backend.delete_library(name) {
+                Ok(()) => REST_OK.clone(),
+                Err(e) => e.into(),
+            },
+            FunctionOp::Flush => {
+                backend.flush_functions();
+                REST_OK.clone()
+            }
+            FunctionOp::Dump => {
+                let mut frames = vec![BulkString::new(DUMP_MARKER).into()];
+                frames.extend(
+                    backend
+                        .libraries()
+                        .values()
+                        .map(|library| BulkString::new(library.code.clone()).into()),
+                );
+                BulkString::new(RespArray::new(frames).encode()).into()
+            }
+            FunctionOp::Restore { payload, policy } => {
+                let Some(codes) = dumped_codes(payload) else {
+                    return SimpleError::new(
+                        "ERR payload version or checksum are wrong".to_string(),
+                    )
+                    .into();
+                };
+                let lua = backend.function_lua();
+                let libraries = match codes
+                    .iter()
+                    .map(|code| load_library(&lua, code))
+                    .collect::<Result<Vec<_>, _>>()
+                {
+                    Ok(libraries) => libraries,
+                    Err(e) => return e,
+                };
+                match backend.add_libraries(libraries, *policy) {
+                    Ok(()) => REST_OK.clone(),
+                    Err(e) => e.into(),
+                }
+            }
+            FunctionOp::Kill => match backend.kill_script() {
+                Ok(()) => REST_OK.clone(),
+                Err(e) => e.into(),
+            },
+        }
+    }
+}
+
+impl Function {
+    // FUNCTION KILL has to get through while a function holds the server
+    pub fn kills(&self) -> bool {
+        self.op == FunctionOp::Kill
+    }
+}
+
+// run the code of a library, which registers its functions with redis.register_function.
+// The code sees a reduced `redis` table while it loads, and cannot create globals.
+fn load_library(lua: &Lua, code: &str) -> Result<Library, RespFrame> {
+    let error = |message: &str| -> RespFrame { SimpleError::new(message.to_string()).into() };
+    let (shebang, body) = code.split_once('\n').unwrap_or((code, ""));
+    let Some(metadata) = shebang.strip_prefix("#!") else {
+        return Err(error("ERR Missing library metadata"));
+    };
+    let mut metadata = metadata.split_whitespace();
+    let engine = metadata.next().unwrap_or_default();
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(error(&format!("ERR Engine '{}' not found", engine)));
+    }
+    let mut name = None;
+    for field in metadata {
+        match field.strip_prefix("name=") {
+            Some(value) => name = Some(value.to_string()),
+            None => {
+                return Err(error(&format!(
+                    "ERR Invalid metadata value given: {}",
+                    field
+                )))
+            }
+        }
+    }
+    let Some(name) = name else {
+        return Err(error("ERR Library name was not given"));
+    };
+    if !valid_name(&name) {
+        return Err(error("ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long"));
+    }
+
+    let registered = Arc::new(Mutex::new(Vec::new()));
+    let load = || -> mlua::Result<()> {
+        install_redis_lib(lua)?;
+        let redis: Table = lua.globals().raw_get("redis")?;
+        let loader = lua.create_table()?;
+        for field in [
+            "log",
+            "LOG_DEBUG",
+            "LOG_VERBOSE",
+            "LOG_NOTICE",
+            "LOG_WARNING",
+        ] {
+            loader.raw_set(field, redis.raw_get::<_, Value>(field)?)?;
+        }
+        let functions = registered.clone();
+        loader.raw_set(
+            "register_function",
+            lua.create_function(move |lua, args: MultiValue| {
+                let function = register_function(lua, args)?;
+                let mut functions = functions.lock().unwrap();
+                if functions
+                    .iter()
+                    .any(|f: &LibraryFunction| f.name == function.name)
+                {
+                    return Err(registration_error("Function already exists in the library"));
+                }
+                functions.push(function);
+                Ok(())
+            })?,
+        )?;
+        let env = lua.create_table()?;
+        env.raw_set("redis", loader)?;
+        let meta = lua.create_table()?;
+        meta.raw_set("__index", lua.globals())?;
+        meta.raw_set(
+            "__newindex",
+            lua.load(
+                r#"return function(_, name)
+                    error("Attempt to modify a readonly table: " .. tostring(name), 2)
+                end"#,
+            )
+            .eval::<LuaFunction>()?,
+        )?;
+        env.set_metatable(Some(meta));
+        let deadline = Instant::now() + LOAD_TIMEOUT;
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS),
+            move |_, _| match Instant::now() >= deadline {
+                true => Err(mlua::Error::external(CallError(
+                    "ERR FUNCTION LOAD timeout".to_string(),
+                ))),
+                false => Ok(()),
+            },
+        );
+        // the first line keeps its place, so that errors name the right lines
+        let loaded = lua
+            .load(format!("\n{}", body))
+            .set_name(format!("@user_function_{}", name))
+            .set_environment(env.clone())
+            .exec();
+        lua.remove_hook();
+        loaded?;
+        // the functions see the whole library when they are called
+        env.raw_set("redis", Value::Nil)
+    };
+    if let Err(e) = load() {
+        return Err(load_error(&e));
+    }
+    let functions = std::mem::take(&mut *registered.lock().unwrap());
+    if functions.is_empty() {
+        return Err(error("ERR No functions registered"));
+    }
+    Ok(Library {
+        name,
+        code: code.to_string(),
+        functions,
+    })
+}
+
+// the arguments of redis.register_function: a name and a callback, or a table that may also
+// give flags and a description
+fn register_function(lua: &Lua, args: MultiValue) -> mlua::Result<LibraryFunction> {
+    let mut args = args.into_iter();
+    let (name, callback, flags, description) = match (args.next(), args.next(), args.next()) {
+        (Some(Value::String(name)), Some(Value::Function(callback)), None) => {
+            (Value::String(name), callback, Value::Nil, Value::Nil)
+        }
+        (Some(Value::Table(table)), None, None) => {
+            for pair in table.clone().pairs::<Value, Value>() {
+                let (field, _) = pair?;
+                let known = match &field {
+                    Value::String(field) => matches!(
+                        field.to_str()?,
+                        "function_name" | "callback" | "flags" | "description"
+                    ),
+                    _ => false,
+                };
+                if !known {
+                    return Err(registration_error(
+                        "unknown argument given to redis.register_function",
+                    ));
+                }
+            }
+            let Value::Function(callback) = table.raw_get("callback")? else {
+                return Err(registration_error(
+                    "callback argument given to redis.register_function must be a function",
+                ));
+            };
+            (
+                table.raw_get("function_name")?,
+                callback,
+                table.raw_get("flags")?,
+                table.raw_get("description")?,
+            )
+        }
+        _ => {
+            return Err(registration_error(
+                "wrong number of arguments to redis.register_function",
+            ))
+        }
+    };
+    let name = match name {
+        Value::String(name) => name.to_str()?.to_string(),
+        _ => {
+            return Err(registration_error(
+                "function_name argument given to redis.register_function must be a string",
+            ))
+        }
+    };
+    if !valid_name(&name) {
+        return Err(registration_error("Function names can only contain letters, numbers, or underscores(_) and must be at least one character long"));
+    }
+    let flags = match flags {
+        Value::Nil => vec![],
+        Value::Table(flags) => flags
+            .sequence_values::<mlua::String>()
+            .map(|flag| {
+                let flag = flag?.to_str()?.to_string();
+                match FUNCTION_FLAGS.contains(&flag.as_str()) {
+                    true => Ok(flag),
+                    false => Err(registration_error("unknown flag given")),
+                }
+            })
+            .collect::<mlua::Result<Vec<_>>>()?,
+        _ => return Err(registration_error(
+            "flags argument to redis.register_function must be a table representing function flags",
+        )),
+    };
+    let description = match description {
+        Value::Nil => None,
+        Value::String(description) => Some(description.to_str()?.to_string()),
+        _ => {
+            return Err(registration_error(
+                "description argument given to redis.register_function must be a string",
+            ))
+        }
+    };
+    Ok(LibraryFunction {
+        name,
+        description,
+        flags,
+        callback: lua.create_registry_value(callback)?,
+    })
+}
+
+// errors raised by redis.register_function reach the client as they are
+fn load_error(e: &mlua::Error) -> RespFrame {
+    let first_line = |message: &str| message.lines().next().unwrap_or_default().to_string();
+    let message = match e {
+        mlua::Error::CallbackError { cause, .. } => return load_error(cause),
+        mlua::Error::ExternalError(external) => match external.downcast_ref::<CallError>() {
+            Some(CallError(message)) => message.clone(),
+            None => format!(
+                "ERR Error registering functions: {}",
+                first_line(&external.to_string())
+            ),
+        },
+        mlua::Error::SyntaxError { message, .. } => {
+            format!("ERR Error compiling function: {}", first_line(message))
+        }
+        mlua::Error::RuntimeError(message) => {
+            format!("ERR Error registering functions: {}", first_line(message))
+        }
+        e => format!(
+            "ERR Error registering functions: {}",
+            first_line(&e.to_string())
+        ),
+    };
+    SimpleError::new(message).into()
+}
+
+fn registration_error(message: &str) -> mlua::Error {
+    mlua::Error::external(CallError(format!("ERR {}", message)))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// the library codes in a FUNCTION DUMP payload
+fn dumped_codes(payload: &[u8]) -> Option<Vec<String>> {
+    let mut buf = BytesMut::from(payload);
+    let frames = RespArray::decode(&mut buf).ok()?;
+    if !buf.is_empty() {
+        return None;
+    }
+    let mut codes = frames.0.into_iter().map(|frame| match frame {
+        RespFrame::BulkString(code) => String::from_utf8(code.0).ok(),
+        _ => None,
+    });
+    match codes.next() {
+        Some(Some(marker)) if marker == DUMP_MARKER => codes.collect(),
+        _ => None,
+    }
+}
+
+// the description of a library in FUNCTION LIST
+fn library_info(backend: &Backend, library: &Library, with_code: bool) -> RespFrame {
+    let functions = library
+        .functions
+        .iter()
+        .map(|function| {
+            let flags = function
+                .flags
+                .iter()
+                .map(|flag| BulkString::new(flag.clone()).into())
+                .collect::<Vec<RespFrame>>();
+            let flags = match backend.session().is_resp3() {
+                true => RespSet::new(flags).into(),
+                false => RespArray::new(flags).into(),
+            };
+            let description = match &function.description {
+                Some(description) => BulkString::new(description.clone()).into(),
+                None => super::REST_NIL.clone(),
+            };
+            info_reply(
+                backend,
+                vec![
+                    ("name", BulkString::new(function.name.clone()).into()),
+                    ("description", description),
+                    ("flags", flags),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut fields = vec![
+        ("library_name", BulkString::new(library.name.clone()).into()),
+        ("engine", BulkString::new("LUA").into()),
+        ("functions", RespArray::new(functions).into()),
+    ];
+    if with_code {
+        fields.push(("library_code", BulkString::new(library.code.clone()).into()));
+    }
+    info_reply(backend, fields)
+}
+
+// a map in RESP3, otherwise a flat array of names and values
+fn info_reply(backend: &Backend, fields: Vec<(&str, RespFrame)>) -> RespFrame {
+    if backend.session().is_resp3() {
+        let mut map = RespMap::new();
+        for (name, value) in fields {
+            map.insert(name.to_string(), value);
+        }
+        map.into()
+    } else {
+        let mut frames = Vec::with_capacity(fields.len() * 2);
+        for (name, value) in fields {
+            frames.push(BulkString::new(name).into());
+            frames.push(value);
+        }
+        RespArray::new(frames).into()
+    }
+}
+
+//fcall :"*3\r\n$5\r\nfcall\r\n$5\r\nhello\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for FCall {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["fcall", "fcall_ro"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        let read_only = name == "fcall_ro";
+        let mut args = extract_args(value)?.into_iter();
+        let function = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let numkeys = extract_int(args.next().ok_or_else(syntax_error)?)?;
+        let (keys, args) = keys_and_args(args, numkeys)?;
+        Ok(FCall {
+            name: function,
+            keys,
+            args,
+            read_only,
+        })
+    }
+}
+
+//function :"*3\r\n$8\r\nfunction\r\n$6\r\ndelete\r\n$3\r\nlib\r\n"
+impl TryFrom<RespArray> for Function {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["function"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let sub = extract_string(args.next().ok_or_else(syntax_error)?)?.to_ascii_lowercase();
+        let args = args.collect::<Vec<_>>();
+        let wrong_arity = || {
+            CommandError::InvalidArgument(format!(
+                "wrong number of arguments for 'function|{}' command",
+                sub
+            ))
+        };
+        let op = match sub.as_str() {
+            "load" => {
+                let mut args = args
+                    .into_iter()
+                    .map(extract_string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let code = args.pop().ok_or_else(wrong_arity)?;
+                let replace = match args.as_slice() {
+                    [] => false,
+                    [option] if option.eq_ignore_ascii_case("replace") => true,
+                    _ => return Err(syntax_error()),
+                };
+                FunctionOp::Load { code, replace }
+            }
+            "list" => {
+                let mut args = args.into_iter();
+                let (mut pattern, mut with_code) = (None, false);
+                while let Some(arg) = args.next() {
+                    match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                        "withcode" => with_code = true,
+                        "libraryname" => {
+                            pattern = Some(extract_string(args.next().ok_or_else(syntax_error)?)?)
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                }
+                FunctionOp::List { pattern, with_code }
+            }
+            "delete" => match args.as_slice() {
+                [name] => FunctionOp::Delete(extract_string(name.clone())?),
+                _ => return Err(wrong_arity()),
+            },
+            // the flush is synchronous either way
+            "flush" => match args.as_slice() {
+                [] => FunctionOp::Flush,
+                [mode] => match extract_string(mode.clone())?.to_ascii_lowercase().as_str() {
+                    "sync" | "async" => FunctionOp::Flush,
+                    _ => return Err(syntax_error()),
+                },
+                _ => return Err(wrong_arity()),
+            },
+            "dump" if args.is_empty() => FunctionOp::Dump,
+            "restore" if matches!(args.len(), 1 | 2) => {
+                let mut args = args.into_iter();
+                let payload = match args.next() {
+                    Some(RespFrame::BulkString(payload)) => payload.0,
+                    _ => return Err(syntax_error()),
+                };
+                let policy = match args.next() {
+                    None => LibraryPolicy::Append,
+                    Some(policy) => match extract_string(policy)?.to_ascii_lowercase().as_str() {
+                        "append" => LibraryPolicy::Append,
+                        "replace" => LibraryPolicy::Replace,
+                        "flush" => LibraryPolicy::Flush,
+                        _ => return Err(syntax_error()),
+                    },
+                };
+                FunctionOp::Restore { payload, policy }
+            }
+            "kill" if args.is_empty() => FunctionOp::Kill,
+            "dump" | "restore" | "kill" => return Err(wrong_arity()),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try FUNCTION HELP.",
+                    sub
+                )))
+            }
+        };
+        Ok(Function { op })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::REST_NIL;
+    use crate::test_util::request;
+
+    const LIBRARY: &str = "#!lua name=mylib\n\
+        local function set(keys, args) return redis.call('set', keys[1], args[1]) end\n\
+        redis.register_function('myset', set)\n\
+        redis.register_function{function_name = 'myget', callback = function(keys) return redis.call('get', keys[1]) end, flags = {'no-writes'}, description = 'reads a key'}";
+
+    fn function(backend: &Backend, args: &[&str]) -> RespFrame {
+        match Function::try_from(request(args)) {
+            Ok(cmd) => cmd.execute(backend),
+            Err(e) => e.into(),
+        }
+    }
+
+    fn fcall(backend: &Backend, args: &[&str]) -> RespFrame {
+        match FCall::try_from(request(args)) {
+            Ok(cmd) => cmd.execute(backend),
+            Err(e) => e.into(),
+        }
+    }
+
+    fn error(message: &str) -> RespFrame {
+        SimpleError::new(message.to_string()).into()
+    }
+
+    #[test]
+    fn test_function_commands_tryfrom() {
+        let cmd = Function::try_from(request(&["function", "load", "REPLACE", "code"])).unwrap();
+        assert_eq!(
+            cmd.op,
+            FunctionOp::Load {
+                code: "code".to_string(),
+                replace: true
+            }
+        );
+        let cmd = Function::try_from(request(&[
+            "function",
+            "list",
+            "withcode",
+            "libraryname",
+            "my*",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cmd.op,
+            FunctionOp::List {
+                pattern: Some("my*".to_string()),
+                with_code: true
+            }
+        );
+        let cmd = Function::try_from(request(&["function", "restore", "x", "flush"])).unwrap();
+        assert_eq!(
+            cmd.op,
+            FunctionOp::Restore {
+                payload: b"x".to_vec(),
+                policy: LibraryPolicy::Flush
+            }
+        );
+        assert!(Function::try_from(request(&["function", "load", "a", "b"])).is_err());
+        assert!(Function::try_from(request(&["function", "delete"])).is_err());
+        assert!(Function::try_from(request(&["function", "nope"])).is_err());
+
+        let cmd = FCall::try_from(request(&["fcall_ro", "f", "1", "k", "a"])).unwrap();
+        assert_eq!(cmd.name, "f");
+        assert_eq!(cmd.keys, vec![b"k".to_vec()]);
+        assert_eq!(cmd.args, vec![b"a".to_vec()]);
+        assert!(cmd.read_only);
+        assert!(FCall::try_from(request(&["fcall", "f", "2", "k"])).is_err());
+    }
+
+    #[test]
+    fn test_function_load_and_fcall() {
+        let backend = Backend::new();
+        assert_eq!(
+            function(&backend, &["function", "load", LIBRARY]),
+            BulkString::new("mylib").into()
+        );
+        assert_eq!(
+            function(&backend, &["function", "load", LIBRARY]),
+            error("ERR Library 'mylib' already exists")
+        );
+        assert_eq!(
+            function(&backend, &["function", "load", "replace", LIBRARY]),
+            BulkString::new("mylib").into()
+        );
+        assert_eq!(
+            function(
+                &backend,
+                &[
+                    "function",
+                    "load",
+                    "#!lua name=other\nredis.register_function('myget', function() end)"
+                ]
+            ),
+            error("ERR Function myget already exists")
+        );
+
+        assert_eq!(
+            fcall(&backend, &["fcall", "myset", "1", "k", "v"]),
+            REST_OK.clone()
+        );
+        assert_eq!(
+            fcall(&backend, &["fcall_ro", "myget", "1", "k"]),
+            BulkString::new("v").into()
+        );
+        assert_eq!(
+            fcall(&backend, &["fcall_ro", "myset", "1", "k", "v"]),
+            error("ERR Can not execute a script with write flag using *_ro command.")
+        );
+        assert_eq!(
+            fcall(&backend, &["fcall", "nope", "0"]),
+            error("ERR Function not found")
+        );
+
+        assert_eq!(
+            function(&backend, &["function", "delete", "mylib"]),
+            REST_OK.clone()
+        );
+        assert_eq!(
+            function(&backend, &["function", "delete", "mylib"]),
+            error("ERR Library not found")
+        );
+        assert_eq!(
+            fcall(&backend, &["fcall", "myget", "1", "k"]),
+            error("ERR Function not found")
+        );
+    }
+
+    #[test]
+    fn test_function_load_errors() {
+        let backend = Backend::new();
+        let load = |code: &str| function(&backend, &["function", "load", code]);
+        assert_eq!(load("return 1"), error("ERR Missing library metadata"));
+        assert_eq!(load("#!js name=lib\n"), error("ERR Engine 'js' not found"));
+        assert_eq!(
+            load("#!lua name=lib\nreturn 1"),
+            error("ERR No functions registered")
+        );
+        assert_eq!(
+            load("#!lua name=lib\nredis.register_function('f', function() end)\nredis.register_function('f', function() end)"),
+            error("ERR Function already exists in the library")
+        );
+        assert_eq!(
+            load("#!lua name=lib\nredis.register_function{function_name = 'f', callback = function() end, flags = {'bad'}}"),
+            error("ERR unknown flag given")
+        );
+        assert!(matches!(
+            load("#!lua name=lib\nredis.register_function('f', function() end"),
+            RespFrame::Error(e) if e.0.starts_with("ERR Error compiling function")
+        ));
+        // neither globals nor commands are available while loading
+        assert!(matches!(
+            load("#!lua name=lib\nx = 1\nredis.register_function('f', function() end)"),
+            RespFrame::Error(e) if e.0.starts_with("ERR Error registering functions")
+        ));
+        assert!(matches!(
+            load("#!lua name=lib\nredis.call('set', 'a', 'b')"),
+            RespFrame::Error(_)
+        ));
+        // endless top level code is stopped, and the interpreter can load libraries again
+        assert_eq!(
+            load("#!lua name=lib\nwhile true do end"),
+            error("ERR FUNCTION LOAD timeout")
+        );
+        assert!(backend.libraries().is_empty());
+        assert_eq!(load(LIBRARY), BulkString::new("mylib").into());
+    }
+
+    #[test]
+    fn test_function_list_dump_restore() {
+        let backend = Backend::new();
+        function(&backend, &["function", "load", LIBRARY]);
+        let functions = RespArray::new(vec![
+            RespArray::new(vec![
+                BulkString::new("name").into(),
+                BulkString::new("myset").into(),
+                BulkString::new("description").into(),
+                REST_NIL.clone(),
+                BulkString::new("flags").into(),
+                RespArray::new(vec![]).into(),
+            ])
+            .into(),
+            RespArray::new(vec![
+                BulkString::new("name").into(),
+                BulkString::new("myget").into(),
+                BulkString::new("description").into(),
+                BulkString::new("reads a key").into(),
+                BulkString::new("flags").into(),
+                RespArray::new(vec![BulkString::new("no-writes").into()]).into(),
+            ])
+            .into(),
+        ]);
+        assert_eq!(
+            function(&backend, &["function", "list", "withcode"]),
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::new("library_name").into(),
+                BulkString::new("mylib").into(),
+                BulkString::new("engine").into(),
+                BulkString::new("LUA").into(),
+                BulkString::new("functions").into(),
+                functions.into(),
+                BulkString::new("library_code").into(),
+                BulkString::new(LIBRARY).into(),
+            ])
+            .into()])
+            .into()
+        );
+        assert_eq!(
+            function(&backend, &["function", "list", "libraryname", "other*"]),
+            RespArray::new(vec![]).into()
+        );
+
+        let RespFrame::BulkString(payload) = function(&backend, &["function", "dump"]) else {
+            panic!("function dump replies with the payload");
+        };
+        let payload = String::from_utf8(payload.0).unwrap();
+        assert_eq!(
+            function(&backend, &["function", "restore", &payload]),
+            error("ERR Library 'mylib' already exists")
+        );
+        assert_eq!(function(&backend, &["function", "flush"]), REST_OK.clone());
+        assert_eq!(
+            function(&backend, &["function", "restore", &payload]),
+            REST_OK.clone()
+        );
+        assert_eq!(
+            fcall(&backend, &["fcall", "myset", "1", "k", "v"]),
+            REST_OK.clone()
+        );
+        assert_eq!(
+            function(&backend, &["function", "restore", &payload, "replace"]),
+            REST_OK.clone()
+        );
+        assert_eq!(
+            function(&backend, &["function", "restore", "garbage"]),
+            error("ERR payload version or checksum are wrong")
+        );
+    }
+}
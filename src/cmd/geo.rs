@@ -0,0 +1,611 @@
+use std::{iter::Peekable, vec::IntoIter};
+
+use crate::{
+    extract_args, extract_int, extract_string, geohash_score, validate_command,
+    validate_command_minimum_length, Backend, BulkString, CommandError, CommandExecutor, GeoMatch,
+    GeoOrder, GeoOrigin, GeoQuery, GeoShape, RespArray, RespFrame, SetCondition, ZAddOptions,
+};
+
+use super::{REST_NIL, REST_NIL_ARRAY};
+
+#[derive(Debug)]
+pub struct GeoAdd {
+    key: String,
+    options: ZAddOptions,
+    pairs: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct GeoPos {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GeoDist {
+    key: String,
+    from: String,
+    to: String,
+    unit: f64,
+}
+
+#[derive(Debug)]
+pub struct GeoHash {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GeoSearch {
+    key: String,
+    args: GeoSearchArgs,
+}
+
+#[derive(Debug)]
+pub struct GeoSearchStore {
+    dst: String,
+    key: String,
+    args: GeoSearchArgs,
+}
+
+#[derive(Debug, PartialEq)]
+struct GeoSearchArgs {
+    query: GeoQuery,
+    withcoord: bool,
+    withdist: bool,
+    withhash: bool,
+    storedist: bool,
+}
+
+impl CommandExecutor for GeoAdd {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .zadd(self.key.clone(), self.options, self.pairs.clone())
+        {
+            Ok(count) => {
+                backend.signal_ready(&self.key);
+                RespFrame::Integer(count)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoPos {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().geopos(&self.key, &self.members) {
+            Ok(positions) => RespArray::new(
+                positions
+                    .into_iter()
+                    .map(|position| match position {
+                        Some((lon, lat)) => coord_frame(backend, lon, lat),
+                        None => REST_NIL_ARRAY.clone(),
+                    })
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoDist {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().geodist(&self.key, &self.from, &self.to) {
+            Ok(Some(dist)) => dist_frame(dist / self.unit),
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoHash {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().geohash(&self.key, &self.members) {
+            Ok(hashes) => RespArray::new(
+                hashes
+                    .into_iter()
+                    .map(|hash| match hash {
+                        Some(hash) => BulkString::new(hash).into(),
+                        None => REST_NIL.clone(),
+                    })
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoSearch {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().geosearch(&self.key, &self.args.query) {
+            Ok(matches) => search_reply(backend, matches, &self.args),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoSearchStore {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let args = &self.args;
+        match backend
+            .db()
+            .geosearchstore(self.dst.clone(), &self.key, &args.query, args.storedist)
+        {
+            Ok(len) => {
+                backend.signal_ready(&self.dst);
+                RespFrame::Integer(len)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// the members found, or with any of the WITH options an array per member holding the
+// distance, the geohash and the coordinates in that order
+fn search_reply(backend: &Backend, matches: Vec<GeoMatch>, args: &GeoSearchArgs) -> RespFrame {
+    let frames = matches
+        .into_iter()
+        .map(|m| {
+            let member: RespFrame = BulkString::new(m.member).into();
+            if !(args.withdist || args.withhash || args.withcoord) {
+                return member;
+            }
+            let mut frames = vec![member];
+            if args.withdist {
+                frames.push(dist_frame(m.dist));
+            }
+            if args.withhash {
+                frames.push(RespFrame::Integer(m.hash as i64));
+            }
+            if args.withcoord {
+                frames.push(coord_frame(backend, m.lon, m.lat));
+            }
+            RespArray::new(frames).into()
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(frames).into()
+}
+
+// distances are always bulk strings with four decimals
+fn dist_frame(dist: f64) -> RespFrame {
+    BulkString::new(format!("{:.4}", dist)).into()
+}
+
+fn coord_frame(backend: &Backend, lon: f64, lat: f64) -> RespFrame {
+    RespArray::new(vec![coord_value(backend, lon), coord_value(backend, lat)]).into()
+}
+
+// a double for RESP3 clients, otherwise a bulk string with 17 decimals and the trailing
+// zeros removed
+fn coord_value(backend: &Backend, value: f64) -> RespFrame {
+    if backend.session().is_resp3() {
+        return RespFrame::Double(value);
+    }
+    let formatted = format!("{:.17}", value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" => BulkString::new("0").into(),
+        formatted => BulkString::new(formatted).into(),
+    }
+}
+
+//geoadd :"*5\r\n$6\r\ngeoadd\r\n$6\r\nSicily\r\n$9\r\n13.361389\r\n$9\r\n38.115556\r\n$7\r\nPalermo\r\n"
+impl TryFrom<RespArray> for GeoAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["geoadd"], 4)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let key = next_key(&mut args)?;
+        let mut options = ZAddOptions::default();
+        let (mut nx, mut xx) = (false, false);
+        while let Some(RespFrame::BulkString(option)) = args.peek() {
+            match option.to_ascii_lowercase().as_slice() {
+                b"nx" => nx = true,
+                b"xx" => xx = true,
+                b"ch" => options.ch = true,
+                _ => break,
+            }
+            args.next();
+        }
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || !args.len().is_multiple_of(3) || (nx && xx) {
+            return Err(syntax_error());
+        }
+        options.condition = match (nx, xx) {
+            (true, _) => Some(SetCondition::Nx),
+            (_, true) => Some(SetCondition::Xx),
+            _ => None,
+        };
+        let mut pairs = Vec::with_capacity(args.len() / 3);
+        let mut args = args.into_iter();
+        while let (Some(lon), Some(lat), Some(member)) = (args.next(), args.next(), args.next()) {
+            let score = parse_lon_lat(lon, lat)?;
+            pairs.push((score as f64, extract_string(member)?));
+        }
+        Ok(GeoAdd {
+            key,
+            options,
+            pairs,
+        })
+    }
+}
+
+//geopos :"*3\r\n$6\r\ngeopos\r\n$6\r\nSicily\r\n$7\r\nPalermo\r\n"
+impl TryFrom<RespArray> for GeoPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["geopos"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let members = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        Ok(GeoPos { key, members })
+    }
+}
+
+//geodist :"*5\r\n$7\r\ngeodist\r\n$6\r\nSicily\r\n$7\r\nPalermo\r\n$7\r\nCatania\r\n$2\r\nkm\r\n"
+impl TryFrom<RespArray> for GeoDist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geodist"], 3, |v, e| v == e || v == e + 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(from), Some(to)) => Ok(GeoDist {
+                key: extract_string(key)?,
+                from: extract_string(from)?,
+                to: extract_string(to)?,
+                unit: args.next().map(parse_unit).transpose()?.unwrap_or(1.0),
+            }),
+            _ => Err(syntax_error()),
+        }
+    }
+}
+
+//geohash :"*3\r\n$7\r\ngeohash\r\n$6\r\nSicily\r\n$7\r\nPalermo\r\n"
+impl TryFrom<RespArray> for GeoHash {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["geohash"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let members = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        Ok(GeoHash { key, members })
+    }
+}
+
+//geosearch :"*8\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$10\r\nfromlonlat\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nbyradius\r\n$3\r\n200\r\n$2\r\nkm\r\n"
+impl TryFrom<RespArray> for GeoSearch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["geosearch"], 5)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let key = next_key(&mut args)?;
+        let args = parse_search_args("GEOSEARCH", &mut args, false)?;
+        Ok(GeoSearch { key, args })
+    }
+}
+
+//geosearchstore :"*9\r\n$14\r\ngeosearchstore\r\n$4\r\nnear\r\n$6\r\nSicily\r\n$10\r\nfrommember\r\n$7\r\nPalermo\r\n$8\r\nbyradius\r\n$3\r\n200\r\n$2\r\nkm\r\n$9\r\nstoredist\r\n"
+impl TryFrom<RespArray> for GeoSearchStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["geosearchstore"], 6)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let dst = next_key(&mut args)?;
+        let key = next_key(&mut args)?;
+        let args = parse_search_args("GEOSEARCHSTORE", &mut args, true)?;
+        Ok(GeoSearchStore { dst, key, args })
+    }
+}
+
+// the arguments shared by GEOSEARCH and GEOSEARCHSTORE, only the latter takes STOREDIST
+fn parse_search_args(
+    name: &str,
+    args: &mut Peekable<IntoIter<RespFrame>>,
+    store: bool,
+) -> Result<GeoSearchArgs, CommandError> {
+    let (mut origin, mut shape, mut unit) = (None, None, 1.0);
+    let (mut order, mut count, mut any) = (None, None, false);
+    let (mut withcoord, mut withdist, mut withhash, mut storedist) = (false, false, false, false);
+    let exactly_one = |options: &str| {
+        CommandError::InvalidArgument(format!(
+            "exactly one of {} can be specified for {}",
+            options, name
+        ))
+    };
+    while let Some(arg) = args.next() {
+        match extract_string(arg)?.to_ascii_lowercase().as_str() {
+            "frommember" => {
+                if origin.is_some() {
+                    return Err(exactly_one("FROMMEMBER or FROMLONLAT"));
+                }
+                let member = args.next().ok_or_else(syntax_error)?;
+                origin = Some(GeoOrigin::Member(extract_string(member)?));
+            }
+            "fromlonlat" => {
+                if origin.is_some() {
+                    return Err(exactly_one("FROMMEMBER or FROMLONLAT"));
+                }
+                let (Some(lon), Some(lat)) = (args.next(), args.next()) else {
+                    return Err(syntax_error());
+                };
+                let (lon, lat) = (parse_float(lon)?, parse_float(lat)?);
+                if geohash_score(lon, lat).is_none() {
+                    return Err(invalid_lon_lat(lon, lat));
+                }
+                origin = Some(GeoOrigin::LonLat(lon, lat));
+            }
+            "byradius" => {
+                if shape.is_some() {
+                    return Err(exactly_one("BYRADIUS and BYBOX"));
+                }
+                let (Some(radius), Some(radius_unit)) = (args.next(), args.next()) else {
+                    return Err(syntax_error());
+                };
+                let radius = parse_numeric(radius, "radius")?;
+                if radius < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "radius cannot be negative".to_string(),
+                    ));
+                }
+                unit = parse_unit(radius_unit)?;
+                shape = Some(GeoShape::Radius(radius));
+            }
+            "bybox" => {
+                if shape.is_some() {
+                    return Err(exactly_one("BYRADIUS and BYBOX"));
+                }
+                let (Some(width), Some(height), Some(box_unit)) =
+                    (args.next(), args.next(), args.next())
+                else {
+                    return Err(syntax_error());
+                };
+                let width = parse_numeric(width, "width")?;
+                let height = parse_numeric(height, "height")?;
+                if width < 0.0 || height < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "height or width cannot be negative".to_string(),
+                    ));
+                }
+                unit = parse_unit(box_unit)?;
+                shape = Some(GeoShape::Box { width, height });
+            }
+            "asc" => order = Some(GeoOrder::Asc),
+            "desc" => order = Some(GeoOrder::Desc),
+            "count" => {
+                let n = extract_int(args.next().ok_or_else(syntax_error)?)?;
+                if n <= 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "COUNT must be > 0".to_string(),
+                    ));
+                }
+                count = Some(n as usize);
+                if let Some(RespFrame::BulkString(arg)) = args.peek() {
+                    if arg.eq_ignore_ascii_case(b"any") {
+                        any = true;
+                        args.next();
+                    }
+                }
+            }
+            "withcoord" => withcoord = true,
+            "withdist" => withdist = true,
+            "withhash" => withhash = true,
+            "storedist" if store => storedist = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    if store && (withcoord || withdist || withhash) {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} is not compatible with WITHDIST, WITHHASH and WITHCOORD options",
+            name
+        )));
+    }
+    let origin = origin.ok_or_else(|| exactly_one("FROMMEMBER or FROMLONLAT"))?;
+    let shape = shape.ok_or_else(|| exactly_one("BYRADIUS and BYBOX"))?;
+    if any && count.is_none() {
+        return Err(CommandError::InvalidArgument(
+            "the ANY argument requires COUNT argument".to_string(),
+        ));
+    }
+    Ok(GeoSearchArgs {
+        query: GeoQuery {
+            origin,
+            shape,
+            unit,
+            order,
+            count,
+            any,
+        },
+        withcoord,
+        withdist,
+        withhash,
+        storedist,
+    })
+}
+
+// the geohash score of a longitude and latitude pair
+fn parse_lon_lat(lon: RespFrame, lat: RespFrame) -> Result<u64, CommandError> {
+    let (lon, lat) = (parse_float(lon)?, parse_float(lat)?);
+    geohash_score(lon, lat).ok_or_else(|| invalid_lon_lat(lon, lat))
+}
+
+// the number of meters in a unit
+fn parse_unit(value: RespFrame) -> Result<f64, CommandError> {
+    match extract_string(value)?.to_ascii_lowercase().as_str() {
+        "m" => Ok(1.0),
+        "km" => Ok(1000.0),
+        "ft" => Ok(0.3048),
+        "mi" => Ok(1609.34),
+        _ => Err(CommandError::InvalidArgument(
+            "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )),
+    }
+}
+
+fn parse_float(value: RespFrame) -> Result<f64, CommandError> {
+    extract_string(value)?
+        .parse::<f64>()
+        .ok()
+        .filter(|v| !v.is_nan())
+        .ok_or_else(|| CommandError::InvalidArgument("value is not a valid float".to_string()))
+}
+
+fn parse_numeric(value: RespFrame, what: &str) -> Result<f64, CommandError> {
+    parse_float(value).map_err(|_| CommandError::InvalidArgument(format!("need numeric {}", what)))
+}
+
+fn invalid_lon_lat(lon: f64, lat: f64) -> CommandError {
+    CommandError::InvalidArgument(format!(
+        "invalid longitude,latitude pair {:.6},{:.6}",
+        lon, lat
+    ))
+}
+
+fn next_key(args: &mut impl Iterator<Item = RespFrame>) -> Result<String, CommandError> {
+    match args.next() {
+        Some(key) => extract_string(key),
+        None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+
+    use anyhow::Result;
+
+    fn sicily() -> Result<Backend> {
+        let backend = Backend::new();
+        let frames = decode(b"*8\r\n$6\r\ngeoadd\r\n$6\r\nSicily\r\n$9\r\n13.361389\r\n$9\r\n38.115556\r\n$7\r\nPalermo\r\n$9\r\n15.087269\r\n$9\r\n37.502669\r\n$7\r\nCatania\r\n")?;
+        let result = GeoAdd::try_from(frames)?.execute(&backend);
+        assert_eq!(result, RespFrame::Integer(2));
+        Ok(backend)
+    }
+
+    fn bulk(s: &str) -> RespFrame {
+        BulkString::new(s).into()
+    }
+
+    #[test]
+    fn test_geoadd_command_tryfrom() -> Result<()> {
+        let frames = decode(b"*7\r\n$6\r\ngeoadd\r\n$3\r\nkey\r\n$2\r\nnx\r\n$2\r\nxx\r\n$1\r\n1\r\n$1\r\n2\r\n$1\r\na\r\n")?;
+        assert!(GeoAdd::try_from(frames).is_err());
+        let frames =
+            decode(b"*5\r\n$6\r\ngeoadd\r\n$3\r\nkey\r\n$1\r\n1\r\n$2\r\n86\r\n$1\r\na\r\n")?;
+        assert_eq!(
+            GeoAdd::try_from(frames).err(),
+            Some(CommandError::InvalidArgument(
+                "invalid longitude,latitude pair 1.000000,86.000000".to_string()
+            ))
+        );
+        let frames = decode(
+            b"*6\r\n$6\r\ngeoadd\r\n$3\r\nkey\r\n$2\r\nch\r\n$1\r\n1\r\n$1\r\n2\r\n$1\r\na\r\n",
+        )?;
+        let cmd = GeoAdd::try_from(frames)?;
+        assert!(cmd.options.ch);
+        assert_eq!(cmd.pairs.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_command_tryfrom() -> Result<()> {
+        let frames = decode(b"*11\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$10\r\nfrommember\r\n$7\r\nPalermo\r\n$5\r\nbybox\r\n$3\r\n400\r\n$3\r\n400\r\n$2\r\nkm\r\n$5\r\ncount\r\n$1\r\n1\r\n$3\r\nany\r\n")?;
+        let cmd = GeoSearch::try_from(frames)?;
+        assert_eq!(
+            cmd.args.query,
+            GeoQuery {
+                origin: GeoOrigin::Member("Palermo".to_string()),
+                shape: GeoShape::Box {
+                    width: 400.0,
+                    height: 400.0
+                },
+                unit: 1000.0,
+                order: None,
+                count: Some(1),
+                any: true,
+            }
+        );
+
+        let frames = decode(b"*6\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$8\r\nbyradius\r\n$3\r\n200\r\n$2\r\nkm\r\n$3\r\nasc\r\n")?;
+        assert_eq!(
+            GeoSearch::try_from(frames).err(),
+            Some(CommandError::InvalidArgument(
+                "exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH"
+                    .to_string()
+            ))
+        );
+        let frames = decode(b"*9\r\n$14\r\ngeosearchstore\r\n$1\r\nd\r\n$6\r\nSicily\r\n$10\r\nfrommember\r\n$7\r\nPalermo\r\n$8\r\nbyradius\r\n$1\r\n1\r\n$2\r\nyd\r\n$9\r\nstoredist\r\n")?;
+        assert!(GeoSearchStore::try_from(frames).is_err());
+        let frames = decode(b"*9\r\n$14\r\ngeosearchstore\r\n$1\r\nd\r\n$6\r\nSicily\r\n$10\r\nfrommember\r\n$7\r\nPalermo\r\n$8\r\nbyradius\r\n$1\r\n1\r\n$2\r\nkm\r\n$8\r\nwithdist\r\n")?;
+        assert!(GeoSearchStore::try_from(frames).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_commands() -> Result<()> {
+        let backend = sicily()?;
+        let frames =
+            decode(b"*4\r\n$6\r\ngeopos\r\n$6\r\nSicily\r\n$7\r\nPalermo\r\n$4\r\nRome\r\n")?;
+        let result = GeoPos::try_from(frames)?.execute(&backend);
+        assert_eq!(
+            result,
+            RespArray::new(vec![
+                RespArray::new(vec![
+                    bulk("13.36138933897018433"),
+                    bulk("38.11555639549629859")
+                ])
+                .into(),
+                REST_NIL_ARRAY.clone(),
+            ])
+            .into()
+        );
+
+        let frames = decode(b"*5\r\n$7\r\ngeodist\r\n$6\r\nSicily\r\n$7\r\nPalermo\r\n$7\r\nCatania\r\n$2\r\nkm\r\n")?;
+        let result = GeoDist::try_from(frames)?.execute(&backend);
+        assert_eq!(result, bulk("166.2742"));
+
+        let frames = decode(b"*3\r\n$7\r\ngeohash\r\n$6\r\nSicily\r\n$7\r\nCatania\r\n")?;
+        let result = GeoHash::try_from(frames)?.execute(&backend);
+        assert_eq!(result, RespArray::new(vec![bulk("sqdtr74hyu0")]).into());
+
+        let frames = decode(b"*11\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$10\r\nfromlonlat\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nbyradius\r\n$3\r\n200\r\n$2\r\nkm\r\n$3\r\nasc\r\n$8\r\nwithdist\r\n$8\r\nwithhash\r\n")?;
+        let result = GeoSearch::try_from(frames)?.execute(&backend);
+        assert_eq!(
+            result,
+            RespArray::new(vec![
+                RespArray::new(vec![
+                    bulk("Catania"),
+                    bulk("56.4413"),
+                    RespFrame::Integer(3479447370796909),
+                ])
+                .into(),
+                RespArray::new(vec![
+                    bulk("Palermo"),
+                    bulk("190.4424"),
+                    RespFrame::Integer(3479099956230698),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+
+        let frames = decode(b"*8\r\n$14\r\ngeosearchstore\r\n$4\r\nnear\r\n$6\r\nSicily\r\n$10\r\nfrommember\r\n$7\r\nCatania\r\n$8\r\nbyradius\r\n$3\r\n100\r\n$2\r\nkm\r\n")?;
+        let result = GeoSearchStore::try_from(frames)?.execute(&backend);
+        assert_eq!(result, RespFrame::Integer(1));
+        assert_eq!(backend.db().zcard("near")?, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,646 @@
+use crate::{
+    backend::Backend, command_name, extract_args, extract_int, extract_string, now_ms,
+    validate_command_minimum_length, BackendError, CommandError, CommandExecutor, ExpireCondition,
+    FieldTtl, RespArray, RespFrame, SetCondition, MAX_FIELD_EXPIRE_MS,
+};
+
+use super::REST_NIL;
+
+// an expire time as given on the command line, it is resolved to unix milliseconds when the
+// command is executed rather than when it is parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireTime {
+    Seconds(i64),
+    Millis(i64),
+    UnixSeconds(i64),
+    UnixMillis(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtlArg {
+    Keep,
+    Persist,
+    At(ExpireTime),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtlUnit {
+    Ttl,
+    PTtl,
+    ExpireTime,
+    PExpireTime,
+}
+
+// HEXPIRE, HPEXPIRE, HEXPIREAT and HPEXPIREAT
+#[derive(Debug)]
+pub struct HExpire {
+    name: &'static str,
+    key: String,
+    expire: ExpireTime,
+    cond: Option<ExpireCondition>,
+    fields: Vec<String>,
+}
+
+// HTTL, HPTTL, HEXPIRETIME and HPEXPIRETIME
+#[derive(Debug)]
+pub struct HTtl {
+    key: String,
+    unit: TtlUnit,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPersist {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HGetEx {
+    key: String,
+    ttl: TtlArg,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HSetEx {
+    key: String,
+    cond: Option<SetCondition>,
+    ttl: TtlArg,
+    pairs: Vec<(String, RespFrame)>,
+}
+
+impl ExpireTime {
+    fn unix_ms(&self, command: &'static str) -> Result<u64, BackendError> {
+        let now = now_ms() as i64;
+        let at = match *self {
+            ExpireTime::Seconds(s) => s.checked_mul(1000).and_then(|ms| ms.checked_add(now)),
+            ExpireTime::Millis(ms) => ms.checked_add(now),
+            ExpireTime::UnixSeconds(s) => s.checked_mul(1000),
+            ExpireTime::UnixMillis(ms) => Some(ms),
+        };
+        match at {
+            Some(at) if (0..=MAX_FIELD_EXPIRE_MS as i64).contains(&at) => Ok(at as u64),
+            _ => Err(BackendError::InvalidExpireTime(command)),
+        }
+    }
+}
+
+impl TtlArg {
+    fn resolve(&self, command: &'static str) -> Result<FieldTtl, BackendError> {
+        match self {
+            TtlArg::Keep => Ok(FieldTtl::Keep),
+            TtlArg::Persist => Ok(FieldTtl::Persist),
+            TtlArg::At(expire) => expire.unix_ms(command).map(FieldTtl::At),
+        }
+    }
+}
+
+impl CommandExecutor for HExpire {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let at = match self.expire.unix_ms(self.name) {
+            Ok(at) => at,
+            Err(e) => return e.into(),
+        };
+        match backend.db().hexpire(&self.key, &self.fields, at, self.cond) {
+            Ok(replies) => integers(replies),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HTtl {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let times = match backend.db().hexpire_time(&self.key, &self.fields) {
+            Ok(times) => times,
+            Err(e) => return e.into(),
+        };
+        let now = now_ms() as i64;
+        let replies = times
+            .into_iter()
+            .map(|at| match self.unit {
+                _ if at < 0 => at,
+                TtlUnit::Ttl => ((at - now).max(0) + 999) / 1000,
+                TtlUnit::PTtl => (at - now).max(0),
+                TtlUnit::ExpireTime => at / 1000,
+                TtlUnit::PExpireTime => at,
+            })
+            .collect();
+        integers(replies)
+    }
+}
+
+impl CommandExecutor for HPersist {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().hpersist(&self.key, &self.fields) {
+            Ok(replies) => integers(replies),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HGetEx {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let ttl = match self.ttl.resolve("hgetex") {
+            Ok(ttl) => ttl,
+            Err(e) => return e.into(),
+        };
+        match backend.db().hgetex(&self.key, &self.fields, ttl) {
+            Ok(values) => {
+                let values = values
+                    .into_iter()
+                    .map(|v| v.unwrap_or_else(|| REST_NIL.clone()))
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(values).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HSetEx {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let ttl = match self.ttl.resolve("hsetex") {
+            Ok(ttl) => ttl,
+            Err(e) => return e.into(),
+        };
+        match backend
+            .db()
+            .hsetex(self.key.clone(), self.pairs.clone(), self.cond, ttl)
+        {
+            Ok(set) => RespFrame::Integer(set as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn integers(values: Vec<i64>) -> RespFrame {
+    RespArray::new(
+        values
+            .into_iter()
+            .map(RespFrame::Integer)
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+//hexpire :"*6\r\n$7\r\nhexpire\r\n$3\r\nkey\r\n$2\r\n60\r\n$6\r\nfields\r\n$1\r\n1\r\n$5\r\nfield\r\n"
+impl TryFrom<RespArray> for HExpire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["hexpire", "hpexpire", "hexpireat", "hpexpireat"]);
+        validate_command_minimum_length(&value, &[name], 5)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let time = extract_int(args.next().ok_or_else(syntax_error)?)?;
+        if time < 0 {
+            return Err(CommandError::InvalidArgument(
+                "invalid expire time, must be >= 0".to_string(),
+            ));
+        }
+        let expire = match name {
+            "hexpire" => ExpireTime::Seconds(time),
+            "hpexpire" => ExpireTime::Millis(time),
+            "hexpireat" => ExpireTime::UnixSeconds(time),
+            _ => ExpireTime::UnixMillis(time),
+        };
+        let mut cond = None;
+        loop {
+            let option = next_option(&mut args)?;
+            let parsed = match option.as_str() {
+                "fields" => break,
+                "nx" => ExpireCondition::Nx,
+                "xx" => ExpireCondition::Xx,
+                "gt" => ExpireCondition::Gt,
+                "lt" => ExpireCondition::Lt,
+                _ => return Err(syntax_error()),
+            };
+            if cond.replace(parsed).is_some() {
+                return Err(CommandError::InvalidArgument(
+                    "Multiple condition flags specified".to_string(),
+                ));
+            }
+        }
+        Ok(HExpire {
+            name,
+            key,
+            expire,
+            cond,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+//httl :"*5\r\n$4\r\nhttl\r\n$3\r\nkey\r\n$6\r\nfields\r\n$1\r\n1\r\n$5\r\nfield\r\n"
+impl TryFrom<RespArray> for HTtl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["httl", "hpttl", "hexpiretime", "hpexpiretime"]);
+        validate_command_minimum_length(&value, &[name], 4)?;
+        let unit = match name {
+            "httl" => TtlUnit::Ttl,
+            "hpttl" => TtlUnit::PTtl,
+            "hexpiretime" => TtlUnit::ExpireTime,
+            _ => TtlUnit::PExpireTime,
+        };
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        if next_option(&mut args)? != "fields" {
+            return Err(syntax_error());
+        }
+        Ok(HTtl {
+            key,
+            unit,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPersist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["hpersist"], 4)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        if next_option(&mut args)? != "fields" {
+            return Err(syntax_error());
+        }
+        Ok(HPersist {
+            key,
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+//hgetex :"*7\r\n$6\r\nhgetex\r\n$3\r\nkey\r\n$2\r\nex\r\n$2\r\n60\r\n$6\r\nfields\r\n$1\r\n1\r\n$5\r\nfield\r\n"
+impl TryFrom<RespArray> for HGetEx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["hgetex"], 4)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let mut ttl = None;
+        loop {
+            let option = next_option(&mut args)?;
+            let parsed = match option.as_str() {
+                "fields" => break,
+                "persist" => TtlArg::Persist,
+                option => TtlArg::At(parse_expire_option(option, &mut args, "hgetex")?),
+            };
+            if ttl.replace(parsed).is_some() {
+                return Err(syntax_error());
+            }
+        }
+        Ok(HGetEx {
+            key,
+            ttl: ttl.unwrap_or(TtlArg::Keep),
+            fields: parse_fields(args)?,
+        })
+    }
+}
+
+//hsetex :"*8\r\n$6\r\nhsetex\r\n$3\r\nkey\r\n$2\r\npx\r\n$3\r\n500\r\n$6\r\nfields\r\n$1\r\n1\r\n$1\r\nf\r\n$1\r\nv\r\n"
+impl TryFrom<RespArray> for HSetEx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["hsetex"], 5)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let (mut cond, mut ttl) = (None, None);
+        loop {
+            let option = next_option(&mut args)?;
+            let duplicate = match option.as_str() {
+                "fields" => break,
+                "fnx" => cond.replace(SetCondition::Nx).is_some(),
+                "fxx" => cond.replace(SetCondition::Xx).is_some(),
+                "keepttl" => ttl.replace(TtlArg::Keep).is_some(),
+                option => {
+                    let expire = parse_expire_option(option, &mut args, "hsetex")?;
+                    ttl.replace(TtlArg::At(expire)).is_some()
+                }
+            };
+            if duplicate {
+                return Err(syntax_error());
+            }
+        }
+        let mut values = parse_numfields(args, 2)?.into_iter();
+        let mut pairs = Vec::new();
+        while let (Some(field), Some(value)) = (values.next(), values.next()) {
+            pairs.push((extract_string(field)?, value));
+        }
+        Ok(HSetEx {
+            key,
+            cond,
+            ttl: ttl.unwrap_or(TtlArg::Persist),
+            pairs,
+        })
+    }
+}
+
+fn next_key(args: &mut impl Iterator<Item = RespFrame>) -> Result<String, CommandError> {
+    match args.next() {
+        Some(key) => extract_string(key),
+        None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+fn next_option(args: &mut impl Iterator<Item = RespFrame>) -> Result<String, CommandError> {
+    match args.next() {
+        Some(option) => Ok(extract_string(option)?.to_ascii_lowercase()),
+        None => Err(syntax_error()),
+    }
+}
+
+// the EX, PX, EXAT and PXAT options of HGETEX and HSETEX
+fn parse_expire_option(
+    option: &str,
+    args: &mut impl Iterator<Item = RespFrame>,
+    command: &'static str,
+) -> Result<ExpireTime, CommandError> {
+    let expire: fn(i64) -> ExpireTime = match option {
+        "ex" => ExpireTime::Seconds,
+        "px" => ExpireTime::Millis,
+        "exat" => ExpireTime::UnixSeconds,
+        "pxat" => ExpireTime::UnixMillis,
+        _ => return Err(syntax_error()),
+    };
+    match args.next().map(extract_int).transpose()? {
+        Some(time) if time > 0 => Ok(expire(time)),
+        Some(_) => Err(CommandError::InvalidArgument(format!(
+            "invalid expire time in '{}' command",
+            command
+        ))),
+        None => Err(syntax_error()),
+    }
+}
+
+// the `numfields field [field ...]` arguments following FIELDS
+fn parse_fields(args: impl Iterator<Item = RespFrame>) -> Result<Vec<String>, CommandError> {
+    parse_numfields(args, 1)?
+        .into_iter()
+        .map(extract_string)
+        .collect()
+}
+
+// `numfields` followed by exactly numfields groups of `width` arguments
+fn parse_numfields(
+    mut args: impl Iterator<Item = RespFrame>,
+    width: usize,
+) -> Result<Vec<RespFrame>, CommandError> {
+    let numfields = match args.next().map(extract_int).transpose()? {
+        Some(n) if n > 0 => n as usize,
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "Parameter `numFields` should be greater than 0".to_string(),
+            ))
+        }
+    };
+    let values = args.collect::<Vec<_>>();
+    if values.len() != numfields.saturating_mul(width) {
+        return Err(CommandError::InvalidArgument(
+            "The `numfields` parameter must match the number of arguments".to_string(),
+        ));
+    }
+    Ok(values)
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+    use crate::{BulkString, NotifyFlags};
+    use anyhow::Result;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn setup() -> Result<Backend> {
+        let backend = Backend::new();
+        backend.db().hset(
+            "key".to_string(),
+            vec![
+                ("f1".to_string(), BulkString::new("v1").into()),
+                ("f2".to_string(), BulkString::new("v2").into()),
+            ],
+        )?;
+        Ok(backend)
+    }
+
+    fn integer_array(values: &[i64]) -> RespFrame {
+        integers(values.to_vec())
+    }
+
+    #[test]
+    fn test_hexpire_command_tryfrom() -> Result<()> {
+        let cmd = HExpire::try_from(decode(
+            b"*7\r\n$8\r\nhpexpire\r\n$3\r\nkey\r\n$3\r\n500\r\n$2\r\nnx\r\n$6\r\nfields\r\n$1\r\n1\r\n$5\r\nfield\r\n",
+        )?)?;
+        assert_eq!(cmd.name, "hpexpire");
+        assert_eq!(cmd.expire, ExpireTime::Millis(500));
+        assert_eq!(cmd.cond, Some(ExpireCondition::Nx));
+        assert_eq!(cmd.fields, strings(&["field"]));
+
+        // numfields does not match the fields given
+        assert!(HExpire::try_from(decode(
+            b"*6\r\n$7\r\nhexpire\r\n$3\r\nkey\r\n$2\r\n60\r\n$6\r\nfields\r\n$1\r\n2\r\n$5\r\nfield\r\n",
+        )?)
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexpire_httl_hpersist() -> Result<()> {
+        let backend = setup()?;
+        let hexpire = HExpire {
+            name: "hexpire",
+            key: "key".to_string(),
+            expire: ExpireTime::Seconds(100),
+            cond: None,
+            fields: strings(&["f1", "missing"]),
+        };
+        assert_eq!(hexpire.execute(&backend), integer_array(&[1, -2]));
+
+        let httl = HTtl {
+            key: "key".to_string(),
+            unit: TtlUnit::Ttl,
+            fields: strings(&["f1", "f2", "missing"]),
+        };
+        assert_eq!(httl.execute(&backend), integer_array(&[100, -1, -2]));
+
+        // GT never applies to a field without ttl, LT always does
+        let hexpire = HExpire {
+            name: "hexpire",
+            key: "key".to_string(),
+            expire: ExpireTime::Seconds(50),
+            cond: Some(ExpireCondition::Gt),
+            fields: strings(&["f1", "f2"]),
+        };
+        assert_eq!(hexpire.execute(&backend), integer_array(&[0, 0]));
+        let hexpire = HExpire {
+            cond: Some(ExpireCondition::Lt),
+            ..hexpire
+        };
+        assert_eq!(hexpire.execute(&backend), integer_array(&[1, 1]));
+
+        let hpersist = HPersist {
+            key: "key".to_string(),
+            fields: strings(&["f1", "f2", "missing"]),
+        };
+        assert_eq!(hpersist.execute(&backend), integer_array(&[1, 1, -2]));
+        assert_eq!(hpersist.execute(&backend), integer_array(&[-1, -1, -2]));
+
+        let hexpire = HExpire {
+            name: "hexpire",
+            key: "nokey".to_string(),
+            expire: ExpireTime::Seconds(50),
+            cond: None,
+            fields: strings(&["f1"]),
+        };
+        assert_eq!(hexpire.execute(&backend), integer_array(&[-2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_fields_are_invisible() -> Result<()> {
+        let backend = setup()?;
+        let at = now_ms() + 20;
+        backend.db().hexpire("key", &strings(&["f1"]), at, None)?;
+        std::thread::sleep(std::time::Duration::from_millis(40));
+        assert_eq!(backend.db().hget("key", "f1")?, None);
+        assert_eq!(backend.db().hlen("key")?, 1);
+        assert_eq!(backend.db().hkeys("key")?, strings(&["f2"]));
+
+        // once every field expired the key is gone as well
+        let at = now_ms() + 20;
+        backend.db().hexpire("key", &strings(&["f2"]), at, None)?;
+        std::thread::sleep(std::time::Duration::from_millis(40));
+        assert_eq!(backend.db().key_type("key"), "none");
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexpire_in_the_past_deletes_fields() -> Result<()> {
+        let backend = setup()?;
+        let hexpire = HExpire {
+            name: "hexpireat",
+            key: "key".to_string(),
+            expire: ExpireTime::UnixSeconds(1),
+            cond: None,
+            fields: strings(&["f1", "f2"]),
+        };
+        assert_eq!(hexpire.execute(&backend), integer_array(&[2, 2]));
+        assert_eq!(backend.db().key_type("key"), "none");
+
+        let hexpire = HExpire {
+            expire: ExpireTime::Seconds(i64::MAX),
+            ..hexpire
+        };
+        assert!(matches!(hexpire.execute(&backend), RespFrame::Error(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hgetex_command() -> Result<()> {
+        let backend = setup()?;
+        let cmd = HGetEx::try_from(decode(
+            b"*7\r\n$6\r\nhgetex\r\n$3\r\nkey\r\n$2\r\nex\r\n$2\r\n60\r\n$6\r\nfields\r\n$1\r\n2\r\n$2\r\nf1\r\n$7\r\nmissing\r\n",
+        )?);
+        assert!(cmd.is_err());
+
+        let cmd = HGetEx::try_from(decode(
+            b"*8\r\n$6\r\nhgetex\r\n$3\r\nkey\r\n$2\r\nex\r\n$2\r\n60\r\n$6\r\nfields\r\n$1\r\n2\r\n$2\r\nf1\r\n$7\r\nmissing\r\n",
+        )?)?;
+        assert_eq!(cmd.ttl, TtlArg::At(ExpireTime::Seconds(60)));
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![BulkString::new("v1").into(), REST_NIL.clone()]).into()
+        );
+        assert_eq!(
+            backend.db().hexpire_time("key", &strings(&["f2"]))?,
+            vec![-1]
+        );
+        assert!(backend.db().hexpire_time("key", &strings(&["f1"]))?[0] > 0);
+
+        let cmd = HGetEx {
+            key: "key".to_string(),
+            ttl: TtlArg::Persist,
+            fields: strings(&["f1"]),
+        };
+        cmd.execute(&backend);
+        assert_eq!(
+            backend.db().hexpire_time("key", &strings(&["f1"]))?,
+            vec![-1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hsetex_command() -> Result<()> {
+        let backend = setup()?;
+        let cmd = HSetEx::try_from(decode(
+            b"*9\r\n$6\r\nhsetex\r\n$3\r\nkey\r\n$3\r\nfnx\r\n$2\r\npx\r\n$4\r\n5000\r\n$6\r\nfields\r\n$1\r\n1\r\n$2\r\nf1\r\n$1\r\nx\r\n",
+        )?)?;
+        assert_eq!(cmd.cond, Some(SetCondition::Nx));
+        // f1 exists already
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = HSetEx {
+            key: "key".to_string(),
+            cond: Some(SetCondition::Nx),
+            ttl: TtlArg::At(ExpireTime::Millis(5000)),
+            pairs: vec![("f3".to_string(), BulkString::new("v3").into())],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(backend.db().hexpire_time("key", &strings(&["f3"]))?[0] > 0);
+
+        // KEEPTTL keeps the ttl, no option drops it
+        let cmd = HSetEx {
+            key: "key".to_string(),
+            cond: Some(SetCondition::Xx),
+            ttl: TtlArg::Keep,
+            pairs: vec![("f3".to_string(), BulkString::new("x").into())],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(backend.db().hexpire_time("key", &strings(&["f3"]))?[0] > 0);
+        let cmd = HSetEx {
+            ttl: TtlArg::Persist,
+            ..cmd
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(
+            backend.db().hexpire_time("key", &strings(&["f3"]))?,
+            vec![-1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hsetex_in_the_past() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_notify_keyspace_events(NotifyFlags::parse("Kgh").unwrap());
+        let cmd = HSetEx {
+            key: "key".to_string(),
+            cond: None,
+            ttl: TtlArg::At(ExpireTime::UnixMillis(1)),
+            pairs: vec![("f1".to_string(), BulkString::new("v1").into())],
+        };
+        // the field is deleted right away, and the key it was the only field of
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(!backend.db().contains("key"));
+        assert_eq!(
+            backend.db().take_events(),
+            vec![("hdel", "key".to_string()), ("del", "key".to_string())]
+        );
+        Ok(())
+    }
+}
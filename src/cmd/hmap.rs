@@ -1,9 +1,13 @@
 use crate::{
-    backend::Backend, extract_args, validate_command_exact_length, validate_command_minimum_length,
-    BulkString, CommandError, CommandExecutor, RespArray, RespFrame,
+    backend::Backend, extract_args, extract_int, extract_string, extract_strings,
+    validate_command_exact_length, validate_command_minimum_length, BulkString, CommandError,
+    CommandExecutor, RespArray, RespFrame,
 };
 
-use super::{REST_NIL, REST_OK};
+use super::{
+    keyspace::{parse_scan_args, scan_reply},
+    REST_NIL,
+};
 
 #[derive(Debug)]
 pub struct HGet {
@@ -14,8 +18,7 @@ pub struct HGet {
 #[derive(Debug)]
 pub struct HSet {
     key: String,
-    field: String,
-    value: RespFrame,
+    pairs: Vec<(String, RespFrame)>,
 }
 
 #[derive(Debug)]
@@ -29,26 +32,98 @@ pub struct HMGet {
     fields: Vec<String>,
 }
 
+#[derive(Debug)]
+pub struct HDel {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HExists {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug)]
+pub struct HLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HKeys {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HVals {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HSetNx {
+    key: String,
+    field: String,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct HStrLen {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug)]
+pub struct HIncrBy {
+    key: String,
+    field: String,
+    incr: i64,
+}
+
+#[derive(Debug)]
+pub struct HIncrByFloat {
+    key: String,
+    field: String,
+    incr: f64,
+}
+
+#[derive(Debug)]
+pub struct HRandField {
+    key: String,
+    count: Option<i64>,
+    withvalues: bool,
+}
+
+#[derive(Debug)]
+pub struct HScan {
+    key: String,
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+}
+
 impl CommandExecutor for HGet {
     fn execute(&self, backend: &Backend) -> RespFrame {
-        match backend.hget(&self.key, &self.field) {
-            Some(value) => value,
-            None => REST_NIL.clone(),
+        match backend.db().hget(&self.key, &self.field) {
+            Ok(Some(value)) => value,
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for HSet {
     fn execute(&self, backend: &Backend) -> RespFrame {
-        backend.hset(self.key.clone(), self.field.clone(), self.value.clone());
-        REST_OK.clone()
+        match backend.db().hset(self.key.clone(), self.pairs.clone()) {
+            Ok(added) => RespFrame::Integer(added),
+            Err(e) => e.into(),
+        }
     }
 }
 
 impl CommandExecutor for HGetAll {
     fn execute(&self, backend: &Backend) -> RespFrame {
-        match backend.hgetall(&self.key) {
-            Some(value) => {
+        match backend.db().hgetall(&self.key) {
+            Ok(Some(value)) => {
                 // let mut frames = RespMap::new();
                 let mut frame = Vec::with_capacity(value.len() * 2);
                 for v in value.iter() {
@@ -57,7 +132,8 @@ impl CommandExecutor for HGetAll {
                 }
                 RespArray::new(frame).into()
             }
-            None => REST_OK.clone(),
+            Ok(None) => RespArray::new(vec![]).into(),
+            Err(e) => e.into(),
         }
     }
 }
@@ -66,15 +142,155 @@ impl CommandExecutor for HMGet {
     fn execute(&self, backend: &Backend) -> RespFrame {
         let mut frame = Vec::with_capacity(self.fields.len());
         for field in self.fields.iter() {
-            match backend.hget(&self.key, field) {
-                Some(value) => frame.push(value),
-                None => frame.push(REST_NIL.clone()),
+            match backend.db().hget(&self.key, field) {
+                Ok(Some(value)) => frame.push(value),
+                Ok(None) => frame.push(REST_NIL.clone()),
+                Err(e) => return e.into(),
             }
         }
         RespArray::new(frame).into()
     }
 }
 
+impl CommandExecutor for HDel {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().hdel(&self.key, &self.fields) {
+            Ok(removed) => RespFrame::Integer(removed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HExists {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().hexists(&self.key, &self.field) {
+            Ok(exists) => RespFrame::Integer(exists as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HLen {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().hlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HKeys {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().hkeys(&self.key) {
+            Ok(keys) => {
+                let keys = keys
+                    .into_iter()
+                    .map(|k| BulkString::new(k).into())
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(keys).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HVals {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().hvals(&self.key) {
+            Ok(vals) => RespArray::new(vals).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HSetNx {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .hsetnx(self.key.clone(), self.field.clone(), self.value.clone())
+        {
+            Ok(set) => RespFrame::Integer(set as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HStrLen {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().hstrlen(&self.key, &self.field) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HIncrBy {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .hincrby(self.key.clone(), self.field.clone(), self.incr)
+        {
+            Ok(value) => RespFrame::Integer(value),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HIncrByFloat {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .hincrbyfloat(self.key.clone(), self.field.clone(), self.incr)
+        {
+            Ok(value) => BulkString::new(value.to_string()).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HRandField {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let fields = match backend.db().hrandfield(&self.key, self.count) {
+            Ok(fields) => fields,
+            Err(e) => return e.into(),
+        };
+        // without a count the reply is a single field, or nil for a missing key
+        if self.count.is_none() {
+            return match fields.into_iter().next() {
+                Some((field, _)) => BulkString::new(field).into(),
+                None => REST_NIL.clone(),
+            };
+        }
+        let mut frames = Vec::with_capacity(fields.len() * 2);
+        for (field, value) in fields {
+            frames.push(BulkString::new(field).into());
+            if self.withvalues {
+                frames.push(value);
+            }
+        }
+        RespArray::new(frames).into()
+    }
+}
+
+impl CommandExecutor for HScan {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .hscan(&self.key, self.cursor, self.pattern.as_deref(), self.count)
+        {
+            Ok((cursor, fields)) => {
+                let mut frames = Vec::with_capacity(fields.len() * 2);
+                for (field, value) in fields {
+                    frames.push(BulkString::new(field).into());
+                    frames.push(value);
+                }
+                scan_reply(cursor, frames)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
 //hget :"*3\r\n$4\r\nHGet\r\n$3\r\nkey\r\n$5\r\nfield\r\n"
 impl TryFrom<RespArray> for HGet {
     type Error = CommandError;
@@ -97,20 +313,25 @@ impl TryFrom<RespArray> for HSet {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command_exact_length(&value, &["hset"], 3)?;
+        validate_command_minimum_length(&value, &["hset"], 3)?;
+        if !value.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'hset' command".to_string(),
+            ));
+        }
         let mut args = extract_args(value)?.into_iter();
-        match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(HSet {
-                    key: String::from_utf8_lossy(&key).to_string(),
-                    field: String::from_utf8_lossy(&field).to_string(),
-                    value,
-                })
-            }
-            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let mut pairs = Vec::new();
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            pairs.push((extract_string(field)?, value));
         }
+        Ok(HSet { key, pairs })
     }
 }
+
 //hgetall :"*2\r\n$7\r\nHGETALL\r\n$3\r\nkey\r\n"
 impl TryFrom<RespArray> for HGetAll {
     type Error = CommandError;
@@ -153,10 +374,204 @@ impl TryFrom<RespArray> for HMGet {
     }
 }
 
+impl TryFrom<RespArray> for HDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["hdel"], 2)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(HDel {
+                key,
+                fields: args.collect(),
+            }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HExists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hexists"], 2)?;
+        let (key, field) = parse_key_field(value)?;
+        Ok(HExists { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for HLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hlen"], 1)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(HLen { key }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HKeys {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hkeys"], 1)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(HKeys { key }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HVals {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hvals"], 1)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(HVals { key }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HSetNx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hsetnx"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(value)) => Ok(HSetNx {
+                key: extract_string(key)?,
+                field: extract_string(field)?,
+                value,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HStrLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hstrlen"], 2)?;
+        let (key, field) = parse_key_field(value)?;
+        Ok(HStrLen { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for HIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hincrby"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(incr)) => Ok(HIncrBy {
+                key: extract_string(key)?,
+                field: extract_string(field)?,
+                incr: extract_int(incr)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HIncrByFloat {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["hincrbyfloat"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(incr)) => {
+                let incr = extract_string(incr)?
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|incr| incr.is_finite())
+                    .ok_or_else(|| {
+                        CommandError::InvalidArgument("value is not a valid float".to_string())
+                    })?;
+                Ok(HIncrByFloat {
+                    key: extract_string(key)?,
+                    field: extract_string(field)?,
+                    incr,
+                })
+            }
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+//hrandfield :"*4\r\n$10\r\nhrandfield\r\n$3\r\nkey\r\n$2\r\n-5\r\n$10\r\nwithvalues\r\n"
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["hrandfield"], 1)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let count = args.next().map(extract_int).transpose()?;
+        let withvalues = match args.next().map(extract_string).transpose()? {
+            Some(option) if option.eq_ignore_ascii_case("withvalues") => true,
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => false,
+        };
+        Ok(HRandField {
+            key,
+            count,
+            withvalues,
+        })
+    }
+}
+
+//hscan :"*5\r\n$5\r\nhscan\r\n$3\r\nkey\r\n$1\r\n0\r\n$5\r\ncount\r\n$2\r\n20\r\n"
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["hscan"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let (cursor, pattern, count) = parse_scan_args(&mut args, |_, _| {
+            Err(CommandError::InvalidArgument("syntax error".to_string()))
+        })?;
+        Ok(HScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
+fn parse_key_field(value: RespArray) -> Result<(String, String), CommandError> {
+    let mut args = extract_strings(value)?.into_iter();
+    match (args.next(), args.next()) {
+        (Some(key), Some(field)) => Ok((key, field)),
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{resp::RespDecode, BulkString};
+    use crate::{resp::RespDecode, BulkString, RespEncode};
     use anyhow::{Ok, Result};
     use bytes::BytesMut;
 
@@ -178,7 +593,10 @@ mod tests {
         let frames = RespArray::decode(&mut buf)?;
         let hset = HSet::try_from(frames)?;
         assert_eq!(hset.key, "key");
-        assert_eq!(hset.field, "field");
+        assert_eq!(
+            hset.pairs,
+            vec![("field".to_string(), BulkString::new("value").into())]
+        );
         Ok(())
     }
 
@@ -211,11 +629,10 @@ mod tests {
         let backend = Backend::new();
         let hset = HSet {
             key: "key".to_string(),
-            field: "field".to_string(),
-            value: BulkString::new("value").into(),
+            pairs: vec![("field".to_string(), BulkString::new("value").into())],
         };
         let result = hset.execute(&backend);
-        assert_eq!(result, REST_OK.clone());
+        assert_eq!(result, RespFrame::Integer(1));
         let hget = HGet {
             key: "key".to_string(),
             field: "field".to_string(),
@@ -230,19 +647,17 @@ mod tests {
         let backend = Backend::new();
         let hset = HSet {
             key: "key".to_string(),
-            field: "field".to_string(),
-            value: BulkString::new("value").into(),
+            pairs: vec![("field".to_string(), BulkString::new("value").into())],
         };
         let result = hset.execute(&backend);
-        assert_eq!(result, REST_OK.clone());
+        assert_eq!(result, RespFrame::Integer(1));
 
         let hset = HSet {
             key: "key".to_string(),
-            field: "field1".to_string(),
-            value: BulkString::new("value1").into(),
+            pairs: vec![("field1".to_string(), BulkString::new("value1").into())],
         };
         let result = hset.execute(&backend);
-        assert_eq!(result, REST_OK.clone());
+        assert_eq!(result, RespFrame::Integer(1));
 
         let hmget = HMGet {
             key: "key".to_string(),
@@ -260,4 +675,211 @@ mod tests {
         assert_eq!(result, array);
         Ok(())
     }
+
+    fn hset(backend: &Backend, key: &str, pairs: &[(&str, &str)]) -> RespFrame {
+        HSet {
+            key: key.to_string(),
+            pairs: pairs
+                .iter()
+                .map(|(f, v)| (f.to_string(), BulkString::new(*v).into()))
+                .collect(),
+        }
+        .execute(backend)
+    }
+
+    #[test]
+    fn test_hset_multiple_pairs_command_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nhset\r\n$3\r\nkey\r\n$2\r\nf1\r\n$2\r\nv1\r\n$2\r\nf2\r\n$2\r\nv2\r\n",
+        );
+        let hset = HSet::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(hset.pairs.len(), 2);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nhset\r\n$3\r\nkey\r\n$2\r\nf1\r\n$2\r\nv1\r\n$2\r\nf2\r\n",
+        );
+        assert!(HSet::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hset_counts_new_fields() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(
+            hset(&backend, "key", &[("f1", "v1"), ("f2", "v2")]),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            hset(&backend, "key", &[("f2", "x"), ("f3", "v3")]),
+            RespFrame::Integer(1)
+        );
+        let hlen = HLen {
+            key: "key".to_string(),
+        };
+        assert_eq!(hlen.execute(&backend), RespFrame::Integer(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hdel_removes_empty_hash() -> Result<()> {
+        let backend = Backend::new();
+        hset(&backend, "key", &[("f1", "v1"), ("f2", "v2")]);
+        let hdel = HDel {
+            key: "key".to_string(),
+            fields: vec!["f1".to_string(), "f2".to_string(), "f3".to_string()],
+        };
+        assert_eq!(hdel.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.db().key_type("key"), "none");
+
+        let hgetall = HGetAll {
+            key: "key".to_string(),
+        };
+        assert_eq!(hgetall.execute(&backend), RespArray::new(vec![]).into());
+        // a missing key is an empty array on the wire, not a null one
+        assert_eq!(hgetall.execute(&backend).encode(), b"*0\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexists_hstrlen_hsetnx() -> Result<()> {
+        let backend = Backend::new();
+        hset(&backend, "key", &[("field", "hello")]);
+        let hexists = HExists {
+            key: "key".to_string(),
+            field: "field".to_string(),
+        };
+        assert_eq!(hexists.execute(&backend), RespFrame::Integer(1));
+        let hstrlen = HStrLen {
+            key: "key".to_string(),
+            field: "field".to_string(),
+        };
+        assert_eq!(hstrlen.execute(&backend), RespFrame::Integer(5));
+
+        let hsetnx = HSetNx {
+            key: "key".to_string(),
+            field: "field".to_string(),
+            value: BulkString::new("world").into(),
+        };
+        assert_eq!(hsetnx.execute(&backend), RespFrame::Integer(0));
+        let hsetnx = HSetNx {
+            key: "key".to_string(),
+            field: "other".to_string(),
+            value: BulkString::new("world").into(),
+        };
+        assert_eq!(hsetnx.execute(&backend), RespFrame::Integer(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincrby_hincrbyfloat() -> Result<()> {
+        let backend = Backend::new();
+        let hincrby = HIncrBy {
+            key: "key".to_string(),
+            field: "n".to_string(),
+            incr: 5,
+        };
+        assert_eq!(hincrby.execute(&backend), RespFrame::Integer(5));
+        assert_eq!(hincrby.execute(&backend), RespFrame::Integer(10));
+
+        let hincrbyfloat = HIncrByFloat {
+            key: "key".to_string(),
+            field: "n".to_string(),
+            incr: 0.5,
+        };
+        assert_eq!(
+            hincrbyfloat.execute(&backend),
+            BulkString::new("10.5").into()
+        );
+        // the field no longer holds an integer
+        assert!(matches!(hincrby.execute(&backend), RespFrame::Error(_)));
+
+        hset(&backend, "key", &[("big", &i64::MAX.to_string())]);
+        let hincrby = HIncrBy {
+            key: "key".to_string(),
+            field: "big".to_string(),
+            incr: 1,
+        };
+        assert!(matches!(hincrby.execute(&backend), RespFrame::Error(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield_command() -> Result<()> {
+        let backend = Backend::new();
+        hset(&backend, "key", &[("f1", "v1"), ("f2", "v2"), ("f3", "v3")]);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$10\r\nhrandfield\r\n$3\r\nkey\r\n$2\r\n-5\r\n$10\r\nwithvalues\r\n",
+        );
+        let cmd = HRandField::try_from(RespArray::decode(&mut buf)?)?;
+        match cmd.execute(&backend) {
+            RespFrame::Array(array) => assert_eq!(array.len(), 10),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+
+        let cmd = HRandField {
+            key: "key".to_string(),
+            count: Some(10),
+            withvalues: false,
+        };
+        match cmd.execute(&backend) {
+            RespFrame::Array(array) => assert_eq!(array.len(), 3),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+
+        let cmd = HRandField {
+            key: "missing".to_string(),
+            count: None,
+            withvalues: false,
+        };
+        assert_eq!(cmd.execute(&backend), REST_NIL.clone());
+
+        // a count too large to reply with is refused
+        let cmd = HRandField {
+            key: "key".to_string(),
+            count: Some(-100_000_000_000),
+            withvalues: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            crate::BackendError::RandomCountOutOfRange.into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hscan_command() -> Result<()> {
+        let backend = Backend::new();
+        for i in 0..50 {
+            hset(&backend, "key", &[(&format!("f{}", i), "v")]);
+        }
+        let mut cursor = 0;
+        let mut seen = 0;
+        loop {
+            let cmd = HScan {
+                key: "key".to_string(),
+                cursor,
+                pattern: Some("f1*".to_string()),
+                count: 7,
+            };
+            let RespFrame::Array(reply) = cmd.execute(&backend) else {
+                panic!("unexpected reply");
+            };
+            let (RespFrame::BulkString(next), RespFrame::Array(fields)) = (&reply[0], &reply[1])
+            else {
+                panic!("unexpected reply");
+            };
+            seen += fields.len() / 2;
+            cursor = String::from_utf8_lossy(next).parse()?;
+            if cursor == 0 {
+                break;
+            }
+        }
+        // f1 and f10..f19
+        assert_eq!(seen, 11);
+        Ok(())
+    }
 }
@@ -0,0 +1,159 @@
+use crate::{
+    extract_args, extract_string, validate_command_minimum_length, Backend, CommandError,
+    CommandExecutor, RespArray, RespFrame,
+};
+
+use super::REST_OK;
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: String,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PfMerge {
+    dst: String,
+    sources: Vec<String>,
+}
+
+impl CommandExecutor for PfAdd {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().pfadd(self.key.clone(), &self.elements) {
+            Ok(changed) => RespFrame::Integer(changed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfCount {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().pfcount(&self.keys) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfMerge {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().pfmerge(self.dst.clone(), &self.sources) {
+            Ok(()) => REST_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+//pfadd :"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n"
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["pfadd"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        // elements are hashed as they were sent
+        let elements = args
+            .map(|arg| match arg {
+                RespFrame::BulkString(element) => Ok(element.0),
+                _ => Err(CommandError::InvalidArgument("Invalid element".to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PfAdd { key, elements })
+    }
+}
+
+//pfcount :"*3\r\n$7\r\npfcount\r\n$1\r\na\r\n$1\r\nb\r\n"
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["pfcount"], 1)?;
+        let keys = extract_args(value)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PfCount { keys })
+    }
+}
+
+//pfmerge :"*4\r\n$7\r\npfmerge\r\n$3\r\ndst\r\n$1\r\na\r\n$1\r\nb\r\n"
+impl TryFrom<RespArray> for PfMerge {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["pfmerge"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let dst = match args.next() {
+            Some(dst) => extract_string(dst)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let sources = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        Ok(PfMerge { dst, sources })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+    use crate::BulkString;
+    use anyhow::Result;
+
+    #[test]
+    fn test_pf_commands_tryfrom() -> Result<()> {
+        let frames = decode(b"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n")?;
+        let cmd = PfAdd::try_from(frames)?;
+        assert_eq!(cmd.key, "hll");
+        assert_eq!(cmd.elements, vec![b"a".to_vec(), b"b".to_vec()]);
+
+        let frames = decode(b"*1\r\n$7\r\npfcount\r\n")?;
+        assert!(PfCount::try_from(frames).is_err());
+
+        let frames = decode(b"*2\r\n$7\r\npfmerge\r\n$3\r\ndst\r\n")?;
+        let cmd = PfMerge::try_from(frames)?;
+        assert_eq!(cmd.dst, "dst");
+        assert!(cmd.sources.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pf_commands() -> Result<()> {
+        let backend = Backend::new();
+        let frames = decode(b"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n")?;
+        let result = PfAdd::try_from(frames)?.execute(&backend);
+        assert_eq!(result, RespFrame::Integer(1));
+        let frames = decode(b"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n")?;
+        let result = PfAdd::try_from(frames)?.execute(&backend);
+        assert_eq!(result, RespFrame::Integer(0));
+
+        let frames = decode(b"*2\r\n$7\r\npfcount\r\n$3\r\nhll\r\n")?;
+        let result = PfCount::try_from(frames)?.execute(&backend);
+        assert_eq!(result, RespFrame::Integer(2));
+
+        // the value is a plain string in the Redis layout
+        let value = backend.db().get("hll")?;
+        let Some(value) = value else {
+            panic!("hll not stored");
+        };
+        assert!(
+            matches!(&value, RespFrame::BulkString(BulkString(bytes)) if bytes.starts_with(b"HYLL"))
+        );
+        backend.db().set("copy".to_string(), value);
+        let frames = decode(b"*3\r\n$7\r\npfmerge\r\n$3\r\ndst\r\n$4\r\ncopy\r\n")?;
+        let result = PfMerge::try_from(frames)?.execute(&backend);
+        assert_eq!(result, REST_OK.clone());
+        let frames = decode(b"*2\r\n$7\r\npfcount\r\n$3\r\ndst\r\n")?;
+        let result = PfCount::try_from(frames)?.execute(&backend);
+        assert_eq!(result, RespFrame::Integer(2));
+        Ok(())
+    }
+}
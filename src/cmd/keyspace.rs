@@ -0,0 +1,714 @@
+use crate::{
+    extract_args, extract_int, extract_string, extract_strings, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BackendError, BulkString, CommandError,
+    CommandExecutor, RespArray, RespFrame, SimpleString,
+};
+
+use super::{REST_NIL, REST_OK};
+
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Unlink {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct KeyType {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Rename {
+    src: String,
+    dst: String,
+}
+
+#[derive(Debug)]
+pub struct RenameNx {
+    src: String,
+    dst: String,
+}
+
+#[derive(Debug)]
+pub struct CopyKey {
+    src: String,
+    dst: String,
+    db: Option<i64>,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct Move {
+    key: String,
+    db: i64,
+}
+
+#[derive(Debug)]
+pub struct Touch {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct RandomKey;
+
+#[derive(Debug)]
+pub struct Keys {
+    pattern: String,
+}
+
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+    key_type: Option<String>,
+}
+
+impl CommandExecutor for Del {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.db().del(&self.keys))
+    }
+}
+
+impl CommandExecutor for Unlink {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.db().unlink(&self.keys))
+    }
+}
+
+impl CommandExecutor for Exists {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.db().exists(&self.keys))
+    }
+}
+
+impl CommandExecutor for KeyType {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        SimpleString::new(backend.db().key_type(&self.key)).into()
+    }
+}
+
+impl CommandExecutor for Rename {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().rename(&self.src, &self.dst, false) {
+            Ok(_) => {
+                backend.signal_ready(&self.dst);
+                REST_OK.clone()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for RenameNx {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().rename(&self.src, &self.dst, true) {
+            Ok(renamed) => {
+                if renamed {
+                    backend.signal_ready(&self.dst);
+                }
+                RespFrame::Integer(renamed as i64)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CopyKey {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let db = match self.db {
+            Some(db) if db < 0 => return BackendError::DbIndexOutOfRange.into(),
+            db => db.map(|db| db as usize),
+        };
+        match backend.copy(&self.src, &self.dst, db, self.replace) {
+            Ok(copied) => RespFrame::Integer(copied as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Move {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if self.db < 0 {
+            return BackendError::DbIndexOutOfRange.into();
+        }
+        match backend.move_key(&self.key, self.db as usize) {
+            Ok(moved) => RespFrame::Integer(moved as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Touch {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.db().touch(&self.keys))
+    }
+}
+
+impl CommandExecutor for RandomKey {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().random_key() {
+            Some(key) => BulkString::new(key).into(),
+            None => REST_NIL.clone(),
+        }
+    }
+}
+
+impl CommandExecutor for Keys {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let keys = backend
+            .db()
+            .keys(&self.pattern)
+            .into_iter()
+            .map(|key| BulkString::new(key).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(keys).into()
+    }
+}
+
+impl CommandExecutor for Scan {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let (cursor, keys) = backend.db().scan(
+            self.cursor,
+            self.pattern.as_deref(),
+            self.count,
+            self.key_type.as_deref(),
+        );
+        let keys = keys
+            .into_iter()
+            .map(|k| BulkString::new(k).into())
+            .collect();
+        scan_reply(cursor, keys)
+    }
+}
+
+// reply shape shared by SCAN, HSCAN, SSCAN and ZSCAN: the next cursor followed by the elements
+pub fn scan_reply(cursor: u64, elements: Vec<RespFrame>) -> RespFrame {
+    RespArray::new(vec![
+        BulkString::new(cursor.to_string()).into(),
+        RespArray::new(elements).into(),
+    ])
+    .into()
+}
+
+// parse the `cursor [MATCH pattern] [COUNT count]` arguments of the scan family, any other
+// option is handed to `extra` together with the remaining arguments
+pub fn parse_scan_args(
+    args: &mut impl Iterator<Item = RespFrame>,
+    mut extra: impl FnMut(&str, &mut dyn Iterator<Item = RespFrame>) -> Result<(), CommandError>,
+) -> Result<(u64, Option<String>, usize), CommandError> {
+    let cursor = match args.next() {
+        Some(cursor) => extract_string(cursor)?
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))?,
+        None => return Err(CommandError::InvalidArgument("Invalid cursor".to_string())),
+    };
+    let (mut pattern, mut count) = (None, 10);
+    while let Some(arg) = args.next() {
+        let arg = extract_string(arg)?.to_ascii_lowercase();
+        match (arg.as_str(), args.next()) {
+            ("match", Some(p)) => pattern = Some(extract_string(p)?),
+            ("count", Some(c)) => match extract_int(c)? {
+                c if c >= 1 => count = c as usize,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            (option, Some(v)) => extra(option, &mut std::iter::once(v))?,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+    Ok((cursor, pattern, count))
+}
+
+impl TryFrom<RespArray> for RandomKey {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["randomkey"], 0)?;
+        Ok(RandomKey)
+    }
+}
+
+//keys :"*2\r\n$4\r\nkeys\r\n$1\r\n*\r\n"
+impl TryFrom<RespArray> for Keys {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["keys"], 1)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(pattern) => Ok(Keys { pattern }),
+            _ => Err(CommandError::InvalidArgument("Invalid pattern".to_string())),
+        }
+    }
+}
+
+//scan :"*4\r\n$4\r\nscan\r\n$1\r\n0\r\n$5\r\nmatch\r\n$3\r\nk:*\r\n"
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["scan"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let mut key_type = None;
+        let (cursor, pattern, count) = parse_scan_args(&mut args, |option, values| {
+            match (option, values.next()) {
+                ("type", Some(t)) => key_type = Some(extract_string(t)?),
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+            Ok(())
+        })?;
+        Ok(Scan {
+            cursor,
+            pattern,
+            count,
+            key_type,
+        })
+    }
+}
+
+//del :"*3\r\n$3\r\ndel\r\n$2\r\nk1\r\n$2\r\nk2\r\n"
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["del"], 1)?;
+        Ok(Del {
+            keys: extract_strings(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unlink {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["unlink"], 1)?;
+        Ok(Unlink {
+            keys: extract_strings(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Exists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["exists"], 1)?;
+        Ok(Exists {
+            keys: extract_strings(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for KeyType {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["type"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(KeyType {
+                key: extract_string(key)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Rename {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["rename"], 2)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(src), Some(dst)) => Ok(Rename { src, dst }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for RenameNx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["renamenx"], 2)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(src), Some(dst)) => Ok(RenameNx { src, dst }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+//copy :"*4\r\n$4\r\ncopy\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$7\r\nreplace\r\n"
+impl TryFrom<RespArray> for CopyKey {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["copy"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let (src, dst) = match (args.next(), args.next()) {
+            (Some(src), Some(dst)) => (extract_string(src)?, extract_string(dst)?),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let mut cmd = CopyKey {
+            src,
+            dst,
+            db: None,
+            replace: false,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(arg)?.to_ascii_lowercase().as_str() {
+                "replace" => cmd.replace = true,
+                "db" => match args.next() {
+                    Some(db) => cmd.db = Some(extract_int(db)?),
+                    None => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                },
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+//move :"*3\r\n$4\r\nmove\r\n$3\r\nkey\r\n$1\r\n1\r\n"
+impl TryFrom<RespArray> for Move {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["move"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(db)) => Ok(Move {
+                key: extract_string(key)?,
+                db: extract_int(db)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Touch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["touch"], 1)?;
+        Ok(Touch {
+            keys: extract_strings(value)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{now_ms, resp::RespDecode, BulkString, RespEncode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    fn setup() -> Result<Backend> {
+        let backend = Backend::new();
+        backend
+            .db()
+            .set("string".to_string(), BulkString::new("value").into());
+        backend.db().hset(
+            "hash".to_string(),
+            vec![("field".to_string(), BulkString::new("value").into())],
+        )?;
+        backend.db().sadd("set".to_string(), keys(&["a", "b"]))?;
+        Ok(backend)
+    }
+
+    #[test]
+    fn test_del_command_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\ndel\r\n$2\r\nk1\r\n$2\r\nk2\r\n");
+        let frames = RespArray::decode(&mut buf)?;
+        let del = Del::try_from(frames)?;
+        assert_eq!(del.keys, keys(&["k1", "k2"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_command_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\ncopy\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$2\r\nDB\r\n$1\r\n0\r\n$7\r\nreplace\r\n",
+        );
+        let frames = RespArray::decode(&mut buf)?;
+        let copy = CopyKey::try_from(frames)?;
+        assert_eq!(copy.src, "src");
+        assert_eq!(copy.dst, "dst");
+        assert_eq!(copy.db, Some(0));
+        assert!(copy.replace);
+        Ok(())
+    }
+
+    #[test]
+    fn test_del_exists_type_execute() -> Result<()> {
+        let backend = setup()?;
+        let exists = Exists {
+            keys: keys(&["string", "hash", "set", "string", "missing"]),
+        };
+        assert_eq!(exists.execute(&backend), RespFrame::Integer(4));
+
+        for (key, expected) in [("string", "string"), ("hash", "hash"), ("set", "set")] {
+            let cmd = KeyType {
+                key: key.to_string(),
+            };
+            assert_eq!(cmd.execute(&backend), SimpleString::new(expected).into());
+        }
+
+        let del = Del {
+            keys: keys(&["string", "hash", "missing"]),
+        };
+        assert_eq!(del.execute(&backend), RespFrame::Integer(2));
+        let unlink = Unlink {
+            keys: keys(&["set"]),
+        };
+        assert_eq!(unlink.execute(&backend), RespFrame::Integer(1));
+        let cmd = KeyType {
+            key: "hash".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), SimpleString::new("none").into());
+        assert_eq!(exists.execute(&backend), RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_execute() -> Result<()> {
+        let backend = setup()?;
+        let expire_at = now_ms() + 60_000;
+        backend.db().expire_at("hash", Some(expire_at));
+
+        let rename = Rename {
+            src: "hash".to_string(),
+            dst: "string".to_string(),
+        };
+        assert_eq!(rename.execute(&backend), REST_OK.clone());
+        assert_eq!(backend.db().key_type("hash"), "none");
+        assert_eq!(backend.db().key_type("string"), "hash");
+        assert_eq!(backend.db().expire_time("string"), Some(expire_at));
+
+        let rename = Rename {
+            src: "missing".to_string(),
+            dst: "string".to_string(),
+        };
+        assert_eq!(rename.execute(&backend), BackendError::NoSuchKey.into());
+
+        let renamenx = RenameNx {
+            src: "set".to_string(),
+            dst: "string".to_string(),
+        };
+        assert_eq!(renamenx.execute(&backend), RespFrame::Integer(0));
+        let renamenx = RenameNx {
+            src: "set".to_string(),
+            dst: "other".to_string(),
+        };
+        assert_eq!(renamenx.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.db().sismember("other", "a"), Ok(true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_touch_execute() -> Result<()> {
+        let backend = setup()?;
+        let copy = CopyKey {
+            src: "set".to_string(),
+            dst: "string".to_string(),
+            db: None,
+            replace: false,
+        };
+        assert_eq!(copy.execute(&backend), RespFrame::Integer(0));
+        let copy = CopyKey {
+            src: "set".to_string(),
+            dst: "string".to_string(),
+            db: None,
+            replace: true,
+        };
+        assert_eq!(copy.execute(&backend), RespFrame::Integer(1));
+        // the copy is independent of the source
+        backend.db().sadd("string".to_string(), keys(&["c"]))?;
+        assert_eq!(backend.db().sismember("set", "c"), Ok(false));
+        assert_eq!(backend.db().sismember("string", "a"), Ok(true));
+
+        let copy = CopyKey {
+            src: "set".to_string(),
+            dst: "set".to_string(),
+            db: Some(3),
+            replace: false,
+        };
+        assert_eq!(copy.execute(&backend), RespFrame::Integer(1));
+        backend.select(3)?;
+        assert_eq!(backend.db().sismember("set", "a"), Ok(true));
+        backend.select(0)?;
+        let copy = CopyKey {
+            src: "set".to_string(),
+            dst: "set".to_string(),
+            db: None,
+            replace: true,
+        };
+        assert_eq!(copy.execute(&backend), BackendError::SameObject.into());
+
+        let touch = Touch {
+            keys: keys(&["set", "string", "missing"]),
+        };
+        assert_eq!(touch.execute(&backend), RespFrame::Integer(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_command_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$4\r\nscan\r\n$2\r\n17\r\n$5\r\nMATCH\r\n$3\r\nk:*\r\n$5\r\nCOUNT\r\n$3\r\n100\r\n$4\r\nTYPE\r\n$4\r\nhash\r\n",
+        );
+        let frames = RespArray::decode(&mut buf)?;
+        let scan = Scan::try_from(frames)?;
+        assert_eq!(scan.cursor, 17);
+        assert_eq!(scan.pattern.as_deref(), Some("k:*"));
+        assert_eq!(scan.count, 100);
+        assert_eq!(scan.key_type.as_deref(), Some("hash"));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nscan\r\n$1\r\n0\r\n$5\r\nCOUNT\r\n");
+        let frames = RespArray::decode(&mut buf)?;
+        assert!(Scan::try_from(frames).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keys_scan_execute() -> Result<()> {
+        let backend = setup()?;
+        for i in 0..30 {
+            backend
+                .db()
+                .set(format!("user:{}", i), BulkString::new("v").into());
+        }
+        let cmd = Keys {
+            pattern: "user:1?".to_string(),
+        };
+        match cmd.execute(&backend) {
+            RespFrame::Array(keys) => assert_eq!(keys.len(), 10),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, keys) = backend.db().scan(cursor, Some("user:*"), 4, None);
+            seen.extend(keys);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 30);
+
+        let (_, keys) = backend.db().scan(0, None, 1000, Some("set"));
+        assert_eq!(keys, vec!["set".to_string()]);
+
+        // deleted and renamed keys leave the scan order with the keyspace
+        backend.db().del(&["user:0".to_string()]);
+        backend.db().rename("user:1", "renamed", false)?;
+        let (next, keys) = backend.db().scan(0, Some("*"), 1000, None);
+        assert_eq!(next, 0);
+        assert!(keys.contains(&"renamed".to_string()));
+        assert!(!keys.iter().any(|k| k == "user:0" || k == "user:1"));
+
+        // a page without keys is an empty array, not a null one
+        let scan = Scan {
+            cursor: 0,
+            pattern: Some("nothing:*".to_string()),
+            count: 1000,
+            key_type: None,
+        };
+        assert_eq!(
+            scan.execute(&backend).encode(),
+            b"*2\r\n$1\r\n0\r\n*0\r\n".to_vec()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_execute() -> Result<()> {
+        let backend = setup()?;
+        let cmd = Move {
+            key: "set".to_string(),
+            db: 1,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.db().key_type("set"), "none");
+        assert_eq!(backend.db_at(1)?.key_type("set"), "set");
+
+        // the key already exists in the target database
+        backend.db().sadd("set".to_string(), keys(&["c"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(backend.db().sismember("set", "c"), Ok(true));
+
+        let cmd = Move {
+            key: "string".to_string(),
+            db: 16,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            BackendError::DbIndexOutOfRange.into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_randomkey_execute() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(RandomKey.execute(&backend), REST_NIL.clone());
+        let backend = setup()?;
+        match RandomKey.execute(&backend) {
+            RespFrame::BulkString(key) => {
+                assert!(["string", "hash", "set"].contains(&String::from_utf8_lossy(&key).as_ref()))
+            }
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+
+        // expired keys are never picked
+        let backend = Backend::new();
+        for i in 0..10 {
+            let key = format!("expired:{}", i);
+            backend.db().set(key.clone(), BulkString::new("v").into());
+            backend.db().expire_at(&key, Some(1));
+        }
+        backend
+            .db()
+            .set("live".to_string(), BulkString::new("v").into());
+        for _ in 0..10 {
+            assert_eq!(RandomKey.execute(&backend), BulkString::new("live").into());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_type() -> Result<()> {
+        let backend = setup()?;
+        assert_eq!(
+            backend.db().sadd("hash".to_string(), keys(&["a"])),
+            Err(crate::BackendError::WrongType)
+        );
+        assert_eq!(backend.db().get("set"), Err(crate::BackendError::WrongType));
+        Ok(())
+    }
+}
@@ -0,0 +1,809 @@
+use crate::{
+    command_name, extract_args, extract_int, extract_string, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BulkString, CommandError, CommandExecutor, ListEnd,
+    RespArray, RespFrame,
+};
+
+use super::{REST_NIL, REST_NIL_ARRAY, REST_OK};
+
+// LPUSH, RPUSH, LPUSHX and RPUSHX
+#[derive(Debug)]
+pub struct ListPush {
+    key: String,
+    end: ListEnd,
+    create: bool,
+    values: Vec<RespFrame>,
+}
+
+// LPOP and RPOP
+#[derive(Debug)]
+pub struct ListPop {
+    key: String,
+    end: ListEnd,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: String,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LSet {
+    key: String,
+    index: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LInsert {
+    key: String,
+    before: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LRem {
+    key: String,
+    count: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LTrim {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LPos {
+    key: String,
+    element: RespFrame,
+    rank: i64,
+    count: Option<usize>,
+    maxlen: usize,
+}
+
+#[derive(Debug)]
+pub struct LMove {
+    src: String,
+    dst: String,
+    from: ListEnd,
+    to: ListEnd,
+}
+
+#[derive(Debug)]
+pub struct LMPop {
+    keys: Vec<String>,
+    end: ListEnd,
+    count: usize,
+}
+
+impl CommandExecutor for ListPush {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .push(self.key.clone(), self.end, self.values.clone(), self.create)
+        {
+            Ok(len) => {
+                backend.signal_ready(&self.key);
+                RespFrame::Integer(len)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ListPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .pop(&self.key, self.end, self.count.unwrap_or(1))
+        {
+            // with a count the reply is an array, a missing key a null array
+            Ok(Some(values)) if self.count.is_some() => RespArray::new(values).into(),
+            Ok(None) if self.count.is_some() => REST_NIL_ARRAY.clone(),
+            Ok(values) => values
+                .and_then(|v| v.into_iter().next())
+                .unwrap_or_else(|| REST_NIL.clone()),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LLen {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().llen(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LRange {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().lrange(&self.key, self.start, self.stop) {
+            Ok(values) => RespArray::new(values).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().lindex(&self.key, self.index) {
+            Ok(Some(value)) => value,
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LSet {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().lset(&self.key, self.index, self.value.clone()) {
+            Ok(()) => REST_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .linsert(&self.key, self.before, &self.pivot, self.value.clone())
+        {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LRem {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().lrem(&self.key, self.count, &self.value) {
+            Ok(removed) => RespFrame::Integer(removed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LTrim {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().ltrim(&self.key, self.start, self.stop) {
+            Ok(()) => REST_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LPos {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let count = self.count.unwrap_or(1);
+        match backend
+            .db()
+            .lpos(&self.key, &self.element, self.rank, count, self.maxlen)
+        {
+            Ok(positions) if self.count.is_some() => RespArray::new(
+                positions
+                    .into_iter()
+                    .map(RespFrame::Integer)
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            Ok(positions) => positions
+                .first()
+                .map(|p| RespFrame::Integer(*p))
+                .unwrap_or_else(|| REST_NIL.clone()),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LMove {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().lmove(&self.src, &self.dst, self.from, self.to) {
+            Ok(Some(value)) => {
+                backend.signal_ready(&self.dst);
+                value
+            }
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LMPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().lmpop(&self.keys, self.end, self.count) {
+            Ok(popped) => lmpop_reply(popped),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// the key together with the popped elements, or a null array if nothing was popped
+pub fn lmpop_reply(popped: Option<(String, Vec<RespFrame>)>) -> RespFrame {
+    match popped {
+        Some((key, values)) => RespArray::new(vec![
+            BulkString::new(key).into(),
+            RespArray::new(values).into(),
+        ])
+        .into(),
+        None => REST_NIL_ARRAY.clone(),
+    }
+}
+
+//lpush :"*4\r\n$5\r\nlpush\r\n$3\r\nkey\r\n$1\r\na\r\n$1\r\nb\r\n"
+impl TryFrom<RespArray> for ListPush {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["lpush", "rpush", "lpushx", "rpushx"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        Ok(ListPush {
+            key: next_key(&mut args)?,
+            end: if name.starts_with('l') {
+                ListEnd::Left
+            } else {
+                ListEnd::Right
+            },
+            create: !name.ends_with('x'),
+            values: args.collect(),
+        })
+    }
+}
+
+//lpop :"*3\r\n$4\r\nlpop\r\n$3\r\nkey\r\n$1\r\n2\r\n"
+impl TryFrom<RespArray> for ListPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["lpop", "rpop"]);
+        validate_command_minimum_length(&value, &[name], 1)?;
+        if value.len() > 3 {
+            return Err(syntax_error());
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let count = match args.next().map(extract_int).transpose()? {
+            Some(count) if count < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "value is out of range, must be positive".to_string(),
+                ))
+            }
+            count => count.map(|c| c as usize),
+        };
+        Ok(ListPop {
+            key,
+            end: if name == "lpop" {
+                ListEnd::Left
+            } else {
+                ListEnd::Right
+            },
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["llen"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        Ok(LLen {
+            key: next_key(&mut args)?,
+        })
+    }
+}
+
+//lrange :"*4\r\n$6\r\nlrange\r\n$3\r\nkey\r\n$1\r\n0\r\n$2\r\n-1\r\n"
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["lrange"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let (start, stop) = (next_int(&mut args)?, next_int(&mut args)?);
+        Ok(LRange { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["lindex"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        Ok(LIndex {
+            key,
+            index: next_int(&mut args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LSet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["lset"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let index = next_int(&mut args)?;
+        Ok(LSet {
+            key,
+            index,
+            value: args.next().ok_or_else(syntax_error)?,
+        })
+    }
+}
+
+//linsert :"*5\r\n$7\r\nlinsert\r\n$3\r\nkey\r\n$6\r\nbefore\r\n$1\r\na\r\n$1\r\nb\r\n"
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["linsert"], 4)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let before = match next_key(&mut args)?.to_ascii_lowercase().as_str() {
+            "before" => true,
+            "after" => false,
+            _ => return Err(syntax_error()),
+        };
+        match (args.next(), args.next()) {
+            (Some(pivot), Some(value)) => Ok(LInsert {
+                key,
+                before,
+                pivot,
+                value,
+            }),
+            _ => Err(syntax_error()),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["lrem"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let count = next_int(&mut args)?;
+        Ok(LRem {
+            key,
+            count,
+            value: args.next().ok_or_else(syntax_error)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LTrim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["ltrim"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let (start, stop) = (next_int(&mut args)?, next_int(&mut args)?);
+        Ok(LTrim { key, start, stop })
+    }
+}
+
+//lpos :"*5\r\n$4\r\nlpos\r\n$3\r\nkey\r\n$1\r\na\r\n$4\r\nrank\r\n$2\r\n-1\r\n"
+impl TryFrom<RespArray> for LPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["lpos"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let element = args.next().ok_or_else(syntax_error)?;
+        let (mut rank, mut count, mut maxlen) = (1, None, 0);
+        while let Some(option) = args.next() {
+            let option = extract_string(option)?.to_ascii_lowercase();
+            let value = next_int(&mut args)?;
+            match option.as_str() {
+                "rank" if value == 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                    ))
+                }
+                "rank" if value == i64::MIN => {
+                    return Err(CommandError::InvalidArgument(
+                        "value is out of range".to_string(),
+                    ))
+                }
+                "rank" => rank = value,
+                "count" if value < 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "COUNT can't be negative".to_string(),
+                    ))
+                }
+                "count" => count = Some(value as usize),
+                "maxlen" if value < 0 => {
+                    return Err(CommandError::InvalidArgument(
+                        "MAXLEN can't be negative".to_string(),
+                    ))
+                }
+                "maxlen" => maxlen = value as usize,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(LPos {
+            key,
+            element,
+            rank,
+            count,
+            maxlen,
+        })
+    }
+}
+
+//lmove :"*5\r\n$5\r\nlmove\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$4\r\nleft\r\n$5\r\nright\r\n"
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["lmove"], 4)?;
+        let mut args = extract_args(value)?.into_iter();
+        let src = next_key(&mut args)?;
+        let dst = next_key(&mut args)?;
+        Ok(LMove {
+            src,
+            dst,
+            from: parse_list_end(&mut args)?,
+            to: parse_list_end(&mut args)?,
+        })
+    }
+}
+
+//lmpop :"*4\r\n$5\r\nlmpop\r\n$1\r\n1\r\n$3\r\nkey\r\n$4\r\nleft\r\n"
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["lmpop"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let (keys, end, count) = parse_lmpop_args(&mut args)?;
+        Ok(LMPop { keys, end, count })
+    }
+}
+
+// LEFT or RIGHT
+pub fn parse_list_end(args: &mut impl Iterator<Item = RespFrame>) -> Result<ListEnd, CommandError> {
+    match next_key(args)?.to_ascii_lowercase().as_str() {
+        "left" => Ok(ListEnd::Left),
+        "right" => Ok(ListEnd::Right),
+        _ => Err(syntax_error()),
+    }
+}
+
+// `numkeys key [key ...] LEFT|RIGHT [COUNT count]` shared by LMPOP and BLMPOP
+pub fn parse_lmpop_args(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Vec<String>, ListEnd, usize), CommandError> {
+    let numkeys = match next_int(args)? {
+        n if n > 0 => n as usize,
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ))
+        }
+    };
+    let keys = args
+        .take(numkeys)
+        .map(extract_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.len() < numkeys {
+        return Err(syntax_error());
+    }
+    let end = parse_list_end(args)?;
+    let count = match args.next().map(extract_string).transpose()? {
+        None => 1,
+        Some(option) if option.eq_ignore_ascii_case("count") => match next_int(args)? {
+            count if count > 0 => count as usize,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "count should be greater than 0".to_string(),
+                ))
+            }
+        },
+        Some(_) => return Err(syntax_error()),
+    };
+    if args.next().is_some() {
+        return Err(syntax_error());
+    }
+    Ok((keys, end, count))
+}
+
+fn next_key(args: &mut impl Iterator<Item = RespFrame>) -> Result<String, CommandError> {
+    match args.next() {
+        Some(key) => extract_string(key),
+        None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+fn next_int(args: &mut impl Iterator<Item = RespFrame>) -> Result<i64, CommandError> {
+    extract_int(args.next().ok_or_else(syntax_error)?)
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+    use crate::BackendError;
+    use anyhow::Result;
+
+    fn frames(values: &[&str]) -> Vec<RespFrame> {
+        values.iter().map(|v| BulkString::new(*v).into()).collect()
+    }
+
+    fn push(backend: &Backend, key: &str, values: &[&str]) {
+        backend
+            .db()
+            .push(key.to_string(), ListEnd::Right, frames(values), true)
+            .unwrap();
+    }
+
+    fn range(backend: &Backend, key: &str) -> Vec<RespFrame> {
+        backend.db().lrange(key, 0, -1).unwrap()
+    }
+
+    #[test]
+    fn test_push_pop_command_tryfrom() -> Result<()> {
+        let cmd = ListPush::try_from(decode(
+            b"*4\r\n$6\r\nRPUSHX\r\n$3\r\nkey\r\n$1\r\na\r\n$1\r\nb\r\n",
+        )?)?;
+        assert_eq!(cmd.end, ListEnd::Right);
+        assert!(!cmd.create);
+        assert_eq!(cmd.values, frames(&["a", "b"]));
+
+        let cmd = ListPop::try_from(decode(b"*3\r\n$4\r\nlpop\r\n$3\r\nkey\r\n$1\r\n2\r\n")?)?;
+        assert_eq!(cmd.end, ListEnd::Left);
+        assert_eq!(cmd.count, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_pop_execute() -> Result<()> {
+        let backend = Backend::new();
+        let lpushx = ListPush {
+            key: "key".to_string(),
+            end: ListEnd::Left,
+            create: false,
+            values: frames(&["a"]),
+        };
+        assert_eq!(lpushx.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(backend.db().key_type("key"), "none");
+
+        let lpush = ListPush {
+            create: true,
+            values: frames(&["a", "b", "c"]),
+            ..lpushx
+        };
+        assert_eq!(lpush.execute(&backend), RespFrame::Integer(3));
+        assert_eq!(range(&backend, "key"), frames(&["c", "b", "a"]));
+
+        let rpop = ListPop {
+            key: "key".to_string(),
+            end: ListEnd::Right,
+            count: None,
+        };
+        assert_eq!(rpop.execute(&backend), BulkString::new("a").into());
+        let lpop = ListPop {
+            key: "key".to_string(),
+            end: ListEnd::Left,
+            count: Some(5),
+        };
+        assert_eq!(
+            lpop.execute(&backend),
+            RespArray::new(frames(&["c", "b"])).into()
+        );
+        assert_eq!(backend.db().key_type("key"), "none");
+        assert_eq!(rpop.execute(&backend), REST_NIL.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lrange_lindex_lset() -> Result<()> {
+        let backend = Backend::new();
+        push(&backend, "key", &["a", "b", "c", "d"]);
+        let cmd = LRange {
+            key: "key".to_string(),
+            start: 1,
+            stop: -2,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(frames(&["b", "c"])).into()
+        );
+
+        let cmd = LIndex {
+            key: "key".to_string(),
+            index: -1,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::new("d").into());
+
+        let cmd = LSet {
+            key: "key".to_string(),
+            index: 0,
+            value: BulkString::new("x").into(),
+        };
+        assert_eq!(cmd.execute(&backend), REST_OK.clone());
+        let cmd = LSet {
+            key: "key".to_string(),
+            index: 10,
+            value: BulkString::new("x").into(),
+        };
+        assert_eq!(cmd.execute(&backend), BackendError::IndexOutOfRange.into());
+        let cmd = LSet {
+            key: "missing".to_string(),
+            index: 0,
+            value: BulkString::new("x").into(),
+        };
+        assert_eq!(cmd.execute(&backend), BackendError::NoSuchKey.into());
+        assert_eq!(range(&backend, "key"), frames(&["x", "b", "c", "d"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_linsert_lrem_ltrim() -> Result<()> {
+        let backend = Backend::new();
+        push(&backend, "key", &["a", "b", "a", "c", "a"]);
+        let cmd = LInsert {
+            key: "key".to_string(),
+            before: false,
+            pivot: BulkString::new("c").into(),
+            value: BulkString::new("d").into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(6));
+        let cmd = LInsert {
+            pivot: BulkString::new("z").into(),
+            ..cmd
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-1));
+
+        let cmd = LRem {
+            key: "key".to_string(),
+            count: -2,
+            value: BulkString::new("a").into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(range(&backend, "key"), frames(&["a", "b", "c", "d"]));
+
+        let cmd = LTrim {
+            key: "key".to_string(),
+            start: 1,
+            stop: 2,
+        };
+        assert_eq!(cmd.execute(&backend), REST_OK.clone());
+        assert_eq!(range(&backend, "key"), frames(&["b", "c"]));
+        let cmd = LTrim {
+            key: "key".to_string(),
+            start: 5,
+            stop: 10,
+        };
+        cmd.execute(&backend);
+        assert_eq!(backend.db().key_type("key"), "none");
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos_execute() -> Result<()> {
+        let backend = Backend::new();
+        push(&backend, "key", &["a", "b", "c", "1", "2", "3", "c", "c"]);
+        let lpos = |rank, count, maxlen| LPos {
+            key: "key".to_string(),
+            element: BulkString::new("c").into(),
+            rank,
+            count,
+            maxlen,
+        };
+        assert_eq!(lpos(1, None, 0).execute(&backend), RespFrame::Integer(2));
+        assert_eq!(lpos(-1, None, 0).execute(&backend), RespFrame::Integer(7));
+        assert_eq!(
+            lpos(2, Some(0), 0).execute(&backend),
+            RespArray::new(vec![RespFrame::Integer(6), RespFrame::Integer(7)]).into()
+        );
+        assert_eq!(lpos(1, None, 2).execute(&backend), REST_NIL.clone());
+
+        let cmd = decode(b"*5\r\n$4\r\nlpos\r\n$3\r\nkey\r\n$1\r\nc\r\n$4\r\nrank\r\n$1\r\n0\r\n")?;
+        assert!(LPos::try_from(cmd).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_lmpop() -> Result<()> {
+        let backend = Backend::new();
+        push(&backend, "src", &["a", "b"]);
+        let cmd = LMove::try_from(decode(
+            b"*5\r\n$5\r\nlmove\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$5\r\nRIGHT\r\n$4\r\nleft\r\n",
+        )?)?;
+        assert_eq!(cmd.execute(&backend), BulkString::new("b").into());
+        assert_eq!(range(&backend, "dst"), frames(&["b"]));
+
+        backend
+            .db()
+            .set("string".to_string(), BulkString::new("v").into());
+        let cmd = LMove {
+            src: "src".to_string(),
+            dst: "string".to_string(),
+            from: ListEnd::Left,
+            to: ListEnd::Left,
+        };
+        assert_eq!(cmd.execute(&backend), BackendError::WrongType.into());
+        assert_eq!(range(&backend, "src"), frames(&["a"]));
+
+        let cmd = LMPop::try_from(decode(
+            b"*7\r\n$5\r\nlmpop\r\n$1\r\n2\r\n$7\r\nmissing\r\n$3\r\ndst\r\n$4\r\nleft\r\n$5\r\ncount\r\n$1\r\n5\r\n",
+        )?)?;
+        assert_eq!(
+            cmd.execute(&backend),
+            lmpop_reply(Some(("dst".to_string(), frames(&["b"]))))
+        );
+        assert_eq!(cmd.execute(&backend), REST_NIL_ARRAY.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_keeps_elements_when_dst_changes_type() -> Result<()> {
+        let backend = Backend::new();
+        let db = backend.db();
+        let count = 50_000;
+        let values = (0..count).map(|i| i.to_string()).collect::<Vec<_>>();
+        push(
+            &backend,
+            "src",
+            &values.iter().map(|v| v.as_str()).collect::<Vec<_>>(),
+        );
+        // `dst` keeps switching between a string and nothing while the elements move
+        let done = std::sync::atomic::AtomicBool::new(false);
+        let moved = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                    db.set("dst".to_string(), BulkString::new("v").into());
+                    db.del(&["dst".to_string()]);
+                }
+            });
+            let moved = (0..count)
+                .filter(|_| {
+                    matches!(
+                        db.lmove("src", "dst", ListEnd::Left, ListEnd::Right),
+                        Ok(Some(_))
+                    )
+                })
+                .count();
+            done.store(true, std::sync::atomic::Ordering::Relaxed);
+            moved
+        });
+        // an element that could not be moved is still in `src`
+        assert_eq!(db.llen("src")? as usize + moved, count);
+        Ok(())
+    }
+}
@@ -18,16 +18,17 @@ pub struct Set {
 
 impl CommandExecutor for Get {
     fn execute(&self, backend: &Backend) -> RespFrame {
-        match backend.get(&self.key) {
-            Some(value) => value,
-            None => REST_NIL.clone(),
+        match backend.db().get(&self.key) {
+            Ok(Some(value)) => value,
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for Set {
     fn execute(&self, backend: &Backend) -> RespFrame {
-        backend.set(self.key.clone(), self.value.clone());
+        backend.db().set(self.key.clone(), self.value.clone());
         REST_OK.clone()
     }
 }
@@ -69,7 +70,7 @@ impl TryFrom<RespArray> for Set {
 mod tests {
     use super::*;
     use crate::resp::RespDecode;
-    use crate::BulkString;
+    use crate::{BulkString, RespEncode};
     use anyhow::{Ok, Result};
     use bytes::BytesMut;
 
@@ -108,5 +109,20 @@ mod tests {
         };
         let resp = get.execute(&backend);
         assert_eq!(resp, BulkString::new("world").into());
+
+        // an empty value is an empty bulk string, only a missing key is null
+        let set = Set {
+            key: "empty".to_string(),
+            value: BulkString::new("").into(),
+        };
+        set.execute(&backend);
+        let get = Get {
+            key: "empty".to_string(),
+        };
+        assert_eq!(get.execute(&backend).encode(), b"$0\r\n\r\n");
+        let get = Get {
+            key: "missing".to_string(),
+        };
+        assert_eq!(get.execute(&backend).encode(), b"$-1\r\n");
     }
 }
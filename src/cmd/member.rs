@@ -1,6 +1,12 @@
 use crate::{
-    extract_args, validate_command_exact_length, validate_command_minimum_length, Backend,
-    CommandError, CommandExecutor, RespArray, RespFrame,
+    extract_args, extract_int, extract_string, extract_strings, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BulkString, CommandError, CommandExecutor, RespArray,
+    RespFrame, RespSet,
+};
+
+use super::{
+    keyspace::{parse_scan_args, scan_reply},
+    REST_NIL,
 };
 
 #[derive(Debug)]
@@ -15,27 +21,184 @@ pub struct SISMember {
     field: String,
 }
 
+#[derive(Debug)]
+pub struct SRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SMembers {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SPop {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct SRandMember {
+    key: String,
+    count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SMove {
+    src: String,
+    dst: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct SScan {
+    key: String,
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+}
+
 impl CommandExecutor for Sadd {
     fn execute(&self, backend: &Backend) -> RespFrame {
-        let res = backend.sadd(self.key.clone(), self.members.clone());
-        RespFrame::Integer(res)
+        match backend.db().sadd(self.key.clone(), self.members.clone()) {
+            Ok(res) => RespFrame::Integer(res),
+            Err(e) => e.into(),
+        }
     }
 }
 
 impl CommandExecutor for SISMember {
     fn execute(&self, backend: &Backend) -> RespFrame {
-        match backend.sismember(&self.key, &self.field) {
-            Some(i) => RespFrame::Integer(i),
-            None => RespFrame::Integer(0),
+        match backend.db().sismember(&self.key, &self.field) {
+            Ok(found) => RespFrame::Integer(found as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SRem {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().srem(&self.key, &self.members) {
+            Ok(removed) => RespFrame::Integer(removed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMembers {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().smembers(&self.key) {
+            Ok(members) => {
+                let members = bulk_strings(members);
+                // RESP3 clients get a real set type
+                if backend.session().is_resp3() {
+                    RespSet::new(members).into()
+                } else {
+                    RespArray::new(members).into()
+                }
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SCard {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().scard(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
         }
     }
 }
 
+impl CommandExecutor for SMIsMember {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().smismember(&self.key, &self.members) {
+            Ok(found) => {
+                let found = found
+                    .into_iter()
+                    .map(|f| RespFrame::Integer(f as i64))
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(found).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().spop(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) if self.count.is_some() => RespArray::new(bulk_strings(members)).into(),
+            Ok(members) => single_member(members),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SRandMember {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().srandmember(&self.key, self.count) {
+            Ok(members) if self.count.is_some() => RespArray::new(bulk_strings(members)).into(),
+            Ok(members) => single_member(members),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().smove(&self.src, &self.dst, &self.member) {
+            Ok(moved) => RespFrame::Integer(moved as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SScan {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .sscan(&self.key, self.cursor, self.pattern.as_deref(), self.count)
+        {
+            Ok((cursor, members)) => scan_reply(cursor, bulk_strings(members)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn bulk_strings(values: Vec<String>) -> Vec<RespFrame> {
+    values
+        .into_iter()
+        .map(|v| BulkString::new(v).into())
+        .collect()
+}
+
+// the reply of SPOP and SRANDMEMBER without a count
+fn single_member(members: Vec<String>) -> RespFrame {
+    match members.into_iter().next() {
+        Some(member) => BulkString::new(member).into(),
+        None => REST_NIL.clone(),
+    }
+}
+
 impl TryFrom<RespArray> for Sadd {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command_minimum_length(&value, &["sadd"], 3)?;
+        validate_command_minimum_length(&value, &["sadd"], 2)?;
         let mut args = extract_args(value)?.into_iter();
         let key = args.next();
         let members: Vec<String> = args
@@ -75,6 +238,144 @@ impl TryFrom<RespArray> for SISMember {
     }
 }
 
+impl TryFrom<RespArray> for SRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["srem"], 2)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(SRem {
+                key,
+                members: args.collect(),
+            }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["smembers"], 1)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(SMembers { key }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["scard"], 1)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(SCard { key }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["smismember"], 2)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(SMIsMember {
+                key,
+                members: args.collect(),
+            }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+//spop :"*3\r\n$4\r\nspop\r\n$3\r\nkey\r\n$1\r\n2\r\n"
+impl TryFrom<RespArray> for SPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["spop"], 1)?;
+        if value.len() > 3 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let count = match args.next().map(extract_int).transpose()? {
+            Some(count) if count < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "value is out of range, must be positive".to_string(),
+                ))
+            }
+            count => count.map(|c| c as usize),
+        };
+        Ok(SPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["srandmember"], 1)?;
+        if value.len() > 3 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let count = args.next().map(extract_int).transpose()?;
+        Ok(SRandMember { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["smove"], 3)?;
+        let mut args = extract_strings(value)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(src), Some(dst), Some(member)) => Ok(SMove { src, dst, member }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+//sscan :"*3\r\n$5\r\nsscan\r\n$3\r\nkey\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for SScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["sscan"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_string(key)?,
+            None => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let (cursor, pattern, count) = parse_scan_args(&mut args, |_, _| {
+            Err(CommandError::InvalidArgument("syntax error".to_string()))
+        })?;
+        Ok(SScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Ok;
@@ -90,6 +391,15 @@ mod tests {
         let cmd = Sadd::try_from(frames).unwrap();
         assert_eq!(cmd.key, "key");
         assert_eq!(cmd.members, vec!["123".to_string(), "345".to_string()]);
+
+        // a single member is enough
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nsadd\r\n$3\r\nkey\r\n$3\r\n123\r\n");
+        let cmd = Sadd::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.members, vec!["123".to_string()]);
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$4\r\nsadd\r\n$3\r\nkey\r\n");
+        assert!(Sadd::try_from(RespArray::decode(&mut buf)?).is_err());
         Ok(())
     }
 
@@ -136,4 +446,208 @@ mod tests {
         assert_eq!(res, RespFrame::Integer(1));
         Ok(())
     }
+
+    fn sadd(backend: &Backend, key: &str, members: &[&str]) {
+        Sadd {
+            key: key.to_string(),
+            members: members.iter().map(|m| m.to_string()).collect(),
+        }
+        .execute(backend);
+    }
+
+    fn sorted(frame: RespFrame) -> Vec<RespFrame> {
+        let mut members = match frame {
+            RespFrame::Array(array) => array.0,
+            RespFrame::Set(set) => set.0,
+            frame => panic!("unexpected reply {:?}", frame),
+        };
+        members.sort_by_key(|m| format!("{:?}", m));
+        members
+    }
+
+    #[test]
+    fn test_srem_removes_empty_set() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        sadd(&backend, "key", &["a", "b"]);
+        let cmd = SRem {
+            key: "key".to_string(),
+            members: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.db().key_type("key"), "none");
+        Ok(())
+    }
+
+    #[test]
+    fn test_smembers_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        sadd(&backend, "key", &["a", "b"]);
+        let cmd = SMembers {
+            key: "key".to_string(),
+        };
+        let expected = vec![BulkString::new("a").into(), BulkString::new("b").into()];
+        let reply = cmd.execute(&backend);
+        assert!(matches!(reply, RespFrame::Array(_)));
+        assert_eq!(sorted(reply), expected);
+
+        backend.session().set_protocol(3);
+        let reply = cmd.execute(&backend);
+        assert!(matches!(reply, RespFrame::Set(_)));
+        assert_eq!(sorted(reply), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scard_smismember_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        sadd(&backend, "key", &["a", "b"]);
+        let cmd = SCard {
+            key: "key".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd = SMIsMember {
+            key: "key".to_string(),
+            members: vec!["a".to_string(), "c".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![RespFrame::Integer(1), RespFrame::Integer(0)]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        sadd(&backend, "key", &["a", "b", "c"]);
+        let cmd = SPop {
+            key: "key".to_string(),
+            count: Some(2),
+        };
+        assert_eq!(sorted(cmd.execute(&backend)).len(), 2);
+        let cmd = SPop {
+            key: "key".to_string(),
+            count: None,
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::BulkString(_)));
+        assert_eq!(backend.db().key_type("key"), "none");
+        assert_eq!(cmd.execute(&backend), REST_NIL.clone());
+
+        let mut buf = BytesMut::from(&b"*3\r\n$4\r\nspop\r\n$3\r\nkey\r\n$2\r\n-1\r\n"[..]);
+        assert!(SPop::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_srandmember_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        sadd(&backend, "key", &["a", "b", "c"]);
+        let cmd = SRandMember {
+            key: "key".to_string(),
+            count: Some(5),
+        };
+        assert_eq!(sorted(cmd.execute(&backend)).len(), 3);
+        // a negative count may return the same member several times
+        let cmd = SRandMember {
+            key: "key".to_string(),
+            count: Some(-5),
+        };
+        assert_eq!(sorted(cmd.execute(&backend)).len(), 5);
+        assert_eq!(backend.db().scard("key")?, 3);
+        // a count too large to reply with is refused
+        let cmd = SRandMember {
+            key: "key".to_string(),
+            count: Some(-100_000_000_000),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            crate::BackendError::RandomCountOutOfRange.into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        sadd(&backend, "src", &["a"]);
+        let cmd = SMove {
+            src: "src".to_string(),
+            dst: "dst".to_string(),
+            member: "a".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(backend.db().key_type("src"), "none");
+        assert!(backend.db().sismember("dst", "a")?);
+
+        backend
+            .db()
+            .set("string".to_string(), BulkString::new("v").into());
+        let cmd = SMove {
+            src: "dst".to_string(),
+            dst: "string".to_string(),
+            member: "a".to_string(),
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        assert!(backend.db().sismember("dst", "a")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove_keeps_members_when_dst_changes_type() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let db = backend.db();
+        let members = (0..50_000).map(|i| i.to_string()).collect::<Vec<_>>();
+        db.sadd("src".to_string(), members.clone())?;
+        // `dst` keeps switching between a string and nothing while the members move
+        let done = std::sync::atomic::AtomicBool::new(false);
+        let moved = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                    db.set("dst".to_string(), BulkString::new("v").into());
+                    db.del(&["dst".to_string()]);
+                }
+            });
+            let moved = members
+                .iter()
+                .filter(|member| db.smove("src", "dst", member).unwrap_or(false))
+                .count();
+            done.store(true, std::sync::atomic::Ordering::Relaxed);
+            moved
+        });
+        // a member that could not be moved is still in `src`
+        assert_eq!(db.scard("src")? as usize + moved, members.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sscan_execute() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let members = (0..30).map(|i| format!("m{}", i)).collect::<Vec<_>>();
+        backend.db().sadd("key".to_string(), members)?;
+        let mut cursor = 0;
+        let mut seen = 0;
+        loop {
+            let cmd = SScan {
+                key: "key".to_string(),
+                cursor,
+                pattern: None,
+                count: 4,
+            };
+            let RespFrame::Array(reply) = cmd.execute(&backend) else {
+                panic!("unexpected reply");
+            };
+            let (RespFrame::BulkString(next), RespFrame::Array(members)) = (&reply[0], &reply[1])
+            else {
+                panic!("unexpected reply");
+            };
+            seen += members.len();
+            cursor = String::from_utf8_lossy(next).parse()?;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(seen, 30);
+        Ok(())
+    }
 }
@@ -1,23 +1,84 @@
+mod bitmap;
+mod blocking;
+mod client;
+mod config;
+mod connection;
+mod db;
 mod echo;
+mod function;
+mod geo;
+mod hexpire;
 mod hmap;
+mod hyperloglog;
+mod keyspace;
+mod list;
 mod map;
 mod member;
+mod pubsub;
+mod script;
+mod setop;
+mod stream;
+mod stream_group;
+mod transaction;
+mod zset;
+mod zsetop;
 
-use crate::{backend::Backend, BulkString, RespArray, RespError, RespFrame, SimpleString};
+use std::{future::Future, time::Duration};
+
+use crate::{
+    backend::Backend, RespArray, RespError, RespFrame, RespNullArray, RespNullBulkString,
+    SimpleError, SimpleString,
+};
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
 use thiserror::Error;
 
 use self::{
+    bitmap::{BitCount, BitField, BitOp, BitPos, GetBit, SetBit},
+    blocking::{BLMPop, BLMove, BListPop, BZMPop, BZPop},
+    client::Client,
+    config::Config,
+    connection::{Hello, Ping, Quit, Reset, Time},
+    db::{DbSize, FlushAll, FlushDb, Select, SwapDb},
     echo::Echo,
-    hmap::{HGet, HGetAll, HMGet, HSet},
+    function::{FCall, Function},
+    geo::{GeoAdd, GeoDist, GeoHash, GeoPos, GeoSearch, GeoSearchStore},
+    hexpire::{HExpire, HGetEx, HPersist, HSetEx, HTtl},
+    hmap::{
+        HDel, HExists, HGet, HGetAll, HIncrBy, HIncrByFloat, HKeys, HLen, HMGet, HRandField, HScan,
+        HSet, HSetNx, HStrLen, HVals,
+    },
+    hyperloglog::{PfAdd, PfCount, PfMerge},
+    keyspace::{
+        CopyKey, Del, Exists, KeyType, Keys, Move, RandomKey, Rename, RenameNx, Scan, Touch, Unlink,
+    },
+    list::{
+        LIndex, LInsert, LLen, LMPop, LMove, LPos, LRange, LRem, LSet, LTrim, ListPop, ListPush,
+    },
     map::{Get, Set},
-    member::{SISMember, Sadd},
+    member::{SCard, SISMember, SMIsMember, SMembers, SMove, SPop, SRandMember, SRem, SScan, Sadd},
+    pubsub::{PubSub, Publish, Subscribe, Unsubscribe},
+    script::{Eval, Script},
+    setop::{SInterCard, SetOp, SetOpStore},
+    stream::{XAdd, XDel, XLen, XRange, XRead, XTrim},
+    stream_group::{XAck, XAutoClaim, XClaim, XGroup, XInfo, XPending, XReadGroup},
+    transaction::{Discard, Exec, Multi, Unwatch, Watch},
+    zset::{
+        ZAdd, ZCard, ZCount, ZIncrBy, ZLexCount, ZMPop, ZMScore, ZPop, ZRandMember, ZRange,
+        ZRangeStore, ZRank, ZRem, ZRemRange, ZScan, ZScore,
+    },
+    zsetop::{ZInterCard, ZSetOp, ZSetOpStore},
 };
 
+pub(crate) use self::{pubsub::subscribed_context_error, transaction::queue_in_transaction};
+
+// how often a command waiting behind a script checks whether the script turned busy
+const BUSY_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 lazy_static! {
     static ref REST_OK: RespFrame = SimpleString::new("OK").into();
-    static ref REST_NIL: RespFrame = BulkString::new("").into();
+    static ref REST_NIL: RespFrame = RespNullBulkString.into();
+    static ref REST_NIL_ARRAY: RespFrame = RespNullArray.into();
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -30,6 +91,19 @@ pub enum CommandError {
     RespError(#[from] RespError),
 }
 
+// a request that could not be parsed is answered with an error, the connection stays usable
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        let message = match e {
+            CommandError::InvalidCommand(message) | CommandError::InvalidArgument(message) => {
+                message
+            }
+            CommandError::RespError(e) => e.to_string(),
+        };
+        SimpleError::new(format!("ERR {}", message)).into()
+    }
+}
+
 #[enum_dispatch]
 pub trait CommandExecutor {
     fn execute(&self, backend: &Backend) -> RespFrame;
@@ -48,6 +122,311 @@ pub enum Command {
     HMGet(HMGet),
     SADD(Sadd),
     SISMEMBER(SISMember),
+    Del(Del),
+    Unlink(Unlink),
+    Exists(Exists),
+    KeyType(KeyType),
+    Rename(Rename),
+    RenameNx(RenameNx),
+    CopyKey(CopyKey),
+    Touch(Touch),
+    Keys(Keys),
+    Scan(Scan),
+    DbSize(DbSize),
+    RandomKey(RandomKey),
+    FlushDb(FlushDb),
+    FlushAll(FlushAll),
+    Select(Select),
+    Move(Move),
+    SwapDb(SwapDb),
+    SetBit(SetBit),
+    GetBit(GetBit),
+    BitCount(BitCount),
+    BitPos(BitPos),
+    BitOp(BitOp),
+    BitField(BitField),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    GeoAdd(GeoAdd),
+    GeoPos(GeoPos),
+    GeoDist(GeoDist),
+    GeoHash(GeoHash),
+    GeoSearch(GeoSearch),
+    GeoSearchStore(GeoSearchStore),
+    HDel(HDel),
+    HExists(HExists),
+    HLen(HLen),
+    HKeys(HKeys),
+    HVals(HVals),
+    HSetNx(HSetNx),
+    HStrLen(HStrLen),
+    HIncrBy(HIncrBy),
+    HIncrByFloat(HIncrByFloat),
+    HRandField(HRandField),
+    HScan(HScan),
+    HExpire(HExpire),
+    HTtl(HTtl),
+    HPersist(HPersist),
+    HGetEx(HGetEx),
+    HSetEx(HSetEx),
+    Hello(Hello),
+    SRem(SRem),
+    SMembers(SMembers),
+    SCard(SCard),
+    SMIsMember(SMIsMember),
+    SPop(SPop),
+    SRandMember(SRandMember),
+    SMove(SMove),
+    SScan(SScan),
+    SetOp(SetOp),
+    SetOpStore(SetOpStore),
+    SInterCard(SInterCard),
+    ListPush(ListPush),
+    ListPop(ListPop),
+    LLen(LLen),
+    LRange(LRange),
+    LIndex(LIndex),
+    LSet(LSet),
+    LInsert(LInsert),
+    LRem(LRem),
+    LTrim(LTrim),
+    LPos(LPos),
+    LMove(LMove),
+    LMPop(LMPop),
+    BListPop(BListPop),
+    BLMove(BLMove),
+    BLMPop(BLMPop),
+    ZAdd(ZAdd),
+    ZRem(ZRem),
+    ZScore(ZScore),
+    ZMScore(ZMScore),
+    ZIncrBy(ZIncrBy),
+    ZCard(ZCard),
+    ZCount(ZCount),
+    ZRank(ZRank),
+    ZRange(ZRange),
+    ZRangeStore(ZRangeStore),
+    ZPop(ZPop),
+    ZRandMember(ZRandMember),
+    ZScan(ZScan),
+    ZLexCount(ZLexCount),
+    ZRemRange(ZRemRange),
+    ZMPop(ZMPop),
+    ZSetOp(ZSetOp),
+    ZSetOpStore(ZSetOpStore),
+    ZInterCard(ZInterCard),
+    BZPop(BZPop),
+    BZMPop(BZMPop),
+    XAdd(XAdd),
+    XRange(XRange),
+    XLen(XLen),
+    XDel(XDel),
+    XTrim(XTrim),
+    XRead(XRead),
+    XGroup(XGroup),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    XInfo(XInfo),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    Publish(Publish),
+    PubSub(PubSub),
+    Config(Config),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    Eval(Eval),
+    Script(Script),
+    FCall(FCall),
+    Function(Function),
+    Client(Client),
+    Ping(Ping),
+    Quit(Quit),
+    Reset(Reset),
+    Time(Time),
+}
+
+impl Command {
+    // execute the command for a connection, then publish the keyspace events it caused
+    pub async fn run(&self, backend: &Backend) -> RespFrame {
+        let frame = self.run_command(backend).await;
+        backend.publish_keyspace_events();
+        backend.send_invalidations();
+        frame
+    }
+
+    // the blocking commands may park the connection until another one provides data
+    async fn run_command(&self, backend: &Backend) -> RespFrame {
+        if backend.script_busy() && !self.kills_script() {
+            return busy_error();
+        }
+        match self {
+            Command::BListPop(cmd) => cmd.execute_blocking(backend).await,
+            Command::BLMove(cmd) => cmd.execute_blocking(backend).await,
+            Command::BLMPop(cmd) => cmd.execute_blocking(backend).await,
+            Command::BZPop(cmd) => cmd.execute_blocking(backend).await,
+            Command::BZMPop(cmd) => cmd.execute_blocking(backend).await,
+            Command::XRead(cmd) => cmd.execute_blocking(backend).await,
+            Command::XReadGroup(cmd) => cmd.execute_blocking(backend).await,
+            Command::Exec(_) | Command::Eval(_) | Command::FCall(_) => {
+                match lock_unless_busy(backend, backend.exclusive_lock()).await {
+                    Some(_exclusive) => self.execute_tracked(backend),
+                    None => busy_error(),
+                }
+            }
+            // has to get through while a script holds the server
+            cmd if cmd.kills_script() => cmd.execute(backend),
+            cmd => match lock_unless_busy(backend, backend.shared_lock()).await {
+                Some(_shared) => cmd.execute_tracked(backend),
+                None => busy_error(),
+            },
+        }
+    }
+
+    // execute the command, remembering the keys a read command reads for client side caching
+    fn execute_tracked(&self, backend: &Backend) -> RespFrame {
+        backend.run_tracked(!self.is_write(), || self.execute(backend))
+    }
+
+    // SCRIPT KILL and FUNCTION KILL
+    fn kills_script(&self) -> bool {
+        match self {
+            Command::Script(cmd) => cmd.kills(),
+            Command::Function(cmd) => cmd.kills(),
+            _ => false,
+        }
+    }
+
+    // the commands that modify data, which read-only scripts may not call and after which a
+    // script can no longer be killed
+    pub fn is_write(&self) -> bool {
+        match self {
+            Command::BitField(cmd) => cmd.writes(),
+            Command::Set(_)
+            | Command::HSet(_)
+            | Command::SADD(_)
+            | Command::Del(_)
+            | Command::Unlink(_)
+            | Command::Rename(_)
+            | Command::RenameNx(_)
+            | Command::CopyKey(_)
+            | Command::FlushDb(_)
+            | Command::FlushAll(_)
+            | Command::Move(_)
+            | Command::SwapDb(_)
+            | Command::SetBit(_)
+            | Command::BitOp(_)
+            | Command::PfAdd(_)
+            | Command::PfMerge(_)
+            | Command::GeoAdd(_)
+            | Command::GeoSearchStore(_)
+            | Command::HDel(_)
+            | Command::HSetNx(_)
+            | Command::HIncrBy(_)
+            | Command::HIncrByFloat(_)
+            | Command::HExpire(_)
+            | Command::HPersist(_)
+            | Command::HGetEx(_)
+            | Command::HSetEx(_)
+            | Command::SRem(_)
+            | Command::SPop(_)
+            | Command::SMove(_)
+            | Command::SetOpStore(_)
+            | Command::ListPush(_)
+            | Command::ListPop(_)
+            | Command::LSet(_)
+            | Command::LInsert(_)
+            | Command::LRem(_)
+            | Command::LTrim(_)
+            | Command::LMove(_)
+            | Command::LMPop(_)
+            | Command::BListPop(_)
+            | Command::BLMove(_)
+            | Command::BLMPop(_)
+            | Command::ZAdd(_)
+            | Command::ZRem(_)
+            | Command::ZIncrBy(_)
+            | Command::ZRangeStore(_)
+            | Command::ZPop(_)
+            | Command::ZRemRange(_)
+            | Command::ZMPop(_)
+            | Command::ZSetOpStore(_)
+            | Command::BZPop(_)
+            | Command::BZMPop(_)
+            | Command::XAdd(_)
+            | Command::XDel(_)
+            | Command::XTrim(_)
+            | Command::XGroup(_)
+            | Command::XReadGroup(_)
+            | Command::XAck(_)
+            | Command::XClaim(_)
+            | Command::XAutoClaim(_) => true,
+            _ => false,
+        }
+    }
+
+    // scripts cannot change the connection, start transactions or run other scripts
+    pub fn allowed_in_script(&self) -> bool {
+        !matches!(
+            self,
+            Command::Multi(_)
+                | Command::Exec(_)
+                | Command::Discard(_)
+                | Command::Watch(_)
+                | Command::Unwatch(_)
+                | Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::Hello(_)
+                | Command::Config(_)
+                | Command::Eval(_)
+                | Command::Script(_)
+                | Command::FCall(_)
+                | Command::Function(_)
+                | Command::Client(_)
+                | Command::Quit(_)
+                | Command::Reset(_)
+        )
+    }
+
+    // the subscription commands reply through the push queue of the connection
+    pub fn replies_with_pushes(&self) -> bool {
+        matches!(self, Command::Subscribe(_) | Command::Unsubscribe(_))
+    }
+
+    // QUIT closes the connection after its reply
+    pub fn closes_connection(&self) -> bool {
+        matches!(self, Command::Quit(_))
+    }
+}
+
+// wait for `lock`, or give up with None once the script holding the server turns busy while
+// the command waits for it
+async fn lock_unless_busy<G>(backend: &Backend, lock: impl Future<Output = G>) -> Option<G> {
+    tokio::pin!(lock);
+    loop {
+        tokio::select! {
+            guard = &mut lock => return Some(guard),
+            _ = tokio::time::sleep(BUSY_CHECK_INTERVAL) => {
+                if backend.script_busy() {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+fn busy_error() -> RespFrame {
+    SimpleError::new(
+        "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE."
+            .to_string(),
+    )
+    .into()
 }
 
 #[derive(Debug)]
@@ -88,6 +467,155 @@ impl TryFrom<RespArray> for Command {
                 b"hmget" => HMGet::try_from(value).map(Command::HMGet),
                 b"sadd" => Sadd::try_from(value).map(Command::SADD),
                 b"sismember" => SISMember::try_from(value).map(Command::SISMEMBER),
+                b"del" => Del::try_from(value).map(Command::Del),
+                b"unlink" => Unlink::try_from(value).map(Command::Unlink),
+                b"exists" => Exists::try_from(value).map(Command::Exists),
+                b"type" => KeyType::try_from(value).map(Command::KeyType),
+                b"rename" => Rename::try_from(value).map(Command::Rename),
+                b"renamenx" => RenameNx::try_from(value).map(Command::RenameNx),
+                b"copy" => CopyKey::try_from(value).map(Command::CopyKey),
+                b"touch" => Touch::try_from(value).map(Command::Touch),
+                b"keys" => Keys::try_from(value).map(Command::Keys),
+                b"scan" => Scan::try_from(value).map(Command::Scan),
+                b"dbsize" => DbSize::try_from(value).map(Command::DbSize),
+                b"randomkey" => RandomKey::try_from(value).map(Command::RandomKey),
+                b"flushdb" => FlushDb::try_from(value).map(Command::FlushDb),
+                b"flushall" => FlushAll::try_from(value).map(Command::FlushAll),
+                b"select" => Select::try_from(value).map(Command::Select),
+                b"move" => Move::try_from(value).map(Command::Move),
+                b"swapdb" => SwapDb::try_from(value).map(Command::SwapDb),
+                b"setbit" => SetBit::try_from(value).map(Command::SetBit),
+                b"getbit" => GetBit::try_from(value).map(Command::GetBit),
+                b"bitcount" => BitCount::try_from(value).map(Command::BitCount),
+                b"bitpos" => BitPos::try_from(value).map(Command::BitPos),
+                b"bitop" => BitOp::try_from(value).map(Command::BitOp),
+                b"bitfield" | b"bitfield_ro" => BitField::try_from(value).map(Command::BitField),
+                b"subscribe" | b"psubscribe" | b"ssubscribe" => {
+                    Subscribe::try_from(value).map(Command::Subscribe)
+                }
+                b"unsubscribe" | b"punsubscribe" | b"sunsubscribe" => {
+                    Unsubscribe::try_from(value).map(Command::Unsubscribe)
+                }
+                b"publish" | b"spublish" => Publish::try_from(value).map(Command::Publish),
+                b"pubsub" => PubSub::try_from(value).map(Command::PubSub),
+                b"config" => Config::try_from(value).map(Command::Config),
+                b"pfadd" => PfAdd::try_from(value).map(Command::PfAdd),
+                b"pfcount" => PfCount::try_from(value).map(Command::PfCount),
+                b"pfmerge" => PfMerge::try_from(value).map(Command::PfMerge),
+                b"geoadd" => GeoAdd::try_from(value).map(Command::GeoAdd),
+                b"geopos" => GeoPos::try_from(value).map(Command::GeoPos),
+                b"geodist" => GeoDist::try_from(value).map(Command::GeoDist),
+                b"geohash" => GeoHash::try_from(value).map(Command::GeoHash),
+                b"geosearch" => GeoSearch::try_from(value).map(Command::GeoSearch),
+                b"geosearchstore" => GeoSearchStore::try_from(value).map(Command::GeoSearchStore),
+                b"hdel" => HDel::try_from(value).map(Command::HDel),
+                b"hexists" => HExists::try_from(value).map(Command::HExists),
+                b"hlen" => HLen::try_from(value).map(Command::HLen),
+                b"hkeys" => HKeys::try_from(value).map(Command::HKeys),
+                b"hvals" => HVals::try_from(value).map(Command::HVals),
+                b"hsetnx" => HSetNx::try_from(value).map(Command::HSetNx),
+                b"hstrlen" => HStrLen::try_from(value).map(Command::HStrLen),
+                b"hincrby" => HIncrBy::try_from(value).map(Command::HIncrBy),
+                b"hincrbyfloat" => HIncrByFloat::try_from(value).map(Command::HIncrByFloat),
+                b"hrandfield" => HRandField::try_from(value).map(Command::HRandField),
+                b"hscan" => HScan::try_from(value).map(Command::HScan),
+                b"hexpire" | b"hpexpire" | b"hexpireat" | b"hpexpireat" => {
+                    HExpire::try_from(value).map(Command::HExpire)
+                }
+                b"httl" | b"hpttl" | b"hexpiretime" | b"hpexpiretime" => {
+                    HTtl::try_from(value).map(Command::HTtl)
+                }
+                b"hpersist" => HPersist::try_from(value).map(Command::HPersist),
+                b"hgetex" => HGetEx::try_from(value).map(Command::HGetEx),
+                b"hsetex" => HSetEx::try_from(value).map(Command::HSetEx),
+                b"hello" => Hello::try_from(value).map(Command::Hello),
+                b"srem" => SRem::try_from(value).map(Command::SRem),
+                b"smembers" => SMembers::try_from(value).map(Command::SMembers),
+                b"scard" => SCard::try_from(value).map(Command::SCard),
+                b"smismember" => SMIsMember::try_from(value).map(Command::SMIsMember),
+                b"spop" => SPop::try_from(value).map(Command::SPop),
+                b"srandmember" => SRandMember::try_from(value).map(Command::SRandMember),
+                b"smove" => SMove::try_from(value).map(Command::SMove),
+                b"sscan" => SScan::try_from(value).map(Command::SScan),
+                b"sinter" | b"sunion" | b"sdiff" => SetOp::try_from(value).map(Command::SetOp),
+                b"sinterstore" | b"sunionstore" | b"sdiffstore" => {
+                    SetOpStore::try_from(value).map(Command::SetOpStore)
+                }
+                b"sintercard" => SInterCard::try_from(value).map(Command::SInterCard),
+                b"lpush" | b"rpush" | b"lpushx" | b"rpushx" => {
+                    ListPush::try_from(value).map(Command::ListPush)
+                }
+                b"lpop" | b"rpop" => ListPop::try_from(value).map(Command::ListPop),
+                b"llen" => LLen::try_from(value).map(Command::LLen),
+                b"lrange" => LRange::try_from(value).map(Command::LRange),
+                b"lindex" => LIndex::try_from(value).map(Command::LIndex),
+                b"lset" => LSet::try_from(value).map(Command::LSet),
+                b"linsert" => LInsert::try_from(value).map(Command::LInsert),
+                b"lrem" => LRem::try_from(value).map(Command::LRem),
+                b"ltrim" => LTrim::try_from(value).map(Command::LTrim),
+                b"lpos" => LPos::try_from(value).map(Command::LPos),
+                b"lmove" => LMove::try_from(value).map(Command::LMove),
+                b"lmpop" => LMPop::try_from(value).map(Command::LMPop),
+                b"blpop" | b"brpop" => BListPop::try_from(value).map(Command::BListPop),
+                b"blmove" | b"brpoplpush" => BLMove::try_from(value).map(Command::BLMove),
+                b"blmpop" => BLMPop::try_from(value).map(Command::BLMPop),
+                b"zadd" => ZAdd::try_from(value).map(Command::ZAdd),
+                b"zrem" => ZRem::try_from(value).map(Command::ZRem),
+                b"zscore" => ZScore::try_from(value).map(Command::ZScore),
+                b"zmscore" => ZMScore::try_from(value).map(Command::ZMScore),
+                b"zincrby" => ZIncrBy::try_from(value).map(Command::ZIncrBy),
+                b"zcard" => ZCard::try_from(value).map(Command::ZCard),
+                b"zcount" => ZCount::try_from(value).map(Command::ZCount),
+                b"zrank" | b"zrevrank" => ZRank::try_from(value).map(Command::ZRank),
+                b"zrange" | b"zrevrange" | b"zrangebyscore" | b"zrevrangebyscore"
+                | b"zrangebylex" | b"zrevrangebylex" => {
+                    ZRange::try_from(value).map(Command::ZRange)
+                }
+                b"zrangestore" => ZRangeStore::try_from(value).map(Command::ZRangeStore),
+                b"zpopmin" | b"zpopmax" => ZPop::try_from(value).map(Command::ZPop),
+                b"zrandmember" => ZRandMember::try_from(value).map(Command::ZRandMember),
+                b"zscan" => ZScan::try_from(value).map(Command::ZScan),
+                b"zlexcount" => ZLexCount::try_from(value).map(Command::ZLexCount),
+                b"zremrangebyrank" | b"zremrangebyscore" | b"zremrangebylex" => {
+                    ZRemRange::try_from(value).map(Command::ZRemRange)
+                }
+                b"zmpop" => ZMPop::try_from(value).map(Command::ZMPop),
+                b"zunion" | b"zinter" | b"zdiff" => ZSetOp::try_from(value).map(Command::ZSetOp),
+                b"zunionstore" | b"zinterstore" | b"zdiffstore" => {
+                    ZSetOpStore::try_from(value).map(Command::ZSetOpStore)
+                }
+                b"zintercard" => ZInterCard::try_from(value).map(Command::ZInterCard),
+                b"bzpopmin" | b"bzpopmax" => BZPop::try_from(value).map(Command::BZPop),
+                b"bzmpop" => BZMPop::try_from(value).map(Command::BZMPop),
+                b"xadd" => XAdd::try_from(value).map(Command::XAdd),
+                b"xrange" | b"xrevrange" => XRange::try_from(value).map(Command::XRange),
+                b"xlen" => XLen::try_from(value).map(Command::XLen),
+                b"xdel" => XDel::try_from(value).map(Command::XDel),
+                b"xtrim" => XTrim::try_from(value).map(Command::XTrim),
+                b"xread" => XRead::try_from(value).map(Command::XRead),
+                b"xgroup" => XGroup::try_from(value).map(Command::XGroup),
+                b"xreadgroup" => XReadGroup::try_from(value).map(Command::XReadGroup),
+                b"xack" => XAck::try_from(value).map(Command::XAck),
+                b"xpending" => XPending::try_from(value).map(Command::XPending),
+                b"xclaim" => XClaim::try_from(value).map(Command::XClaim),
+                b"xautoclaim" => XAutoClaim::try_from(value).map(Command::XAutoClaim),
+                b"xinfo" => XInfo::try_from(value).map(Command::XInfo),
+                b"multi" => Multi::try_from(value).map(Command::Multi),
+                b"exec" => Exec::try_from(value).map(Command::Exec),
+                b"discard" => Discard::try_from(value).map(Command::Discard),
+                b"watch" => Watch::try_from(value).map(Command::Watch),
+                b"unwatch" => Unwatch::try_from(value).map(Command::Unwatch),
+                b"eval" | b"evalsha" | b"eval_ro" | b"evalsha_ro" => {
+                    Eval::try_from(value).map(Command::Eval)
+                }
+                b"script" => Script::try_from(value).map(Command::Script),
+                b"fcall" | b"fcall_ro" => FCall::try_from(value).map(Command::FCall),
+                b"function" => Function::try_from(value).map(Command::Function),
+                b"client" => Client::try_from(value).map(Command::Client),
+                b"ping" => Ping::try_from(value).map(Command::Ping),
+                b"quit" => Quit::try_from(value).map(Command::Quit),
+                b"reset" => Reset::try_from(value).map(Command::Reset),
+                b"time" => Time::try_from(value).map(Command::Time),
                 _ => Ok(UnRecognized.into()),
             },
             _ => Err(CommandError::InvalidCommand(
@@ -148,14 +676,62 @@ pub fn validate_command_minimum_length(
     validate_command(value, names, expect_len, |v, e| v >= e)
 }
 
+// the name the command was called with, for the commands sharing one implementation
+pub fn command_name(value: &RespArray, names: &[&'static str]) -> &'static str {
+    names
+        .iter()
+        .copied()
+        .find(|name| {
+            matches!(value.first(), Some(RespFrame::BulkString(cmd)) if cmd.eq_ignore_ascii_case(name.as_bytes()))
+        })
+        .unwrap_or(names[0])
+}
+
 pub fn extract_args(value: RespArray) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(1).collect::<Vec<RespFrame>>())
 }
 
+pub fn extract_string(value: RespFrame) -> Result<String, CommandError> {
+    match value {
+        RespFrame::BulkString(s) => Ok(String::from_utf8_lossy(&s).to_string()),
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+pub fn extract_strings(value: RespArray) -> Result<Vec<String>, CommandError> {
+    extract_args(value)?
+        .into_iter()
+        .map(extract_string)
+        .collect()
+}
+
+pub fn extract_int(value: RespFrame) -> Result<i64, CommandError> {
+    extract_string(value)?.parse().map_err(|_| {
+        CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::BulkString;
+    use crate::test_util::request;
+    use crate::{backend::BUSY_SCRIPT_THRESHOLD, BulkString};
+
+    #[tokio::test]
+    async fn test_command_waiting_for_busy_script() -> anyhow::Result<()> {
+        let server = Backend::new();
+        // a script holds the server, and turns busy while the command waits for it
+        let exclusive = server.exclusive_lock().await;
+        server.start_script();
+        let get = Command::try_from(RespFrame::from(request(&["get", "k"])))?;
+        let started = std::time::Instant::now();
+        assert_eq!(get.run(&server.connect()).await, busy_error());
+        assert!(started.elapsed() >= BUSY_SCRIPT_THRESHOLD);
+        server.finish_script();
+        drop(exclusive);
+        assert_eq!(get.run(&server).await, REST_NIL.clone());
+        Ok(())
+    }
 
     #[test]
     fn test_validate_command() {
@@ -0,0 +1,400 @@
+use crate::{
+    command_name, extract_args, extract_string, key_slot, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BulkString, CommandError, CommandExecutor, RespArray,
+    RespFrame, RespMap, SimpleError, SubscriptionKind,
+};
+
+// the commands a RESP2 connection with subscriptions may still run
+const SUBSCRIBED_COMMANDS: [&str; 9] = [
+    "subscribe",
+    "ssubscribe",
+    "psubscribe",
+    "unsubscribe",
+    "sunsubscribe",
+    "punsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+// SUBSCRIBE, PSUBSCRIBE and SSUBSCRIBE
+#[derive(Debug)]
+pub struct Subscribe {
+    kind: SubscriptionKind,
+    names: Vec<String>,
+}
+
+// UNSUBSCRIBE, PUNSUBSCRIBE and SUNSUBSCRIBE
+#[derive(Debug)]
+pub struct Unsubscribe {
+    kind: SubscriptionKind,
+    names: Vec<String>,
+}
+
+// PUBLISH and SPUBLISH
+#[derive(Debug)]
+pub struct Publish {
+    shard: bool,
+    channel: String,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PubSub {
+    op: PubSubOp,
+}
+
+#[derive(Debug, PartialEq)]
+enum PubSubOp {
+    Channels(Option<String>),
+    NumSub(Vec<String>),
+    NumPat,
+    ShardChannels(Option<String>),
+    ShardNumSub(Vec<String>),
+}
+
+// the replies are pushed to the connection, one per name. The number of subscriptions left
+// is returned for callers without a push queue.
+impl CommandExecutor for Subscribe {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if self.kind == SubscriptionKind::Shard && !same_slot(&self.names) {
+            return cross_slot_error();
+        }
+        RespFrame::Integer(backend.subscribe(self.kind, &self.names) as i64)
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if self.kind == SubscriptionKind::Shard && !same_slot(&self.names) {
+            return cross_slot_error();
+        }
+        RespFrame::Integer(backend.unsubscribe(self.kind, &self.names) as i64)
+    }
+}
+
+impl CommandExecutor for Publish {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let received = if self.shard {
+            backend.spublish(&self.channel, &self.message)
+        } else {
+            backend.publish(&self.channel, &self.message)
+        };
+        RespFrame::Integer(received)
+    }
+}
+
+impl CommandExecutor for PubSub {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match &self.op {
+            PubSubOp::Channels(pattern) => {
+                channels_reply(backend.pubsub_channels(pattern.as_deref()))
+            }
+            PubSubOp::NumSub(channels) => numsub_reply(backend, backend.pubsub_numsub(channels)),
+            PubSubOp::NumPat => RespFrame::Integer(backend.pubsub_numpat()),
+            PubSubOp::ShardChannels(pattern) => {
+                channels_reply(backend.pubsub_shardchannels(pattern.as_deref()))
+            }
+            PubSubOp::ShardNumSub(channels) => {
+                numsub_reply(backend, backend.pubsub_shardnumsub(channels))
+            }
+        }
+    }
+}
+
+fn channels_reply(channels: Vec<String>) -> RespFrame {
+    RespArray::new(
+        channels
+            .into_iter()
+            .map(|channel| BulkString::new(channel).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+// shard channels hash to slots like keys, one command only works on a single slot
+fn same_slot(channels: &[String]) -> bool {
+    channels
+        .windows(2)
+        .all(|pair| key_slot(pair[0].as_bytes()) == key_slot(pair[1].as_bytes()))
+}
+
+fn cross_slot_error() -> RespFrame {
+    SimpleError::new("CROSSSLOT Keys in request don't hash to the same slot".to_string()).into()
+}
+
+// each channel with its number of subscribers, as a map for RESP3 clients
+fn numsub_reply(backend: &Backend, counts: Vec<(String, i64)>) -> RespFrame {
+    if backend.session().is_resp3() {
+        let mut map = RespMap::new();
+        for (channel, count) in counts {
+            map.insert(channel, RespFrame::Integer(count));
+        }
+        map.into()
+    } else {
+        let mut frames = Vec::with_capacity(counts.len() * 2);
+        for (channel, count) in counts {
+            frames.push(BulkString::new(channel).into());
+            frames.push(RespFrame::Integer(count));
+        }
+        RespArray::new(frames).into()
+    }
+}
+
+// a RESP2 connection with subscriptions only reads messages, apart from the commands
+// managing them
+pub(crate) fn subscribed_context_error(backend: &Backend, frame: &RespFrame) -> Option<RespFrame> {
+    if backend.session().is_resp3() || backend.subscriptions() == 0 {
+        return None;
+    }
+    let name = match frame {
+        RespFrame::Array(array) => match array.first() {
+            Some(RespFrame::BulkString(name)) => String::from_utf8_lossy(name).to_lowercase(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    if SUBSCRIBED_COMMANDS.contains(&name.as_str()) {
+        return None;
+    }
+    Some(
+        SimpleError::new(format!(
+            "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+            name
+        ))
+        .into(),
+    )
+}
+
+//subscribe :"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n$5\r\nsport\r\n"
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["subscribe", "psubscribe", "ssubscribe"]);
+        validate_command_minimum_length(&value, &[name], 1)?;
+        let kind = match name {
+            "psubscribe" => SubscriptionKind::Pattern,
+            "ssubscribe" => SubscriptionKind::Shard,
+            _ => SubscriptionKind::Channel,
+        };
+        let names = extract_args(value)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Subscribe { kind, names })
+    }
+}
+
+//unsubscribe :"*2\r\n$11\r\nunsubscribe\r\n$4\r\nnews\r\n"
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["unsubscribe", "punsubscribe", "sunsubscribe"]);
+        validate_command_minimum_length(&value, &[name], 0)?;
+        let kind = match name {
+            "punsubscribe" => SubscriptionKind::Pattern,
+            "sunsubscribe" => SubscriptionKind::Shard,
+            _ => SubscriptionKind::Channel,
+        };
+        let names = extract_args(value)?
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Unsubscribe { kind, names })
+    }
+}
+
+//publish :"*3\r\n$7\r\npublish\r\n$4\r\nnews\r\n$5\r\nhello\r\n"
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["publish", "spublish"]);
+        validate_command_exact_length(&value, &[name], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(channel), Some(RespFrame::BulkString(message))) => Ok(Publish {
+                shard: name == "spublish",
+                channel: extract_string(channel)?,
+                message: message.0,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid message".to_string())),
+        }
+    }
+}
+
+//pubsub :"*3\r\n$6\r\npubsub\r\n$8\r\nchannels\r\n$2\r\nn*\r\n"
+impl TryFrom<RespArray> for PubSub {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["pubsub"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let sub = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let mut args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        let op = match sub.to_ascii_lowercase().as_str() {
+            "channels" if args.len() <= 1 => PubSubOp::Channels(args.pop()),
+            "numsub" => PubSubOp::NumSub(args),
+            "numpat" if args.is_empty() => PubSubOp::NumPat,
+            "shardchannels" if args.len() <= 1 => PubSubOp::ShardChannels(args.pop()),
+            "shardnumsub" => PubSubOp::ShardNumSub(args),
+            "channels" | "numpat" | "shardchannels" => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "wrong number of arguments for 'pubsub|{}' command",
+                    sub.to_ascii_lowercase()
+                )))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try PUBSUB HELP.",
+                    sub
+                )))
+            }
+        };
+        Ok(PubSub { op })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::decode;
+    use crate::RespPush;
+    use anyhow::Result;
+
+    #[test]
+    fn test_pubsub_commands_tryfrom() -> Result<()> {
+        let frames = decode(b"*3\r\n$10\r\npsubscribe\r\n$2\r\nn*\r\n$2\r\ns*\r\n")?;
+        let cmd = Subscribe::try_from(frames)?;
+        assert_eq!(cmd.kind, SubscriptionKind::Pattern);
+        assert_eq!(cmd.names, ["n*", "s*"]);
+
+        let frames = decode(b"*1\r\n$9\r\nsubscribe\r\n")?;
+        assert!(Subscribe::try_from(frames).is_err());
+        let frames = decode(b"*1\r\n$11\r\nunsubscribe\r\n")?;
+        assert!(Unsubscribe::try_from(frames)?.names.is_empty());
+
+        let frames = decode(b"*3\r\n$6\r\npubsub\r\n$8\r\nchannels\r\n$2\r\nn*\r\n")?;
+        let cmd = PubSub::try_from(frames)?;
+        assert_eq!(cmd.op, PubSubOp::Channels(Some("n*".to_string())));
+        let frames = decode(b"*3\r\n$6\r\npubsub\r\n$6\r\nnumpat\r\n$1\r\nx\r\n")?;
+        assert!(PubSub::try_from(frames).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pubsub_commands() -> Result<()> {
+        let server = Backend::new();
+        let subscriber = server.connect();
+        let mut pushes = subscriber.open_pushes();
+        let frames = decode(b"*2\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n")?;
+        Subscribe::try_from(frames)?.execute(&subscriber);
+        assert!(matches!(pushes.try_recv(), Ok(RespFrame::Push(_))));
+
+        // RESP2 subscribers only run the subscription commands
+        let get = RespFrame::Array(decode(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n")?);
+        assert_eq!(
+            subscribed_context_error(&subscriber, &get),
+            Some(SimpleError::new("ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context".to_string()).into())
+        );
+        let ping = RespFrame::Array(decode(b"*1\r\n$4\r\nping\r\n")?);
+        assert_eq!(subscribed_context_error(&subscriber, &ping), None);
+        assert_eq!(subscribed_context_error(&server, &get), None);
+
+        let frames = decode(b"*3\r\n$7\r\npublish\r\n$4\r\nnews\r\n$5\r\nhello\r\n")?;
+        let result = Publish::try_from(frames)?.execute(&server);
+        assert_eq!(result, RespFrame::Integer(1));
+        assert_eq!(
+            pushes.try_recv().ok(),
+            Some(
+                RespPush::new(vec![
+                    BulkString::new("message").into(),
+                    BulkString::new("news").into(),
+                    BulkString::new("hello").into(),
+                ])
+                .into()
+            )
+        );
+
+        let frames = decode(b"*3\r\n$6\r\npubsub\r\n$6\r\nnumsub\r\n$4\r\nnews\r\n")?;
+        let result = PubSub::try_from(frames)?.execute(&server);
+        assert_eq!(
+            result,
+            RespArray::new(vec![BulkString::new("news").into(), RespFrame::Integer(1)]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_shard_pubsub_commands() -> Result<()> {
+        let server = Backend::new();
+        let subscriber = server.connect();
+        let mut pushes = subscriber.open_pushes();
+
+        // channels in different slots are refused
+        let frames = decode(b"*3\r\n$10\r\nssubscribe\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")?;
+        let result = Subscribe::try_from(frames)?.execute(&subscriber);
+        assert!(matches!(result, RespFrame::Error(_)));
+        assert!(pushes.try_recv().is_err());
+
+        let frames = decode(b"*3\r\n$10\r\nssubscribe\r\n$5\r\n{u}.a\r\n$5\r\n{u}.b\r\n")?;
+        let result = Subscribe::try_from(frames)?.execute(&subscriber);
+        assert_eq!(result, RespFrame::Integer(2));
+        let frames = decode(b"*2\r\n$9\r\nsubscribe\r\n$5\r\n{u}.a\r\n")?;
+        Subscribe::try_from(frames)?.execute(&subscriber);
+        // shard subscriptions are counted on their own
+        let counts = (0..3)
+            .filter_map(|_| match pushes.try_recv() {
+                Ok(RespFrame::Push(push)) => Some(push[2].clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counts,
+            [
+                RespFrame::Integer(1),
+                RespFrame::Integer(2),
+                RespFrame::Integer(1)
+            ]
+        );
+
+        // SPUBLISH only reaches shard subscribers, PUBLISH only the others
+        let frames = decode(b"*3\r\n$8\r\nspublish\r\n$5\r\n{u}.a\r\n$2\r\nhi\r\n")?;
+        let result = Publish::try_from(frames)?.execute(&server);
+        assert_eq!(result, RespFrame::Integer(1));
+        assert_eq!(
+            pushes.try_recv().ok(),
+            Some(
+                RespPush::new(vec![
+                    BulkString::new("smessage").into(),
+                    BulkString::new("{u}.a").into(),
+                    BulkString::new("hi").into(),
+                ])
+                .into()
+            )
+        );
+        let frames = decode(b"*3\r\n$7\r\npublish\r\n$5\r\n{u}.b\r\n$2\r\nhi\r\n")?;
+        let result = Publish::try_from(frames)?.execute(&server);
+        assert_eq!(result, RespFrame::Integer(0));
+
+        let frames = decode(b"*2\r\n$6\r\npubsub\r\n$13\r\nshardchannels\r\n")?;
+        let result = PubSub::try_from(frames)?.execute(&server);
+        let RespFrame::Array(channels) = result else {
+            panic!("expected an array");
+        };
+        assert_eq!(channels.len(), 2);
+        let frames = decode(b"*3\r\n$6\r\npubsub\r\n$11\r\nshardnumsub\r\n$5\r\n{u}.b\r\n")?;
+        let result = PubSub::try_from(frames)?.execute(&server);
+        assert_eq!(
+            result,
+            RespArray::new(vec![BulkString::new("{u}.b").into(), RespFrame::Integer(1)]).into()
+        );
+        Ok(())
+    }
+}
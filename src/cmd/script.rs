@@ -0,0 +1,737 @@
+use std::{fmt, sync::Arc};
+
+use mlua::{HookTriggers, IntoLua, Lua, MultiValue, Value};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tracing::info;
+
+use crate::{
+    command_name, extract_args, extract_int, extract_string, sha1_hex,
+    validate_command_minimum_length, Backend, BulkString, Command, CommandError, CommandExecutor,
+    RespArray, RespFrame, RunningScript, SimpleError, SimpleString,
+};
+
+use super::{REST_NIL, REST_OK};
+
+// a key or argument passed to a script
+type Arg = Vec<u8>;
+
+// how many instructions a script runs between checks for SCRIPT KILL
+pub(super) const KILL_CHECK_INSTRUCTIONS: u32 = 1000;
+
+// EVAL, EVALSHA, EVAL_RO and EVALSHA_RO
+#[derive(Debug)]
+pub struct Eval {
+    script: ScriptSource,
+    keys: Vec<Vec<u8>>,
+    args: Vec<Vec<u8>>,
+    read_only: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum ScriptSource {
+    Body(String),
+    Sha(String),
+}
+
+#[derive(Debug)]
+pub struct Script {
+    op: ScriptOp,
+}
+
+#[derive(Debug, PartialEq)]
+enum ScriptOp {
+    Load(String),
+    Exists(Vec<String>),
+    Flush,
+    Kill,
+}
+
+// what the redis library of a running script works with
+struct ScriptRun {
+    backend: Backend,
+    script: Arc<RunningScript>,
+    read_only: bool,
+}
+
+// an error reply raised as a Lua error, like those of the commands called with redis.call.
+// The client gets it as it is.
+#[derive(Debug)]
+pub(super) struct CallError(pub(super) String);
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CallError {}
+
+impl CommandExecutor for Eval {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let (sha, body) = match &self.script {
+            ScriptSource::Body(body) => (backend.load_script(body), body.clone()),
+            ScriptSource::Sha(sha) => match backend.script_body(sha) {
+                Some(body) => (sha.to_ascii_lowercase(), body),
+                None => {
+                    return SimpleError::new(
+                        "NOSCRIPT No matching script. Please use EVAL.".to_string(),
+                    )
+                    .into()
+                }
+            },
+        };
+        let lua = backend.lua();
+        let globals = lua.globals();
+        let result = run_script(&lua, backend, self.read_only, || {
+            globals.raw_set("KEYS", string_sequence(&lua, &self.keys)?)?;
+            globals.raw_set("ARGV", string_sequence(&lua, &self.args)?)?;
+            lua.load(&body)
+                .set_name("@user_script")
+                .call::<_, Value>(())
+        });
+        match result {
+            Ok(frame) => frame,
+            Err(e) => script_error(&e, &sha),
+        }
+    }
+}
+
+impl CommandExecutor for Script {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match &self.op {
+            ScriptOp::Load(body) => BulkString::new(backend.load_script(body)).into(),
+            ScriptOp::Exists(shas) => RespArray::new(
+                backend
+                    .scripts_exist(shas)
+                    .into_iter()
+                    .map(|exists| RespFrame::Integer(exists as i64))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            ScriptOp::Flush => {
+                backend.flush_scripts();
+                REST_OK.clone()
+            }
+            ScriptOp::Kill => match backend.kill_script() {
+                Ok(()) => REST_OK.clone(),
+                Err(e) => e.into(),
+            },
+        }
+    }
+}
+
+impl Script {
+    // SCRIPT KILL has to get through while a script holds the server
+    pub fn kills(&self) -> bool {
+        self.op == ScriptOp::Kill
+    }
+}
+
+// run a script or function with the redis library bound to a client of the caller, where
+// SCRIPT KILL and FUNCTION KILL can stop it
+pub(super) fn run_script<'lua>(
+    lua: &'lua Lua,
+    backend: &Backend,
+    read_only: bool,
+    run: impl FnOnce() -> mlua::Result<Value<'lua>>,
+) -> mlua::Result<RespFrame> {
+    install_redis_lib(lua)?;
+    let script = backend.start_script();
+    lua.set_app_data(ScriptRun {
+        backend: backend.script_client(),
+        script: script.clone(),
+        read_only,
+    });
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS),
+        move |_, _| match script.killed() {
+            true => Err(mlua::Error::external(CallError(
+                "ERR Script killed by user with SCRIPT KILL...".to_string(),
+            ))),
+            false => Ok(()),
+        },
+    );
+    let result = run_blocking(|| run().map(lua_to_frame));
+    lua.remove_hook();
+    lua.remove_app_data::<ScriptRun>();
+    backend.finish_script();
+    result
+}
+
+// Run a script without stalling the other connections: a worker of the multi threaded runtime
+// hands its tasks to another worker for as long as the script runs. On a current thread
+// runtime, as in tests, or outside of any runtime, the script runs in place.
+fn run_blocking<R>(f: impl FnOnce() -> R) -> R {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
+
+// KEYS and ARGV, as Lua strings
+pub(super) fn string_sequence<'lua>(
+    lua: &'lua Lua,
+    values: &[Vec<u8>],
+) -> mlua::Result<mlua::Table<'lua>> {
+    let values = values
+        .iter()
+        .map(|value| lua.create_string(value))
+        .collect::<mlua::Result<Vec<_>>>()?;
+    lua.create_sequence_from(values)
+}
+
+// the `redis` table of the scripts, set up once per interpreter. Scripts may not create
+// globals, or read ones that do not exist.
+pub(super) fn install_redis_lib(lua: &Lua) -> mlua::Result<()> {
+    let globals = lua.globals();
+    if !matches!(globals.raw_get::<_, Value>("redis")?, Value::Nil) {
+        return Ok(());
+    }
+    let redis = lua.create_table()?;
+    redis.set(
+        "call",
+        lua.create_function(|lua, args: MultiValue| redis_call(lua, args, true))?,
+    )?;
+    redis.set(
+        "pcall",
+        lua.create_function(|lua, args: MultiValue| redis_call(lua, args, false))?,
+    )?;
+    redis.set(
+        "status_reply",
+        lua.create_function(|lua, status: mlua::String| {
+            let reply = lua.create_table()?;
+            reply.set("ok", status)?;
+            Ok(reply)
+        })?,
+    )?;
+    redis.set(
+        "error_reply",
+        lua.create_function(|lua, error: mlua::String| {
+            let reply = lua.create_table()?;
+            reply.set("err", error)?;
+            Ok(reply)
+        })?,
+    )?;
+    redis.set(
+        "sha1hex",
+        lua.create_function(|_, data: mlua::String| Ok(sha1_hex(data.as_bytes())))?,
+    )?;
+    redis.set(
+        "log",
+        lua.create_function(|_, (level, message): (i64, mlua::String)| {
+            info!("script log {}: {}", level, message.to_string_lossy());
+            Ok(())
+        })?,
+    )?;
+    for (i, level) in ["LOG_DEBUG", "LOG_VERBOSE", "LOG_NOTICE", "LOG_WARNING"]
+        .iter()
+        .enumerate()
+    {
+        redis.set(*level, i)?;
+    }
+    globals.raw_set("redis", redis)?;
+    lua.load(
+        r#"
+        setmetatable(_G, {
+            __newindex = function(_, name)
+                error("Attempt to modify a readonly table: " .. tostring(name), 2)
+            end,
+            __index = function(_, name)
+                error("Script attempted to access nonexistent global variable '" .. tostring(name) .. "'", 2)
+            end,
+        })
+        "#,
+    )
+    .set_name("@redis_lib")
+    .exec()
+}
+
+// redis.call raises the error replies, redis.pcall returns them as {err = ...} tables
+fn redis_call<'lua>(
+    lua: &'lua Lua,
+    args: MultiValue<'lua>,
+    raise: bool,
+) -> mlua::Result<Value<'lua>> {
+    let reply = match script_request(args) {
+        Ok(request) => call_from_script(lua, request),
+        Err(e) => e,
+    };
+    match reply {
+        RespFrame::Error(e) if raise => Err(mlua::Error::external(CallError(e.0))),
+        reply => frame_to_lua(lua, reply),
+    }
+}
+
+// run a command for the script, like EXEC does for the queued ones
+fn call_from_script(lua: &Lua, request: RespFrame) -> RespFrame {
+    let Some(run) = lua.app_data_ref::<ScriptRun>() else {
+        return SimpleError::new("ERR no script is running".to_string()).into();
+    };
+    let cmd = match Command::try_from(request) {
+        Ok(cmd) => cmd,
+        Err(e) => return e.into(),
+    };
+    if matches!(cmd, Command::UnRecognized(_)) {
+        return SimpleError::new("ERR Unknown Redis command called from script".to_string()).into();
+    }
+    if !cmd.allowed_in_script() {
+        return SimpleError::new("ERR This Redis command is not allowed from script".to_string())
+            .into();
+    }
+    if cmd.is_write() {
+        if run.read_only {
+            return SimpleError::new(
+                "ERR Write commands are not allowed from read-only scripts.".to_string(),
+            )
+            .into();
+        }
+        run.script.set_wrote();
+    }
+    cmd.execute(&run.backend)
+}
+
+// the arguments of redis.call as a request, only strings and numbers are accepted
+fn script_request(args: MultiValue) -> Result<RespFrame, RespFrame> {
+    if args.is_empty() {
+        return Err(SimpleError::new(
+            "ERR Please specify at least one argument for this redis lib call".to_string(),
+        )
+        .into());
+    }
+    args.into_iter()
+        .map(|arg| match arg {
+            Value::String(s) => Ok(BulkString::new(s.as_bytes().to_vec()).into()),
+            Value::Integer(i) => Ok(BulkString::new(i.to_string()).into()),
+            Value::Number(n) if n.fract() == 0.0 => {
+                Ok(BulkString::new((n as i64).to_string()).into())
+            }
+            Value::Number(n) => Ok(BulkString::new(n.to_string()).into()),
+            _ => Err(SimpleError::new(
+                "ERR Lua redis lib command arguments must be strings or integers".to_string(),
+            )
+            .into()),
+        })
+        .collect::<Result<Vec<RespFrame>, RespFrame>>()
+        .map(|frames| RespArray::new(frames).into())
+}
+
+// the conversion of replies to Lua values, as scripts see RESP2: nil becomes false, status
+// and error replies become {ok = ...} and {err = ...} tables
+fn frame_to_lua(lua: &Lua, frame: RespFrame) -> mlua::Result<Value<'_>> {
+    let sequence = |frames: Vec<RespFrame>| -> mlua::Result<Value<'_>> {
+        let values = frames
+            .into_iter()
+            .map(|frame| frame_to_lua(lua, frame))
+            .collect::<mlua::Result<Vec<_>>>()?;
+        Ok(Value::Table(lua.create_sequence_from(values)?))
+    };
+    let field = |name: &str, value: String| -> mlua::Result<Value<'_>> {
+        let table = lua.create_table()?;
+        table.set(name, value)?;
+        Ok(Value::Table(table))
+    };
+    match frame {
+        RespFrame::Integer(i) => i.into_lua(lua),
+        RespFrame::BulkString(s) => Ok(Value::String(lua.create_string(&s.0)?)),
+        RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => Ok(Value::Boolean(false)),
+        RespFrame::SimpleString(s) => field("ok", s.0),
+        RespFrame::Error(e) => field("err", e.0),
+        RespFrame::Null(_) => Ok(Value::Boolean(false)),
+        RespFrame::Boolean(b) => (b as i64).into_lua(lua),
+        RespFrame::Double(d) => Ok(Value::String(lua.create_string(d.to_string())?)),
+        RespFrame::Array(array) => sequence(array.0),
+        RespFrame::Set(set) => sequence(set.0),
+        RespFrame::Push(push) => sequence(push.0),
+        RespFrame::Map(map) => sequence(
+            map.0
+                .into_iter()
+                .flat_map(|(k, v)| [BulkString::new(k).into(), v])
+                .collect(),
+        ),
+    }
+}
+
+// the conversion of the value a script returns: numbers are truncated to integers, false and
+// nil become nil, and a table is an array up to its first nil
+fn lua_to_frame(value: Value) -> RespFrame {
+    match value {
+        Value::Boolean(true) => RespFrame::Integer(1),
+        Value::Integer(i) => RespFrame::Integer(i),
+        Value::Number(n) => RespFrame::Integer(n as i64),
+        Value::String(s) => BulkString::new(s.as_bytes().to_vec()).into(),
+        Value::Table(table) => {
+            if let Ok(Value::String(e)) = table.raw_get("err") {
+                return SimpleError::new(e.to_string_lossy().to_string()).into();
+            }
+            if let Ok(Value::String(s)) = table.raw_get("ok") {
+                return SimpleString::new(s.to_string_lossy().to_string()).into();
+            }
+            let frames = (1..)
+                .map_while(|i| match table.raw_get::<_, Value>(i) {
+                    Ok(Value::Nil) | Err(_) => None,
+                    Ok(value) => Some(lua_to_frame(value)),
+                })
+                .collect::<Vec<_>>();
+            RespArray::new(frames).into()
+        }
+        _ => REST_NIL.clone(),
+    }
+}
+
+// the reply for a script or function that failed: errors raised by redis.call keep their own
+// message, the others name the script
+pub(super) fn script_error(e: &mlua::Error, name: &str) -> RespFrame {
+    let message = match e {
+        mlua::Error::CallbackError { cause, .. } => return script_error(cause, name),
+        mlua::Error::ExternalError(external) => match external.downcast_ref::<CallError>() {
+            Some(CallError(message)) => return SimpleError::new(message.clone()).into(),
+            None => external.to_string(),
+        },
+        mlua::Error::SyntaxError { message, .. } => {
+            return SimpleError::new(format!(
+                "ERR Error compiling script (new function): {}",
+                message
+            ))
+            .into()
+        }
+        mlua::Error::RuntimeError(message) => message.clone(),
+        e => e.to_string(),
+    };
+    // an error reply is a single line, the stack traceback is dropped
+    let message = message.lines().next().unwrap_or_default();
+    SimpleError::new(format!("ERR {} script: {}", message, name)).into()
+}
+
+//eval :"*3\r\n$4\r\neval\r\n$8\r\nreturn 1\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for Eval {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["eval", "evalsha", "eval_ro", "evalsha_ro"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let script = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let numkeys = extract_int(args.next().ok_or_else(syntax_error)?)?;
+        let (keys, args) = keys_and_args(args, numkeys)?;
+        let script = match name {
+            "eval" | "eval_ro" => ScriptSource::Body(script),
+            _ => ScriptSource::Sha(script),
+        };
+        Ok(Eval {
+            script,
+            keys,
+            args,
+            read_only: name.ends_with("_ro"),
+        })
+    }
+}
+
+//script :"*3\r\n$6\r\nscript\r\n$4\r\nload\r\n$8\r\nreturn 1\r\n"
+impl TryFrom<RespArray> for Script {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["script"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        let sub = extract_string(args.next().ok_or_else(syntax_error)?)?.to_ascii_lowercase();
+        let args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+        let op = match (sub.as_str(), args.len()) {
+            ("load", 1) => ScriptOp::Load(args.into_iter().next().unwrap_or_default()),
+            ("exists", n) if n > 0 => ScriptOp::Exists(args),
+            // the flush is synchronous either way
+            ("flush", 0) => ScriptOp::Flush,
+            ("flush", 1) if matches!(args[0].to_ascii_lowercase().as_str(), "sync" | "async") => {
+                ScriptOp::Flush
+            }
+            ("kill", 0) => ScriptOp::Kill,
+            ("load" | "exists" | "flush" | "kill", _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "wrong number of arguments for 'script|{}' command",
+                    sub
+                )))
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try SCRIPT HELP.",
+                    sub
+                )))
+            }
+        };
+        Ok(Script { op })
+    }
+}
+
+// the arguments after numkeys, split into the keys and the other arguments
+pub(super) fn keys_and_args(
+    args: impl Iterator<Item = RespFrame>,
+    numkeys: i64,
+) -> Result<(Vec<Arg>, Vec<Arg>), CommandError> {
+    let mut args = args
+        .map(|arg| match arg {
+            RespFrame::BulkString(s) => Ok(s.0),
+            _ => Err(syntax_error()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if numkeys < 0 {
+        return Err(CommandError::InvalidArgument(
+            "Number of keys can't be negative".to_string(),
+        ));
+    }
+    if numkeys as usize > args.len() {
+        return Err(CommandError::InvalidArgument(
+            "Number of keys can't be greater than number of args".to_string(),
+        ));
+    }
+    let rest = args.split_off(numkeys as usize);
+    Ok((args, rest))
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::request;
+    use std::time::Duration;
+
+    fn eval(backend: &Backend, args: &[&str]) -> RespFrame {
+        match Eval::try_from(request(args)) {
+            Ok(cmd) => cmd.execute(backend),
+            Err(e) => e.into(),
+        }
+    }
+
+    #[test]
+    fn test_script_commands_tryfrom() {
+        let cmd = Eval::try_from(request(&["eval", "return 1", "1", "k", "a"])).unwrap();
+        assert_eq!(cmd.script, ScriptSource::Body("return 1".to_string()));
+        assert_eq!(cmd.keys, vec![b"k".to_vec()]);
+        assert_eq!(cmd.args, vec![b"a".to_vec()]);
+        assert!(!cmd.read_only);
+        let cmd = Eval::try_from(request(&["EVALSHA_RO", "abc", "0"])).unwrap();
+        assert_eq!(cmd.script, ScriptSource::Sha("abc".to_string()));
+        assert!(cmd.read_only);
+        assert!(Eval::try_from(request(&["eval", "return 1", "2", "k"])).is_err());
+        assert!(Eval::try_from(request(&["eval", "return 1", "-1"])).is_err());
+
+        let cmd = Script::try_from(request(&["script", "exists", "a", "b"])).unwrap();
+        assert_eq!(
+            cmd.op,
+            ScriptOp::Exists(vec!["a".to_string(), "b".to_string()])
+        );
+        assert!(Script::try_from(request(&["script", "load"])).is_err());
+        assert!(Script::try_from(request(&["script", "nope"])).is_err());
+    }
+
+    #[test]
+    fn test_eval_conversions() {
+        let backend = Backend::new();
+        assert_eq!(
+            eval(
+                &backend,
+                &["eval", "return {1, 2.9, 'a', false, {true}, nil, 3}", "0"]
+            ),
+            RespArray::new(vec![
+                RespFrame::Integer(1),
+                RespFrame::Integer(2),
+                BulkString::new("a").into(),
+                REST_NIL.clone(),
+                RespArray::new(vec![RespFrame::Integer(1)]).into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            eval(
+                &backend,
+                &["eval", "return redis.status_reply('FINE')", "0"]
+            ),
+            SimpleString::new("FINE").into()
+        );
+        assert_eq!(
+            eval(&backend, &["eval", "return {err = 'BAD thing'}", "0"]),
+            SimpleError::new("BAD thing".to_string()).into()
+        );
+        assert_eq!(
+            eval(
+                &backend,
+                &[
+                    "eval",
+                    "return {KEYS[1], ARGV[1], #ARGV}",
+                    "1",
+                    "k",
+                    "a",
+                    "b"
+                ]
+            ),
+            RespArray::new(vec![
+                BulkString::new("k").into(),
+                BulkString::new("a").into(),
+                RespFrame::Integer(2),
+            ])
+            .into()
+        );
+        assert_eq!(
+            eval(&backend, &["eval", "return redis.sha1hex('')", "0"]),
+            BulkString::new("da39a3ee5e6b4b0d3255bfef95601890afd80709").into()
+        );
+    }
+
+    #[test]
+    fn test_eval_redis_call() {
+        let backend = Backend::new();
+        let script = "redis.call('set', KEYS[1], ARGV[1]); return {redis.call('get', KEYS[1]), redis.call('set', KEYS[1], ARGV[1]), redis.call('get', 'missing')}";
+        assert_eq!(
+            eval(&backend, &["eval", script, "1", "k", "v"]),
+            RespArray::new(vec![
+                BulkString::new("v").into(),
+                REST_OK.clone(),
+                REST_NIL.clone()
+            ])
+            .into()
+        );
+        assert_eq!(backend.db().get("k"), Ok(Some(BulkString::new("v").into())));
+
+        // redis.call raises the error of the command, redis.pcall returns it
+        let wrongtype = "WRONGTYPE Operation against a key holding the wrong kind of value";
+        assert_eq!(
+            eval(
+                &backend,
+                &["eval", "return redis.call('lpush', 'k', 'x')", "0"]
+            ),
+            SimpleError::new(wrongtype.to_string()).into()
+        );
+        assert_eq!(
+            eval(
+                &backend,
+                &["eval", "return redis.pcall('lpush', 'k', 'x')['err']", "0"]
+            ),
+            BulkString::new(wrongtype).into()
+        );
+        assert!(matches!(
+            eval(&backend, &["eval", "return redis.call('multi')", "0"]),
+            RespFrame::Error(_)
+        ));
+        assert!(matches!(
+            eval(
+                &backend,
+                &["eval", "return redis.call('nosuchcommand')", "0"]
+            ),
+            RespFrame::Error(_)
+        ));
+
+        // read-only scripts may only read
+        assert_eq!(
+            eval(&backend, &["eval_ro", "return redis.call('get', 'k')", "0"]),
+            BulkString::new("v").into()
+        );
+        assert_eq!(
+            eval(&backend, &["eval_ro", "return redis.call('del', 'k')", "0"]),
+            SimpleError::new(
+                "ERR Write commands are not allowed from read-only scripts.".to_string()
+            )
+            .into()
+        );
+
+        // scripts cannot leave globals behind
+        assert!(matches!(
+            eval(&backend, &["eval", "leak = 1", "0"]),
+            RespFrame::Error(_)
+        ));
+        assert!(matches!(
+            eval(&backend, &["eval", "return (", "0"]),
+            RespFrame::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_evalsha_and_script() {
+        let backend = Backend::new();
+        let sha = Script::try_from(request(&["script", "load", "return ARGV[1]"]))
+            .unwrap()
+            .execute(&backend);
+        let RespFrame::BulkString(sha) = sha else {
+            panic!("script load replies with the sha");
+        };
+        let sha = String::from_utf8_lossy(&sha.0).to_string();
+        assert_eq!(
+            eval(&backend, &["evalsha", &sha, "0", "hi"]),
+            BulkString::new("hi").into()
+        );
+        assert_eq!(
+            Script::try_from(request(&["script", "exists", &sha, "nope"]))
+                .unwrap()
+                .execute(&backend),
+            RespArray::new(vec![RespFrame::Integer(1), RespFrame::Integer(0)]).into()
+        );
+        Script::try_from(request(&["script", "flush"]))
+            .unwrap()
+            .execute(&backend);
+        assert_eq!(
+            eval(&backend, &["evalsha", &sha, "0", "hi"]),
+            SimpleError::new("NOSCRIPT No matching script. Please use EVAL.".to_string()).into()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_script_kill() {
+        let backend = Backend::new();
+        let running = backend.clone();
+        let script = tokio::task::spawn_blocking(move || {
+            eval(&running, &["eval", "while true do end", "0"])
+        });
+        let kill = Script::try_from(request(&["script", "kill"])).unwrap();
+        loop {
+            match kill.execute(&backend) {
+                RespFrame::SimpleString(_) => break,
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+        assert_eq!(
+            script.await.unwrap(),
+            SimpleError::new("ERR Script killed by user with SCRIPT KILL...".to_string()).into()
+        );
+        assert_eq!(
+            kill.execute(&backend),
+            SimpleError::new("NOTBUSY No scripts in execution right now.".to_string()).into()
+        );
+
+        // a script that wrote has to run to completion
+        let running = backend.clone();
+        let script = tokio::task::spawn_blocking(move || {
+            eval(
+                &running,
+                &["eval", "redis.call('set', 'k', 'v') local n = 0 while n < 20000000 do n = n + 1 end return n", "0"],
+            )
+        });
+        let unkillable = loop {
+            match kill.execute(&backend) {
+                RespFrame::Error(e) if e.0.starts_with("UNKILLABLE") => break true,
+                RespFrame::Error(_) if script.is_finished() => break false,
+                _ => tokio::time::sleep(Duration::from_millis(1)).await,
+            }
+        };
+        assert!(unkillable);
+        assert_eq!(script.await.unwrap(), RespFrame::Integer(20000000));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_script_leaves_the_worker_to_other_tasks() {
+        let backend = Backend::new();
+        let running = backend.clone();
+        let script =
+            tokio::spawn(async move { eval(&running, &["eval", "while true do end", "0"]) });
+        while !backend.script_busy() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        // the only worker runs the script, yet other tasks still get to run
+        let other = tokio::time::timeout(Duration::from_secs(5), tokio::spawn(async { 1 })).await;
+        let kill = Script::try_from(request(&["script", "kill"])).unwrap();
+        assert_eq!(kill.execute(&backend), REST_OK.clone());
+        assert!(matches!(script.await.unwrap(), RespFrame::Error(_)));
+        assert_eq!(other.ok().map(Result::unwrap), Some(1));
+    }
+}
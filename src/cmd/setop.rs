@@ -0,0 +1,321 @@
+use crate::{
+    command_name, extract_args, extract_int, extract_string, extract_strings,
+    validate_command_minimum_length, Backend, BulkString, CommandError, CommandExecutor, RespArray,
+    RespFrame, RespSet, SetOperator,
+};
+
+// SINTER, SUNION and SDIFF
+#[derive(Debug)]
+pub struct SetOp {
+    op: SetOperator,
+    keys: Vec<String>,
+}
+
+// SINTERSTORE, SUNIONSTORE and SDIFFSTORE
+#[derive(Debug)]
+pub struct SetOpStore {
+    op: SetOperator,
+    dst: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+impl CommandExecutor for SetOp {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().set_op(self.op, &self.keys) {
+            Ok(members) => {
+                let members = members
+                    .into_iter()
+                    .map(|m| BulkString::new(m).into())
+                    .collect::<Vec<RespFrame>>();
+                if backend.session().is_resp3() {
+                    RespSet::new(members).into()
+                } else {
+                    RespArray::new(members).into()
+                }
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SetOpStore {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .set_op_store(self.op, self.dst.clone(), &self.keys)
+        {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().sintercard(&self.keys, self.limit) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+//sinter :"*3\r\n$6\r\nsinter\r\n$2\r\nk1\r\n$2\r\nk2\r\n"
+impl TryFrom<RespArray> for SetOp {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["sinter", "sunion", "sdiff"]);
+        validate_command_minimum_length(&value, &[name], 1)?;
+        let op = match name {
+            "sinter" => SetOperator::Inter,
+            "sunion" => SetOperator::Union,
+            _ => SetOperator::Diff,
+        };
+        Ok(SetOp {
+            op,
+            keys: extract_strings(value)?,
+        })
+    }
+}
+
+//sinterstore :"*4\r\n$11\r\nsinterstore\r\n$3\r\ndst\r\n$2\r\nk1\r\n$2\r\nk2\r\n"
+impl TryFrom<RespArray> for SetOpStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["sinterstore", "sunionstore", "sdiffstore"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        let op = match name {
+            "sinterstore" => SetOperator::Inter,
+            "sunionstore" => SetOperator::Union,
+            _ => SetOperator::Diff,
+        };
+        let mut args = extract_strings(value)?.into_iter();
+        match args.next() {
+            Some(dst) => Ok(SetOpStore {
+                op,
+                dst,
+                keys: args.collect(),
+            }),
+            None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+//sintercard :"*6\r\n$10\r\nsintercard\r\n$1\r\n2\r\n$2\r\nk1\r\n$2\r\nk2\r\n$5\r\nlimit\r\n$1\r\n5\r\n"
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["sintercard"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let numkeys = match args.next().map(extract_int).transpose()? {
+            Some(n) if n > 0 => n as usize,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "numkeys should be greater than 0".to_string(),
+                ))
+            }
+        };
+        let keys = args
+            .by_ref()
+            .take(numkeys)
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.len() < numkeys {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let option = args.next().map(extract_string).transpose()?;
+        let limit = match (option, args.next()) {
+            (None, _) => 0,
+            (Some(option), Some(limit)) if option.eq_ignore_ascii_case("limit") => {
+                match extract_int(limit)? {
+                    limit if limit >= 0 => limit as usize,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "LIMIT can't be negative".to_string(),
+                        ))
+                    }
+                }
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn setup() -> Result<Backend> {
+        let backend = Backend::new();
+        backend
+            .db()
+            .sadd("a".to_string(), strings(&["1", "2", "3", "4"]))?;
+        backend
+            .db()
+            .sadd("b".to_string(), strings(&["2", "3", "5"]))?;
+        backend.db().sadd("c".to_string(), strings(&["3", "6"]))?;
+        Ok(backend)
+    }
+
+    fn members(frame: RespFrame) -> Vec<String> {
+        let frames = match frame {
+            RespFrame::Array(array) => array.0,
+            RespFrame::Set(set) => set.0,
+            frame => panic!("unexpected reply {:?}", frame),
+        };
+        let mut members = frames
+            .into_iter()
+            .map(|f| match f {
+                RespFrame::BulkString(s) => String::from_utf8_lossy(&s).to_string(),
+                f => panic!("unexpected member {:?}", f),
+            })
+            .collect::<Vec<_>>();
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn test_setop_command_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::from(&b"*3\r\n$6\r\nSUNION\r\n$2\r\nk1\r\n$2\r\nk2\r\n"[..]);
+        let cmd = SetOp::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.op, SetOperator::Union);
+        assert_eq!(cmd.keys, strings(&["k1", "k2"]));
+
+        let mut buf = BytesMut::from(
+            &b"*6\r\n$10\r\nsintercard\r\n$1\r\n2\r\n$2\r\nk1\r\n$2\r\nk2\r\n$5\r\nlimit\r\n$1\r\n5\r\n"[..],
+        );
+        let cmd = SInterCard::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.keys, strings(&["k1", "k2"]));
+        assert_eq!(cmd.limit, 5);
+
+        let mut buf =
+            BytesMut::from(&b"*4\r\n$10\r\nsintercard\r\n$1\r\n3\r\n$2\r\nk1\r\n$2\r\nk2\r\n"[..]);
+        assert!(SInterCard::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_setop_execute() -> Result<()> {
+        let backend = setup()?;
+        let cmd = |op| SetOp {
+            op,
+            keys: strings(&["a", "b", "c"]),
+        };
+        assert_eq!(members(cmd(SetOperator::Inter).execute(&backend)), ["3"]);
+        assert_eq!(
+            members(cmd(SetOperator::Union).execute(&backend)),
+            ["1", "2", "3", "4", "5", "6"]
+        );
+        assert_eq!(
+            members(cmd(SetOperator::Diff).execute(&backend)),
+            ["1", "4"]
+        );
+
+        let cmd = SetOp {
+            op: SetOperator::Inter,
+            keys: strings(&["a", "missing"]),
+        };
+        assert_eq!(backend.db().set_op(cmd.op, &cmd.keys)?.len(), 0);
+        let cmd = SetOp {
+            op: SetOperator::Diff,
+            keys: strings(&["missing", "a"]),
+        };
+        assert_eq!(backend.db().set_op(cmd.op, &cmd.keys)?.len(), 0);
+        // the same key twice is read locked twice
+        let keys = strings(&["b", "b"]);
+        assert_eq!(backend.db().set_op(SetOperator::Inter, &keys)?.len(), 3);
+        // an expired set counts as missing
+        backend.db().sadd("gone".to_string(), strings(&["3"]))?;
+        backend.db().expire_at("gone", Some(1));
+        let keys = strings(&["a", "gone"]);
+        assert_eq!(backend.db().set_op(SetOperator::Union, &keys)?.len(), 4);
+
+        backend.session().set_protocol(3);
+        assert!(matches!(
+            SetOp {
+                op: SetOperator::Union,
+                keys: strings(&["a"]),
+            }
+            .execute(&backend),
+            RespFrame::Set(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_setop_wrong_type() -> Result<()> {
+        let backend = setup()?;
+        backend
+            .db()
+            .set("s".to_string(), BulkString::new("v").into());
+        let cmd = SetOp {
+            op: SetOperator::Union,
+            keys: strings(&["a", "s"]),
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_setop_store_execute() -> Result<()> {
+        let backend = setup()?;
+        backend
+            .db()
+            .set("dst".to_string(), BulkString::new("v").into());
+        let cmd = SetOpStore {
+            op: SetOperator::Inter,
+            dst: "dst".to_string(),
+            keys: strings(&["a", "b"]),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.db().key_type("dst"), "set");
+        assert_eq!(backend.db().scard("dst")?, 2);
+
+        // an empty result removes the destination
+        let cmd = SetOpStore {
+            op: SetOperator::Diff,
+            dst: "dst".to_string(),
+            keys: strings(&["c", "a", "b", "c"]),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(backend.db().key_type("dst"), "none");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard_execute() -> Result<()> {
+        let backend = setup()?;
+        let cmd = SInterCard {
+            keys: strings(&["a", "b"]),
+            limit: 0,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd = SInterCard {
+            keys: strings(&["a", "b"]),
+            limit: 1,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,777 @@
+use std::{iter::Peekable, time::Duration, vec::IntoIter};
+
+use crate::{
+    command_name, extract_args, extract_int, extract_string, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BackendError, BlockedOp, BulkString, CommandError,
+    CommandExecutor, NewStreamId, RespArray, RespFrame, RespMap, StreamEntry, StreamFields,
+    StreamId, StreamTrim, TrimStrategy,
+};
+
+use super::{REST_NIL, REST_NIL_ARRAY};
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+    nomkstream: bool,
+    trim: Option<StreamTrim>,
+    id: NewStreamId,
+    fields: StreamFields,
+}
+
+// XRANGE and XREVRANGE
+#[derive(Debug)]
+pub struct XRange {
+    key: String,
+    start: StreamId,
+    end: StreamId,
+    rev: bool,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct XDel {
+    key: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XTrim {
+    key: String,
+    trim: StreamTrim,
+}
+
+#[derive(Debug)]
+pub struct XRead {
+    count: Option<usize>,
+    // BLOCK in milliseconds, zero blocks forever
+    block: Option<u64>,
+    streams: Vec<(String, ReadFrom)>,
+}
+
+// the options shared by XREAD and XREADGROUP, with the ids still unparsed
+#[derive(Debug, Default)]
+pub struct ReadArgs {
+    pub count: Option<usize>,
+    // BLOCK in milliseconds, zero blocks forever
+    pub block: Option<u64>,
+    pub noack: bool,
+    pub streams: Vec<(String, String)>,
+}
+
+// where XREAD starts reading a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadFrom {
+    // the entries after an id
+    After(StreamId),
+    // `$`, only entries added from now on
+    New,
+    // `+`, the last entry
+    Last,
+}
+
+impl CommandExecutor for XAdd {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().xadd(
+            self.key.clone(),
+            self.id,
+            self.fields.clone(),
+            self.nomkstream,
+            self.trim.as_ref(),
+        ) {
+            Ok(Some(id)) => {
+                backend.signal_ready(&self.key);
+                BulkString::new(id.to_string()).into()
+            }
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRange {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if self.count == Some(0) {
+            return RespArray::new(vec![]).into();
+        }
+        match backend
+            .db()
+            .xrange(&self.key, self.start, self.end, self.rev, self.count)
+        {
+            Ok(entries) => entries_frame(entries),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XLen {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().xlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XDel {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().xdel(&self.key, &self.ids) {
+            Ok(deleted) => RespFrame::Integer(deleted),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().xtrim(&self.key, &self.trim) {
+            Ok(trimmed) => RespFrame::Integer(trimmed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// `execute` never blocks, like the blocking list commands, see `Command::run`
+impl CommandExecutor for XRead {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let streams = match self.resolve(backend) {
+            Ok(streams) => streams,
+            Err(e) => return e.into(),
+        };
+        match self.op(backend, streams)(&backend.db()) {
+            Ok(Some(reply)) => reply,
+            Ok(None) => REST_NIL_ARRAY.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl XRead {
+    pub async fn execute_blocking(&self, backend: &Backend) -> RespFrame {
+        let Some(block) = self.block else {
+            return self.execute(backend);
+        };
+        // `$` refers to the last id when the command was called, not when it is woken up
+        let streams = match self.resolve(backend) {
+            Ok(streams) => streams,
+            Err(e) => return e.into(),
+        };
+        let keys = streams.iter().map(|(key, _)| key.clone()).collect();
+        let timeout = (block > 0).then(|| Duration::from_millis(block));
+        backend
+            .block_on(keys, timeout, None, self.op(backend, streams))
+            .await
+            .unwrap_or_else(|| REST_NIL_ARRAY.clone())
+    }
+
+    // the id of each stream to read after
+    fn resolve(&self, backend: &Backend) -> Result<Vec<(String, StreamId)>, BackendError> {
+        let db = backend.db();
+        self.streams
+            .iter()
+            .map(|(key, from)| {
+                let after = match (from, db.xtail(key)?) {
+                    (ReadFrom::After(id), _) => *id,
+                    (_, None) => StreamId::MIN,
+                    (ReadFrom::Last, Some((_, Some(last)))) => last.prev().unwrap_or_default(),
+                    (_, Some((last, _))) => last,
+                };
+                Ok((key.clone(), after))
+            })
+            .collect()
+    }
+
+    fn op(&self, backend: &Backend, streams: Vec<(String, StreamId)>) -> BlockedOp {
+        let (count, backend) = (self.count, backend.clone());
+        Box::new(move |db| {
+            let read = db.xread(&streams, count)?;
+            let read = read
+                .into_iter()
+                .map(|(key, entries)| (key, entries_frame(entries)))
+                .collect::<Vec<_>>();
+            Ok((!read.is_empty()).then(|| xread_reply(&backend, read)))
+        })
+    }
+}
+
+// an entry as `[id, [field, value, ...]]`
+pub fn entry_frame((id, fields): StreamEntry) -> RespFrame {
+    let mut frames = Vec::with_capacity(fields.len() * 2);
+    for (field, value) in fields {
+        frames.push(BulkString::new(field).into());
+        frames.push(value);
+    }
+    RespArray::new(vec![
+        BulkString::new(id.to_string()).into(),
+        RespArray::new(frames).into(),
+    ])
+    .into()
+}
+
+pub fn entries_frame(entries: Vec<StreamEntry>) -> RespFrame {
+    RespArray::new(entries.into_iter().map(entry_frame).collect::<Vec<_>>()).into()
+}
+
+// the entries read per stream: a map in RESP3, otherwise an array of [key, entries] pairs
+pub fn xread_reply(backend: &Backend, read: Vec<(String, RespFrame)>) -> RespFrame {
+    if backend.session().is_resp3() {
+        let mut map = RespMap::new();
+        for (key, entries) in read {
+            map.insert(key, entries);
+        }
+        map.into()
+    } else {
+        let frames = read
+            .into_iter()
+            .map(|(key, entries)| RespArray::new(vec![BulkString::new(key).into(), entries]).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(frames).into()
+    }
+}
+
+//xadd :"*5\r\n$4\r\nxadd\r\n$1\r\ns\r\n$1\r\n*\r\n$1\r\nf\r\n$1\r\nv\r\n"
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xadd"], 2)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let (mut nomkstream, mut trim) = (false, None);
+        let id = loop {
+            let arg = extract_string(args.next().ok_or_else(syntax_error)?)?;
+            match arg.to_ascii_lowercase().as_str() {
+                "nomkstream" => nomkstream = true,
+                strategy @ ("maxlen" | "minid") => {
+                    trim = Some(parse_stream_trim(strategy, &mut args)?)
+                }
+                _ => break parse_new_id(&arg)?,
+            }
+        };
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'xadd' command".to_string(),
+            ));
+        }
+        let mut fields = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((extract_string(field)?, value));
+        }
+        Ok(XAdd {
+            key,
+            nomkstream,
+            trim,
+            id,
+            fields,
+        })
+    }
+}
+
+//xrange :"*4\r\n$6\r\nxrange\r\n$1\r\ns\r\n$1\r\n-\r\n$1\r\n+\r\n"
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["xrange", "xrevrange"]);
+        let rev = name == "xrevrange";
+        if value.len() != 4 && value.len() != 6 {
+            validate_command_exact_length(&value, &[name], 3)?;
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let mut bounds = [args.next(), args.next()]
+            .into_iter()
+            .map(|bound| extract_string(bound.ok_or_else(syntax_error)?));
+        let (first, second) = (bounds.next().unwrap()?, bounds.next().unwrap()?);
+        let (start, end) = if rev {
+            (second, first)
+        } else {
+            (first, second)
+        };
+        let count = match (args.next().map(extract_string).transpose()?, args.next()) {
+            (None, _) => None,
+            (Some(option), Some(count)) if option.eq_ignore_ascii_case("count") => {
+                Some(extract_int(count)?.max(0) as usize)
+            }
+            _ => return Err(syntax_error()),
+        };
+        Ok(XRange {
+            key,
+            start: parse_range_bound(&start, false)?,
+            end: parse_range_bound(&end, true)?,
+            rev,
+            count,
+        })
+    }
+}
+
+//xlen :"*2\r\n$4\r\nxlen\r\n$1\r\ns\r\n"
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["xlen"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        Ok(XLen {
+            key: extract_string(args.next().ok_or_else(syntax_error)?)?,
+        })
+    }
+}
+
+//xdel :"*3\r\n$4\r\nxdel\r\n$1\r\ns\r\n$3\r\n1-1\r\n"
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xdel"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let ids = args
+            .map(|id| parse_stream_id(&extract_string(id)?, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XDel { key, ids })
+    }
+}
+
+//xtrim :"*4\r\n$5\r\nxtrim\r\n$1\r\ns\r\n$6\r\nmaxlen\r\n$2\r\n10\r\n"
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xtrim"], 3)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let strategy = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let trim = match strategy.to_ascii_lowercase().as_str() {
+            strategy @ ("maxlen" | "minid") => parse_stream_trim(strategy, &mut args)?,
+            _ => return Err(syntax_error()),
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XTrim { key, trim })
+    }
+}
+
+//xread :"*4\r\n$5\r\nxread\r\n$7\r\nstreams\r\n$1\r\ns\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for XRead {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xread"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let read = parse_read_args("xread", &mut args)?;
+        let streams = read
+            .streams
+            .into_iter()
+            .map(|(key, id)| {
+                let from = match id.as_str() {
+                    "$" => ReadFrom::New,
+                    "+" => ReadFrom::Last,
+                    id => ReadFrom::After(parse_stream_id(id, 0)?),
+                };
+                Ok((key, from))
+            })
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        Ok(XRead {
+            count: read.count,
+            block: read.block,
+            streams,
+        })
+    }
+}
+
+// `[COUNT count] [BLOCK ms] [NOACK] STREAMS key [key ...] id [id ...]`, NOACK is only known to
+// XREADGROUP
+pub fn parse_read_args(
+    name: &str,
+    args: &mut IntoIter<RespFrame>,
+) -> Result<ReadArgs, CommandError> {
+    let mut read = ReadArgs::default();
+    loop {
+        let option = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        match option.to_ascii_lowercase().as_str() {
+            "count" => {
+                let n = extract_int(args.next().ok_or_else(syntax_error)?)?;
+                // like Redis a count of zero or less reads everything
+                read.count = (n > 0).then_some(n as usize);
+            }
+            "block" => {
+                let ms = extract_int(args.next().ok_or_else(syntax_error)?)?;
+                if ms < 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "timeout is negative".to_string(),
+                    ));
+                }
+                read.block = Some(ms as u64);
+            }
+            "noack" if name == "xreadgroup" => read.noack = true,
+            "streams" => break,
+            _ => return Err(syntax_error()),
+        }
+    }
+    let args = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+    if args.is_empty() || args.len() % 2 != 0 {
+        return Err(CommandError::InvalidArgument(format!(
+            "Unbalanced '{}' list of streams: for each stream key an ID or '{}' must be specified.",
+            name,
+            if name == "xread" { "$" } else { ">" }
+        )));
+    }
+    let (keys, ids) = args.split_at(args.len() / 2);
+    read.streams = keys.iter().cloned().zip(ids.iter().cloned()).collect();
+    Ok(read)
+}
+
+// `MAXLEN|MINID [=|~] threshold [LIMIT count]` once the strategy has been read
+pub fn parse_stream_trim(
+    strategy: &str,
+    args: &mut Peekable<IntoIter<RespFrame>>,
+) -> Result<StreamTrim, CommandError> {
+    let mut threshold = extract_string(args.next().ok_or_else(syntax_error)?)?;
+    let approx = threshold == "~";
+    if approx || threshold == "=" {
+        threshold = extract_string(args.next().ok_or_else(syntax_error)?)?;
+    }
+    let strategy = if strategy == "maxlen" {
+        match threshold.parse::<i64>() {
+            Ok(len) if len >= 0 => TrimStrategy::MaxLen(len as usize),
+            Ok(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "The MAXLEN argument must be >= 0.".to_string(),
+                ))
+            }
+            Err(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "value is not an integer or out of range".to_string(),
+                ))
+            }
+        }
+    } else {
+        TrimStrategy::MinId(parse_stream_id(&threshold, 0)?)
+    };
+    let limit = match args.peek() {
+        Some(RespFrame::BulkString(s)) if s.eq_ignore_ascii_case(b"limit") => {
+            args.next();
+            if !approx {
+                return Err(CommandError::InvalidArgument(
+                    "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+                ));
+            }
+            match extract_int(args.next().ok_or_else(syntax_error)?)? {
+                limit if limit >= 0 => Some(limit as usize),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "The LIMIT argument must be >= 0.".to_string(),
+                    ))
+                }
+            }
+        }
+        _ => None,
+    };
+    Ok(StreamTrim {
+        strategy,
+        approx,
+        limit,
+    })
+}
+
+pub fn parse_stream_id(id: &str, missing_seq: u64) -> Result<StreamId, CommandError> {
+    StreamId::parse(id, missing_seq).ok_or_else(invalid_stream_id)
+}
+
+// `*`, `ms-*` or an explicit id
+fn parse_new_id(id: &str) -> Result<NewStreamId, CommandError> {
+    if id == "*" {
+        return Ok(NewStreamId::Auto);
+    }
+    match id.strip_suffix("-*") {
+        Some(ms) => ms
+            .parse()
+            .map(NewStreamId::Partial)
+            .map_err(|_| invalid_stream_id()),
+        None => parse_stream_id(id, 0).map(NewStreamId::Explicit),
+    }
+}
+
+// `-`, `+`, an id that may lack its sequence number, or an exclusive `(id`
+pub fn parse_range_bound(bound: &str, end: bool) -> Result<StreamId, CommandError> {
+    match bound {
+        "-" => return Ok(StreamId::MIN),
+        "+" => return Ok(StreamId::MAX),
+        _ => (),
+    }
+    let missing_seq = if end { u64::MAX } else { 0 };
+    match bound.strip_prefix('(') {
+        Some(id) => {
+            let id = parse_stream_id(id, missing_seq)?;
+            let (id, which) = if end {
+                (id.prev(), "end")
+            } else {
+                (id.next(), "start")
+            };
+            id.ok_or_else(|| {
+                CommandError::InvalidArgument(format!("invalid {} ID for the interval", which))
+            })
+        }
+        None => parse_stream_id(bound, missing_seq),
+    }
+}
+
+fn invalid_stream_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{decode, request};
+
+    use anyhow::Result;
+
+    fn run(backend: &Backend, args: &[&str]) -> Result<RespFrame> {
+        let cmd = crate::Command::try_from(RespFrame::from(request(args)))?;
+        Ok(cmd.execute(backend))
+    }
+
+    fn entry(id: &str, fields: &[&str]) -> RespFrame {
+        RespArray::new(vec![
+            BulkString::new(id).into(),
+            RespArray::new(
+                fields
+                    .iter()
+                    .map(|f| BulkString::new(*f).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+        ])
+        .into()
+    }
+
+    #[test]
+    fn test_xadd_tryfrom() -> Result<()> {
+        let cmd = XAdd::try_from(decode(
+            b"*5\r\n$4\r\nxadd\r\n$1\r\ns\r\n$1\r\n*\r\n$1\r\nf\r\n$1\r\nv\r\n",
+        )?)?;
+        assert_eq!(cmd.id, NewStreamId::Auto);
+        assert_eq!(
+            cmd.fields,
+            vec![("f".to_string(), BulkString::new("v").into())]
+        );
+
+        let cmd = XAdd::try_from(request(&[
+            "xadd",
+            "s",
+            "nomkstream",
+            "maxlen",
+            "~",
+            "10",
+            "limit",
+            "5",
+            "5-*",
+            "f",
+            "v",
+        ]))?;
+        assert!(cmd.nomkstream);
+        assert_eq!(cmd.id, NewStreamId::Partial(5));
+        assert_eq!(
+            cmd.trim,
+            Some(StreamTrim {
+                strategy: TrimStrategy::MaxLen(10),
+                approx: true,
+                limit: Some(5),
+            })
+        );
+
+        let err = |args: &[&str]| XAdd::try_from(request(args)).unwrap_err();
+        assert_eq!(
+            err(&["xadd", "s", "maxlen", "10", "limit", "5", "*", "f", "v"]),
+            CommandError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string()
+            )
+        );
+        assert_eq!(
+            err(&["xadd", "s", "maxlen", "-1", "*", "f", "v"]),
+            CommandError::InvalidArgument("The MAXLEN argument must be >= 0.".to_string())
+        );
+        assert_eq!(err(&["xadd", "s", "1-x", "f", "v"]), invalid_stream_id());
+        assert_eq!(
+            err(&["xadd", "s", "*", "f"]),
+            CommandError::InvalidArgument(
+                "wrong number of arguments for 'xadd' command".to_string()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrange_bounds() -> Result<()> {
+        let cmd = XRange::try_from(request(&["xrevrange", "s", "(5", "3", "count", "2"]))?;
+        assert!(cmd.rev);
+        assert_eq!(cmd.start, StreamId::new(3, 0));
+        assert_eq!(cmd.end, StreamId::new(5, u64::MAX - 1));
+        assert_eq!(cmd.count, Some(2));
+
+        let cmd = XRange::try_from(request(&["xrange", "s", "(1-1", "+"]))?;
+        assert_eq!(cmd.start, StreamId::new(1, 2));
+        assert_eq!(cmd.end, StreamId::MAX);
+
+        let err = XRange::try_from(request(&["xrange", "s", "-", "(0-0"])).unwrap_err();
+        assert_eq!(
+            err,
+            CommandError::InvalidArgument("invalid end ID for the interval".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_commands() -> Result<()> {
+        let backend = Backend::new();
+        for id in ["1-1", "1-2", "2-1", "3-1"] {
+            assert_eq!(
+                run(&backend, &["xadd", "s", id, "f", id])?,
+                BulkString::new(id).into()
+            );
+        }
+        assert_eq!(
+            run(&backend, &["xadd", "s", "2-5", "f", "v"])?,
+            BackendError::StreamIdTooSmall.into()
+        );
+        assert_eq!(
+            run(&backend, &["xadd", "s", "3-*", "f", "v"])?,
+            BulkString::new("3-2").into()
+        );
+        assert_eq!(
+            run(&backend, &["xadd", "none", "nomkstream", "*", "f", "v"])?,
+            REST_NIL.clone()
+        );
+        assert_eq!(run(&backend, &["xlen", "s"])?, RespFrame::Integer(5));
+
+        let expected: RespFrame = RespArray::new(vec![
+            entry("1-2", &["f", "1-2"]),
+            entry("2-1", &["f", "2-1"]),
+        ])
+        .into();
+        assert_eq!(run(&backend, &["xrange", "s", "(1-1", "2"])?, expected);
+        let expected: RespFrame = RespArray::new(vec![entry("3-2", &["f", "v"])]).into();
+        assert_eq!(
+            run(&backend, &["xrevrange", "s", "+", "-", "count", "1"])?,
+            expected
+        );
+
+        assert_eq!(
+            run(&backend, &["xdel", "s", "1-1", "9-9"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["xtrim", "s", "minid", "3"])?,
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["xtrim", "s", "maxlen", "~", "0"])?,
+            RespFrame::Integer(0)
+        );
+        run(&backend, &["xadd", "s", "maxlen", "1", "*", "f", "v"])?;
+        assert_eq!(run(&backend, &["xlen", "s"])?, RespFrame::Integer(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_xread() -> Result<()> {
+        let backend = Backend::new();
+        run(&backend, &["xadd", "a", "1-1", "f", "a1"])?;
+        run(&backend, &["xadd", "a", "1-2", "f", "a2"])?;
+        run(&backend, &["xadd", "b", "2-1", "f", "b1"])?;
+
+        let reply = run(
+            &backend,
+            &["xread", "count", "1", "streams", "a", "b", "0", "+"],
+        )?;
+        let expected: RespFrame = RespArray::new(vec![
+            RespArray::new(vec![
+                BulkString::new("a").into(),
+                RespArray::new(vec![entry("1-1", &["f", "a1"])]).into(),
+            ])
+            .into(),
+            RespArray::new(vec![
+                BulkString::new("b").into(),
+                RespArray::new(vec![entry("2-1", &["f", "b1"])]).into(),
+            ])
+            .into(),
+        ])
+        .into();
+        assert_eq!(reply, expected);
+
+        // nothing after the last id
+        assert_eq!(
+            run(&backend, &["xread", "streams", "a", "b", "$", "2-1"])?,
+            REST_NIL_ARRAY.clone()
+        );
+
+        backend.session().set_protocol(3);
+        let mut map = RespMap::new();
+        map.insert(
+            "a".to_string(),
+            RespArray::new(vec![entry("1-2", &["f", "a2"])]).into(),
+        );
+        assert_eq!(
+            run(&backend, &["xread", "streams", "a", "1-1"])?,
+            map.into()
+        );
+
+        let err = XRead::try_from(request(&["xread", "streams", "a", "b", "0"])).unwrap_err();
+        assert!(matches!(err, CommandError::InvalidArgument(msg) if msg.starts_with("Unbalanced")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xread_block() -> Result<()> {
+        let backend = Backend::new();
+        run(&backend, &["xadd", "s", "1-1", "f", "old"])?;
+
+        // two readers from `$`, both see the next entry
+        let mut tasks = Vec::new();
+        for _ in 0..2 {
+            let reader = backend.connect();
+            let cmd = XRead::try_from(request(&["xread", "block", "0", "streams", "s", "$"]))?;
+            tasks.push(tokio::spawn(
+                async move { cmd.execute_blocking(&reader).await },
+            ));
+        }
+        // one waiting for an id far ahead must not hold up the others
+        let ahead = backend.connect();
+        let cmd = XRead::try_from(request(&["xread", "block", "0", "streams", "s", "9-0"]))?;
+        let ahead = tokio::spawn(async move { cmd.execute_blocking(&ahead).await });
+        while backend.blocked_clients() < 3 {
+            tokio::task::yield_now().await;
+        }
+        run(&backend, &["xadd", "s", "2-1", "f", "new"])?;
+        let expected: RespFrame = RespArray::new(vec![RespArray::new(vec![
+            BulkString::new("s").into(),
+            RespArray::new(vec![entry("2-1", &["f", "new"])]).into(),
+        ])
+        .into()])
+        .into();
+        for task in tasks {
+            assert_eq!(task.await?, expected);
+        }
+        assert_eq!(backend.blocked_clients(), 1);
+        ahead.abort();
+
+        let cmd = XRead::try_from(request(&["xread", "block", "10", "streams", "s", "$"]))?;
+        assert_eq!(cmd.execute_blocking(&backend).await, REST_NIL_ARRAY.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,1023 @@
+use std::{time::Duration, vec::IntoIter};
+
+use crate::{
+    extract_args, extract_int, extract_string, validate_command_minimum_length, Backend, BlockedOp,
+    BulkString, ClaimOptions, CommandError, CommandExecutor, GroupEntry, GroupRead, RespArray,
+    RespFrame, RespMap, StreamId,
+};
+
+use super::{
+    stream::{
+        entries_frame, entry_frame, parse_range_bound, parse_read_args, parse_stream_id,
+        xread_reply,
+    },
+    REST_NIL, REST_NIL_ARRAY, REST_OK,
+};
+
+// XGROUP CREATE, DESTROY, SETID, CREATECONSUMER and DELCONSUMER
+#[derive(Debug)]
+pub struct XGroup {
+    key: String,
+    group: String,
+    op: XGroupOp,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum XGroupOp {
+    // the id is None for `$`
+    Create {
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    Destroy,
+    SetId {
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    },
+    CreateConsumer(String),
+    DelConsumer(String),
+}
+
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: String,
+    consumer: String,
+    count: Option<usize>,
+    // BLOCK in milliseconds, zero blocks forever
+    block: Option<u64>,
+    noack: bool,
+    streams: Vec<(String, GroupRead)>,
+}
+
+#[derive(Debug)]
+pub struct XAck {
+    key: String,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XPending {
+    key: String,
+    group: String,
+    // the extended form, without it a summary is returned
+    range: Option<PendingRange>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct PendingRange {
+    min_idle: Option<u64>,
+    start: StreamId,
+    end: StreamId,
+    count: usize,
+    consumer: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct XClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    ids: Vec<StreamId>,
+    options: ClaimOptions,
+}
+
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    justid: bool,
+}
+
+// XINFO STREAM, GROUPS and CONSUMERS
+#[derive(Debug)]
+pub struct XInfo {
+    key: String,
+    // the group whose consumers are listed, None for GROUPS
+    group: Option<String>,
+    stream: bool,
+}
+
+impl CommandExecutor for XGroup {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let (db, key, group) = (backend.db(), &self.key, &self.group);
+        let reply = match &self.op {
+            XGroupOp::Create {
+                id,
+                mkstream,
+                entries_read,
+            } => db
+                .xgroup_create(key, group, *id, *mkstream, *entries_read)
+                .map(|_| REST_OK.clone()),
+            XGroupOp::Destroy => db.xgroup_destroy(key, group).map(|destroyed| {
+                // readers blocked on the group get to see it is gone
+                backend.signal_ready(key);
+                RespFrame::Integer(destroyed as i64)
+            }),
+            XGroupOp::SetId { id, entries_read } => db
+                .xgroup_setid(key, group, *id, *entries_read)
+                .map(|_| REST_OK.clone()),
+            XGroupOp::CreateConsumer(consumer) => db
+                .xgroup_createconsumer(key, group, consumer)
+                .map(|created| RespFrame::Integer(created as i64)),
+            XGroupOp::DelConsumer(consumer) => db
+                .xgroup_delconsumer(key, group, consumer)
+                .map(RespFrame::Integer),
+        };
+        reply.unwrap_or_else(|e| e.into())
+    }
+}
+
+// `execute` never blocks, see `Command::run`
+impl CommandExecutor for XReadGroup {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match self.op(backend)(&backend.db()) {
+            Ok(Some(reply)) => reply,
+            Ok(None) => REST_NIL_ARRAY.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl XReadGroup {
+    pub async fn execute_blocking(&self, backend: &Backend) -> RespFrame {
+        let Some(block) = self.block else {
+            return self.execute(backend);
+        };
+        let keys = self.streams.iter().map(|(key, _)| key.clone()).collect();
+        let timeout = (block > 0).then(|| Duration::from_millis(block));
+        backend
+            .block_on(keys, timeout, None, self.op(backend))
+            .await
+            .unwrap_or_else(|| REST_NIL_ARRAY.clone())
+    }
+
+    fn op(&self, backend: &Backend) -> BlockedOp {
+        let (group, consumer) = (self.group.clone(), self.consumer.clone());
+        let (streams, count, noack) = (self.streams.clone(), self.count, self.noack);
+        let backend = backend.clone();
+        Box::new(move |db| {
+            let read = db.xreadgroup(&group, &consumer, &streams, count, noack)?;
+            let read = read
+                .into_iter()
+                .map(|(key, entries)| (key, group_entries_frame(entries)))
+                .collect::<Vec<_>>();
+            Ok((!read.is_empty()).then(|| xread_reply(&backend, read)))
+        })
+    }
+}
+
+impl CommandExecutor for XAck {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().xack(&self.key, &self.group, &self.ids) {
+            Ok(acked) => RespFrame::Integer(acked),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XPending {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let db = backend.db();
+        let Some(range) = &self.range else {
+            return match db.xpending_summary(&self.key, &self.group) {
+                Ok(summary) => {
+                    let (first, last) = match summary.bounds {
+                        Some((first, last)) => (id_frame(first), id_frame(last)),
+                        None => (REST_NIL.clone(), REST_NIL.clone()),
+                    };
+                    // without pending entries the consumers are a null array
+                    let consumers = summary
+                        .consumers
+                        .into_iter()
+                        .map(|(name, count)| {
+                            RespArray::new(vec![
+                                BulkString::new(name).into(),
+                                BulkString::new(count.to_string()).into(),
+                            ])
+                            .into()
+                        })
+                        .collect::<Vec<RespFrame>>();
+                    RespArray::new(vec![
+                        RespFrame::Integer(summary.count as i64),
+                        first,
+                        last,
+                        match consumers.is_empty() {
+                            true => REST_NIL_ARRAY.clone(),
+                            false => RespArray::new(consumers).into(),
+                        },
+                    ])
+                    .into()
+                }
+                Err(e) => e.into(),
+            };
+        };
+        match db.xpending(
+            &self.key,
+            &self.group,
+            range.start,
+            range.end,
+            range.count,
+            range.consumer.as_deref(),
+            range.min_idle,
+        ) {
+            Ok(pending) => {
+                let frames = pending
+                    .into_iter()
+                    .map(|detail| {
+                        RespArray::new(vec![
+                            id_frame(detail.id),
+                            BulkString::new(detail.consumer).into(),
+                            RespFrame::Integer(detail.idle as i64),
+                            RespFrame::Integer(detail.deliveries as i64),
+                        ])
+                        .into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(frames).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XClaim {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().xclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            &self.ids,
+            &self.options,
+        ) {
+            Ok(claimed) if self.options.justid => ids_frame(claimed.into_iter().map(|(id, _)| id)),
+            Ok(claimed) => entries_frame(claimed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XAutoClaim {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.justid,
+        ) {
+            Ok(result) => {
+                let claimed = if self.justid {
+                    ids_frame(result.claimed.into_iter().map(|(id, _)| id))
+                } else {
+                    entries_frame(result.claimed)
+                };
+                RespArray::new(vec![
+                    id_frame(result.next),
+                    claimed,
+                    ids_frame(result.deleted.into_iter()),
+                ])
+                .into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XInfo {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let db = backend.db();
+        if self.stream {
+            return match db.xinfo_stream(&self.key) {
+                Ok(info) => {
+                    let entry =
+                        |entry: Option<_>| entry.map(entry_frame).unwrap_or(REST_NIL.clone());
+                    // entries are kept in nodes of up to 100 like the radix tree of Redis
+                    let nodes = info.length.div_ceil(100) as i64;
+                    info_reply(
+                        backend,
+                        vec![
+                            ("length", RespFrame::Integer(info.length as i64)),
+                            ("radix-tree-keys", RespFrame::Integer(nodes)),
+                            ("radix-tree-nodes", RespFrame::Integer(nodes + 1)),
+                            ("last-generated-id", id_frame(info.last_id)),
+                            ("max-deleted-entry-id", id_frame(info.max_deleted_id)),
+                            (
+                                "entries-added",
+                                RespFrame::Integer(info.entries_added as i64),
+                            ),
+                            ("recorded-first-entry-id", id_frame(info.first_id)),
+                            ("groups", RespFrame::Integer(info.groups as i64)),
+                            ("first-entry", entry(info.first_entry)),
+                            ("last-entry", entry(info.last_entry)),
+                        ],
+                    )
+                }
+                Err(e) => e.into(),
+            };
+        }
+        let optional =
+            |n: Option<u64>| n.map_or(REST_NIL.clone(), |n| RespFrame::Integer(n as i64));
+        let frames = match &self.group {
+            None => db.xinfo_groups(&self.key).map(|groups| {
+                groups
+                    .into_iter()
+                    .map(|group| {
+                        info_reply(
+                            backend,
+                            vec![
+                                ("name", BulkString::new(group.name).into()),
+                                ("consumers", RespFrame::Integer(group.consumers as i64)),
+                                ("pending", RespFrame::Integer(group.pending as i64)),
+                                ("last-delivered-id", id_frame(group.last_id)),
+                                ("entries-read", optional(group.entries_read)),
+                                ("lag", optional(group.lag)),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            }),
+            Some(group) => db.xinfo_consumers(&self.key, group).map(|consumers| {
+                consumers
+                    .into_iter()
+                    .map(|consumer| {
+                        let inactive = consumer.inactive.map_or(-1, |at| at as i64);
+                        info_reply(
+                            backend,
+                            vec![
+                                ("name", BulkString::new(consumer.name).into()),
+                                ("pending", RespFrame::Integer(consumer.pending as i64)),
+                                ("idle", RespFrame::Integer(consumer.idle as i64)),
+                                ("inactive", RespFrame::Integer(inactive)),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            }),
+        };
+        match frames {
+            Ok(frames) => RespArray::new(frames).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn id_frame(id: StreamId) -> RespFrame {
+    BulkString::new(id.to_string()).into()
+}
+
+fn ids_frame(ids: impl Iterator<Item = StreamId>) -> RespFrame {
+    RespArray::new(ids.map(id_frame).collect::<Vec<_>>()).into()
+}
+
+// entries read by a consumer, those deleted from the stream meanwhile as `[id, nil]`
+fn group_entries_frame(entries: Vec<GroupEntry>) -> RespFrame {
+    let frames = entries
+        .into_iter()
+        .map(|(id, fields)| match fields {
+            Some(fields) => entry_frame((id, fields)),
+            None => RespArray::new(vec![id_frame(id), REST_NIL_ARRAY.clone()]).into(),
+        })
+        .collect::<Vec<_>>();
+    RespArray::new(frames).into()
+}
+
+// a map in RESP3, otherwise a flat array of names and values
+fn info_reply(backend: &Backend, fields: Vec<(&str, RespFrame)>) -> RespFrame {
+    if backend.session().is_resp3() {
+        let mut map = RespMap::new();
+        for (name, value) in fields {
+            map.insert(name.to_string(), value);
+        }
+        map.into()
+    } else {
+        let mut frames = Vec::with_capacity(fields.len() * 2);
+        for (name, value) in fields {
+            frames.push(BulkString::new(name).into());
+            frames.push(value);
+        }
+        RespArray::new(frames).into()
+    }
+}
+
+//xgroup :"*5\r\n$6\r\nxgroup\r\n$6\r\ncreate\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\n$\r\n"
+impl TryFrom<RespArray> for XGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xgroup"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let sub = extract_string(args.next().ok_or_else(syntax_error)?)?.to_ascii_lowercase();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let group = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let op = match sub.as_str() {
+            "create" => {
+                let id = parse_group_id(args.next())?;
+                let (mut mkstream, mut entries_read) = (false, None);
+                while let Some(option) = args.next() {
+                    match extract_string(option)?.to_ascii_lowercase().as_str() {
+                        "mkstream" => mkstream = true,
+                        "entriesread" => entries_read = parse_entries_read(args.next())?,
+                        _ => return Err(syntax_error()),
+                    }
+                }
+                XGroupOp::Create {
+                    id,
+                    mkstream,
+                    entries_read,
+                }
+            }
+            "setid" => {
+                let id = parse_group_id(args.next())?;
+                let entries_read = match args.next().map(extract_string).transpose()? {
+                    None => None,
+                    Some(option) if option.eq_ignore_ascii_case("entriesread") => {
+                        parse_entries_read(args.next())?
+                    }
+                    Some(_) => return Err(syntax_error()),
+                };
+                XGroupOp::SetId { id, entries_read }
+            }
+            "destroy" => XGroupOp::Destroy,
+            "createconsumer" | "delconsumer" => {
+                let consumer = extract_string(args.next().ok_or_else(syntax_error)?)?;
+                if sub == "createconsumer" {
+                    XGroupOp::CreateConsumer(consumer)
+                } else {
+                    XGroupOp::DelConsumer(consumer)
+                }
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try XGROUP HELP.",
+                    sub
+                )))
+            }
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XGroup { key, group, op })
+    }
+}
+
+//xreadgroup :"*7\r\n$10\r\nxreadgroup\r\n$5\r\ngroup\r\n$1\r\ng\r\n$1\r\nc\r\n$7\r\nstreams\r\n$1\r\ns\r\n$1\r\n>\r\n"
+impl TryFrom<RespArray> for XReadGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xreadgroup"], 6)?;
+        let mut args = extract_args(value)?.into_iter();
+        let option = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        if !option.eq_ignore_ascii_case("group") {
+            return Err(CommandError::InvalidArgument(
+                "Missing GROUP option for XREADGROUP".to_string(),
+            ));
+        }
+        let group = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let consumer = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let read = parse_read_args("xreadgroup", &mut args)?;
+        let streams = read
+            .streams
+            .into_iter()
+            .map(|(key, id)| {
+                let from = match id.as_str() {
+                    ">" => GroupRead::New,
+                    id => GroupRead::History(parse_stream_id(id, 0)?),
+                };
+                Ok((key, from))
+            })
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        Ok(XReadGroup {
+            group,
+            consumer,
+            count: read.count,
+            block: read.block,
+            noack: read.noack,
+            streams,
+        })
+    }
+}
+
+//xack :"*4\r\n$4\r\nxack\r\n$1\r\ns\r\n$1\r\ng\r\n$3\r\n1-0\r\n"
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xack"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let group = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        Ok(XAck {
+            key,
+            group,
+            ids: parse_ids(args)?,
+        })
+    }
+}
+
+//xpending :"*3\r\n$8\r\nxpending\r\n$1\r\ns\r\n$1\r\ng\r\n"
+impl TryFrom<RespArray> for XPending {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xpending"], 2)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let group = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        if args.peek().is_none() {
+            return Ok(XPending {
+                key,
+                group,
+                range: None,
+            });
+        }
+        let mut start = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let mut min_idle = None;
+        if start.eq_ignore_ascii_case("idle") {
+            min_idle = Some(parse_min_idle(args.next())?);
+            start = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        }
+        let end = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let count = extract_int(args.next().ok_or_else(syntax_error)?)?.max(0) as usize;
+        let consumer = args.next().map(extract_string).transpose()?;
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XPending {
+            key,
+            group,
+            range: Some(PendingRange {
+                min_idle,
+                start: parse_range_bound(&start, false)?,
+                end: parse_range_bound(&end, true)?,
+                count,
+                consumer,
+            }),
+        })
+    }
+}
+
+//xclaim :"*6\r\n$6\r\nxclaim\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\nc\r\n$1\r\n0\r\n$3\r\n1-0\r\n"
+impl TryFrom<RespArray> for XClaim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xclaim"], 5)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let group = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let consumer = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let min_idle = parse_min_idle(args.next())?;
+        // ids come first, the options start with the first argument that is not an id
+        let mut ids = Vec::new();
+        while let Some(RespFrame::BulkString(arg)) = args.peek() {
+            match StreamId::parse(&String::from_utf8_lossy(arg), 0) {
+                Some(id) => ids.push(id),
+                None if ids.is_empty() => return Err(invalid_stream_id()),
+                None => break,
+            }
+            args.next();
+        }
+        let mut options = ClaimOptions::default();
+        while let Some(option) = args.next() {
+            let mut value = || extract_int(args.next().ok_or_else(syntax_error)?);
+            match extract_string(option)?.to_ascii_lowercase().as_str() {
+                "idle" => options.idle = Some(value()?.max(0) as u64),
+                "time" => options.time = Some(value()?.max(0) as u64),
+                "retrycount" => options.retry_count = Some(value()?.max(0) as u64),
+                "force" => options.force = true,
+                "justid" => options.justid = true,
+                "lastid" => {
+                    let id = extract_string(args.next().ok_or_else(syntax_error)?)?;
+                    options.last_id = Some(parse_stream_id(&id, 0)?);
+                }
+                option => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Unrecognized XCLAIM option '{}'",
+                        option
+                    )))
+                }
+            }
+        }
+        Ok(XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            options,
+        })
+    }
+}
+
+//xautoclaim :"*6\r\n$10\r\nxautoclaim\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\nc\r\n$1\r\n0\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for XAutoClaim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xautoclaim"], 5)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let group = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let consumer = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let min_idle = parse_min_idle(args.next())?;
+        let start = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let (mut count, mut justid) = (100, false);
+        while let Some(option) = args.next() {
+            match extract_string(option)?.to_ascii_lowercase().as_str() {
+                "count" => {
+                    count = match extract_int(args.next().ok_or_else(syntax_error)?)? {
+                        n if n > 0 && n <= i64::MAX / 10 => n as usize,
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "COUNT must be > 0".to_string(),
+                            ))
+                        }
+                    }
+                }
+                "justid" => justid = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start: parse_range_bound(&start, false)?,
+            count,
+            justid,
+        })
+    }
+}
+
+//xinfo :"*3\r\n$5\r\nxinfo\r\n$6\r\nstream\r\n$1\r\ns\r\n"
+impl TryFrom<RespArray> for XInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["xinfo"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let sub = extract_string(args.next().ok_or_else(syntax_error)?)?.to_ascii_lowercase();
+        let key = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        let info = match sub.as_str() {
+            "stream" => XInfo {
+                key,
+                group: None,
+                stream: true,
+            },
+            "groups" => XInfo {
+                key,
+                group: None,
+                stream: false,
+            },
+            "consumers" => XInfo {
+                key,
+                group: Some(extract_string(args.next().ok_or_else(syntax_error)?)?),
+                stream: false,
+            },
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'. Try XINFO HELP.",
+                    sub
+                )))
+            }
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(info)
+    }
+}
+
+// an id or `$`, which is None
+fn parse_group_id(id: Option<RespFrame>) -> Result<Option<StreamId>, CommandError> {
+    let id = extract_string(id.ok_or_else(syntax_error)?)?;
+    match id.as_str() {
+        "$" => Ok(None),
+        id => parse_stream_id(id, 0).map(Some),
+    }
+}
+
+// ENTRIESREAD, where -1 means unknown
+fn parse_entries_read(value: Option<RespFrame>) -> Result<Option<u64>, CommandError> {
+    match extract_int(value.ok_or_else(syntax_error)?)? {
+        -1 => Ok(None),
+        n if n >= 0 => Ok(Some(n as u64)),
+        _ => Err(CommandError::InvalidArgument(
+            "value for ENTRIESREAD must be positive or -1".to_string(),
+        )),
+    }
+}
+
+fn parse_min_idle(value: Option<RespFrame>) -> Result<u64, CommandError> {
+    let idle = extract_int(value.ok_or_else(syntax_error)?).map_err(|_| {
+        CommandError::InvalidArgument("Invalid min-idle-time argument for XCLAIM".to_string())
+    })?;
+    Ok(idle.max(0) as u64)
+}
+
+fn parse_ids(args: IntoIter<RespFrame>) -> Result<Vec<StreamId>, CommandError> {
+    args.map(|id| parse_stream_id(&extract_string(id)?, 0))
+        .collect()
+}
+
+fn invalid_stream_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{bulks, decode, request};
+    use crate::BackendError;
+    use anyhow::Result;
+
+    fn run(backend: &Backend, args: &[&str]) -> Result<RespFrame> {
+        let cmd = crate::Command::try_from(RespFrame::from(request(args)))?;
+        Ok(cmd.execute(backend))
+    }
+
+    fn entry(id: &str, value: &str) -> RespFrame {
+        RespArray::new(vec![BulkString::new(id).into(), bulks(&["f", value])]).into()
+    }
+
+    fn setup() -> Result<Backend> {
+        let backend = Backend::new();
+        for id in ["1-0", "2-0", "3-0"] {
+            run(&backend, &["xadd", "s", id, "f", id])?;
+        }
+        assert_eq!(
+            run(&backend, &["xgroup", "create", "s", "g", "0"])?,
+            REST_OK.clone()
+        );
+        Ok(backend)
+    }
+
+    #[test]
+    fn test_xgroup_tryfrom() -> Result<()> {
+        let cmd = XGroup::try_from(decode(
+            b"*5\r\n$6\r\nxgroup\r\n$6\r\ncreate\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\n$\r\n",
+        )?)?;
+        assert_eq!(
+            cmd.op,
+            XGroupOp::Create {
+                id: None,
+                mkstream: false,
+                entries_read: None,
+            }
+        );
+        let cmd = XGroup::try_from(request(&[
+            "xgroup",
+            "setid",
+            "s",
+            "g",
+            "5-1",
+            "entriesread",
+            "3",
+        ]))?;
+        assert_eq!(
+            cmd.op,
+            XGroupOp::SetId {
+                id: Some(StreamId::new(5, 1)),
+                entries_read: Some(3),
+            }
+        );
+        let err = XGroup::try_from(request(&["xgroup", "rename", "s", "g"])).unwrap_err();
+        assert_eq!(
+            err,
+            CommandError::InvalidArgument(
+                "unknown subcommand 'rename'. Try XGROUP HELP.".to_string()
+            )
+        );
+
+        let cmd = XPending::try_from(request(&[
+            "xpending", "s", "g", "idle", "100", "-", "+", "10", "c",
+        ]))?;
+        assert_eq!(
+            cmd.range,
+            Some(PendingRange {
+                min_idle: Some(100),
+                start: StreamId::MIN,
+                end: StreamId::MAX,
+                count: 10,
+                consumer: Some("c".to_string()),
+            })
+        );
+
+        let cmd = XClaim::try_from(request(&[
+            "xclaim",
+            "s",
+            "g",
+            "c",
+            "10",
+            "1-0",
+            "2",
+            "retrycount",
+            "3",
+            "justid",
+        ]))?;
+        assert_eq!(cmd.ids, [StreamId::new(1, 0), StreamId::new(2, 0)]);
+        assert_eq!(cmd.options.retry_count, Some(3));
+        assert!(cmd.options.justid);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xreadgroup_and_ack() -> Result<()> {
+        let backend = setup()?;
+        let reply = run(
+            &backend,
+            &[
+                "xreadgroup",
+                "group",
+                "g",
+                "alice",
+                "count",
+                "2",
+                "streams",
+                "s",
+                ">",
+            ],
+        )?;
+        let expected: RespFrame = RespArray::new(vec![RespArray::new(vec![
+            BulkString::new("s").into(),
+            RespArray::new(vec![entry("1-0", "1-0"), entry("2-0", "2-0")]).into(),
+        ])
+        .into()])
+        .into();
+        assert_eq!(reply, expected);
+
+        // the summary and the extended form of the pending entries
+        let expected: RespFrame = RespArray::new(vec![
+            RespFrame::Integer(2),
+            BulkString::new("1-0").into(),
+            BulkString::new("2-0").into(),
+            RespArray::new(vec![bulks(&["alice", "2"])]).into(),
+        ])
+        .into();
+        assert_eq!(run(&backend, &["xpending", "s", "g"])?, expected);
+        let RespFrame::Array(pending) = run(&backend, &["xpending", "s", "g", "-", "+", "1"])?
+        else {
+            panic!("expected an array");
+        };
+        let RespFrame::Array(first) = &pending[0] else {
+            panic!("expected an array");
+        };
+        assert_eq!(first[1], BulkString::new("alice").into());
+        assert_eq!(first[3], RespFrame::Integer(1));
+
+        assert_eq!(
+            run(&backend, &["xack", "s", "g", "1-0", "2-0", "3-0"])?,
+            RespFrame::Integer(2)
+        );
+        assert_eq!(
+            run(&backend, &["xpending", "s", "g"])?,
+            RespArray::new(vec![
+                RespFrame::Integer(0),
+                REST_NIL.clone(),
+                REST_NIL.clone(),
+                REST_NIL_ARRAY.clone(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            run(
+                &backend,
+                &["xreadgroup", "group", "x", "c", "streams", "s", ">"]
+            )?,
+            BackendError::NoGroup("s".to_string(), "x".to_string()).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xclaim_xautoclaim_xinfo() -> Result<()> {
+        let backend = setup()?;
+        run(
+            &backend,
+            &["xreadgroup", "group", "g", "alice", "streams", "s", ">"],
+        )?;
+        assert_eq!(
+            run(&backend, &["xclaim", "s", "g", "bob", "0", "1-0", "justid"])?,
+            bulks(&["1-0"])
+        );
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::new("3-0").into(),
+            RespArray::new(vec![entry("1-0", "1-0"), entry("2-0", "2-0")]).into(),
+            RespArray::new(vec![]).into(),
+        ])
+        .into();
+        assert_eq!(
+            run(
+                &backend,
+                &["xautoclaim", "s", "g", "carol", "0", "-", "count", "2"]
+            )?,
+            expected
+        );
+
+        let info = run(&backend, &["xinfo", "groups", "s"])?;
+        let expected: RespFrame = RespArray::new(vec![RespArray::new(vec![
+            BulkString::new("name").into(),
+            BulkString::new("g").into(),
+            BulkString::new("consumers").into(),
+            RespFrame::Integer(3),
+            BulkString::new("pending").into(),
+            RespFrame::Integer(3),
+            BulkString::new("last-delivered-id").into(),
+            BulkString::new("3-0").into(),
+            BulkString::new("entries-read").into(),
+            RespFrame::Integer(3),
+            BulkString::new("lag").into(),
+            RespFrame::Integer(0),
+        ])
+        .into()])
+        .into();
+        assert_eq!(info, expected);
+
+        backend.session().set_protocol(3);
+        let RespFrame::Map(info) = run(&backend, &["xinfo", "stream", "s"])? else {
+            panic!("expected a map");
+        };
+        assert_eq!(info["length"], RespFrame::Integer(3));
+        assert_eq!(info["last-entry"], entry("3-0", "3-0"));
+        let RespFrame::Array(consumers) = run(&backend, &["xinfo", "consumers", "s", "g"])? else {
+            panic!("expected an array");
+        };
+        assert_eq!(consumers.len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xreadgroup_block() -> Result<()> {
+        let backend = setup()?;
+        run(&backend, &["xgroup", "setid", "s", "g", "$"])?;
+        let reader = backend.connect();
+        let cmd = XReadGroup::try_from(request(&[
+            "xreadgroup",
+            "group",
+            "g",
+            "c",
+            "block",
+            "0",
+            "streams",
+            "s",
+            ">",
+        ]))?;
+        let task = tokio::spawn(async move { cmd.execute_blocking(&reader).await });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        run(&backend, &["xadd", "s", "4-0", "f", "4-0"])?;
+        let expected: RespFrame = RespArray::new(vec![RespArray::new(vec![
+            BulkString::new("s").into(),
+            RespArray::new(vec![entry("4-0", "4-0")]).into(),
+        ])
+        .into()])
+        .into();
+        assert_eq!(task.await?, expected);
+
+        // destroying the group wakes its readers up with an error
+        let reader = backend.connect();
+        let cmd = XReadGroup::try_from(request(&[
+            "xreadgroup",
+            "group",
+            "g",
+            "c",
+            "block",
+            "0",
+            "streams",
+            "s",
+            ">",
+        ]))?;
+        let task = tokio::spawn(async move { cmd.execute_blocking(&reader).await });
+        while backend.blocked_clients() < 1 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            run(&backend, &["xgroup", "destroy", "s", "g"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            task.await?,
+            BackendError::NoGroup("s".to_string(), "g".to_string()).into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{
+    extract_strings, validate_command_exact_length, validate_command_minimum_length, Backend,
+    Command, CommandError, CommandExecutor, RespArray, RespFrame, SimpleError, SimpleString,
+};
+
+use super::{REST_NIL_ARRAY, REST_OK};
+
+// the commands that still run right away while a transaction is queueing
+const IMMEDIATE_COMMANDS: [&str; 6] = ["exec", "discard", "multi", "watch", "quit", "reset"];
+
+#[derive(Debug)]
+pub struct Multi;
+
+#[derive(Debug)]
+pub struct Exec;
+
+#[derive(Debug)]
+pub struct Discard;
+
+#[derive(Debug)]
+pub struct Watch {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Unwatch;
+
+impl CommandExecutor for Multi {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if !backend.multi() {
+            return SimpleError::new("ERR MULTI calls can not be nested".to_string()).into();
+        }
+        REST_OK.clone()
+    }
+}
+
+// the queued commands run while the server is held exclusively (see `Command::run`), so no
+// other client sees the transaction half done. Nil is returned when a watched key changed
+// since WATCH.
+impl CommandExecutor for Exec {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let Some(queued) = backend.take_transaction() else {
+            return SimpleError::new("ERR EXEC without MULTI".to_string()).into();
+        };
+        let changed = backend.watched_keys_changed();
+        backend.unwatch();
+        if queued.aborted {
+            return SimpleError::new(
+                "EXECABORT Transaction discarded because of previous errors.".to_string(),
+            )
+            .into();
+        }
+        if changed {
+            return REST_NIL_ARRAY.clone();
+        }
+        let replies = queued
+            .requests
+            .into_iter()
+            .map(|request| match Command::try_from(request) {
+                Ok(cmd) => cmd.execute(backend),
+                Err(e) => e.into(),
+            })
+            .collect::<Vec<_>>();
+        RespArray::new(replies).into()
+    }
+}
+
+impl CommandExecutor for Discard {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if backend.take_transaction().is_none() {
+            return SimpleError::new("ERR DISCARD without MULTI".to_string()).into();
+        }
+        backend.unwatch();
+        REST_OK.clone()
+    }
+}
+
+impl CommandExecutor for Watch {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        if backend.in_multi() {
+            return SimpleError::new("ERR WATCH inside MULTI is not allowed".to_string()).into();
+        }
+        backend.watch(&self.keys);
+        REST_OK.clone()
+    }
+}
+
+impl CommandExecutor for Unwatch {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        backend.unwatch();
+        REST_OK.clone()
+    }
+}
+
+// the reply to a request made between MULTI and EXEC, None for the commands that run right
+// away. The others are only checked and queued, one that fails to parse or is unknown aborts
+// the transaction.
+pub(crate) fn queue_in_transaction(backend: &Backend, frame: &RespFrame) -> Option<RespFrame> {
+    if !backend.in_multi() {
+        return None;
+    }
+    if let RespFrame::Array(array) = frame {
+        if let Some(RespFrame::BulkString(name)) = array.first() {
+            let name = String::from_utf8_lossy(name).to_lowercase();
+            if IMMEDIATE_COMMANDS.contains(&name.as_str()) {
+                return None;
+            }
+        }
+    }
+    match Command::try_from(frame.clone()) {
+        Ok(Command::UnRecognized(_)) => {
+            backend.abort_transaction();
+            Some(unknown_command(frame).into())
+        }
+        Ok(_) => {
+            backend.queue_request(frame.clone());
+            Some(SimpleString::new("QUEUED").into())
+        }
+        Err(e) => {
+            backend.abort_transaction();
+            Some(e.into())
+        }
+    }
+}
+
+// the error of redis for a command it does not know, naming the first arguments
+fn unknown_command(frame: &RespFrame) -> CommandError {
+    let mut parts = match frame {
+        RespFrame::Array(array) => array.iter().filter_map(|part| match part {
+            RespFrame::BulkString(part) => Some(String::from_utf8_lossy(part).into_owned()),
+            _ => None,
+        }),
+        _ => return CommandError::InvalidCommand("unknown command".to_string()),
+    };
+    let name = parts.next().unwrap_or_default();
+    let args = parts
+        .take(16)
+        .map(|arg| format!("'{}' ", arg))
+        .collect::<String>();
+    CommandError::InvalidCommand(format!(
+        "unknown command '{}', with args beginning with: {}",
+        name, args
+    ))
+}
+
+//multi :"*1\r\n$5\r\nmulti\r\n"
+impl TryFrom<RespArray> for Multi {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["multi"], 0)?;
+        Ok(Multi)
+    }
+}
+
+//exec :"*1\r\n$4\r\nexec\r\n"
+impl TryFrom<RespArray> for Exec {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["exec"], 0)?;
+        Ok(Exec)
+    }
+}
+
+//discard :"*1\r\n$7\r\ndiscard\r\n"
+impl TryFrom<RespArray> for Discard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["discard"], 0)?;
+        Ok(Discard)
+    }
+}
+
+//watch :"*3\r\n$5\r\nwatch\r\n$1\r\na\r\n$1\r\nb\r\n"
+impl TryFrom<RespArray> for Watch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["watch"], 1)?;
+        Ok(Watch {
+            keys: extract_strings(value)?,
+        })
+    }
+}
+
+//unwatch :"*1\r\n$7\r\nunwatch\r\n"
+impl TryFrom<RespArray> for Unwatch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["unwatch"], 0)?;
+        Ok(Unwatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::request;
+    use crate::{now_ms, BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    // what the connection does with a request, queueing it during a transaction
+    async fn handle(backend: &Backend, args: &[&str]) -> RespFrame {
+        let frame = RespFrame::from(request(args));
+        match queue_in_transaction(backend, &frame) {
+            Some(reply) => reply,
+            None => match Command::try_from(frame) {
+                Ok(cmd) => cmd.run(backend).await,
+                Err(e) => e.into(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_transaction_commands_tryfrom() -> Result<()> {
+        let mut buf = BytesMut::from(&b"*3\r\n$5\r\nwatch\r\n$1\r\na\r\n$1\r\nb\r\n"[..]);
+        let cmd = Watch::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.keys, vec!["a".to_string(), "b".to_string()]);
+
+        let mut buf = BytesMut::from(&b"*2\r\n$5\r\nmulti\r\n$1\r\na\r\n"[..]);
+        assert!(Multi::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multi_exec() {
+        let backend = Backend::new();
+        assert_eq!(handle(&backend, &["multi"]).await, REST_OK.clone());
+        assert!(matches!(
+            handle(&backend, &["multi"]).await,
+            RespFrame::Error(_)
+        ));
+        let queued: RespFrame = SimpleString::new("QUEUED").into();
+        assert_eq!(handle(&backend, &["set", "a", "1"]).await, queued);
+        assert_eq!(handle(&backend, &["lpush", "a", "x"]).await, queued);
+        assert_eq!(handle(&backend, &["get", "a"]).await, queued);
+        assert_eq!(backend.db().get("a"), Ok(None));
+        assert_eq!(
+            handle(&backend, &["exec"]).await,
+            RespArray::new(vec![
+                REST_OK.clone(),
+                SimpleError::new(
+                    "WRONGTYPE Operation against a key holding the wrong kind of value".to_string()
+                )
+                .into(),
+                BulkString::new("1").into(),
+            ])
+            .into()
+        );
+        assert!(matches!(
+            handle(&backend, &["exec"]).await,
+            RespFrame::Error(_)
+        ));
+
+        handle(&backend, &["multi"]).await;
+        handle(&backend, &["set", "a", "2"]).await;
+        assert_eq!(handle(&backend, &["discard"]).await, REST_OK.clone());
+        assert_eq!(backend.db().get("a"), Ok(Some(BulkString::new("1").into())));
+        assert!(matches!(
+            handle(&backend, &["discard"]).await,
+            RespFrame::Error(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_exec_abort() {
+        let backend = Backend::new();
+        handle(&backend, &["multi"]).await;
+        handle(&backend, &["set", "a", "1"]).await;
+        assert!(matches!(
+            handle(&backend, &["set", "a"]).await,
+            RespFrame::Error(_)
+        ));
+        assert_eq!(
+            handle(&backend, &["exec"]).await,
+            SimpleError::new(
+                "EXECABORT Transaction discarded because of previous errors.".to_string()
+            )
+            .into()
+        );
+        assert_eq!(backend.db().get("a"), Ok(None));
+        assert!(!backend.in_multi());
+
+        // an unknown command is not queued either
+        handle(&backend, &["multi"]).await;
+        handle(&backend, &["set", "a", "1"]).await;
+        assert_eq!(
+            handle(&backend, &["nosuchcommand", "x", "y"]).await,
+            SimpleError::new(
+                "ERR unknown command 'nosuchcommand', with args beginning with: 'x' 'y' "
+                    .to_string()
+            )
+            .into()
+        );
+        assert_eq!(
+            handle(&backend, &["exec"]).await,
+            SimpleError::new(
+                "EXECABORT Transaction discarded because of previous errors.".to_string()
+            )
+            .into()
+        );
+        assert_eq!(backend.db().get("a"), Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let server = Backend::new();
+        let client = server.connect();
+        let other = server.connect();
+
+        // an untouched watched key lets the transaction run
+        handle(&client, &["watch", "a"]).await;
+        handle(&client, &["multi"]).await;
+        assert!(matches!(
+            handle(&client, &["watch", "b"]).await,
+            RespFrame::Error(_)
+        ));
+        handle(&client, &["set", "a", "1"]).await;
+        assert_eq!(
+            handle(&client, &["exec"]).await,
+            RespArray::new(vec![REST_OK.clone()]).into()
+        );
+
+        // a modification by another client makes EXEC fail, and releases the watch
+        handle(&client, &["watch", "a"]).await;
+        handle(&other, &["set", "a", "2"]).await;
+        handle(&client, &["multi"]).await;
+        handle(&client, &["set", "a", "3"]).await;
+        assert_eq!(handle(&client, &["exec"]).await, REST_NIL_ARRAY.clone());
+        assert_eq!(server.db().get("a"), Ok(Some(BulkString::new("2").into())));
+        handle(&client, &["multi"]).await;
+        handle(&client, &["set", "a", "3"]).await;
+        assert_eq!(
+            handle(&client, &["exec"]).await,
+            RespArray::new(vec![REST_OK.clone()]).into()
+        );
+
+        // as does a new ttl on the key
+        handle(&client, &["watch", "a"]).await;
+        server.db().expire_at("a", Some(now_ms() + 60_000));
+        handle(&client, &["multi"]).await;
+        assert_eq!(handle(&client, &["exec"]).await, REST_NIL_ARRAY.clone());
+
+        // and flushing the database of an existing key
+        handle(&client, &["watch", "a"]).await;
+        handle(&other, &["flushall"]).await;
+        handle(&client, &["multi"]).await;
+        assert_eq!(handle(&client, &["exec"]).await, REST_NIL_ARRAY.clone());
+
+        // UNWATCH forgets about the keys
+        handle(&client, &["watch", "a"]).await;
+        handle(&other, &["set", "a", "4"]).await;
+        handle(&client, &["unwatch"]).await;
+        handle(&client, &["multi"]).await;
+        assert_eq!(
+            handle(&client, &["exec"]).await,
+            RespArray::new(vec![]).into()
+        );
+    }
+}
@@ -0,0 +1,1182 @@
+use crate::{
+    command_name, extract_args, extract_int, extract_string, validate_command_exact_length,
+    validate_command_minimum_length, Backend, BulkString, CommandError, CommandExecutor, LexBound,
+    LexRange, RespArray, RespFrame, ScoreBound, ScoreRange, SetCondition, ZAddOptions, ZPopped,
+    ZRangeSpec,
+};
+
+use super::{
+    keyspace::{parse_scan_args, scan_reply},
+    REST_NIL, REST_NIL_ARRAY,
+};
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    options: ZAddOptions,
+    incr: bool,
+    pairs: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZMScore {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: String,
+    incr: f64,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: String,
+    range: ScoreRange,
+}
+
+// ZRANK and ZREVRANK
+#[derive(Debug)]
+pub struct ZRank {
+    key: String,
+    member: String,
+    rev: bool,
+    withscore: bool,
+}
+
+// ZRANGE and its older forms ZREVRANGE, ZRANGEBYSCORE, ZREVRANGEBYSCORE, ZRANGEBYLEX and
+// ZREVRANGEBYLEX
+#[derive(Debug)]
+pub struct ZRange {
+    key: String,
+    range: ZRangeArgs,
+}
+
+#[derive(Debug)]
+pub struct ZRangeStore {
+    dst: String,
+    src: String,
+    range: ZRangeArgs,
+}
+
+// ZPOPMIN and ZPOPMAX
+#[derive(Debug)]
+pub struct ZPop {
+    key: String,
+    max: bool,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZRandMember {
+    key: String,
+    count: Option<i64>,
+    withscores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZScan {
+    key: String,
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct ZLexCount {
+    key: String,
+    range: LexRange,
+}
+
+// ZREMRANGEBYRANK, ZREMRANGEBYSCORE and ZREMRANGEBYLEX
+#[derive(Debug)]
+pub struct ZRemRange {
+    key: String,
+    spec: ZRangeSpec,
+}
+
+#[derive(Debug)]
+pub struct ZMPop {
+    keys: Vec<String>,
+    max: bool,
+    count: usize,
+}
+
+#[derive(Debug, PartialEq)]
+struct ZRangeArgs {
+    spec: ZRangeSpec,
+    rev: bool,
+    limit: Option<(i64, i64)>,
+    withscores: bool,
+}
+
+impl CommandExecutor for ZAdd {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let db = backend.db();
+        if self.incr {
+            let (score, member) = self.pairs[0].clone();
+            return match db.zadd_incr(self.key.clone(), self.options, score, member) {
+                Ok(Some(score)) => {
+                    backend.signal_ready(&self.key);
+                    score_frame(backend, score)
+                }
+                Ok(None) => REST_NIL.clone(),
+                Err(e) => e.into(),
+            };
+        }
+        match db.zadd(self.key.clone(), self.options, self.pairs.clone()) {
+            Ok(count) => {
+                backend.signal_ready(&self.key);
+                RespFrame::Integer(count)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zrem(&self.key, &self.members) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zscore(&self.key, &self.member) {
+            Ok(Some(score)) => score_frame(backend, score),
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZMScore {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zmscore(&self.key, &self.members) {
+            Ok(scores) => RespArray::new(
+                scores
+                    .into_iter()
+                    .map(|score| match score {
+                        Some(score) => score_frame(backend, score),
+                        None => REST_NIL.clone(),
+                    })
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zadd_incr(
+            self.key.clone(),
+            ZAddOptions::default(),
+            self.incr,
+            self.member.clone(),
+        ) {
+            Ok(score) => {
+                backend.signal_ready(&self.key);
+                score_frame(backend, score.unwrap_or_default())
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zcard(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zcount(&self.key, &self.range) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRank {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zrank(&self.key, &self.member, self.rev) {
+            Ok(Some((rank, score))) if self.withscore => {
+                RespArray::new(vec![RespFrame::Integer(rank), score_frame(backend, score)]).into()
+            }
+            Ok(Some((rank, _))) => RespFrame::Integer(rank),
+            Ok(None) if self.withscore => REST_NIL_ARRAY.clone(),
+            Ok(None) => REST_NIL.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let range = &self.range;
+        match backend
+            .db()
+            .zrange(&self.key, &range.spec, range.rev, range.limit)
+        {
+            Ok(members) => scored_reply(backend, members, range.withscores),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRangeStore {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let range = &self.range;
+        match backend.db().zrangestore(
+            self.dst.clone(),
+            &self.src,
+            &range.spec,
+            range.rev,
+            range.limit,
+        ) {
+            Ok(len) => {
+                backend.signal_ready(&self.dst);
+                RespFrame::Integer(len)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .zpop(&self.key, self.max, self.count.unwrap_or(1))
+        {
+            // RESP3 clients get (member, score) pairs when a count was given
+            Ok(popped) if self.count.is_some() => scored_reply(backend, popped, true),
+            Ok(popped) => RespArray::new(
+                popped
+                    .into_iter()
+                    .flat_map(|(member, score)| {
+                        [BulkString::new(member).into(), score_frame(backend, score)]
+                    })
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRandMember {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let members = match backend.db().zrandmember(&self.key, self.count) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        // without a count the reply is a single member, or nil for a missing key
+        if self.count.is_none() {
+            return match members.into_iter().next() {
+                Some((member, _)) => BulkString::new(member).into(),
+                None => REST_NIL.clone(),
+            };
+        }
+        scored_reply(backend, members, self.withscores)
+    }
+}
+
+impl CommandExecutor for ZScan {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend
+            .db()
+            .zscan(&self.key, self.cursor, self.pattern.as_deref(), self.count)
+        {
+            Ok((cursor, members)) => {
+                let mut frames = Vec::with_capacity(members.len() * 2);
+                for (member, score) in members {
+                    frames.push(BulkString::new(member).into());
+                    frames.push(BulkString::new(format_score(score)).into());
+                }
+                scan_reply(cursor, frames)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZLexCount {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zlexcount(&self.key, &self.range) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRemRange {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zremrange(&self.key, &self.spec) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zmpop(&self.keys, self.max, self.count) {
+            Ok(popped) => zmpop_reply(backend, popped),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// the key together with the popped (member, score) pairs, or a null array if nothing was popped
+pub fn zmpop_reply(backend: &Backend, popped: Option<ZPopped>) -> RespFrame {
+    match popped {
+        Some((key, members)) => RespArray::new(vec![
+            BulkString::new(key).into(),
+            RespArray::new(
+                members
+                    .into_iter()
+                    .map(|(member, score)| {
+                        RespArray::new(vec![
+                            BulkString::new(member).into(),
+                            score_frame(backend, score),
+                        ])
+                        .into()
+                    })
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+        ])
+        .into(),
+        None => REST_NIL_ARRAY.clone(),
+    }
+}
+
+// a score as a double for RESP3 clients and as a bulk string otherwise
+pub fn score_frame(backend: &Backend, score: f64) -> RespFrame {
+    if backend.session().is_resp3() {
+        RespFrame::Double(score)
+    } else {
+        BulkString::new(format_score(score)).into()
+    }
+}
+
+pub fn format_score(score: f64) -> String {
+    score.to_string()
+}
+
+// members, each followed by its score with `withscores`. RESP3 clients get the member and
+// score as a pair instead.
+pub fn scored_reply(backend: &Backend, members: Vec<(String, f64)>, withscores: bool) -> RespFrame {
+    let resp3 = backend.session().is_resp3();
+    let mut frames = Vec::with_capacity(members.len() * 2);
+    for (member, score) in members {
+        let member: RespFrame = BulkString::new(member).into();
+        match (withscores, resp3) {
+            (false, _) => frames.push(member),
+            (true, true) => {
+                frames.push(RespArray::new(vec![member, score_frame(backend, score)]).into())
+            }
+            (true, false) => {
+                frames.push(member);
+                frames.push(score_frame(backend, score));
+            }
+        }
+    }
+    RespArray::new(frames).into()
+}
+
+//zadd :"*5\r\n$4\r\nzadd\r\n$3\r\nkey\r\n$2\r\nch\r\n$1\r\n1\r\n$1\r\na\r\n"
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zadd"], 3)?;
+        let mut args = extract_args(value)?.into_iter().peekable();
+        let key = next_key(&mut args)?;
+        let (mut options, mut incr) = (ZAddOptions::default(), false);
+        let (mut nx, mut xx) = (false, false);
+        while let Some(RespFrame::BulkString(option)) = args.peek() {
+            match option.to_ascii_lowercase().as_slice() {
+                b"nx" => nx = true,
+                b"xx" => xx = true,
+                b"gt" => options.gt = true,
+                b"lt" => options.lt = true,
+                b"ch" => options.ch = true,
+                b"incr" => incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if nx && xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (options.gt && options.lt) || (nx && (options.gt || options.lt)) {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        options.condition = match (nx, xx) {
+            (true, _) => Some(SetCondition::Nx),
+            (_, true) => Some(SetCondition::Xx),
+            _ => None,
+        };
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(syntax_error());
+        }
+        if incr && args.len() != 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        let mut pairs = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let (Some(score), Some(member)) = (args.next(), args.next()) {
+            pairs.push((parse_score(score)?, extract_string(member)?));
+        }
+        Ok(ZAdd {
+            key,
+            options,
+            incr,
+            pairs,
+        })
+    }
+}
+
+//zrem :"*3\r\n$4\r\nzrem\r\n$3\r\nkey\r\n$1\r\na\r\n"
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zrem"], 2)?;
+        let (key, members) = parse_key_members(value)?;
+        Ok(ZRem { key, members })
+    }
+}
+
+//zscore :"*3\r\n$6\r\nzscore\r\n$3\r\nkey\r\n$1\r\na\r\n"
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["zscore"], 2)?;
+        let (key, mut members) = parse_key_members(value)?;
+        Ok(ZScore {
+            key,
+            member: members.remove(0),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZMScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zmscore"], 2)?;
+        let (key, members) = parse_key_members(value)?;
+        Ok(ZMScore { key, members })
+    }
+}
+
+//zincrby :"*4\r\n$7\r\nzincrby\r\n$3\r\nkey\r\n$1\r\n2\r\n$1\r\na\r\n"
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["zincrby"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let incr = parse_score(args.next().ok_or_else(syntax_error)?)?;
+        Ok(ZIncrBy {
+            key,
+            incr,
+            member: next_key(&mut args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["zcard"], 1)?;
+        let mut args = extract_args(value)?.into_iter();
+        Ok(ZCard {
+            key: next_key(&mut args)?,
+        })
+    }
+}
+
+//zcount :"*4\r\n$6\r\nzcount\r\n$3\r\nkey\r\n$2\r\n(1\r\n$4\r\n+inf\r\n"
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["zcount"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let min = parse_score_bound(args.next().ok_or_else(syntax_error)?)?;
+        let max = parse_score_bound(args.next().ok_or_else(syntax_error)?)?;
+        Ok(ZCount {
+            key,
+            range: ScoreRange { min, max },
+        })
+    }
+}
+
+//zrank :"*4\r\n$5\r\nzrank\r\n$3\r\nkey\r\n$1\r\na\r\n$9\r\nwithscore\r\n"
+impl TryFrom<RespArray> for ZRank {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["zrank", "zrevrank"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        if value.len() > 4 {
+            return Err(syntax_error());
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let member = next_key(&mut args)?;
+        let withscore = match args.next().map(extract_string).transpose()? {
+            Some(option) if option.eq_ignore_ascii_case("withscore") => true,
+            Some(_) => return Err(syntax_error()),
+            None => false,
+        };
+        Ok(ZRank {
+            key,
+            member,
+            rev: name == "zrevrank",
+            withscore,
+        })
+    }
+}
+
+//zrange :"*6\r\n$6\r\nzrange\r\n$3\r\nkey\r\n$1\r\n0\r\n$2\r\n-1\r\n$3\r\nrev\r\n$10\r\nwithscores\r\n"
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(
+            &value,
+            &[
+                "zrange",
+                "zrevrange",
+                "zrangebyscore",
+                "zrevrangebyscore",
+                "zrangebylex",
+                "zrevrangebylex",
+            ],
+        );
+        validate_command_minimum_length(&value, &[name], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        Ok(ZRange {
+            key,
+            range: parse_zrange_args(name, &mut args)?,
+        })
+    }
+}
+
+//zrangestore :"*7\r\n$11\r\nzrangestore\r\n$3\r\ndst\r\n$3\r\nsrc\r\n$1\r\n1\r\n$1\r\n3\r\n$7\r\nbyscore\r\n"
+impl TryFrom<RespArray> for ZRangeStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zrangestore"], 4)?;
+        let mut args = extract_args(value)?.into_iter();
+        let dst = next_key(&mut args)?;
+        let src = next_key(&mut args)?;
+        Ok(ZRangeStore {
+            dst,
+            src,
+            range: parse_zrange_args("zrangestore", &mut args)?,
+        })
+    }
+}
+
+//zpopmin :"*3\r\n$7\r\nzpopmin\r\n$3\r\nkey\r\n$1\r\n2\r\n"
+impl TryFrom<RespArray> for ZPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["zpopmin", "zpopmax"]);
+        validate_command_minimum_length(&value, &[name], 1)?;
+        if value.len() > 3 {
+            return Err(syntax_error());
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let count = match args.next().map(extract_int).transpose()? {
+            Some(count) if count < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "value is out of range, must be positive".to_string(),
+                ))
+            }
+            count => count.map(|c| c as usize),
+        };
+        Ok(ZPop {
+            key,
+            max: name == "zpopmax",
+            count,
+        })
+    }
+}
+
+//zrandmember :"*4\r\n$11\r\nzrandmember\r\n$3\r\nkey\r\n$2\r\n-5\r\n$10\r\nwithscores\r\n"
+impl TryFrom<RespArray> for ZRandMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zrandmember"], 1)?;
+        if value.len() > 4 {
+            return Err(syntax_error());
+        }
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let count = args.next().map(extract_int).transpose()?;
+        let withscores = match args.next().map(extract_string).transpose()? {
+            Some(option) if option.eq_ignore_ascii_case("withscores") => true,
+            Some(_) => return Err(syntax_error()),
+            None => false,
+        };
+        Ok(ZRandMember {
+            key,
+            count,
+            withscores,
+        })
+    }
+}
+
+//zscan :"*3\r\n$5\r\nzscan\r\n$3\r\nkey\r\n$1\r\n0\r\n"
+impl TryFrom<RespArray> for ZScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zscan"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let (cursor, pattern, count) = parse_scan_args(&mut args, |_, _| Err(syntax_error()))?;
+        Ok(ZScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
+//zlexcount :"*4\r\n$9\r\nzlexcount\r\n$3\r\nkey\r\n$1\r\n-\r\n$2\r\n[c\r\n"
+impl TryFrom<RespArray> for ZLexCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_exact_length(&value, &["zlexcount"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let min = parse_lex_bound(args.next().ok_or_else(syntax_error)?)?;
+        let max = parse_lex_bound(args.next().ok_or_else(syntax_error)?)?;
+        Ok(ZLexCount {
+            key,
+            range: LexRange { min, max },
+        })
+    }
+}
+
+//zremrangebyrank :"*4\r\n$15\r\nzremrangebyrank\r\n$3\r\nkey\r\n$1\r\n0\r\n$1\r\n1\r\n"
+impl TryFrom<RespArray> for ZRemRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(
+            &value,
+            &["zremrangebyrank", "zremrangebyscore", "zremrangebylex"],
+        );
+        validate_command_exact_length(&value, &[name], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let key = next_key(&mut args)?;
+        let min = args.next().ok_or_else(syntax_error)?;
+        let max = args.next().ok_or_else(syntax_error)?;
+        let spec = match name {
+            "zremrangebyrank" => ZRangeSpec::Rank(extract_int(min)?, extract_int(max)?),
+            "zremrangebyscore" => ZRangeSpec::Score(ScoreRange {
+                min: parse_score_bound(min)?,
+                max: parse_score_bound(max)?,
+            }),
+            _ => ZRangeSpec::Lex(LexRange {
+                min: parse_lex_bound(min)?,
+                max: parse_lex_bound(max)?,
+            }),
+        };
+        Ok(ZRemRange { key, spec })
+    }
+}
+
+//zmpop :"*4\r\n$5\r\nzmpop\r\n$1\r\n1\r\n$3\r\nkey\r\n$3\r\nmin\r\n"
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zmpop"], 3)?;
+        let mut args = extract_args(value)?.into_iter();
+        let (keys, max, count) = parse_zmpop_args(&mut args)?;
+        Ok(ZMPop { keys, max, count })
+    }
+}
+
+// `numkeys key [key ...] MIN|MAX [COUNT count]` shared by ZMPOP and BZMPOP
+pub fn parse_zmpop_args(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Vec<String>, bool, usize), CommandError> {
+    let numkeys = match extract_int(args.next().ok_or_else(syntax_error)?)? {
+        n if n > 0 => n as usize,
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ))
+        }
+    };
+    let keys = args
+        .take(numkeys)
+        .map(extract_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.len() < numkeys {
+        return Err(syntax_error());
+    }
+    let max = match next_key(args)?.to_ascii_lowercase().as_str() {
+        "min" => false,
+        "max" => true,
+        _ => return Err(syntax_error()),
+    };
+    let count = match args.next().map(extract_string).transpose()? {
+        None => 1,
+        Some(option) if option.eq_ignore_ascii_case("count") => {
+            match extract_int(args.next().ok_or_else(syntax_error)?)? {
+                count if count > 0 => count as usize,
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ))
+                }
+            }
+        }
+        Some(_) => return Err(syntax_error()),
+    };
+    if args.next().is_some() {
+        return Err(syntax_error());
+    }
+    Ok((keys, max, count))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeBy {
+    Rank,
+    Score,
+    Lex,
+}
+
+// `start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]`, the older forms take
+// only the options that make sense for them
+fn parse_zrange_args(
+    name: &str,
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<ZRangeArgs, CommandError> {
+    let start = args.next().ok_or_else(syntax_error)?;
+    let stop = args.next().ok_or_else(syntax_error)?;
+    let (mut by, mut rev) = match name {
+        "zrevrange" => (RangeBy::Rank, true),
+        "zrangebyscore" => (RangeBy::Score, false),
+        "zrevrangebyscore" => (RangeBy::Score, true),
+        "zrangebylex" => (RangeBy::Lex, false),
+        "zrevrangebylex" => (RangeBy::Lex, true),
+        _ => (RangeBy::Rank, false),
+    };
+    let generic = name == "zrange" || name == "zrangestore";
+    let (mut limit, mut withscores) = (None, false);
+    while let Some(option) = args.next() {
+        match extract_string(option)?.to_ascii_lowercase().as_str() {
+            "byscore" if generic => by = RangeBy::Score,
+            "bylex" if generic => by = RangeBy::Lex,
+            "rev" if generic => rev = true,
+            "limit" if name != "zrevrange" => {
+                let offset = extract_int(args.next().ok_or_else(syntax_error)?)?;
+                let count = extract_int(args.next().ok_or_else(syntax_error)?)?;
+                limit = Some((offset, count));
+            }
+            "withscores" if name != "zrangestore" => withscores = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    if limit.is_some() && by == RangeBy::Rank {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_string(),
+        ));
+    }
+    if withscores && by == RangeBy::Lex {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+        ));
+    }
+    // reversed score and lex ranges are given from the high end
+    let (min, max) = if rev && by != RangeBy::Rank {
+        (stop, start)
+    } else {
+        (start, stop)
+    };
+    let spec = match by {
+        RangeBy::Rank => ZRangeSpec::Rank(extract_int(min)?, extract_int(max)?),
+        RangeBy::Score => ZRangeSpec::Score(ScoreRange {
+            min: parse_score_bound(min)?,
+            max: parse_score_bound(max)?,
+        }),
+        RangeBy::Lex => ZRangeSpec::Lex(LexRange {
+            min: parse_lex_bound(min)?,
+            max: parse_lex_bound(max)?,
+        }),
+    };
+    Ok(ZRangeArgs {
+        spec,
+        rev,
+        limit,
+        withscores,
+    })
+}
+
+pub fn parse_score(value: RespFrame) -> Result<f64, CommandError> {
+    extract_string(value)?
+        .parse::<f64>()
+        .ok()
+        .filter(|score| !score.is_nan())
+        .ok_or_else(|| CommandError::InvalidArgument("value is not a valid float".to_string()))
+}
+
+// a score optionally prefixed with `(` to exclude it, `-inf` and `+inf` included
+pub fn parse_score_bound(value: RespFrame) -> Result<ScoreBound, CommandError> {
+    let bound = extract_string(value)?;
+    let (value, exclusive) = match bound.strip_prefix('(') {
+        Some(value) => (value, true),
+        None => (bound.as_str(), false),
+    };
+    match value.parse::<f64>() {
+        Ok(value) if !value.is_nan() => Ok(ScoreBound { value, exclusive }),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max is not a float".to_string(),
+        )),
+    }
+}
+
+// `-`, `+`, `[member` or `(member`
+pub fn parse_lex_bound(value: RespFrame) -> Result<LexBound, CommandError> {
+    let bound = extract_string(value)?;
+    match bound.as_bytes().first() {
+        Some(b'-') if bound.len() == 1 => Ok(LexBound::Min),
+        Some(b'+') if bound.len() == 1 => Ok(LexBound::Max),
+        Some(b'[') => Ok(LexBound::Inclusive(bound[1..].to_string())),
+        Some(b'(') => Ok(LexBound::Exclusive(bound[1..].to_string())),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+fn parse_key_members(value: RespArray) -> Result<(String, Vec<String>), CommandError> {
+    let mut args = extract_args(value)?.into_iter();
+    let key = next_key(&mut args)?;
+    let members = args.map(extract_string).collect::<Result<Vec<_>, _>>()?;
+    Ok((key, members))
+}
+
+fn next_key(args: &mut impl Iterator<Item = RespFrame>) -> Result<String, CommandError> {
+    match args.next() {
+        Some(key) => extract_string(key),
+        None => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{bulks, decode, request};
+    use crate::BackendError;
+    use anyhow::Result;
+
+    fn leaderboard(backend: &Backend) -> Result<()> {
+        let cmd = ZAdd::try_from(request(&[
+            "zadd", "board", "10", "alice", "20", "bob", "30", "carol", "20", "dave",
+        ]))?;
+        assert_eq!(cmd.execute(backend), RespFrame::Integer(4));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_tryfrom() -> Result<()> {
+        let cmd = ZAdd::try_from(decode(
+            b"*5\r\n$4\r\nzadd\r\n$3\r\nkey\r\n$2\r\nch\r\n$1\r\n1\r\n$1\r\na\r\n",
+        )?)?;
+        assert!(cmd.options.ch);
+        assert_eq!(cmd.pairs, vec![(1.0, "a".to_string())]);
+
+        let err = |args: &[&str]| ZAdd::try_from(request(args)).unwrap_err();
+        assert_eq!(
+            err(&["zadd", "key", "nx", "xx", "1", "a"]),
+            CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string()
+            )
+        );
+        assert_eq!(
+            err(&["zadd", "key", "gt", "nx", "1", "a"]),
+            CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string()
+            )
+        );
+        assert_eq!(
+            err(&["zadd", "key", "incr", "1", "a", "2", "b"]),
+            CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string()
+            )
+        );
+        assert_eq!(err(&["zadd", "key", "1", "a", "2"]), syntax_error());
+        assert_eq!(
+            err(&["zadd", "key", "nan", "a"]),
+            CommandError::InvalidArgument("value is not a valid float".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_execute() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+        let cmd = ZAdd::try_from(request(&[
+            "zadd", "board", "xx", "ch", "15", "alice", "1", "eve",
+        ]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd = ZAdd::try_from(request(&["zadd", "board", "lt", "incr", "5", "alice"]))?;
+        assert_eq!(cmd.execute(&backend), REST_NIL.clone());
+        let cmd = ZIncrBy::try_from(request(&["zincrby", "board", "2.5", "alice"]))?;
+        assert_eq!(cmd.execute(&backend), BulkString::new("17.5").into());
+        let cmd = ZMScore::try_from(request(&["zmscore", "board", "alice", "eve"]))?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![BulkString::new("17.5").into(), REST_NIL.clone()]).into()
+        );
+
+        backend.session().set_protocol(3);
+        let cmd = ZScore::try_from(request(&["zscore", "board", "bob"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(20.0));
+
+        backend
+            .db()
+            .set("string".to_string(), BulkString::new("v").into());
+        let cmd = ZAdd::try_from(request(&["zadd", "string", "1", "a"]))?;
+        assert_eq!(cmd.execute(&backend), BackendError::WrongType.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrank_zcount_zrem() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+        let cmd = ZRank::try_from(request(&["zrevrank", "board", "alice", "withscore"]))?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![RespFrame::Integer(3), BulkString::new("10").into()]).into()
+        );
+        let cmd = ZRank::try_from(request(&["zrank", "board", "dave"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd = ZRank::try_from(request(&["zrank", "board", "nobody"]))?;
+        assert_eq!(cmd.execute(&backend), REST_NIL.clone());
+
+        let cmd = ZCount::try_from(decode(
+            b"*4\r\n$6\r\nzcount\r\n$5\r\nboard\r\n$3\r\n(10\r\n$4\r\n+inf\r\n",
+        )?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        assert_eq!(
+            ZCount::try_from(request(&["zcount", "board", "x", "1"])).unwrap_err(),
+            CommandError::InvalidArgument("min or max is not a float".to_string())
+        );
+
+        let cmd = ZRem::try_from(request(&["zrem", "board", "bob", "nobody"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd = ZCard::try_from(request(&["zcard", "board"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_forms() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+        let range = |args: &[&str]| -> Result<RespFrame> {
+            Ok(ZRange::try_from(request(args))?.execute(&backend))
+        };
+        assert_eq!(
+            range(&["zrange", "board", "0", "1", "rev", "withscores"])?,
+            bulks(&["carol", "30", "dave", "20"])
+        );
+        assert_eq!(
+            range(&["zrange", "board", "(30", "15", "byscore", "rev", "limit", "1", "5"])?,
+            bulks(&["bob"])
+        );
+        assert_eq!(
+            range(&["zrangebyscore", "board", "-inf", "20"])?,
+            bulks(&["alice", "bob", "dave"])
+        );
+        assert_eq!(
+            range(&["zrevrange", "board", "-1", "-1"])?,
+            bulks(&["alice"])
+        );
+        let cmd = ZAdd::try_from(request(&["zadd", "names", "0", "a", "0", "b", "0", "c"]))?;
+        cmd.execute(&backend);
+        assert_eq!(
+            range(&["zrange", "names", "[b", "(d", "bylex"])?,
+            bulks(&["b", "c"])
+        );
+        assert_eq!(
+            range(&["zrevrangebylex", "names", "+", "-", "limit", "0", "2"])?,
+            bulks(&["c", "b"])
+        );
+        assert_eq!(
+            ZRange::try_from(request(&["zrange", "board", "0", "1", "limit", "0", "1"]))
+                .unwrap_err(),
+            CommandError::InvalidArgument(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            ZRange::try_from(request(&["zrangebylex", "board", "a", "+"])).unwrap_err(),
+            CommandError::InvalidArgument("min or max not valid string range item".to_string())
+        );
+
+        backend.session().set_protocol(3);
+        let expected: RespFrame = RespArray::new(vec![RespArray::new(vec![
+            BulkString::new("alice").into(),
+            RespFrame::Double(10.0),
+        ])
+        .into()])
+        .into();
+        assert_eq!(
+            range(&["zrange", "board", "0", "0", "withscores"])?,
+            expected
+        );
+
+        let cmd = ZRangeStore::try_from(request(&[
+            "zrangestore",
+            "top",
+            "board",
+            "+inf",
+            "20",
+            "byscore",
+            "rev",
+        ]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        assert_eq!(backend.db().zcard("top"), Ok(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange_zlexcount_zmpop() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+        let cmd = ZRemRange::try_from(decode(
+            b"*4\r\n$15\r\nzremrangebyrank\r\n$5\r\nboard\r\n$1\r\n0\r\n$1\r\n0\r\n",
+        )?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd = ZRemRange::try_from(request(&["zremrangebyscore", "board", "(20", "+inf"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = ZAdd::try_from(request(&["zadd", "names", "0", "a", "0", "b", "0", "c"]))?;
+        cmd.execute(&backend);
+        let cmd = ZLexCount::try_from(request(&["zlexcount", "names", "(a", "+"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd = ZRemRange::try_from(request(&["zremrangebylex", "names", "-", "[b"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = ZMPop::try_from(request(&[
+            "zmpop", "2", "missing", "board", "max", "count", "5",
+        ]))?;
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::new("board").into(),
+            RespArray::new(vec![bulks(&["dave", "20"]), bulks(&["bob", "20"])]).into(),
+        ])
+        .into();
+        assert_eq!(cmd.execute(&backend), expected);
+        assert_eq!(cmd.execute(&backend), REST_NIL_ARRAY.clone());
+        assert_eq!(
+            ZMPop::try_from(request(&["zmpop", "1", "board", "middle"])).unwrap_err(),
+            syntax_error()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zpop_zrandmember_zscan() -> Result<()> {
+        let backend = Backend::new();
+        leaderboard(&backend)?;
+        let cmd = ZPop::try_from(request(&["zpopmax", "board"]))?;
+        assert_eq!(cmd.execute(&backend), bulks(&["carol", "30"]));
+        let cmd = ZPop::try_from(request(&["zpopmin", "board", "2"]))?;
+        assert_eq!(cmd.execute(&backend), bulks(&["alice", "10", "bob", "20"]));
+
+        let cmd = ZRandMember::try_from(request(&["zrandmember", "board", "-3", "withscores"]))?;
+        assert_eq!(
+            cmd.execute(&backend),
+            bulks(&["dave", "20", "dave", "20", "dave", "20"])
+        );
+        let cmd = ZRandMember::try_from(request(&["zrandmember", "missing"]))?;
+        assert_eq!(cmd.execute(&backend), REST_NIL.clone());
+        // a count too large to reply with is refused
+        let cmd = ZRandMember::try_from(request(&["zrandmember", "board", "-100000000000"]))?;
+        assert_eq!(
+            cmd.execute(&backend),
+            BackendError::RandomCountOutOfRange.into()
+        );
+
+        let cmd = ZScan::try_from(request(&["zscan", "board", "0", "match", "d*"]))?;
+        assert_eq!(
+            cmd.execute(&backend),
+            scan_reply(
+                0,
+                vec![BulkString::new("dave").into(), BulkString::new("20").into()]
+            )
+        );
+        Ok(())
+    }
+}
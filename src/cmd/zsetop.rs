@@ -0,0 +1,350 @@
+use crate::{
+    command_name, extract_args, extract_int, extract_string, validate_command_minimum_length,
+    Aggregate, Backend, CommandError, CommandExecutor, RespArray, RespFrame, SetOperator,
+};
+
+use super::zset::scored_reply;
+
+// ZUNION, ZINTER and ZDIFF
+#[derive(Debug)]
+pub struct ZSetOp {
+    op: SetOperator,
+    args: ZSetOpArgs,
+    withscores: bool,
+}
+
+// ZUNIONSTORE, ZINTERSTORE and ZDIFFSTORE
+#[derive(Debug)]
+pub struct ZSetOpStore {
+    op: SetOperator,
+    dst: String,
+    args: ZSetOpArgs,
+}
+
+#[derive(Debug)]
+pub struct ZInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ZSetOpArgs {
+    keys: Vec<String>,
+    weights: Option<Vec<f64>>,
+    aggregate: Aggregate,
+}
+
+impl CommandExecutor for ZSetOp {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let args = &self.args;
+        match backend
+            .db()
+            .zset_op(self.op, &args.keys, args.weights.as_deref(), args.aggregate)
+        {
+            Ok(members) => scored_reply(backend, members, self.withscores),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZSetOpStore {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        let args = &self.args;
+        match backend.db().zset_op_store(
+            self.op,
+            self.dst.clone(),
+            &args.keys,
+            args.weights.as_deref(),
+            args.aggregate,
+        ) {
+            Ok(len) => {
+                backend.signal_ready(&self.dst);
+                RespFrame::Integer(len)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZInterCard {
+    fn execute(&self, backend: &Backend) -> RespFrame {
+        match backend.db().zintercard(&self.keys, self.limit) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+//zunion :"*5\r\n$6\r\nzunion\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$10\r\nwithscores\r\n"
+impl TryFrom<RespArray> for ZSetOp {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["zunion", "zinter", "zdiff"]);
+        validate_command_minimum_length(&value, &[name], 2)?;
+        let op = set_operator(name);
+        let mut args = extract_args(value)?.into_iter();
+        let mut withscores = false;
+        let args = parse_zset_op_args(name, op, &mut args, |option| {
+            let known = option == "withscores";
+            withscores |= known;
+            known
+        })?;
+        Ok(ZSetOp {
+            op,
+            args,
+            withscores,
+        })
+    }
+}
+
+//zunionstore :"*5\r\n$11\r\nzunionstore\r\n$3\r\ndst\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n"
+impl TryFrom<RespArray> for ZSetOpStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value, &["zunionstore", "zinterstore", "zdiffstore"]);
+        validate_command_minimum_length(&value, &[name], 3)?;
+        let op = set_operator(name);
+        let mut args = extract_args(value)?.into_iter();
+        let dst = extract_string(args.next().ok_or_else(syntax_error)?)?;
+        Ok(ZSetOpStore {
+            op,
+            dst,
+            args: parse_zset_op_args(name, op, &mut args, |_| false)?,
+        })
+    }
+}
+
+//zintercard :"*6\r\n$10\r\nzintercard\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$5\r\nlimit\r\n$1\r\n5\r\n"
+impl TryFrom<RespArray> for ZInterCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_minimum_length(&value, &["zintercard"], 2)?;
+        let mut args = extract_args(value)?.into_iter();
+        let numkeys = match args.next().map(extract_int).transpose()? {
+            Some(n) if n > 0 => n as usize,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "numkeys should be greater than 0".to_string(),
+                ))
+            }
+        };
+        let keys = args
+            .by_ref()
+            .take(numkeys)
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.len() < numkeys {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let option = args.next().map(extract_string).transpose()?;
+        let limit = match (option, args.next()) {
+            (None, _) => 0,
+            (Some(option), Some(limit)) if option.eq_ignore_ascii_case("limit") => {
+                match extract_int(limit)? {
+                    limit if limit >= 0 => limit as usize,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "LIMIT can't be negative".to_string(),
+                        ))
+                    }
+                }
+            }
+            _ => return Err(syntax_error()),
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(ZInterCard { keys, limit })
+    }
+}
+
+fn set_operator(name: &str) -> SetOperator {
+    match name.trim_end_matches("store") {
+        "zinter" => SetOperator::Inter,
+        "zunion" => SetOperator::Union,
+        _ => SetOperator::Diff,
+    }
+}
+
+// `numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX]`, ZDIFF takes neither
+// option. Any other single word option is offered to `flag`, which returns whether it is known.
+fn parse_zset_op_args(
+    name: &str,
+    op: SetOperator,
+    args: &mut impl Iterator<Item = RespFrame>,
+    mut flag: impl FnMut(&str) -> bool,
+) -> Result<ZSetOpArgs, CommandError> {
+    let numkeys = match extract_int(args.next().ok_or_else(syntax_error)?)? {
+        n if n > 0 => n as usize,
+        _ => {
+            return Err(CommandError::InvalidArgument(format!(
+                "at least 1 input key is needed for '{}' command",
+                name
+            )))
+        }
+    };
+    let keys = args
+        .take(numkeys)
+        .map(extract_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.len() < numkeys {
+        return Err(syntax_error());
+    }
+    let mut parsed = ZSetOpArgs {
+        keys,
+        ..Default::default()
+    };
+    let combines = op != SetOperator::Diff;
+    while let Some(option) = args.next() {
+        match extract_string(option)?.to_ascii_lowercase().as_str() {
+            "weights" if combines => {
+                let weights = args
+                    .take(numkeys)
+                    .map(|weight| {
+                        extract_string(weight)?
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|weight| !weight.is_nan())
+                            .ok_or_else(|| {
+                                CommandError::InvalidArgument(
+                                    "weight value is not a float".to_string(),
+                                )
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if weights.len() < numkeys {
+                    return Err(syntax_error());
+                }
+                parsed.weights = Some(weights);
+            }
+            "aggregate" if combines => {
+                parsed.aggregate = match extract_string(args.next().ok_or_else(syntax_error)?)?
+                    .to_ascii_lowercase()
+                    .as_str()
+                {
+                    "sum" => Aggregate::Sum,
+                    "min" => Aggregate::Min,
+                    "max" => Aggregate::Max,
+                    _ => return Err(syntax_error()),
+                };
+            }
+            option if flag(option) => (),
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(parsed)
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{bulks, request};
+    use crate::{RespDecode, ZAddOptions};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn setup() -> Result<Backend> {
+        let backend = Backend::new();
+        let zadd = |key: &str, pairs: &[(f64, &str)]| {
+            let pairs = pairs.iter().map(|(s, m)| (*s, m.to_string())).collect();
+            backend
+                .db()
+                .zadd(key.to_string(), ZAddOptions::default(), pairs)
+        };
+        zadd("a", &[(1.0, "x"), (2.0, "y"), (3.0, "z")])?;
+        zadd("b", &[(4.0, "y"), (5.0, "w")])?;
+        Ok(backend)
+    }
+
+    #[test]
+    fn test_zsetop_command_tryfrom() -> Result<()> {
+        let cmd = ZSetOpStore::try_from(RespArray::decode(&mut BytesMut::from(
+            &b"*5\r\n$11\r\nzunionstore\r\n$3\r\ndst\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n"[..],
+        ))?)?;
+        assert_eq!(cmd.op, SetOperator::Union);
+        assert_eq!(cmd.dst, "dst");
+        assert_eq!(cmd.args.keys, vec!["a".to_string(), "b".to_string()]);
+
+        let cmd = ZSetOp::try_from(request(&[
+            "zinter",
+            "2",
+            "a",
+            "b",
+            "weights",
+            "1",
+            "0.5",
+            "aggregate",
+            "max",
+            "withscores",
+        ]))?;
+        assert_eq!(cmd.args.weights, Some(vec![1.0, 0.5]));
+        assert_eq!(cmd.args.aggregate, Aggregate::Max);
+        assert!(cmd.withscores);
+
+        let err = |args: &[&str]| ZSetOp::try_from(request(args)).unwrap_err();
+        assert_eq!(
+            err(&["zdiff", "2", "a", "b", "weights", "1", "2"]),
+            syntax_error()
+        );
+        assert_eq!(err(&["zunion", "3", "a", "b"]), syntax_error());
+        assert_eq!(
+            err(&["zunion", "0", "a"]),
+            CommandError::InvalidArgument(
+                "at least 1 input key is needed for 'zunion' command".to_string()
+            )
+        );
+        assert_eq!(
+            err(&["zunion", "1", "a", "weights", "x"]),
+            CommandError::InvalidArgument("weight value is not a float".to_string())
+        );
+        assert!(
+            ZSetOpStore::try_from(request(&["zinterstore", "d", "1", "a", "withscores"])).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zsetop_execute() -> Result<()> {
+        let backend = setup()?;
+        let cmd = ZSetOp::try_from(request(&["zunion", "2", "a", "b", "withscores"]))?;
+        assert_eq!(
+            cmd.execute(&backend),
+            bulks(&["x", "1", "z", "3", "w", "5", "y", "6"])
+        );
+        let cmd = ZSetOp::try_from(request(&[
+            "zinter",
+            "2",
+            "a",
+            "b",
+            "weights",
+            "10",
+            "1",
+            "aggregate",
+            "min",
+            "withscores",
+        ]))?;
+        assert_eq!(cmd.execute(&backend), bulks(&["y", "4"]));
+        let cmd = ZSetOp::try_from(request(&["zdiff", "2", "a", "b"]))?;
+        assert_eq!(cmd.execute(&backend), bulks(&["x", "z"]));
+
+        let cmd = ZSetOpStore::try_from(request(&["zinterstore", "dst", "2", "a", "b"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.db().zscore("dst", "y"), Ok(Some(6.0)));
+        let cmd = ZSetOpStore::try_from(request(&["zdiffstore", "dst", "2", "b", "b"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(backend.db().exists(&["dst".to_string()]), 0);
+
+        let cmd = ZInterCard::try_from(request(&["zintercard", "2", "a", "b", "limit", "5"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        Ok(())
+    }
+}
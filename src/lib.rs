@@ -2,6 +2,8 @@ mod backend;
 mod cmd;
 mod network;
 mod resp;
+#[cfg(test)]
+mod test_util;
 
 pub use backend::*;
 pub use cmd::*;
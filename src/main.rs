@@ -12,6 +12,7 @@ async fn main() -> Result<()> {
     let listener = TcpListener::bind(addr).await?;
 
     let backend = Backend::new();
+    tokio::spawn(backend.clone().active_expire());
     loop {
         let (stream, saddr) = listener.accept().await?;
         info!("Accepted connection from: {}", saddr);
@@ -1,37 +1,123 @@
-use crate::{backend::Backend, CommandExecutor, RespDecode, RespEncode, RespError, RespFrame};
-use anyhow::Result;
-use futures::{SinkExt,StreamExt};
-use tokio::net::TcpStream;
+use std::collections::VecDeque;
+
+use crate::{backend::Backend, RespArray, RespDecode, RespEncode, RespError, RespFrame};
+use anyhow::{anyhow, Result};
+use futures::{SinkExt, StreamExt};
+use tokio::{net::TcpStream, sync::mpsc};
 use tokio_util::codec::{Decoder, Encoder, Framed};
 use tracing::info;
 
+// requests of a client read ahead while a blocking command waits, reading stops beyond that
+const MAX_PENDING_REQUESTS: usize = 1024;
+
 pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
+    let backend = backend.connect();
+    let mut pushes = backend.open_pushes();
     let mut framed = Framed::new(stream, RespFrameCodec);
+    let result = serve(&mut framed, &backend, &mut pushes).await;
+    backend.close_pushes();
+    backend.unwatch();
+    backend.disable_tracking();
+    result
+}
+
+async fn serve(
+    framed: &mut Framed<TcpStream, RespFrameCodec>,
+    backend: &Backend,
+    pushes: &mut mpsc::Receiver<RespFrame>,
+) -> Result<()> {
+    // requests read while a blocking command was still waiting, handled in order afterwards
+    let mut pending = VecDeque::new();
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
-                println!("Received frame: {:?}", frame);
-                let backend = backend.clone();
-                let request = RedisRequest { frame, backend };
-                let response = handle_request(request).await?;
-                println!("Send response: {:?}", response);
-                println!("msg is {:?}", &response.clone().frame);
-                framed.send(response.frame).await?;
-            }
-            Some(Err(e)) => {
-                return Err(e);
+        let frame = match pending.pop_front() {
+            Some(frame) => frame,
+            None => tokio::select! {
+                next = framed.next() => match next {
+                    Some(Ok(frame)) => frame,
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(()),
+                },
+                push = pushes.recv() => {
+                    send_push(framed, backend, push).await?;
+                    continue;
+                }
+            },
+        };
+        println!("Received frame: {:?}", frame);
+        let request = RedisRequest {
+            frame,
+            backend: backend.clone(),
+        };
+        let handling = handle_request(request);
+        tokio::pin!(handling);
+        // keep reading while the request is handled so that a client leaving is noticed, a
+        // blocked command is dropped then and stops waiting. A client pipelining more than
+        // MAX_PENDING_REQUESTS behind it is left unread until the command is done.
+        let response = loop {
+            tokio::select! {
+                biased;
+                response = &mut handling => break response?,
+                next = framed.next(), if pending.len() < MAX_PENDING_REQUESTS => match next {
+                    Some(Ok(frame)) => pending.push_back(frame),
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(()),
+                },
             }
-            None => return Ok(()),
+        };
+        // the pushes queued by the command go out before its reply
+        while let std::result::Result::Ok(push) = pushes.try_recv() {
+            send_push(framed, backend, Some(push)).await?;
+        }
+        println!("Send response: {:?}", response);
+        if let Some(frame) = response.frame {
+            framed.send(frame).await?;
+        }
+        if response.close {
+            return Ok(());
+        }
+    }
+}
+
+// write a pushed frame, as a plain array for RESP2 clients. The queue closes when the server
+// drops a client too slow to read its messages.
+async fn send_push(
+    framed: &mut Framed<TcpStream, RespFrameCodec>,
+    backend: &Backend,
+    push: Option<RespFrame>,
+) -> Result<()> {
+    match push {
+        Some(RespFrame::Push(push)) if !backend.session().is_resp3() => {
+            framed.send(RespArray::new(push.0).into()).await
         }
+        Some(frame) => framed.send(frame).await,
+        None => Err(anyhow!(
+            "client dropped for reading its messages too slowly"
+        )),
     }
 }
 
 pub async fn handle_request(request: RedisRequest) -> Result<RedisResponse> {
     let (frame, backend) = (request.frame, request.backend);
-    let cmd = crate::Command::try_from(frame)?;
+    if let Some(error) = crate::cmd::subscribed_context_error(&backend, &frame) {
+        return Ok(RedisResponse::reply(error));
+    }
+    if let Some(reply) = crate::cmd::queue_in_transaction(&backend, &frame) {
+        return Ok(RedisResponse::reply(reply));
+    }
+    let cmd = match crate::Command::try_from(frame) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            backend.abort_transaction();
+            return Ok(RedisResponse::reply(e.into()));
+        }
+    };
     info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
-    Ok(RedisResponse { frame })
+    let frame = cmd.run(&backend).await;
+    let frame = (!cmd.replies_with_pushes()).then_some(frame);
+    Ok(RedisResponse {
+        frame,
+        close: cmd.closes_connection(),
+    })
 }
 
 #[derive(Debug)]
@@ -39,7 +125,18 @@ pub struct RespFrameCodec;
 
 #[derive(Debug, Clone)]
 pub struct RedisResponse {
-    frame: RespFrame,
+    frame: Option<RespFrame>,
+    // close the connection once the frame is written
+    close: bool,
+}
+
+impl RedisResponse {
+    fn reply(frame: RespFrame) -> Self {
+        RedisResponse {
+            frame: Some(frame),
+            close: false,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -78,6 +175,7 @@ mod tests {
     use bytes::BytesMut;
 
     use super::*;
+    use crate::test_util::{bulks, request, serve};
     use crate::{BulkString, RespArray, SimpleString};
 
     #[test]
@@ -108,7 +206,7 @@ mod tests {
         })
         .await?;
         let new_frame: RespFrame = SimpleString::new("OK".to_string()).into();
-        assert_eq!(response.frame, new_frame);
+        assert_eq!(response.frame, Some(new_frame));
 
         let mut buf = BytesMut::from("*2\r\n$3\r\nget\r\n$5\r\nhello\r\n");
         let frame: RespFrame = RespArray::decode(&mut buf)?.into();
@@ -118,7 +216,122 @@ mod tests {
         })
         .await?;
         let new_frame: RespFrame = BulkString::new("world".to_string()).into();
-        assert_eq!(response.frame, new_frame);
+        assert_eq!(response.frame, Some(new_frame));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_handler_blocking() -> Result<()> {
+        use std::time::Duration;
+
+        let addr = serve().await?;
+        let connect = || async { Ok(Framed::new(TcpStream::connect(addr).await?, RespFrameCodec)) };
+
+        // a client that leaves while blocked does not consume the pushed element
+        let mut gone = connect().await?;
+        gone.send(request(&["blpop", "list", "0"]).into()).await?;
+        let mut blocked = connect().await?;
+        blocked
+            .send(request(&["brpop", "list", "0"]).into())
+            .await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(gone);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut pusher = connect().await?;
+        pusher.send(request(&["lpush", "list", "a"]).into()).await?;
+        assert_eq!(
+            pusher.next().await.transpose()?,
+            Some(RespFrame::Integer(1))
+        );
+        assert_eq!(
+            blocked.next().await.transpose()?,
+            Some(bulks(&["list", "a"]))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_handler_pubsub() -> Result<()> {
+        let addr = serve().await?;
+        let connect = || async { Ok(Framed::new(TcpStream::connect(addr).await?, RespFrameCodec)) };
+
+        let mut subscriber = connect().await?;
+        subscriber
+            .send(request(&["subscribe", "news"]).into())
+            .await?;
+        let reply = RespArray::new(vec![
+            BulkString::new("subscribe").into(),
+            BulkString::new("news").into(),
+            RespFrame::Integer(1),
+        ]);
+        assert_eq!(subscriber.next().await.transpose()?, Some(reply.into()));
+
+        // only the subscription commands run in the RESP2 subscribed context
+        subscriber.send(request(&["get", "news"]).into()).await?;
+        assert!(matches!(
+            subscriber.next().await.transpose()?,
+            Some(RespFrame::Error(_))
+        ));
+
+        let mut publisher = connect().await?;
+        publisher
+            .send(request(&["publish", "news", "hello"]).into())
+            .await?;
+        assert_eq!(
+            publisher.next().await.transpose()?,
+            Some(RespFrame::Integer(1))
+        );
+        assert_eq!(
+            subscriber.next().await.transpose()?,
+            Some(bulks(&["message", "news", "hello"]))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_handler_empty_values() -> Result<()> {
+        use std::io::{Read, Write};
+
+        let addr = serve().await?;
+        // read the raw bytes, as the codec decodes both kinds of empty string alike
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut client = std::net::TcpStream::connect(addr)?;
+            // an empty string stays a value on the wire, only a missing one is null
+            for (args, expected) in [
+                (&["echo", ""][..], &b"$0\r\n\r\n"[..]),
+                (&["ping", ""], b"$0\r\n\r\n"),
+                (&["set", "k", ""], b"+OK\r\n"),
+                (&["get", "k"], b"$0\r\n\r\n"),
+                (&["get", "missing"], b"$-1\r\n"),
+            ] {
+                client.write_all(&RespFrame::from(request(args)).encode())?;
+                let mut reply = vec![0; expected.len()];
+                client.read_exact(&mut reply)?;
+                assert_eq!(reply, expected, "{:?}", args);
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    async fn test_stream_handler_ping_quit() -> Result<()> {
+        let addr = serve().await?;
+
+        let mut client = Framed::new(TcpStream::connect(addr).await?, RespFrameCodec);
+        client.send(request(&["ping"]).into()).await?;
+        assert_eq!(
+            client.next().await.transpose()?,
+            Some(SimpleString::new("PONG").into())
+        );
+        // the connection is closed right after the reply to QUIT
+        client.send(request(&["quit"]).into()).await?;
+        assert_eq!(
+            client.next().await.transpose()?,
+            Some(SimpleString::new("OK").into())
+        );
+        assert!(client.next().await.is_none());
         Ok(())
     }
 }
@@ -1,5 +1,5 @@
 use super::{util::calc_total_length, BUF_CAP, CRLF_LEN};
-use crate::{parse_length, RespDecode, RespEncode, RespError, RespFrame};
+use crate::{extract_fixed_data, parse_length, RespDecode, RespEncode, RespError, RespFrame};
 use bytes::{Buf, BytesMut};
 use std::ops::Deref;
 
@@ -8,9 +8,6 @@ pub struct RespArray(pub(crate) Vec<RespFrame>);
 
 impl RespEncode for RespArray {
     fn encode(self) -> Vec<u8> {
-        if self.0.is_empty() {
-            return b"*-1\r\n".to_vec();
-        }
         let mut buf = Vec::with_capacity(BUF_CAP);
         buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
         for frame in self.0 {
@@ -25,10 +22,7 @@ impl RespDecode for RespArray {
 
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         let (end, len) = parse_length(buf, Self::PREFIX)?;
-        if len == -1 {
-            return Ok(RespArray::new(Vec::new()));
-        }
-        let len = len as usize;
+        let len = non_negative(len)?;
 
         let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
         if buf.len() < total_len {
@@ -44,7 +38,7 @@ impl RespDecode for RespArray {
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let len = len as usize;
+        let len = non_negative(len)?;
         calc_total_length(buf, end, len, Self::PREFIX)
     }
 }
@@ -52,6 +46,32 @@ impl RespDecode for RespArray {
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct RespNullArray;
 
+// the null array, a missing reply for RESP2 clients. An empty array is a reply.
+impl RespEncode for RespNullArray {
+    fn encode(self) -> Vec<u8> {
+        b"*-1\r\n".to_vec()
+    }
+}
+
+impl RespDecode for RespNullArray {
+    const PREFIX: &'static str = "*";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        extract_fixed_data(buf, "*-1\r\n", "NullArray")?;
+        Ok(RespNullArray)
+    }
+
+    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
+        Ok(5)
+    }
+}
+
+// the length of an array, -1 is the null array
+fn non_negative(len: isize) -> Result<usize, RespError> {
+    usize::try_from(len)
+        .map_err(|_| RespError::InvalidFrame(format!("invalid array length: {}", len)))
+}
+
 impl Deref for RespArray {
     type Target = Vec<RespFrame>;
     fn deref(&self) -> &Self::Target {
@@ -87,6 +107,11 @@ mod tests {
     #[test]
     fn test_null_array_decode() {
         let mut buf = BytesMut::from("*-1\r\n");
+        let s = RespNullArray::decode(&mut buf).unwrap();
+        assert_eq!(s, RespNullArray);
+        assert!(RespArray::decode(&mut BytesMut::from("*-1\r\n")).is_err());
+
+        let mut buf = BytesMut::from("*0\r\n");
         let s = RespArray::decode(&mut buf).unwrap();
         assert_eq!(s, RespArray::new(vec![]));
     }
@@ -107,7 +132,9 @@ mod tests {
 
     #[test]
     fn test_null_array_encode() {
-        let s: RespFrame = RespArray::new(Vec::new()).into();
+        let s: RespFrame = RespNullArray.into();
         assert_eq!(s.encode(), b"*-1\r\n");
+        let s: RespFrame = RespArray::new(Vec::new()).into();
+        assert_eq!(s.encode(), b"*0\r\n");
     }
 }
@@ -1,5 +1,5 @@
 use super::CRLF_LEN;
-use crate::{parse_length, RespDecode, RespEncode, RespError};
+use crate::{extract_fixed_data, parse_length, RespDecode, RespEncode, RespError};
 use bytes::{Buf, BytesMut};
 use std::ops::Deref;
 
@@ -11,9 +11,6 @@ pub struct RespNullBulkString;
 
 impl RespEncode for BulkString {
     fn encode(self) -> Vec<u8> {
-        if self.len() == 0 {
-            return b"$-1\r\n".to_vec();
-        }
         let mut buf = Vec::with_capacity(self.len() + 16);
         buf.extend_from_slice(&format!("${}\r\n", self.len()).into_bytes());
         buf.extend_from_slice(&self);
@@ -27,11 +24,7 @@ impl RespDecode for BulkString {
 
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         let (end, len) = parse_length(buf, Self::PREFIX)?;
-        if len == -1 {
-            buf.advance(end + CRLF_LEN);
-            return Ok(BulkString::new(Vec::new()));
-        }
-        let len = len as usize;
+        let len = non_negative(len)?;
         let remained = &buf[end + CRLF_LEN..];
         if remained.len() < len + CRLF_LEN {
             return Err(RespError::NotComplete);
@@ -45,11 +38,37 @@ impl RespDecode for BulkString {
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let len = len as usize;
+        let len = non_negative(len)?;
         Ok(end + CRLF_LEN + len + CRLF_LEN)
     }
 }
 
+// the null bulk string, a missing value for RESP2 clients. An empty bulk string is a value.
+impl RespEncode for RespNullBulkString {
+    fn encode(self) -> Vec<u8> {
+        b"$-1\r\n".to_vec()
+    }
+}
+
+impl RespDecode for RespNullBulkString {
+    const PREFIX: &'static str = "$";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        extract_fixed_data(buf, "$-1\r\n", "NullBulkString")?;
+        Ok(RespNullBulkString)
+    }
+
+    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
+        Ok(5)
+    }
+}
+
+// the length of a bulk string, -1 is the null bulk string
+fn non_negative(len: isize) -> Result<usize, RespError> {
+    usize::try_from(len)
+        .map_err(|_| RespError::InvalidFrame(format!("invalid bulk string length: {}", len)))
+}
+
 impl Deref for BulkString {
     type Target = Vec<u8>;
     fn deref(&self) -> &Self::Target {
@@ -84,6 +103,11 @@ mod tests {
     #[test]
     fn test_null_bulk_string_decode() -> anyhow::Result<()> {
         let mut buf = BytesMut::from("$-1\r\n");
+        let s = RespNullBulkString::decode(&mut buf)?;
+        assert_eq!(s, RespNullBulkString);
+        assert!(BulkString::decode(&mut BytesMut::from("$-1\r\n")).is_err());
+
+        let mut buf = BytesMut::from("$0\r\n\r\n");
         let s = BulkString::decode(&mut buf)?;
         assert_eq!(s, BulkString::new(""));
         Ok(())
@@ -97,7 +121,9 @@ mod tests {
 
     #[test]
     fn test_null_bulk_string_encode() {
-        let s: RespFrame = BulkString::new("").into();
+        let s: RespFrame = RespNullBulkString.into();
         assert_eq!(s.encode(), b"$-1\r\n");
+        let s: RespFrame = BulkString::new("").into();
+        assert_eq!(s.encode(), b"$0\r\n\r\n");
     }
 }
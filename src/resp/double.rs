@@ -5,7 +5,11 @@ use bytes::BytesMut;
 impl RespEncode for f64 {
     fn encode(self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(32);
-        let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
+        let ret = if self.is_infinite() {
+            format!(",{}inf\r\n", if self < 0.0 { "-" } else { "" })
+        } else if self.is_nan() {
+            ",nan\r\n".to_string()
+        } else if self.abs() > 1e+8 || self.abs() < 1e-8 {
             format!(",{:+e}\r\n", self)
         } else {
             let sign = if self < 0.0 { "" } else { "+" };
@@ -60,5 +64,7 @@ mod tests {
         assert_eq!(s.encode(), b",+1.23456e8\r\n");
         let s: RespFrame = (-1.23456e-9).into();
         assert_eq!(s.encode(), b",-1.23456e-9\r\n");
+        let s: RespFrame = f64::NEG_INFINITY.into();
+        assert_eq!(s.encode(), b",-inf\r\n");
     }
 }
@@ -1,6 +1,6 @@
 use crate::{
-    BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespSet, SimpleError,
-    SimpleString,
+    BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespNullArray,
+    RespNullBulkString, RespPush, RespSet, SimpleError, SimpleString,
 };
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
@@ -12,12 +12,15 @@ pub enum RespFrame {
     Error(SimpleError),
     Integer(i64),
     BulkString(BulkString),
+    NullBulkString(RespNullBulkString),
     Array(RespArray),
+    NullArray(RespNullArray),
     Null(RespNull),
     Boolean(bool),
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
 }
 
 impl RespDecode for RespFrame {
@@ -39,21 +42,23 @@ impl RespDecode for RespFrame {
             }
             Some(b'$') => {
                 // try null bulk string first
+                if buf.starts_with(b"$-1\r\n") {
+                    return Ok(RespNullBulkString::decode(buf)?.into());
+                }
                 match BulkString::decode(buf) {
-                    // Ok(frame) => Ok(frame.into()),
                     Ok(frame) => Ok(frame.into()),
                     Err(RespError::NotComplete) => Err(RespError::NotComplete),
                     Err(_) => Err(RespError::InvalidFrame(format!(
                         "unknown frame type: {:?}",
                         buf
-                    ))), // Err(_) => {
-                         //     let frame = BulkString::decode(buf)?;
-                         //     Ok(frame.into())
-                         // }
+                    ))),
                 }
             }
             Some(b'*') => {
                 // try null array first
+                if buf.starts_with(b"*-1\r\n") {
+                    return Ok(RespNullArray::decode(buf)?.into());
+                }
                 match RespArray::decode(buf) {
                     Ok(frame) => Ok(frame.into()),
                     Err(RespError::NotComplete) => Err(RespError::NotComplete),
@@ -83,6 +88,10 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
             //when receive none, return NotComplete error
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
@@ -95,9 +104,12 @@ impl RespDecode for RespFrame {
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         let mut iter = buf.iter().peekable();
         match iter.peek() {
+            Some(b'*') if buf.starts_with(b"*-1\r\n") => RespNullArray::expect_length(buf),
             Some(b'*') => RespArray::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
+            Some(b'$') if buf.starts_with(b"$-1\r\n") => RespNullBulkString::expect_length(buf),
             Some(b'$') => BulkString::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
             Some(b'+') => SimpleString::expect_length(buf),
@@ -6,6 +6,7 @@ mod frame;
 mod integer;
 mod map;
 mod null;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
@@ -17,6 +18,7 @@ pub use self::{
     frame::RespFrame,
     map::RespMap,
     null::RespNull,
+    push::RespPush,
     set::RespSet,
     simple_error::SimpleError,
     simple_string::SimpleString,
@@ -0,0 +1,38 @@
+// helpers shared by the tests of the commands and the connections
+
+use std::net::SocketAddr;
+
+use bytes::BytesMut;
+use tokio::net::TcpListener;
+
+use crate::{stream_handler, Backend, BulkString, RespArray, RespDecode, RespFrame};
+
+// a request as a client sends it, an array of bulk strings
+pub fn request(args: &[&str]) -> RespArray {
+    RespArray::new(
+        args.iter()
+            .map(|arg| BulkString::new(*arg).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
+
+pub fn bulks(values: &[&str]) -> RespFrame {
+    request(values).into()
+}
+
+pub fn decode(input: &[u8]) -> anyhow::Result<RespArray> {
+    Ok(RespArray::decode(&mut BytesMut::from(input))?)
+}
+
+// a server on a free port, serving each connection on its own task
+pub async fn serve() -> anyhow::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let backend = Backend::new();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(stream_handler(stream, backend.clone()));
+        }
+    });
+    Ok(addr)
+}